@@ -29,6 +29,11 @@ pub enum Cli {
         #[command(flatten)]
         project_args: ProjectCli,
     },
+    /// Validates the project's ambient.toml and pipeline.json files without building, and prints a JSON report
+    Check {
+        #[command(flatten)]
+        check_args: CheckCli,
+    },
     /// Builds and runs the project in server-only mode
     Serve {
         #[command(flatten)]
@@ -69,6 +74,14 @@ pub struct RunCli {
 pub struct ProjectCli {
     /// The path of the project to run; if not specified, this will default to the current directory
     pub path: Option<PathBuf>,
+    /// Rebuild all assets, instead of only the ones that changed since the last build
+    #[arg(long)]
+    pub clean: bool,
+}
+#[derive(Args, Clone)]
+pub struct CheckCli {
+    /// The path of the project to check; if not specified, this will default to the current directory
+    pub path: Option<PathBuf>,
 }
 #[derive(Args, Clone)]
 pub struct HostCli {
@@ -77,6 +90,10 @@ pub struct HostCli {
     /// Defaults to localhost
     #[arg(long)]
     pub public_host: Option<String>,
+
+    /// Watch the project's assets for changes, rebuilding them and reloading them in connected clients
+    #[arg(long)]
+    pub watch: bool,
 }
 
 impl Cli {
@@ -86,6 +103,7 @@ impl Cli {
             Cli::New { .. } => None,
             Cli::Run { run_args, .. } => Some(run_args),
             Cli::Build { .. } => None,
+            Cli::Check { .. } => None,
             Cli::Serve { .. } => None,
             Cli::View { .. } => None,
             Cli::Join { run_args, .. } => Some(run_args),
@@ -99,6 +117,7 @@ impl Cli {
             Cli::New { project_args, .. } => Some(project_args),
             Cli::Run { project_args, .. } => Some(project_args),
             Cli::Build { project_args, .. } => Some(project_args),
+            Cli::Check { .. } => None,
             Cli::Serve { project_args, .. } => Some(project_args),
             Cli::View { project_args, .. } => Some(project_args),
             Cli::Join { .. } => None,
@@ -106,12 +125,20 @@ impl Cli {
             Cli::UpdateInterfaceComponents => None,
         }
     }
+    /// The path of the project the command operates on, if one was given
+    pub fn project_path(&self) -> Option<&PathBuf> {
+        match self {
+            Cli::Check { check_args } => check_args.path.as_ref(),
+            _ => self.project().and_then(|p| p.path.as_ref()),
+        }
+    }
     /// Extract host-relevant state only
     pub fn host(&self) -> Option<&HostCli> {
         match self {
             Cli::New { .. } => None,
             Cli::Run { host_args, .. } => Some(host_args),
             Cli::Build { .. } => None,
+            Cli::Check { .. } => None,
             Cli::Serve { host_args, .. } => Some(host_args),
             Cli::View { .. } => None,
             Cli::Join { .. } => None,
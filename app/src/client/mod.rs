@@ -4,7 +4,7 @@ use ambient_app::AppBuilder;
 use ambient_cameras::UICamera;
 use ambient_core::camera::active_camera;
 use ambient_debugger::Debugger;
-use ambient_ecs::{EntityData, SystemGroup};
+use ambient_ecs::{EntityData, SystemGroup, World};
 use ambient_element::{element_component, Element, ElementComponentExt, Hooks};
 use ambient_network::{
     client::{GameClient, GameClientNetworkStats, GameClientRenderTarget, GameClientServerStats, GameClientView, UseOnce},
@@ -37,7 +37,12 @@ fn MainApp(hooks: &mut Hooks, server_addr: SocketAddr, user_id: String, show_deb
             resolution,
             on_disconnect: cb(move || {}),
             init_world: cb(UseOnce::new(Box::new(move |world, _render_target| {
-                world.add_resource(ambient_network::events::event_registry(), Arc::new(ServerEventRegistry::new()));
+                let registry = ServerEventRegistry::new();
+                registry.register(|world: &mut World, event: shared::reload::AssetsChanged| {
+                    shared::reload::reload_assets(world, &event.paths);
+                    Ok(())
+                });
+                world.add_resource(ambient_network::events::event_registry(), Arc::new(registry));
             }))),
             on_loaded: cb(move |_game_state, _game_client| Ok(Box::new(|| {}))),
             error_view: cb(move |error| Dock(vec![Text::el("Error").header_style(), Text::el(error)]).el()),
@@ -64,7 +64,7 @@ fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     let current_dir = std::env::current_dir()?;
-    let project_path = cli.project().and_then(|p| p.path.clone()).unwrap_or_else(|| current_dir.clone());
+    let project_path = cli.project_path().cloned().unwrap_or_else(|| current_dir.clone());
     let project_path =
         if project_path.is_absolute() { project_path } else { ambient_std::path::normalize(&current_dir.join(project_path)) };
 
@@ -98,6 +98,17 @@ fn main() -> anyhow::Result<()> {
         return Ok(());
     }
 
+    // If check: validate the project and print the report, immediately exit
+    if let Cli::Check { .. } = &cli {
+        let report = ambient_build::check::check(&project_path);
+        report.log();
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        if report.has_errors() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // If a project was specified, assume that assets need to be built
     let manifest = cli
         .project()
@@ -111,7 +122,8 @@ fn main() -> anyhow::Result<()> {
     if let Some(manifest) = manifest.as_ref() {
         let project_name = manifest.project.name.as_deref().unwrap_or("project");
         log::info!("Building {}", project_name);
-        runtime.block_on(ambient_build::build(PhysicsKey.get(&assets), &assets, project_path.clone(), manifest));
+        let clean = cli.project().map(|p| p.clean).unwrap_or_default();
+        runtime.block_on(ambient_build::build(PhysicsKey.get(&assets), &assets, project_path.clone(), manifest, clean));
         log::info!("Done building {}", project_name);
     }
 
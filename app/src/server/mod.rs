@@ -6,12 +6,14 @@ use std::{
     time::SystemTime,
 };
 
-use ambient_core::{app_start_time, asset_cache, dtime, no_sync, time};
+use ambient_core::{app_start_time, asset_cache, async_ecs::async_run, dtime, no_sync, time};
 use ambient_ecs::{ComponentDesc, ComponentRegistry, EntityData, Networked, SystemGroup, World, WorldStreamCompEvent};
 use ambient_network::{
     bi_stream_handlers, datagram_handlers,
+    events::broadcast_event,
     server::{ForkingEvent, GameServer, ShutdownEvent},
 };
+use ambient_physics::physx::PhysicsKey;
 use ambient_prefab::PrefabFromUrl;
 use ambient_std::{
     asset_cache::{AssetCache, AsyncAssetKeyExt, SyncAssetKeyExt},
@@ -58,13 +60,15 @@ pub fn start(
     start_http_interface(runtime, &project_path);
 
     ComponentRegistry::get_mut().add_external(manifest.all_defined_components(false).unwrap());
+    ComponentRegistry::get_mut().add_migrations(manifest.all_defined_migrations(false).unwrap());
 
     let manifest = manifest.clone();
+    let watch = cli.host().map(|h| h.watch).unwrap_or_default();
     runtime.spawn(async move {
         let mut server_world = World::new_with_config("server", true);
         server_world.init_shape_change_tracking();
 
-        server_world.add_components(server_world.resource_entity(), create_resources(assets.clone())).unwrap();
+        server_world.add_components(server_world.resource_entity(), create_resources(assets.clone(), &manifest)).unwrap();
 
         wasm::initialize(&mut server_world, project_path.clone(), &manifest).await.unwrap();
 
@@ -74,6 +78,15 @@ pub fn start(
             let obj = PrefabFromUrl(asset_path.into()).get(&assets).await.unwrap();
             obj.spawn_into_world(&mut server_world, None);
         }
+        if watch {
+            let async_run = server_world.resource(async_run()).clone();
+            tokio::spawn(ambient_build::watch(PhysicsKey.get(&assets), project_path.clone(), move |paths| {
+                async_run.run(move |world| {
+                    shared::reload::reload_assets(world, &paths);
+                    broadcast_event(world, shared::reload::AssetsChanged { paths });
+                });
+            }));
+        }
         log::info!("Starting server");
         server
             .run(server_world, Arc::new(systems), Arc::new(on_forking_systems), Arc::new(on_shutdown_systems), Arc::new(is_sync_component))
@@ -93,7 +106,13 @@ fn systems(_world: &mut World) -> SystemGroup {
             ambient_core::remove_at_time_system(),
             Box::new(ambient_physics::server_systems()),
             Box::new(shared::player::server_systems()),
+            Box::new(ambient_world_audio::environment::server_systems(shared::player::player_camera())),
             Box::new(ambient_prefab::systems()),
+            Box::new(ambient_animation::animation_server_systems()),
+            ambient_physics::apply_motion_system(
+                ambient_animation::animation_root_motion_translation(),
+                ambient_animation::animation_root_motion_rotation(),
+            ),
             Box::new(wasm::systems()),
             Box::new(shared::player::server_systems_final()),
             ambient_physics::run_simulation_system(),
@@ -111,10 +130,12 @@ fn is_sync_component(component: ComponentDesc, _: WorldStreamCompEvent) -> bool
     component.has_attribute::<Networked>()
 }
 
-fn create_resources(assets: AssetCache) -> EntityData {
+fn create_resources(assets: AssetCache, manifest: &ambient_project::Manifest) -> EntityData {
     let mut server_resources = EntityData::new().set(asset_cache(), assets.clone()).set(no_sync(), ());
 
     ambient_physics::create_server_resources(&assets, &mut server_resources);
+    server_resources.set_self(ambient_physics::physics_timestep(), 1. / manifest.physics.simulation_rate.max(1) as f32);
+    server_resources.set_self(ambient_physics::physics_max_substeps(), manifest.physics.max_substeps);
 
     server_resources.append_self(ambient_core::async_ecs::async_ecs_resources());
     server_resources.set_self(ambient_core::runtime(), tokio::runtime::Handle::current());
@@ -122,6 +143,7 @@ fn create_resources(assets: AssetCache) -> EntityData {
     server_resources.set_self(time(), now);
     server_resources.set_self(app_start_time(), now);
     server_resources.set_self(dtime(), 1. / 60.);
+    server_resources.set_self(ambient_animation::animation_events(), Vec::new());
 
     let mut handlers = HashMap::new();
     ambient_network::register_rpc_bi_stream_handler(&mut handlers, shared::create_rpc_registry());
@@ -27,7 +27,13 @@ fn make_components() -> toml_edit::Table {
 
     let namespaces = [
         ("core", "Core", "Contains all core components for the Ambient Runtime."),
+        ("core::animation", "Animation", "Components for animating entities, including animation graphs."),
         ("core::app", "App", "High-level state relevant to the application (including the in-development Editor)."),
+        (
+            "core::audio",
+            "Audio",
+            "Audio mixing, the volumes of the buses sounds are mixed in, and the acoustics of the world.\nThe volumes of the buses can be set on the resources of a client, and on the synced resources of the server for all clients, which are multiplied. A bus is muted if either of them mutes it.",
+        ),
         ("core::camera", "Camera", "Camera matrices, types, parameters, and more."),
         ("core::ecs", "Entity Component System", "Core components for the ECS and entities."),
         ("core::game_objects", "Game Objects", "Pre-defined game objects that implement specific behaviours."),
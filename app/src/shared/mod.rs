@@ -3,6 +3,7 @@ use ambient_rpc::RpcRegistry;
 
 pub mod components;
 pub mod player;
+pub mod reload;
 
 pub fn create_rpc_registry() -> RpcRegistry<GameRpcArgs> {
     let mut reg = RpcRegistry::new();
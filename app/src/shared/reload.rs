@@ -0,0 +1,46 @@
+use ambient_core::asset_cache;
+use ambient_decals::decal;
+use ambient_ecs::{query, World};
+use ambient_model::model_from_url;
+use ambient_prefab::prefab_from_url;
+use serde::{Deserialize, Serialize};
+
+/// Sent to all clients when the server has rebuilt assets in watch mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetsChanged {
+    /// The files that were rebuilt, relative to the build directory
+    pub paths: Vec<String>,
+}
+
+/// Reloads everything in the world that was loaded from one of the changed `paths`
+pub fn reload_assets(world: &mut World, paths: &[String]) {
+    world.resource(asset_cache()).invalidate(|key| paths.iter().any(|path| key.contains(&format!("/{path}"))));
+
+    for (id, url) in query(model_from_url()).collect_cloned(world, None) {
+        if is_affected(asset_crate_root(&url), paths) {
+            world.set(id, model_from_url(), url).ok();
+        }
+    }
+    for (id, url) in query(prefab_from_url()).collect_cloned(world, None) {
+        if is_affected(asset_crate_root(&url), paths) {
+            world.set(id, prefab_from_url(), url).ok();
+        }
+    }
+    for (id, url) in query(decal()).collect_cloned(world, None) {
+        if paths.iter().any(|path| url.to_string().ends_with(&format!("/{path}"))) {
+            world.set(id, decal(), url).ok();
+        }
+    }
+}
+
+/// Model and prefab urls either point to the root of an asset crate, or to a file within it
+fn asset_crate_root(url: &str) -> &str {
+    let root = ["/models/", "/prefabs/"].iter().filter_map(|dir| url.find(dir)).min().map(|i| &url[..i]).unwrap_or(url);
+    root.trim_end_matches('/')
+}
+
+/// Returns true if any of the `paths` (relative to the build directory) are inside `root`, which may be
+/// a relative path or an absolute url
+fn is_affected(root: &str, paths: &[String]) -> bool {
+    paths.iter().any(|path| path.match_indices('/').map(|(i, _)| &path[..i]).any(|dir| root == dir || root.ends_with(&format!("/{dir}"))))
+}
@@ -0,0 +1,503 @@
+use std::{sync::Arc, time::Duration};
+
+use ambient_ecs::{with_component_registry, ComponentDesc};
+use ambient_std::{
+    asset_cache::{AssetCache, AssetKeepalive, AsyncAssetKey, AsyncAssetKeyExt},
+    asset_url::{AbsAssetUrl, AnimationAssetType, TypedAssetUrl},
+    download_asset::{AssetError, JsonFromUrl},
+};
+use anyhow::Context;
+use async_trait::async_trait;
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use super::{AnimationAction, AnimationActionTime, AnimationClipRef};
+
+fn default_one() -> f32 {
+    1.
+}
+fn default_true() -> bool {
+    true
+}
+
+/// A data-driven animation state machine, produced by the `AnimationGraphs` pipeline.
+///
+/// The graph is driven by parameters, which are the values of components on the animated entity
+/// (for instance `my_project::speed`). Only `f32`, `i32`, `u32` and `bool` components can be used as parameters;
+/// missing components read as `0`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationGraph {
+    /// The layers are evaluated in order, and each layer is blended on top of the ones before it.
+    pub layers: Vec<AnimationGraphLayer>,
+}
+impl AnimationGraph {
+    /// Clip urls are relative to the graph file; this makes them absolute
+    pub fn resolve_clips(&mut self, base_url: &AbsAssetUrl) -> anyhow::Result<()> {
+        for layer in &mut self.layers {
+            for state in &mut layer.states {
+                for clip in state.motion.clips_mut() {
+                    *clip = clip.resolve(base_url).with_context(|| format!("Invalid clip url {clip}"))?.into();
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Checks that all states referenced by the graph exist
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for layer in &self.layers {
+            if layer.states.is_empty() {
+                anyhow::bail!("Layer {:?} has no states", layer.name);
+            }
+            let state_names = layer.default_state.iter().chain(layer.transitions.iter().flat_map(|t| t.from.iter().chain([&t.to])));
+            for name in state_names {
+                if layer.state_index(name).is_none() {
+                    anyhow::bail!("Layer {:?} references missing state {name:?}", layer.name);
+                }
+            }
+        }
+        Ok(())
+    }
+    /// The names of all parameters used by the graph, without duplicates
+    pub fn parameters(&self) -> Vec<String> {
+        let mut parameters = Vec::new();
+        for layer in &self.layers {
+            for state in &layer.states {
+                match &state.motion {
+                    AnimationMotion::BlendSpace1D { parameter, .. } => parameters.push(parameter.clone()),
+                    AnimationMotion::BlendSpace2D { parameter_x, parameter_y, .. } => {
+                        parameters.extend([parameter_x.clone(), parameter_y.clone()])
+                    }
+                    AnimationMotion::Empty | AnimationMotion::Clip { .. } => {}
+                }
+            }
+            parameters.extend(layer.transitions.iter().flat_map(|t| t.conditions.iter().map(|c| c.parameter.clone())));
+        }
+        parameters.sort();
+        parameters.dedup();
+        parameters
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationGraphLayer {
+    pub name: String,
+    /// Bind ids of the bones this layer animates, along with all bones below them. The layer animates all bones if this is empty.
+    #[serde(default)]
+    pub mask: Vec<String>,
+    /// How much this layer overrides the layers before it, from 0 to 1. The first layer is always fully applied.
+    #[serde(default = "default_one")]
+    pub weight: f32,
+    /// The state this layer starts in. Defaults to the first state.
+    #[serde(default)]
+    pub default_state: Option<String>,
+    pub states: Vec<AnimationGraphState>,
+    #[serde(default)]
+    pub transitions: Vec<AnimationGraphTransition>,
+}
+impl AnimationGraphLayer {
+    pub fn state_index(&self, name: &str) -> Option<usize> {
+        self.states.iter().position(|state| state.name == name)
+    }
+    fn default_state_index(&self) -> usize {
+        self.default_state.as_ref().and_then(|name| self.state_index(name)).unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationGraphState {
+    pub name: String,
+    pub motion: AnimationMotion,
+    /// Playback speed of the motion.
+    #[serde(default = "default_one")]
+    pub speed: f32,
+    #[serde(default = "default_true")]
+    pub looping: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum AnimationMotion {
+    /// Plays nothing, so that the layers before this one show through.
+    Empty,
+    /// Plays a single clip.
+    Clip { clip: TypedAssetUrl<AnimationAssetType> },
+    /// Blends between the two clips closest to the value of `parameter`.
+    BlendSpace1D { parameter: String, clips: Vec<BlendSpace1DClip> },
+    /// Blends between clips placed in 2D, weighted by their distance to the value of (`parameter_x`, `parameter_y`).
+    BlendSpace2D { parameter_x: String, parameter_y: String, clips: Vec<BlendSpace2DClip> },
+}
+impl AnimationMotion {
+    fn clips_mut(&mut self) -> Vec<&mut TypedAssetUrl<AnimationAssetType>> {
+        match self {
+            AnimationMotion::Empty => Vec::new(),
+            AnimationMotion::Clip { clip } => vec![clip],
+            AnimationMotion::BlendSpace1D { clips, .. } => clips.iter_mut().map(|c| &mut c.clip).collect(),
+            AnimationMotion::BlendSpace2D { clips, .. } => clips.iter_mut().map(|c| &mut c.clip).collect(),
+        }
+    }
+    /// The clips of this motion and their weights, which add up to 1
+    pub fn weights(&self, parameter: impl Fn(&str) -> f32) -> Vec<(TypedAssetUrl<AnimationAssetType>, f32)> {
+        match self {
+            AnimationMotion::Empty => Vec::new(),
+            AnimationMotion::Clip { clip } => vec![(clip.clone(), 1.)],
+            AnimationMotion::BlendSpace1D { parameter: name, clips } => {
+                let value = parameter(name);
+                let mut clips = clips.iter().collect::<Vec<_>>();
+                clips.sort_by(|a, b| a.position.total_cmp(&b.position));
+                let (first, last) = match (clips.first(), clips.last()) {
+                    (Some(first), Some(last)) => (first, last),
+                    _ => return Vec::new(),
+                };
+                if value <= first.position {
+                    return vec![(first.clip.clone(), 1.)];
+                }
+                if value >= last.position {
+                    return vec![(last.clip.clone(), 1.)];
+                }
+                let i = clips.windows(2).position(|w| value <= w[1].position).unwrap();
+                let (a, b) = (clips[i], clips[i + 1]);
+                let p = (value - a.position) / (b.position - a.position);
+                vec![(a.clip.clone(), 1. - p), (b.clip.clone(), p)]
+            }
+            AnimationMotion::BlendSpace2D { parameter_x, parameter_y, clips } => {
+                let value = Vec2::new(parameter(parameter_x), parameter(parameter_y));
+                if let Some(clip) = clips.iter().find(|c| c.position.distance_squared(value) < 1e-6) {
+                    return vec![(clip.clip.clone(), 1.)];
+                }
+                let inverse_distances = clips.iter().map(|c| 1. / c.position.distance_squared(value)).collect::<Vec<_>>();
+                let total = inverse_distances.iter().sum::<f32>();
+                clips.iter().zip(inverse_distances).map(|(c, d)| (c.clip.clone(), d / total)).collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlendSpace1DClip {
+    pub clip: TypedAssetUrl<AnimationAssetType>,
+    pub position: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlendSpace2DClip {
+    pub clip: TypedAssetUrl<AnimationAssetType>,
+    pub position: Vec2,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationGraphTransition {
+    /// The state to transition from. The transition can be taken from any other state if this isn't set.
+    #[serde(default)]
+    pub from: Option<String>,
+    pub to: String,
+    /// All conditions have to be met for the transition to be taken.
+    #[serde(default)]
+    pub conditions: Vec<AnimationGraphCondition>,
+    /// The duration of the crossfade between the two states, in seconds.
+    #[serde(default)]
+    pub duration: f32,
+    /// If set, the transition can only be taken once the current state has played for this many seconds.
+    #[serde(default)]
+    pub exit_time: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationGraphCondition {
+    pub parameter: String,
+    pub op: AnimationGraphConditionOp,
+    pub value: f32,
+}
+impl AnimationGraphCondition {
+    pub fn is_met(&self, parameter: impl Fn(&str) -> f32) -> bool {
+        let value = parameter(&self.parameter);
+        match self.op {
+            AnimationGraphConditionOp::Greater => value > self.value,
+            AnimationGraphConditionOp::Less => value < self.value,
+            AnimationGraphConditionOp::Equal => value == self.value,
+            AnimationGraphConditionOp::NotEqual => value != self.value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnimationGraphConditionOp {
+    Greater,
+    Less,
+    Equal,
+    NotEqual,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationGraphFromUrl(pub AbsAssetUrl);
+#[async_trait]
+impl AsyncAssetKey<Result<Arc<AnimationGraph>, AssetError>> for AnimationGraphFromUrl {
+    fn keepalive(&self) -> AssetKeepalive {
+        // Graphs are only peeked, like the clips they reference
+        AssetKeepalive::Forever
+    }
+    async fn load(self, assets: AssetCache) -> Result<Arc<AnimationGraph>, AssetError> {
+        let graph = JsonFromUrl::<AnimationGraph>::new(self.0.clone(), true).get(&assets).await?;
+        graph.validate()?;
+        let mut graph = (*graph).clone();
+        graph.resolve_clips(&self.0)?;
+        Ok(Arc::new(graph))
+    }
+}
+
+/// The runtime state of an [AnimationGraph] on an entity. Graphs are only evaluated on the server, which networks the clips
+/// the layers play as [AnimationGraphLayerActions].
+#[derive(Debug, Clone)]
+pub struct AnimationGraphPlayback {
+    pub graph: Arc<AnimationGraph>,
+    /// The components the parameters are read from, which are looked up once when the playback is created. Parameters
+    /// without a component aren't included, and read as `0`
+    pub parameters: Vec<(String, ComponentDesc)>,
+    pub layers: Vec<AnimationLayerPlayback>,
+}
+impl AnimationGraphPlayback {
+    pub fn new(graph: Arc<AnimationGraph>, time: Duration) -> Self {
+        let parameters = with_component_registry(|r| {
+            graph.parameters().into_iter().filter_map(|name| r.get_by_path(&name).map(|desc| (name, desc))).collect()
+        });
+        let layers = graph
+            .layers
+            .iter()
+            .map(|layer| AnimationLayerPlayback {
+                state: layer.default_state_index(),
+                start_time: time,
+                previous: None,
+                fade_duration: 0.,
+                actions: Vec::new(),
+            })
+            .collect();
+        Self { graph, parameters, layers }
+    }
+    /// Advances all layers to `time`, takes the first transition whose conditions are met, and updates the actions of
+    /// each layer
+    pub fn advance(&mut self, time: Duration, parameter: impl Fn(&str) -> f32) {
+        for (layer, playback) in self.graph.layers.iter().zip(self.layers.iter_mut()) {
+            let fade_time = time.saturating_sub(playback.start_time).as_secs_f32();
+            if fade_time >= playback.fade_duration {
+                playback.previous = None;
+            }
+
+            let current = &layer.states[playback.state];
+            let state_time = fade_time * current.speed;
+            let transition = layer.transitions.iter().find(|t| {
+                let from_current = match &t.from {
+                    Some(from) => from == &current.name,
+                    None => t.to != current.name,
+                };
+                from_current
+                    && t.exit_time.map(|exit_time| state_time >= exit_time).unwrap_or(true)
+                    && t.conditions.iter().all(|c| c.is_met(&parameter))
+            });
+            if let Some(transition) = transition {
+                if let Some(to) = layer.state_index(&transition.to) {
+                    playback.previous = if transition.duration > 0. { Some((playback.state, playback.start_time)) } else { None };
+                    playback.state = to;
+                    playback.start_time = time;
+                    playback.fade_duration = transition.duration;
+                }
+            }
+
+            let fade = if playback.previous.is_some() {
+                (time.saturating_sub(playback.start_time).as_secs_f32() / playback.fade_duration).min(1.)
+            } else {
+                1.
+            };
+            // The actions are timed from the start of their state, so that they only change when the weights do
+            let state_actions = |state: &AnimationGraphState, start_time: Duration, weight: f32| {
+                let (looping, speed) = (state.looping, state.speed);
+                state.motion.weights(&parameter).into_iter().map(move |(clip, clip_weight)| AnimationAction {
+                    clip: AnimationClipRef::FromModelAsset(clip),
+                    time: AnimationActionTime::Offset { start_time, speed },
+                    looping,
+                    weight: clip_weight * weight,
+                })
+            };
+            let mut actions = state_actions(&layer.states[playback.state], playback.start_time, fade).collect::<Vec<_>>();
+            if let Some((state, start_time)) = playback.previous {
+                actions.extend(state_actions(&layer.states[state], start_time, 1. - fade));
+            }
+            playback.actions = actions;
+        }
+    }
+    /// The clips played by each layer, which are networked to the clients to be blended
+    pub fn layer_actions(&self) -> Vec<AnimationGraphLayerActions> {
+        self.graph
+            .layers
+            .iter()
+            .zip(self.layers.iter())
+            .map(|(layer, playback)| AnimationGraphLayerActions {
+                mask: layer.mask.clone(),
+                weight: layer.weight,
+                actions: playback.actions.clone(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationLayerPlayback {
+    /// The index of the current state
+    pub state: usize,
+    /// When the current state started, which is also when the crossfade to it started
+    pub start_time: Duration,
+    /// The state being faded out, and when it started
+    pub previous: Option<(usize, Duration)>,
+    pub fade_duration: f32,
+    /// The clips to blend for this layer
+    pub actions: Vec<AnimationAction>,
+}
+
+/// The clips played by a layer of an [AnimationGraph]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnimationGraphLayerActions {
+    /// The [AnimationGraphLayer::mask] of the layer
+    pub mask: Vec<String>,
+    /// The [AnimationGraphLayer::weight] of the layer
+    pub weight: f32,
+    pub actions: Vec<AnimationAction>,
+}
+
+#[cfg(test)]
+fn weights(actions: &[AnimationAction]) -> Vec<f32> {
+    actions.iter().map(|a| a.weight).collect()
+}
+
+#[test]
+fn test_animation_graph() {
+    let graph: AnimationGraph = serde_json::from_str(
+        r#"{
+            "layers": [{
+                "name": "base",
+                "states": [
+                    { "name": "idle", "motion": { "type": "Clip", "clip": "https://example.com/idle.anim" } },
+                    { "name": "move", "motion": { "type": "BlendSpace1D", "parameter": "speed", "clips": [
+                        { "clip": "https://example.com/walk.anim", "position": 1 },
+                        { "clip": "https://example.com/run.anim", "position": 3 }
+                    ] } }
+                ],
+                "transitions": [
+                    { "from": "idle", "to": "move", "conditions": [{ "parameter": "speed", "op": "Greater", "value": 0.1 }], "duration": 0.5 },
+                    { "to": "idle", "conditions": [{ "parameter": "speed", "op": "Less", "value": 0.1 }] }
+                ]
+            }]
+        }"#,
+    )
+    .unwrap();
+    graph.validate().unwrap();
+    assert_eq!(graph.parameters(), vec!["speed".to_string()]);
+
+    let mut playback = AnimationGraphPlayback::new(Arc::new(graph), Duration::ZERO);
+    playback.advance(Duration::from_millis(100), |_| 0.);
+    assert_eq!(playback.layers[0].state, 0);
+    assert_eq!(playback.layers[0].actions.len(), 1);
+
+    // Halfway between walk and run, crossfading from idle
+    playback.advance(Duration::from_millis(200), |_| 2.);
+    playback.advance(Duration::from_millis(450), |_| 2.);
+    let layer = &playback.layers[0];
+    assert_eq!(layer.state, 1);
+    assert_eq!(weights(&layer.actions), vec![0.25, 0.25, 0.5]);
+    assert_eq!(layer.actions[0].time, AnimationActionTime::Offset { start_time: Duration::from_millis(200), speed: 1. });
+
+    // The actions don't change while the parameters and weights stay the same
+    playback.advance(Duration::from_millis(700), |_| 2.);
+    let actions = playback.layers[0].actions.clone();
+    assert_eq!(weights(&actions), vec![0.5, 0.5]);
+    playback.advance(Duration::from_millis(800), |_| 2.);
+    assert_eq!(playback.layers[0].actions, actions);
+
+    playback.advance(Duration::from_millis(900), |_| 0.);
+    assert_eq!(playback.layers[0].state, 0);
+    assert!(playback.layers[0].previous.is_none());
+}
+
+#[test]
+fn test_animation_graph_conditions() {
+    let condition = |op, value| AnimationGraphCondition { parameter: "x".to_string(), op, value };
+    assert!(condition(AnimationGraphConditionOp::Greater, 1.).is_met(|_| 2.));
+    assert!(!condition(AnimationGraphConditionOp::Greater, 2.).is_met(|_| 2.));
+    assert!(condition(AnimationGraphConditionOp::Less, 3.).is_met(|_| 2.));
+    assert!(!condition(AnimationGraphConditionOp::Less, 2.).is_met(|_| 2.));
+    assert!(condition(AnimationGraphConditionOp::Equal, 2.).is_met(|_| 2.));
+    assert!(!condition(AnimationGraphConditionOp::NotEqual, 2.).is_met(|_| 2.));
+    // Missing parameters read as 0
+    assert!(condition(AnimationGraphConditionOp::Equal, 0.).is_met(|_| 0.));
+}
+
+#[test]
+fn test_animation_graph_blend_space_2d() {
+    let clip = |name: &str| TypedAssetUrl::<AnimationAssetType>::parse(format!("https://example.com/{name}.anim")).unwrap();
+    let motion = AnimationMotion::BlendSpace2D {
+        parameter_x: "x".to_string(),
+        parameter_y: "y".to_string(),
+        clips: vec![
+            BlendSpace2DClip { clip: clip("left"), position: Vec2::new(-1., 0.) },
+            BlendSpace2DClip { clip: clip("right"), position: Vec2::new(1., 0.) },
+        ],
+    };
+    let at = |x: f32, y: f32| motion.weights(|name| if name == "x" { x } else { y }).into_iter().map(|(_, w)| w).collect::<Vec<_>>();
+    assert_eq!(at(-1., 0.), vec![1.]);
+    assert_eq!(at(0., 1.), vec![0.5, 0.5]);
+    let weights = at(0.5, 0.);
+    assert!(weights[1] > weights[0]);
+    assert!((weights.iter().sum::<f32>() - 1.).abs() < 1e-5);
+}
+
+#[test]
+fn test_animation_graph_exit_time_and_layers() {
+    let graph: AnimationGraph = serde_json::from_str(
+        r#"{
+            "layers": [{
+                "name": "base",
+                "default_state": "jump",
+                "states": [
+                    { "name": "idle", "motion": { "type": "Clip", "clip": "https://example.com/idle.anim" } },
+                    { "name": "jump", "motion": { "type": "Clip", "clip": "https://example.com/jump.anim" }, "speed": 2, "looping": false }
+                ],
+                "transitions": [{ "from": "jump", "to": "idle", "exit_time": 1 }]
+            }, {
+                "name": "upper",
+                "mask": ["Spine"],
+                "weight": 0.5,
+                "states": [{ "name": "empty", "motion": { "type": "Empty" } }]
+            }]
+        }"#,
+    )
+    .unwrap();
+    graph.validate().unwrap();
+    assert!(graph.parameters().is_empty());
+
+    let mut playback = AnimationGraphPlayback::new(Arc::new(graph), Duration::ZERO);
+    // The jump plays at twice the speed, so it reaches its exit time after half a second
+    playback.advance(Duration::from_millis(400), |_| 0.);
+    assert_eq!(playback.layers[0].state, 1);
+    assert!(!playback.layers[0].actions[0].looping);
+    playback.advance(Duration::from_millis(500), |_| 0.);
+    assert_eq!(playback.layers[0].state, 0);
+
+    let layers = playback.layer_actions();
+    assert_eq!(layers.len(), 2);
+    assert!(layers[0].mask.is_empty());
+    assert_eq!(layers[0].weight, 1.);
+    assert_eq!(layers[1].mask, vec!["Spine".to_string()]);
+    assert_eq!(layers[1].weight, 0.5);
+    assert!(layers[1].actions.is_empty());
+}
+
+#[test]
+fn test_animation_graph_validate() {
+    let graph = |layer: &str| serde_json::from_str::<AnimationGraph>(&format!(r#"{{ "layers": [{layer}] }}"#)).unwrap();
+    assert!(graph(r#"{ "name": "base", "states": [] }"#).validate().is_err());
+    let missing_state = r#"{
+        "name": "base",
+        "states": [{ "name": "idle", "motion": { "type": "Empty" } }],
+        "transitions": [{ "from": "idle", "to": "run" }]
+    }"#;
+    assert!(graph(missing_state).validate().is_err());
+    let missing_default = r#"{ "name": "base", "default_state": "run", "states": [{ "name": "idle", "motion": { "type": "Empty" } }] }"#;
+    assert!(graph(missing_default).validate().is_err());
+}
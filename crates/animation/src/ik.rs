@@ -0,0 +1,314 @@
+use ambient_core::{
+    hierarchy::parent,
+    transform::{local_to_parent, local_to_world, rotation, scale, translation},
+};
+use ambient_ecs::{query, EntityId, SystemGroup, World};
+use ambient_model::animation_binder;
+use glam::{Mat4, Quat, Vec3};
+
+use crate::{
+    ik_chain, ik_chain_length, ik_chain_target, ik_chain_weight, ik_look_at, ik_look_at_axis, ik_look_at_target, ik_look_at_weight,
+    ik_model, ik_two_bone, ik_two_bone_pole, ik_two_bone_target, ik_two_bone_weight,
+};
+
+const FABRIK_ITERATIONS: usize = 10;
+const FABRIK_TOLERANCE: f32 = 0.001;
+
+/// Adjusts the pose of skinned models after the animations have been blended. The constraints are on the model entity itself,
+/// or on other entities which point to it with `ik_model`.
+pub fn ik_systems() -> SystemGroup {
+    SystemGroup::new(
+        "ik",
+        vec![
+            query((ik_two_bone(), ik_two_bone_target())).to_system(|q, world, qs, _| {
+                for (id, (bind_id, target)) in q.collect_cloned(world, qs) {
+                    let weight = world.get(id, ik_two_bone_weight()).unwrap_or(1.);
+                    let pole = world.get(id, ik_two_bone_pole()).ok();
+                    if let Some(end) = bone(world, id, &bind_id) {
+                        solve_two_bone(world, end, target, pole, weight);
+                    }
+                }
+            }),
+            query((ik_chain(), ik_chain_target())).to_system(|q, world, qs, _| {
+                for (id, (bind_id, target)) in q.collect_cloned(world, qs) {
+                    let weight = world.get(id, ik_chain_weight()).unwrap_or(1.);
+                    let length = world.get(id, ik_chain_length()).unwrap_or(2) as usize;
+                    if let Some(end) = bone(world, id, &bind_id) {
+                        solve_chain(world, end, length, target, weight);
+                    }
+                }
+            }),
+            // Runs last, so that heads can look at things while the rest of the body reaches for others
+            query((ik_look_at(), ik_look_at_target())).to_system(|q, world, qs, _| {
+                for (id, (bind_id, target)) in q.collect_cloned(world, qs) {
+                    let weight = world.get(id, ik_look_at_weight()).unwrap_or(1.);
+                    let axis = world.get(id, ik_look_at_axis()).unwrap_or(Vec3::Z);
+                    if let Some(bone) = bone(world, id, &bind_id) {
+                        solve_look_at(world, bone, axis, target, weight);
+                    }
+                }
+            }),
+        ],
+    )
+}
+
+/// The entity of the bone with the bind id, in the model the constraints of `id` are applied to
+fn bone(world: &World, id: EntityId, bind_id: &str) -> Option<EntityId> {
+    let model = world.get(id, ik_model()).unwrap_or(id);
+    world.get_ref(model, animation_binder()).ok()?.get(bind_id).copied()
+}
+
+/// The transforms aren't updated until after the animations, so they're computed from the local transforms of the bones
+pub(crate) fn world_transform(world: &World, id: EntityId) -> Mat4 {
+    let parent_transform = match world.get(id, parent()) {
+        Ok(parent) => world_transform(world, parent),
+        Err(_) => return world.get(id, local_to_world()).unwrap_or_default(),
+    };
+    let local_transform =
+        if world.has_component(id, translation()) || world.has_component(id, rotation()) || world.has_component(id, scale()) {
+            Mat4::from_scale_rotation_translation(
+                world.get(id, scale()).unwrap_or(Vec3::ONE),
+                world.get(id, rotation()).unwrap_or_default(),
+                world.get(id, translation()).unwrap_or_default(),
+            )
+        } else {
+            world.get(id, local_to_parent()).unwrap_or_default()
+        };
+    parent_transform * local_transform
+}
+
+fn parent_world_rotation(world: &World, id: EntityId) -> Quat {
+    match world.get(id, parent()) {
+        Ok(parent) => world_transform(world, parent).to_scale_rotation_translation().1,
+        Err(_) => Quat::IDENTITY,
+    }
+}
+
+/// Sets the world rotations of the bones, which are ordered from parent to child, blended with their current ones by `weight`
+fn set_world_rotations(world: &mut World, bones: &[(EntityId, Quat, Quat)], weight: f32) {
+    let mut parent_rotation = match bones.first() {
+        Some((id, ..)) => parent_world_rotation(world, *id),
+        None => return,
+    };
+    for (i, &(id, current, solved)) in bones.iter().enumerate() {
+        if i > 0 && world.get(id, parent()).ok() != Some(bones[i - 1].0) {
+            parent_rotation = parent_world_rotation(world, id);
+        }
+        let rotation_ = slerp(current, solved, weight.clamp(0., 1.));
+        world.set(id, rotation(), (parent_rotation.inverse() * rotation_).normalize()).ok();
+        parent_rotation = rotation_;
+    }
+}
+
+fn slerp(from: Quat, to: Quat, p: f32) -> Quat {
+    if from.dot(to) < 0. {
+        from.slerp(-to, p)
+    } else {
+        from.slerp(to, p)
+    }
+}
+
+fn perpendicular(v: Vec3) -> Vec3 {
+    let other = if v.x.abs() < 0.9 { Vec3::X } else { Vec3::Y };
+    v.cross(other).normalize()
+}
+
+/// Bends the grandparent and parent of `end` so that `end` reaches `target`, with the parent pointing towards `pole` if it's set
+fn solve_two_bone(world: &mut World, end: EntityId, target: Vec3, pole: Option<Vec3>, weight: f32) -> Option<()> {
+    let mid = world.get(end, parent()).ok()?;
+    let root = world.get(mid, parent()).ok()?;
+    let (_, root_rotation, a) = world_transform(world, root).to_scale_rotation_translation();
+    let (_, mid_rotation, b) = world_transform(world, mid).to_scale_rotation_translation();
+    let c = world_transform(world, end).w_axis.truncate();
+    let (lab, lcb) = ((b - a).length(), (c - b).length());
+    if lab == 0. || lcb == 0. {
+        return None;
+    }
+    let lat = (target - a).length().clamp(0.001, lab + lcb - 0.001);
+
+    // Bend the middle joint so that the end is as far from the root as the target
+    let current_angle = (a - b).normalize().dot((c - b).normalize()).clamp(-1., 1.).acos();
+    let angle = ((lab * lab + lcb * lcb - lat * lat) / (2. * lab * lcb)).clamp(-1., 1.).acos();
+    let mut axis = (a - b).cross(c - b);
+    if axis.length_squared() < 1e-8 {
+        axis = pole.map(|pole| (a - b).cross(pole - b)).filter(|axis| axis.length_squared() > 1e-8).unwrap_or_else(|| perpendicular(a - b));
+    }
+    let bend = Quat::from_axis_angle(axis.normalize(), angle - current_angle);
+    let c = b + bend * (c - b);
+    let mut mid_solved = bend * mid_rotation;
+
+    // Then rotate the whole chain around the root to point it at the target
+    let aim = match ((c - a).try_normalize(), (target - a).try_normalize()) {
+        (Some(from), Some(to)) => Quat::from_rotation_arc(from, to),
+        _ => Quat::IDENTITY,
+    };
+    let mut root_solved = aim * root_rotation;
+    mid_solved = aim * mid_solved;
+
+    // And twist it around the line to the target so that the middle joint points towards the pole
+    if let Some(pole) = pole {
+        let axis = (target - a).normalize_or_zero();
+        let b = a + aim * (b - a);
+        let project = |v: Vec3| v - axis * axis.dot(v);
+        let (from, to) = (project(b - a), project(pole - a));
+        if axis != Vec3::ZERO && from.length_squared() > 1e-8 && to.length_squared() > 1e-8 {
+            let twist = Quat::from_axis_angle(axis, axis.dot(from.cross(to)).atan2(from.dot(to)));
+            root_solved = twist * root_solved;
+            mid_solved = twist * mid_solved;
+        }
+    }
+    set_world_rotations(world, &[(root, root_rotation, root_solved), (mid, mid_rotation, mid_solved)], weight);
+    Some(())
+}
+
+/// Turns `bone` so that its `axis` faces `target`
+fn solve_look_at(world: &mut World, bone: EntityId, axis: Vec3, target: Vec3, weight: f32) -> Option<()> {
+    let (_, current, position) = world_transform(world, bone).to_scale_rotation_translation();
+    let forward = (current * axis).try_normalize()?;
+    let direction = (target - position).try_normalize()?;
+    set_world_rotations(world, &[(bone, current, Quat::from_rotation_arc(forward, direction) * current)], weight);
+    Some(())
+}
+
+/// Bends `length` bones above `end` so that `end` reaches `target`, using FABRIK
+fn solve_chain(world: &mut World, end: EntityId, length: usize, target: Vec3, weight: f32) -> Option<()> {
+    let mut bones = vec![end];
+    for _ in 0..length {
+        bones.insert(0, world.get(bones[0], parent()).ok()?);
+    }
+    let transforms = bones.iter().map(|id| world_transform(world, *id).to_scale_rotation_translation()).collect::<Vec<_>>();
+    let positions = transforms.iter().map(|(_, _, position)| *position).collect::<Vec<_>>();
+    let lengths = positions.windows(2).map(|w| (w[1] - w[0]).length()).collect::<Vec<_>>();
+
+    let mut solved = positions.clone();
+    let root = positions[0];
+    if (target - root).length() >= lengths.iter().sum::<f32>() {
+        // Out of reach, so the chain is stretched towards the target
+        let direction = (target - root).try_normalize()?;
+        for i in 1..solved.len() {
+            solved[i] = solved[i - 1] + direction * lengths[i - 1];
+        }
+    } else {
+        for _ in 0..FABRIK_ITERATIONS {
+            let last = solved.len() - 1;
+            solved[last] = target;
+            for i in (0..last).rev() {
+                solved[i] = solved[i + 1] + (solved[i] - solved[i + 1]).normalize_or_zero() * lengths[i];
+            }
+            solved[0] = root;
+            for i in 1..solved.len() {
+                solved[i] = solved[i - 1] + (solved[i] - solved[i - 1]).normalize_or_zero() * lengths[i - 1];
+            }
+            if (solved[last] - target).length() < FABRIK_TOLERANCE {
+                break;
+            }
+        }
+    }
+
+    // Each bone is rotated so that it points to its solved child; the end bone keeps its rotation relative to its parent
+    let rotations = (0..bones.len() - 1)
+        .map(|i| {
+            let (from, to) = (positions[i + 1] - positions[i], solved[i + 1] - solved[i]);
+            let current = transforms[i].1;
+            let solved = match (from.try_normalize(), to.try_normalize()) {
+                (Some(from), Some(to)) => Quat::from_rotation_arc(from, to) * current,
+                _ => current,
+            };
+            (bones[i], current, solved)
+        })
+        .collect::<Vec<_>>();
+    set_world_rotations(world, &rotations, weight);
+    Some(())
+}
+
+/// Spawns a chain of bones under a model at the origin, each offset from its parent, and returns them from the root
+#[cfg(test)]
+fn spawn_bones(world: &mut World, offsets: &[Vec3]) -> Vec<EntityId> {
+    use ambient_ecs::EntityData;
+
+    ambient_core::init_all_components();
+    let mut bones = vec![EntityData::new().set(local_to_world(), Mat4::IDENTITY).spawn(world)];
+    for offset in offsets {
+        let bone = EntityData::new().set(parent(), *bones.last().unwrap()).set(translation(), *offset).set(rotation(), Quat::IDENTITY);
+        bones.push(bone.spawn(world));
+    }
+    bones.remove(0);
+    bones
+}
+
+#[cfg(test)]
+fn world_position(world: &World, id: EntityId) -> Vec3 {
+    world_transform(world, id).w_axis.truncate()
+}
+
+#[test]
+fn test_solve_two_bone() {
+    let mut world = World::new("test_solve_two_bone");
+    let bones = spawn_bones(&mut world, &[Vec3::ZERO, Vec3::Y, Vec3::Y]);
+    let target = Vec3::new(1., 1., 0.);
+    solve_two_bone(&mut world, bones[2], target, Some(Vec3::new(0., 0., 5.)), 1.).unwrap();
+    assert!(world_position(&world, bones[2]).distance(target) < 1e-3);
+    // The bones keep their lengths, and the knee points towards the pole
+    let mid = world_position(&world, bones[1]);
+    assert!((mid.length() - 1.).abs() < 1e-3);
+    assert!(mid.z > 0.5);
+
+    let mut world = World::new("test_solve_two_bone");
+    let bones = spawn_bones(&mut world, &[Vec3::ZERO, Vec3::Y, Vec3::Y]);
+    solve_two_bone(&mut world, bones[2], target, Some(Vec3::new(0., 0., -5.)), 1.).unwrap();
+    assert!(world_position(&world, bones[2]).distance(target) < 1e-3);
+    assert!(world_position(&world, bones[1]).z < -0.5);
+}
+
+#[test]
+fn test_solve_two_bone_out_of_reach() {
+    let mut world = World::new("test_solve_two_bone_out_of_reach");
+    let bones = spawn_bones(&mut world, &[Vec3::ZERO, Vec3::Y, Vec3::Y]);
+    // The chain is stretched as far as it goes towards the target
+    solve_two_bone(&mut world, bones[2], Vec3::new(5., 0., 0.), None, 1.).unwrap();
+    assert!(world_position(&world, bones[2]).distance(Vec3::new(2., 0., 0.)) < 0.1);
+
+    // Without any weight, the pose is kept
+    let mut world = World::new("test_solve_two_bone_out_of_reach");
+    let bones = spawn_bones(&mut world, &[Vec3::ZERO, Vec3::Y, Vec3::Y]);
+    solve_two_bone(&mut world, bones[2], Vec3::new(5., 0., 0.), None, 0.).unwrap();
+    assert!(world_position(&world, bones[2]).distance(Vec3::new(0., 2., 0.)) < 1e-5);
+}
+
+#[test]
+fn test_solve_look_at() {
+    let mut world = World::new("test_solve_look_at");
+    let bones = spawn_bones(&mut world, &[Vec3::Y]);
+    solve_look_at(&mut world, bones[0], Vec3::Z, Vec3::new(5., 1., 0.), 1.).unwrap();
+    let rotation = world_transform(&world, bones[0]).to_scale_rotation_translation().1;
+    assert!((rotation * Vec3::Z).distance(Vec3::X) < 1e-4);
+
+    // Half the weight turns it halfway
+    let mut world = World::new("test_solve_look_at");
+    let bones = spawn_bones(&mut world, &[Vec3::Y]);
+    solve_look_at(&mut world, bones[0], Vec3::Z, Vec3::new(5., 1., 0.), 0.5).unwrap();
+    let rotation = world_transform(&world, bones[0]).to_scale_rotation_translation().1;
+    assert!(((rotation * Vec3::Z).angle_between(Vec3::X) - std::f32::consts::FRAC_PI_4).abs() < 1e-4);
+}
+
+#[test]
+fn test_solve_chain() {
+    let mut world = World::new("test_solve_chain");
+    let bones = spawn_bones(&mut world, &[Vec3::ZERO, Vec3::Y, Vec3::Y, Vec3::Y]);
+    let target = Vec3::new(1., 1.5, 0.5);
+    solve_chain(&mut world, bones[3], 3, target, 1.).unwrap();
+    assert!(world_position(&world, bones[3]).distance(target) < 0.01);
+    let positions = bones.iter().map(|id| world_position(&world, *id)).collect::<Vec<_>>();
+    for w in positions.windows(2) {
+        assert!((w[0].distance(w[1]) - 1.).abs() < 1e-3);
+    }
+
+    // Out of reach, the chain points straight at the target
+    let mut world = World::new("test_solve_chain");
+    let bones = spawn_bones(&mut world, &[Vec3::ZERO, Vec3::Y, Vec3::Y, Vec3::Y]);
+    solve_chain(&mut world, bones[3], 3, Vec3::new(0., 0., 10.), 1.).unwrap();
+    assert!(world_position(&world, bones[3]).distance(Vec3::new(0., 0., 3.)) < 1e-3);
+
+    // The chain can't be longer than the hierarchy
+    assert!(solve_chain(&mut world, bones[3], 5, Vec3::ZERO, 1.).is_none());
+}
@@ -1,26 +1,42 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::Arc,
     time::{Duration, SystemTime},
 };
 
-use ambient_core::{asset_cache, hierarchy::children, time};
-use ambient_ecs::{components, query, Debuggable, EntityId, MakeDefault, Networked, Store, SystemGroup};
+use ambient_core::{
+    asset_cache,
+    hierarchy::{children, parent},
+    time,
+    transform::{rotation, scale},
+};
+use ambient_ecs::{
+    components, query, Component, ComponentDesc, Debuggable, Description, DynSystem, EntityData, EntityId, FnSystem, MakeDefault, Name,
+    Networked, Store, SystemGroup, World,
+};
 use ambient_model::{animation_binder, model, model_from_url, ModelFromUrl};
 use ambient_std::{
     asset_cache::{AssetCache, AsyncAssetKeyExt},
-    asset_url::{AnimationAssetType, ModelAssetType, TypedAssetUrl},
+    asset_url::{AnimationAssetType, AnimationGraphAssetType, ModelAssetType, TypedAssetUrl},
 };
 use ambient_ui::Editable;
 use convert_case::{Case, Casing};
 use derive_more::Display;
+use glam::{Quat, Vec3};
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
+mod graph;
+mod ik;
+mod pose_override;
 mod resources;
 mod retargeting;
+mod skeleton;
 
+pub use graph::*;
 pub use resources::*;
 pub use retargeting::*;
+pub use skeleton::*;
 
 components!("animation", {
     @[Debuggable, Networked, Store]
@@ -40,6 +56,128 @@ components!("animation", {
     /// This is a shorthand for working directly with the animation_controller
     @[MakeDefault, Editable, Debuggable, Networked, Store]
     loop_animation: TypedAssetUrl<AnimationAssetType>,
+
+    @[
+        Debuggable, Networked, Store,
+        Name["Animation graph"],
+        Description["Animate this entity with the animation graph at the given URL. The graph is driven by the values of the components it uses as parameters."]
+    ]
+    animation_graph: TypedAssetUrl<AnimationGraphAssetType>,
+    /// The state of the animation graph of this entity, which is only evaluated on the server
+    @[Debuggable]
+    animation_graph_playback: AnimationGraphPlayback,
+    /// The clips played by each layer of the animation graph of this entity, which the clients blend
+    @[Debuggable, Networked, Store]
+    animation_graph_actions: Vec<AnimationGraphLayerActions>,
+
+    /// The last played time of the clips that fire events on this entity
+    @[Debuggable]
+    animation_event_times: HashMap<String, f32>,
+    /// The last played time of the clips that move this entity with their root motion
+    @[Debuggable]
+    animation_root_motion_times: HashMap<String, f32>,
+    /// Resource: the animation events fired this frame. Events are only recorded if this resource exists
+    @[Debuggable]
+    animation_events: Vec<FiredAnimationEvent>,
+
+    /// The root motion of the clips played this frame, in world space. It's applied to the translation of the entity by the
+    /// physics, which moves its character controller instead if it has one
+    @[Debuggable]
+    animation_root_motion_translation: Vec3,
+    /// The root motion of the clips played this frame, which is applied to the rotation of the entity like the translation
+    @[Debuggable]
+    animation_root_motion_rotation: Quat,
+
+    @[
+        Debuggable, Networked, Store,
+        Name["IK model"],
+        Description["The model entity the IK constraints of this entity are applied to. If it's not set, they're applied to this entity's own model."]
+    ]
+    ik_model: EntityId,
+    @[
+        Debuggable, Networked, Store,
+        Name["IK two-bone end"],
+        Description["The bind id of the end bone of a two-bone IK chain, such as a hand or a foot. Its parent and grandparent are bent so that it reaches `ik_two_bone_target`."]
+    ]
+    ik_two_bone: String,
+    @[
+        Debuggable, Networked, Store,
+        Name["IK two-bone target"],
+        Description["The world position the end bone of the two-bone IK chain reaches for."]
+    ]
+    ik_two_bone_target: Vec3,
+    @[
+        Debuggable, Networked, Store,
+        Name["IK two-bone pole"],
+        Description["The world position the middle joint of the two-bone IK chain, such as an elbow or a knee, points towards."]
+    ]
+    ik_two_bone_pole: Vec3,
+    @[
+        Debuggable, Networked, Store,
+        Name["IK two-bone weight"],
+        Description["How much the two-bone IK overrides the animation, from 0 to 1. Defaults to 1."]
+    ]
+    ik_two_bone_weight: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["IK look-at bone"],
+        Description["The bind id of a bone, such as the head, which is turned to face `ik_look_at_target`."]
+    ]
+    ik_look_at: String,
+    @[
+        Debuggable, Networked, Store,
+        Name["IK look-at target"],
+        Description["The world position the look-at bone faces."]
+    ]
+    ik_look_at_target: Vec3,
+    @[
+        Debuggable, Networked, Store,
+        Name["IK look-at axis"],
+        Description["The axis of the look-at bone, in its own space, which is turned towards the target. Defaults to Z."]
+    ]
+    ik_look_at_axis: Vec3,
+    @[
+        Debuggable, Networked, Store,
+        Name["IK look-at weight"],
+        Description["How much the look-at IK overrides the animation, from 0 to 1. Defaults to 1."]
+    ]
+    ik_look_at_weight: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["IK chain end"],
+        Description["The bind id of the end bone of an IK chain solved with FABRIK. The `ik_chain_length` bones above it are bent so that it reaches `ik_chain_target`."]
+    ]
+    ik_chain: String,
+    @[
+        Debuggable, Networked, Store,
+        Name["IK chain length"],
+        Description["The number of bones above the end bone of the IK chain that are bent. Defaults to 2."]
+    ]
+    ik_chain_length: u32,
+    @[
+        Debuggable, Networked, Store,
+        Name["IK chain target"],
+        Description["The world position the end bone of the IK chain reaches for."]
+    ]
+    ik_chain_target: Vec3,
+    @[
+        Debuggable, Networked, Store,
+        Name["IK chain weight"],
+        Description["How much the chain IK overrides the animation, from 0 to 1. Defaults to 1."]
+    ]
+    ik_chain_weight: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Pose override of"],
+        Description["The entity whose bone `pose_override_bone` is moved to the world translation and rotation of this entity after the animations and IK, such as by a link of a ragdoll."]
+    ]
+    pose_override_of: EntityId,
+    @[
+        Debuggable, Networked, Store,
+        Name["Pose override bone"],
+        Description["The bind id of the bone of `pose_override_of` that is posed by this entity."]
+    ]
+    pose_override_bone: String,
 });
 
 // Running
@@ -106,6 +244,14 @@ pub struct AnimationAction {
 }
 impl AnimationAction {
     fn time(&self, time: Duration, clip: &AnimationClip) -> f32 {
+        let anim_time = self.unwrapped_time(time, clip);
+        if self.looping {
+            return anim_time % clip.duration();
+        }
+        anim_time
+    }
+    /// The time in the clip before looping is applied, so that it keeps increasing while the clip is played
+    fn unwrapped_time(&self, time: Duration, clip: &AnimationClip) -> f32 {
         let anim_time = match self.time {
             AnimationActionTime::Offset { start_time, speed } => {
                 if time < start_time {
@@ -118,10 +264,13 @@ impl AnimationAction {
             AnimationActionTime::Absolute { time } => time,
         };
         if self.looping {
-            return anim_time % clip.duration();
+            return anim_time;
         }
         anim_time + clip.start
     }
+    fn is_reversed(&self) -> bool {
+        matches!(self.time, AnimationActionTime::Offset { speed, .. } if speed < 0.)
+    }
 }
 
 #[derive(Debug, Default, Display, Clone, PartialEq, Serialize, Deserialize)]
@@ -148,6 +297,13 @@ impl AnimationController {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct FiredAnimationEvent {
+    /// The animated entity
+    pub entity: EntityId,
+    pub name: String,
+}
+
 #[derive(Debug)]
 struct AnimationBlendOutput {
     target: EntityId,
@@ -207,86 +363,435 @@ pub fn animation_systems() -> SystemGroup {
                 let mut outputs: HashMap<String, AnimationBlendOutput> = HashMap::new();
                 let mut in_error = Vec::new();
                 for (id, (controller, binder)) in q.iter(world, qs) {
-                    let retaget = world.get(id, animation_retargeting()).unwrap_or(AnimationRetargeting::None);
-                    let model = world.get_ref(id, model_from_url()).ok().and_then(|def| TypedAssetUrl::parse(def).ok());
-                    // Calc
-                    for action in controller.actions.iter() {
-                        match action.clip.get_clip(assets.clone(), retaget, model.clone()) {
-                            Some(Err(err)) => {
-                                in_error.push((id, err));
-                                break;
-                            }
-                            Some(Ok(clip)) => {
-                                let anim_time = action.time(time, &clip);
-                                for track in clip.tracks.iter() {
-                                    let value = AnimationTrackInterpolator::new().value(track, anim_time);
-                                    let key = format!(
-                                        "{}_{:?}_{}_{:?}",
-                                        id,
-                                        track.target,
-                                        track.outputs.component().index(),
-                                        track.outputs.field()
-                                    );
-                                    if action.weight == 0.0 {
-                                        continue;
-                                    }
-                                    if let Some(o) = outputs.get_mut(&key) {
-                                        o.weight += action.weight;
-                                        let p = action.weight / o.weight;
-                                        o.value = o.value.mix(value, p);
-                                    } else {
-                                        outputs.insert(
-                                            key.to_string(),
-                                            AnimationBlendOutput {
-                                                target: match &track.target {
-                                                    AnimationTarget::BinderId(index) => match binder.get(index) {
-                                                        Some(entity) => *entity,
-                                                        None => {
-                                                            continue;
-                                                        }
-                                                    },
-                                                    AnimationTarget::Entity(entity) => *entity,
-                                                },
-                                                value,
-                                                weight: action.weight,
-                                            },
-                                        );
-                                    }
-                                }
-                            }
-                            None => {}
-                        }
+                    let ctx = BlendContext::new(world, id, binder, &assets, time);
+                    if let Err(err) = ctx.blend(&controller.actions, None, &mut outputs) {
+                        in_error.push((id, err));
                     }
                 }
-
-                // Apply
-                for (_, output) in outputs.into_iter() {
-                    match output.value {
-                        AnimationOutput::Vec3 { component, value } => {
-                            world.set(output.target, component, value).ok();
-                        }
-                        AnimationOutput::Quat { component, value } => {
-                            world.set(output.target, component, value).ok();
-                        }
-                        AnimationOutput::Vec3Field { component, field, value } => {
-                            if let Ok(d) = world.get_mut(output.target, component) {
-                                match field {
-                                    Vec3Field::X => d.x = value,
-                                    Vec3Field::Y => d.y = value,
-                                    Vec3Field::Z => d.z = value,
-                                }
-                            }
-                        }
+                apply_outputs(world, outputs);
+                for (id, err) in in_error {
+                    world.add_component(id, animation_errors(), err).unwrap();
+                }
+            }),
+            // Runs after the controllers, so the graph wins if an entity has both
+            query((animation_graph_actions(), animation_binder())).excl(animation_errors()).to_system(|q, world, qs, _| {
+                let assets = world.resource(asset_cache()).clone();
+                let time = *world.resource(time());
+                let mut outputs: HashMap<String, AnimationBlendOutput> = HashMap::new();
+                let mut in_error = Vec::new();
+                for (id, (layers, binder)) in q.iter(world, qs) {
+                    let ctx = BlendContext::new(world, id, binder, &assets, time);
+                    let mut entity_outputs: HashMap<String, AnimationBlendOutput> = HashMap::new();
+                    if let Err(err) = ctx.blend_layers(layers, |mask| mask_entities(world, binder, mask), &mut entity_outputs) {
+                        in_error.push((id, err));
                     }
+                    outputs.extend(entity_outputs);
                 }
+                apply_outputs(world, outputs);
                 for (id, err) in in_error {
                     world.add_component(id, animation_errors(), err).unwrap();
                 }
             }),
+            Box::new(ik::ik_systems()),
+            Box::new(pose_override::pose_override_systems()),
+            animation_events_system(),
         ],
     )
 }
 
+/// Evaluates the animation graphs and fires animation events, without animating anything. Models aren't loaded on the
+/// server, so this is what runs there; the events are sent to the WASM modules, and the clips the graphs play are
+/// networked to the clients in [animation_graph_actions].
+pub fn animation_server_systems() -> SystemGroup {
+    SystemGroup::new(
+        "animation_server_systems",
+        vec![Box::new(animation_graph_systems()), animation_events_system(), animation_root_motion_system()],
+    )
+}
+
+fn animation_graph_systems() -> SystemGroup {
+    SystemGroup::new(
+        "animation_graph_systems",
+        vec![
+            query(animation_graph().changed()).to_system(|q, world, qs, _| {
+                for (id, _) in q.collect_cloned(world, qs) {
+                    world.remove_components(id, vec![animation_errors().desc(), animation_graph_playback().desc()]).unwrap();
+                }
+            }),
+            query(()).incl(animation_graph()).despawned().to_system(|q, world, qs, _| {
+                for (id, _) in q.collect_cloned(world, qs) {
+                    world.remove_components(id, vec![animation_graph_playback().desc(), animation_graph_actions().desc()]).ok();
+                }
+            }),
+            query(animation_graph()).excl(animation_errors()).to_system(|q, world, qs, _| {
+                let assets = world.resource(asset_cache()).clone();
+                let time = *world.resource(time());
+                for (id, url) in q.collect_cloned(world, qs) {
+                    let graph = match url.abs() {
+                        Some(url) => AnimationGraphFromUrl(url).peek(&assets).map(|x| x.map_err(|err| format!("{err:#}"))),
+                        None => Some(Err(format!("Animation graph url is not absolute: {url}"))),
+                    };
+                    match graph {
+                        Some(Ok(graph)) => {
+                            if !world.has_component(id, animation_graph_playback()) {
+                                world.add_component(id, animation_graph_playback(), AnimationGraphPlayback::new(graph, time)).unwrap();
+                            }
+                            let mut playback = world.get_ref(id, animation_graph_playback()).unwrap().clone();
+                            let parameters = playback
+                                .parameters
+                                .iter()
+                                .map(|(name, desc)| (name.clone(), parameter_value(world, id, *desc)))
+                                .collect::<HashMap<_, _>>();
+                            playback.advance(time, |parameter| parameters.get(parameter).copied().unwrap_or_default());
+                            let actions = playback.layer_actions();
+                            world.set(id, animation_graph_playback(), playback).unwrap();
+                            // The actions are only networked when they change
+                            if world.has_component(id, animation_graph_actions()) {
+                                world.set_if_changed(id, animation_graph_actions(), actions).unwrap();
+                            } else {
+                                world.add_component(id, animation_graph_actions(), actions).unwrap();
+                            }
+                        }
+                        Some(Err(err)) => world.add_component(id, animation_errors(), err).unwrap(),
+                        None => {}
+                    }
+                }
+            }),
+        ],
+    )
+}
+
+/// Fires the events of the clips played by animation controllers and graphs into the [animation_events] resource, if it exists.
+/// Only the action with the most weight of a controller or graph layer fires events, so that blended clips don't fire the same event twice.
+fn animation_events_system() -> DynSystem {
+    Box::new(FnSystem::new(|world, _| {
+        if world.resource_opt(animation_events()).is_none() {
+            return;
+        }
+        let assets = world.resource(asset_cache()).clone();
+        let time = *world.resource(time());
+
+        let mut playing = Vec::new();
+        for (id, controller) in query(animation_controller()).iter(world, None) {
+            playing.extend(dominant_action(&controller.actions).map(|action| (id, "controller".to_string(), action.clone())));
+        }
+        for (id, layers) in query(animation_graph_actions()).iter(world, None) {
+            for (i, layer) in layers.iter().enumerate() {
+                playing.extend(dominant_action(&layer.actions).map(|action| (id, format!("layer{i}"), action.clone())));
+            }
+        }
+
+        let mut fired = Vec::new();
+        let mut event_times: HashMap<EntityId, HashMap<String, f32>> = HashMap::new();
+        for (id, slot, action) in playing {
+            let clip = match &action.clip {
+                AnimationClipRef::Clip(clip) => Some(Ok(clip.clone())),
+                AnimationClipRef::FromModelAsset(url) => url.abs().and_then(|url| AnimationClipFromUrl::new(url, true).peek(&assets)),
+            };
+            let clip = match clip {
+                Some(Ok(clip)) => clip,
+                _ => continue,
+            };
+            let key = action_key(&slot, &action);
+            let current = action.unwrapped_time(time, &clip);
+            let previous = world.get_ref(id, animation_event_times()).ok().and_then(|times| times.get(&key).copied());
+            if let Some(previous) = previous {
+                let events = action_events(&action, &clip, previous, current);
+                fired.extend(events.into_iter().map(|event| FiredAnimationEvent { entity: id, name: event.name.clone() }));
+            }
+            event_times.entry(id).or_default().insert(key, current);
+        }
+
+        update_action_times(world, animation_event_times(), event_times);
+        *world.resource_mut(animation_events()) = fired;
+    }))
+}
+
+/// The events of `clip` passed by `action` since the previous frame, with unwrapped times
+fn action_events<'a>(action: &AnimationAction, clip: &'a AnimationClip, previous: f32, current: f32) -> Vec<&'a AnimationEvent> {
+    let reversed = action.is_reversed();
+    let restarted = if reversed { current > previous } else { current < previous };
+    if restarted {
+        // The events since the start of the action are fired, which are before it if it's reversed
+        let start = action_start(action, clip);
+        let start = if reversed { start + f32::EPSILON } else { start - f32::EPSILON };
+        clip.events_between(start, current, action.looping)
+    } else {
+        clip.events_between(previous, current, action.looping)
+    }
+}
+
+/// Identifies an action across frames, for the systems which compare its time to the one of the previous frame
+fn action_key(slot: &str, action: &AnimationAction) -> String {
+    match &action.clip {
+        AnimationClipRef::Clip(clip) => format!("{slot}:{}", clip.id),
+        AnimationClipRef::FromModelAsset(url) => format!("{slot}:{url}"),
+    }
+}
+
+/// The unwrapped time `action` starts from, or restarts from when it loops
+fn action_start(action: &AnimationAction, clip: &AnimationClip) -> f32 {
+    if action.looping {
+        0.
+    } else {
+        clip.start
+    }
+}
+
+/// Replaces the action times in `component`, and removes it from the entities which stopped playing actions
+fn update_action_times(world: &mut World, component: Component<HashMap<String, f32>>, times: HashMap<EntityId, HashMap<String, f32>>) {
+    let stopped = query(component).iter(world, None).map(|(id, _)| id).filter(|id| !times.contains_key(id)).collect_vec();
+    for id in stopped {
+        world.remove_component(id, component).unwrap();
+    }
+    for (id, times) in times {
+        if world.has_component(id, component) {
+            world.set(id, component, times).unwrap();
+        } else {
+            world.add_component(id, component, times).unwrap();
+        }
+    }
+}
+
+/// Extracts the root motion of the clips played by animation controllers and graph layers without a mask, into
+/// [animation_root_motion_translation] and [animation_root_motion_rotation]. Only clips which had their root motion
+/// extracted when they were built move the entity.
+fn animation_root_motion_system() -> DynSystem {
+    Box::new(FnSystem::new(|world, _| {
+        let assets = world.resource(asset_cache()).clone();
+        let time = *world.resource(time());
+
+        let mut playing = HashMap::new();
+        for (id, controller) in query(animation_controller()).iter(world, None) {
+            // The children only play the controller of their parent
+            if world.get(id, parent()).map(|parent| world.has_component(parent, copy_animation_controller_to_children())).unwrap_or(false) {
+                continue;
+            }
+            let actions =
+                controller.actions.iter().enumerate().map(|(i, action)| (format!("controller{i}"), action.clone(), action.weight));
+            playing.insert(id, actions.collect_vec());
+        }
+        for (id, layers) in query(animation_graph_actions()).iter(world, None) {
+            let actions = layers
+                .iter()
+                .enumerate()
+                .filter(|(_, layer)| layer.mask.is_empty())
+                .flat_map(|(i, layer)| {
+                    layer
+                        .actions
+                        .iter()
+                        .enumerate()
+                        .map(move |(j, action)| (format!("layer{i}:{j}"), action.clone(), action.weight * layer.weight))
+                })
+                .collect_vec();
+            playing.insert(id, actions);
+        }
+
+        let mut motions = Vec::new();
+        let mut motion_times: HashMap<EntityId, HashMap<String, f32>> = HashMap::new();
+        for (id, actions) in playing {
+            let retarget = world.get(id, animation_retargeting()).unwrap_or(AnimationRetargeting::None);
+            let model = world.get_ref(id, model_from_url()).ok().and_then(|def| TypedAssetUrl::parse(def).ok());
+            let (mut translation, mut yaw, mut total_weight) = (Vec3::ZERO, 0., 0.);
+            for (slot, action, weight) in actions {
+                if weight <= 0. {
+                    continue;
+                }
+                let clip = match action.clip.get_clip(assets.clone(), retarget, model.clone()) {
+                    Some(Ok(clip)) => clip,
+                    _ => continue,
+                };
+                let key = action_key(&slot, &action);
+                let current = action.unwrapped_time(time, &clip);
+                let previous = world.get_ref(id, animation_root_motion_times()).ok().and_then(|times| times.get(&key).copied());
+                motion_times.entry(id).or_default().insert(key, current);
+                // Actions don't move the entity on the frame they start playing
+                let previous = match previous {
+                    Some(previous) => previous,
+                    None => continue,
+                };
+                // Restarted actions move the entity from their start
+                let restarted = if action.is_reversed() { current > previous } else { current < previous };
+                let from = if restarted { action_start(&action, &clip) } else { previous };
+                if let Some((action_translation, action_yaw)) = clip.root_motion_between(from, current, action.looping) {
+                    translation += action_translation * weight;
+                    yaw += action_yaw * weight;
+                }
+                total_weight += weight;
+            }
+            if total_weight > 0. {
+                motions.push((id, translation / total_weight, yaw / total_weight));
+            }
+        }
+
+        update_action_times(world, animation_root_motion_times(), motion_times);
+        for (id, translation, yaw) in motions {
+            let translation = world.get(id, rotation()).unwrap_or_default() * (world.get(id, scale()).unwrap_or(Vec3::ONE) * translation);
+            let rotation = Quat::from_rotation_z(yaw);
+            if world.has_component(id, animation_root_motion_translation()) {
+                world.set(id, animation_root_motion_translation(), translation).unwrap();
+                world.set(id, animation_root_motion_rotation(), rotation).unwrap();
+            } else {
+                let data =
+                    EntityData::new().set(animation_root_motion_translation(), translation).set(animation_root_motion_rotation(), rotation);
+                world.add_components(id, data).unwrap();
+            }
+        }
+    }))
+}
+
+fn dominant_action(actions: &[AnimationAction]) -> Option<&AnimationAction> {
+    actions.iter().filter(|action| action.weight > 0.).max_by(|a, b| a.weight.total_cmp(&b.weight))
+}
+
+struct BlendContext<'a> {
+    id: EntityId,
+    binder: &'a HashMap<String, EntityId>,
+    assets: &'a AssetCache,
+    time: Duration,
+    retarget: AnimationRetargeting,
+    model: Option<TypedAssetUrl<ModelAssetType>>,
+}
+impl<'a> BlendContext<'a> {
+    fn new(world: &World, id: EntityId, binder: &'a HashMap<String, EntityId>, assets: &'a AssetCache, time: Duration) -> Self {
+        Self {
+            id,
+            binder,
+            assets,
+            time,
+            retarget: world.get(id, animation_retargeting()).unwrap_or(AnimationRetargeting::None),
+            model: world.get_ref(id, model_from_url()).ok().and_then(|def| TypedAssetUrl::parse(def).ok()),
+        }
+    }
+    /// Blends the weighted actions into `outputs`. Only the entities in `mask` are animated, if it's set
+    fn blend(
+        &self,
+        actions: &[AnimationAction],
+        mask: Option<&HashSet<EntityId>>,
+        outputs: &mut HashMap<String, AnimationBlendOutput>,
+    ) -> Result<(), String> {
+        for action in actions {
+            let clip = match action.clip.get_clip(self.assets.clone(), self.retarget, self.model.clone()) {
+                Some(Ok(clip)) => clip,
+                Some(Err(err)) => return Err(err),
+                None => continue,
+            };
+            let anim_time = action.time(self.time, &clip);
+            for track in clip.tracks.iter() {
+                if action.weight == 0.0 {
+                    continue;
+                }
+                let target = match &track.target {
+                    AnimationTarget::BinderId(index) => match self.binder.get(index) {
+                        Some(entity) => *entity,
+                        None => continue,
+                    },
+                    AnimationTarget::Entity(entity) => *entity,
+                };
+                if mask.map(|mask| !mask.contains(&target)).unwrap_or(false) {
+                    continue;
+                }
+                let value = AnimationTrackInterpolator::new().value(track, anim_time);
+                let key = format!(
+                    "{}_{:?}_{}_{:?}_{:?}",
+                    self.id,
+                    track.target,
+                    track.outputs.component().index(),
+                    track.outputs.field(),
+                    track.outputs.index()
+                );
+                if let Some(o) = outputs.get_mut(&key) {
+                    o.weight += action.weight;
+                    let p = action.weight / o.weight;
+                    o.value = o.value.mix(value, p);
+                } else {
+                    outputs.insert(key, AnimationBlendOutput { target, value, weight: action.weight });
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Blends the layers of an animation graph into `outputs`. `mask` gives the entities animated by a layer with a mask
+    fn blend_layers(
+        &self,
+        layers: &[AnimationGraphLayerActions],
+        mask: impl Fn(&[String]) -> Option<HashSet<EntityId>>,
+        outputs: &mut HashMap<String, AnimationBlendOutput>,
+    ) -> Result<(), String> {
+        for layer in layers {
+            let mask = mask(&layer.mask);
+            let mut layer_outputs = HashMap::new();
+            self.blend(&layer.actions, mask.as_ref(), &mut layer_outputs)?;
+            // A layer fading to or from an empty state only partially covers the layers before it
+            for (key, output) in layer_outputs {
+                if let Some(o) = outputs.get_mut(&key) {
+                    o.value = o.value.mix(output.value, layer.weight * output.weight.min(1.));
+                } else {
+                    outputs.insert(key, output);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn apply_outputs(world: &mut World, outputs: HashMap<String, AnimationBlendOutput>) {
+    for (_, output) in outputs.into_iter() {
+        match output.value {
+            AnimationOutput::Vec3 { component, value } => {
+                world.set(output.target, component, value).ok();
+            }
+            AnimationOutput::Quat { component, value } => {
+                world.set(output.target, component, value).ok();
+            }
+            AnimationOutput::Vec3Field { component, field, value } => {
+                if let Ok(d) = world.get_mut(output.target, component) {
+                    match field {
+                        Vec3Field::X => d.x = value,
+                        Vec3Field::Y => d.y = value,
+                        Vec3Field::Z => d.z = value,
+                    }
+                }
+            }
+            AnimationOutput::Weight { component, index, value } => {
+                if let Some(weight) = world.get_mut(output.target, component).ok().and_then(|d| d.get_mut(index)) {
+                    *weight = value;
+                }
+            }
+        }
+    }
+}
+
+/// The bound entities in `mask` and all of their descendants, or `None` if the mask is empty
+fn mask_entities(world: &World, binder: &HashMap<String, EntityId>, mask: &[String]) -> Option<HashSet<EntityId>> {
+    if mask.is_empty() {
+        return None;
+    }
+    let mut entities = HashSet::new();
+    let mut stack = mask.iter().filter_map(|bind_id| binder.get(bind_id).copied()).collect::<Vec<_>>();
+    while let Some(entity) = stack.pop() {
+        if entities.insert(entity) {
+            stack.extend(world.get_ref(entity, children()).cloned().unwrap_or_default());
+        }
+    }
+    Some(entities)
+}
+
+/// Reads an animation graph parameter, which is the value of the component `desc` on the entity
+fn parameter_value(world: &World, id: EntityId, desc: ComponentDesc) -> f32 {
+    if desc.is::<f32>() {
+        world.get(id, Component::<f32>::new(desc)).unwrap_or_default()
+    } else if desc.is::<i32>() {
+        world.get(id, Component::<i32>::new(desc)).unwrap_or_default() as f32
+    } else if desc.is::<u32>() {
+        world.get(id, Component::<u32>::new(desc)).unwrap_or_default() as f32
+    } else if desc.is::<bool>() {
+        world.get(id, Component::<bool>::new(desc)).map(|x| if x { 1. } else { 0. }).unwrap_or_default()
+    } else {
+        0.
+    }
+}
+
 pub fn animation_bind_id_from_name(name: &str) -> String {
     let name = if let Some((_a, b)) = name.split_once(':') { b.to_string() } else { name.to_string() };
     fn normalize_name(value: &str) -> String {
@@ -339,3 +844,119 @@ fn test_animation() {
     assert_eq!(1., int.value(&track, 1.).as_vec3_value().unwrap().x);
     assert_eq!(1., int.value(&track, 1.5).as_vec3_value().unwrap().x);
 }
+
+#[test]
+fn test_action_events() {
+    let mut clip = AnimationClip::from_tracks(vec![AnimationTrack {
+        target: AnimationTarget::BinderId("".to_string()),
+        inputs: vec![0., 1.],
+        outputs: AnimationOutputs::Vec3 { component: ambient_core::transform::translation(), data: vec![Vec3::ZERO, Vec3::ONE] },
+    }]);
+    clip.events = vec![AnimationEvent { name: "start".to_string(), time: 0. }, AnimationEvent { name: "step".to_string(), time: 0.5 }];
+    let action = |speed: f32, looping: bool| AnimationAction {
+        clip: AnimationClipRef::Clip(Arc::new(clip.clone())),
+        time: AnimationActionTime::Offset { start_time: Duration::ZERO, speed },
+        looping,
+        weight: 1.,
+    };
+    let names = |events: Vec<&AnimationEvent>| events.into_iter().map(|event| event.name.clone()).collect_vec();
+
+    assert_eq!(names(action_events(&action(1., false), &clip, 0.25, 0.75)), vec!["step"]);
+    assert!(action_events(&action(1., false), &clip, 0.5, 0.75).is_empty());
+    // Restarting fires the events from the start of the clip again
+    assert_eq!(names(action_events(&action(1., false), &clip, 0.75, 0.25)), vec!["start"]);
+    assert_eq!(names(action_events(&action(1., true), &clip, 0.75, 1.25)), vec!["start"]);
+
+    // Reversed actions play backwards, and restart by jumping forwards
+    assert_eq!(names(action_events(&action(-1., true), &clip, -0.25, -0.75)), vec!["step"]);
+    assert!(action_events(&action(-1., true), &clip, -0.25, -0.25).is_empty());
+    assert_eq!(names(action_events(&action(-1., true), &clip, -0.75, -0.25)), vec!["start"]);
+}
+
+#[test]
+fn test_animation_graph_parameters() {
+    init_components();
+
+    let graph: AnimationGraph = serde_json::from_str(
+        r#"{
+            "layers": [{
+                "name": "base",
+                "states": [{ "name": "aim", "motion": { "type": "BlendSpace1D", "parameter": "core::animation::ik_two_bone_weight", "clips": [
+                    { "clip": "https://example.com/low.anim", "position": 0 },
+                    { "clip": "https://example.com/high.anim", "position": 1 }
+                ] } }],
+                "transitions": [
+                    { "to": "aim", "conditions": [
+                        { "parameter": "core::animation::ik_chain_length", "op": "Greater", "value": 1 },
+                        { "parameter": "my_project::missing", "op": "Equal", "value": 0 }
+                    ] }
+                ]
+            }]
+        }"#,
+    )
+    .unwrap();
+    let playback = AnimationGraphPlayback::new(Arc::new(graph), Duration::ZERO);
+    // Parameters without a component are left out
+    let names = playback.parameters.iter().map(|(name, _)| name.as_str()).collect_vec();
+    assert_eq!(names, vec!["core::animation::ik_chain_length", "core::animation::ik_two_bone_weight"]);
+
+    let mut world = World::new("test_animation_graph_parameters");
+    let id = EntityData::new().set(ik_two_bone_weight(), 0.25).set(ik_chain_length(), 3).spawn(&mut world);
+    let values = playback.parameters.iter().map(|(_, desc)| parameter_value(&world, id, *desc)).collect_vec();
+    assert_eq!(values, vec![3., 0.25]);
+    let other = EntityData::new().spawn(&mut world);
+    assert_eq!(parameter_value(&world, other, ik_chain_length().desc()), 0.);
+}
+
+#[tokio::test]
+async fn test_animation_graph_root_motion() {
+    use ambient_ecs::{FrameEvent, System};
+    use glam::vec3;
+
+    ambient_core::init_all_components();
+    ambient_model::init_components();
+    init_components();
+
+    let mut clip = AnimationClip { id: "walk".to_string(), tracks: Vec::new(), start: 0., end: 1., events: Vec::new(), root_motion: None };
+    clip.root_motion = Some(AnimationRootMotion {
+        bind_id: "Hips".to_string(),
+        inputs: vec![0., 1.],
+        translation: vec![Vec3::ZERO, vec3(0., 2., 0.)],
+        yaw: vec![0., 0.],
+    });
+    let clip = Arc::new(clip);
+    let graph: AnimationGraph = serde_json::from_str(
+        r#"{ "layers": [{ "name": "base", "states": [{ "name": "walk", "motion": { "type": "Clip", "clip": "https://example.com/walk.anim" } }] }] }"#,
+    )
+    .unwrap();
+
+    let start = Duration::from_secs(10);
+    let mut playback = AnimationGraphPlayback::new(Arc::new(graph), start);
+    playback.advance(start, |_| 0.);
+    // The clip is used directly, instead of being loaded from its url
+    let mut layers = playback.layer_actions();
+    for action in &mut layers[0].actions {
+        action.clip = AnimationClipRef::Clip(clip.clone());
+    }
+
+    let mut world = World::new("test_animation_graph_root_motion");
+    world.add_resource(asset_cache(), AssetCache::new(tokio::runtime::Handle::current()));
+    world.add_resource(time(), start);
+    let id = EntityData::new().set(animation_graph_actions(), layers).set(rotation(), Quat::IDENTITY).spawn(&mut world);
+
+    let mut system = animation_root_motion_system();
+    system.run(&mut world, &FrameEvent);
+    assert!(world.get(id, animation_root_motion_translation()).is_err());
+
+    world.set(world.resource_entity(), time(), start + Duration::from_millis(250)).unwrap();
+    system.run(&mut world, &FrameEvent);
+    let translation = world.get(id, animation_root_motion_translation()).unwrap();
+    assert!(translation.distance(vec3(0., 0.5, 0.)) < 1e-5);
+    assert_eq!(world.get(id, animation_root_motion_rotation()).unwrap(), Quat::IDENTITY);
+
+    // Looping past the end of the clip keeps moving the entity forward
+    world.set(world.resource_entity(), time(), start + Duration::from_millis(1250)).unwrap();
+    system.run(&mut world, &FrameEvent);
+    let translation = world.get(id, animation_root_motion_translation()).unwrap();
+    assert!(translation.distance(vec3(0., 2., 0.)) < 1e-4);
+}
@@ -0,0 +1,71 @@
+use ambient_core::{
+    asset_cache,
+    hierarchy::parent,
+    transform::{rotation, translation},
+};
+use ambient_ecs::{query, EntityId, SystemGroup, World};
+use ambient_model::{animation_binder, model_from_url, ModelFromUrl};
+use ambient_std::{asset_cache::AsyncAssetKeyExt, asset_url::TypedAssetUrl};
+use glam::Mat4;
+
+use crate::{ik::world_transform, pose_override_bone, pose_override_of};
+
+/// Moves the bones of models to the world poses of the entities that override them
+pub fn pose_override_systems() -> SystemGroup {
+    SystemGroup::new(
+        "pose_override",
+        vec![
+            query((pose_override_of(), pose_override_bone(), translation(), rotation())).to_system(|q, world, qs, _| {
+                let mut bones = Vec::new();
+                for (_, (target, bind_id, pos, rot)) in q.iter(world, qs) {
+                    if let Some(bone) = world.get_ref(*target, animation_binder()).ok().and_then(|binder| binder.get(bind_id).copied()) {
+                        bones.push((bone, *pos, *rot));
+                    }
+                }
+                // Each child is made relative to the final pose of its parent, so the parents are posed first
+                bones.sort_by_cached_key(|(bone, _, _)| depth(world, *bone));
+                for (bone, pos, rot) in bones {
+                    let parent_transform = world.get(bone, parent()).map(|parent| world_transform(world, parent)).unwrap_or(Mat4::IDENTITY);
+                    let (_, parent_rotation, _) = parent_transform.to_scale_rotation_translation();
+                    world.set(bone, translation(), parent_transform.inverse().transform_point3(pos)).ok();
+                    world.set(bone, rotation(), (parent_rotation.inverse() * rot).normalize()).ok();
+                }
+            }),
+            // Animations don't necessarily move every bone, so the bones are put back in the base pose of the model when the
+            // override ends
+            query((pose_override_of(), pose_override_bone())).despawned().to_system(|q, world, qs, _| {
+                let assets = world.resource(asset_cache()).clone();
+                for (_, (target, bind_id)) in q.collect_cloned(world, qs) {
+                    let bone = match world.get_ref(target, animation_binder()).ok().and_then(|binder| binder.get(&bind_id).copied()) {
+                        Some(bone) => bone,
+                        None => continue,
+                    };
+                    let model = match world.get_ref(target, model_from_url()).ok().and_then(|url| TypedAssetUrl::parse(url).ok()) {
+                        Some(url) => match ModelFromUrl(url).peek(&assets) {
+                            Some(Ok(model)) => model,
+                            _ => continue,
+                        },
+                        None => continue,
+                    };
+                    if let Some(node) = model.get_entity_id_by_bind_id(&bind_id) {
+                        if let Ok(pos) = model.0.get(node, translation()) {
+                            world.set(bone, translation(), pos).ok();
+                        }
+                        if let Ok(rot) = model.0.get(node, rotation()) {
+                            world.set(bone, rotation(), rot).ok();
+                        }
+                    }
+                }
+            }),
+        ],
+    )
+}
+
+fn depth(world: &World, mut id: EntityId) -> usize {
+    let mut depth = 0;
+    while let Ok(parent) = world.get(id, parent()) {
+        id = parent;
+        depth += 1;
+    }
+    depth
+}
@@ -13,6 +13,8 @@ pub enum AnimationOutput {
     Vec3 { component: Component<glam::Vec3>, value: glam::Vec3 },
     Quat { component: Component<glam::Quat>, value: glam::Quat },
     Vec3Field { component: Component<glam::Vec3>, field: Vec3Field, value: f32 },
+    /// A single element of a list of weights, such as the morph target weights of a mesh
+    Weight { component: Component<Vec<f32>>, index: usize, value: f32 },
 }
 impl AnimationOutput {
     pub fn mix(&self, value: AnimationOutput, p: f32) -> Self {
@@ -30,6 +32,10 @@ impl AnimationOutput {
                 AnimationOutput::Vec3Field { component, field, value: mix(*left, right, p) }
             }
 
+            (AnimationOutput::Weight { value: left, .. }, AnimationOutput::Weight { value: right, index, component }) => {
+                AnimationOutput::Weight { component, index, value: mix(*left, right, p) }
+            }
+
             _ => unreachable!(),
         }
     }
@@ -57,6 +63,7 @@ pub enum AnimationOutputs {
     Vec3 { component: Component<glam::Vec3>, data: Vec<glam::Vec3> },
     Quat { component: Component<glam::Quat>, data: Vec<glam::Quat> },
     Vec3Field { component: Component<glam::Vec3>, field: Vec3Field, data: Vec<f32> },
+    Weight { component: Component<Vec<f32>>, index: usize, data: Vec<f32> },
 }
 impl AnimationOutputs {
     pub fn component(&self) -> ComponentDesc {
@@ -64,6 +71,7 @@ impl AnimationOutputs {
             AnimationOutputs::Vec3 { component, .. } => component.desc(),
             AnimationOutputs::Quat { component, .. } => component.desc(),
             AnimationOutputs::Vec3Field { component, .. } => component.desc(),
+            AnimationOutputs::Weight { component, .. } => component.desc(),
         }
     }
     pub fn field(&self) -> Option<Vec3Field> {
@@ -72,6 +80,12 @@ impl AnimationOutputs {
             _ => None,
         }
     }
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            AnimationOutputs::Weight { index, .. } => Some(*index),
+            _ => None,
+        }
+    }
     pub fn field_values_mut(&mut self) -> Option<&mut Vec<f32>> {
         match self {
             AnimationOutputs::Vec3Field { data, .. } => Some(data),
@@ -85,6 +99,9 @@ impl AnimationOutputs {
             AnimationOutputs::Vec3Field { data, component, field } => {
                 AnimationOutput::Vec3Field { component: *component, field: *field, value: data[index] }
             }
+            AnimationOutputs::Weight { data, component, index: weight_index } => {
+                AnimationOutput::Weight { component: *component, index: *weight_index, value: data[index] }
+            }
         }
     }
 }
@@ -177,28 +194,129 @@ impl AnimationTrackInterpolator {
 
 pub type AnimationClipFromUrl = BincodeFromUrl<AnimationClip>;
 
+/// A named point in time of a clip, such as a footstep, which is fired when the clip is played past it
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct AnimationEvent {
+    pub name: String,
+    /// In seconds, in the same time as the keyframes of the clip
+    pub time: f32,
+}
+
+/// The horizontal translation and the yaw of the root bone of a clip, which were extracted from its pose so that they can be
+/// applied to the animated entity instead. Both are relative to the first keyframe, in the space of the model, which is z up.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct AnimationRootMotion {
+    /// The bind id of the root bone
+    pub bind_id: String,
+    pub inputs: Vec<f32>,
+    pub translation: Vec<Vec3>,
+    pub yaw: Vec<f32>,
+}
+impl AnimationRootMotion {
+    fn sample(&self, time: f32) -> (Vec3, f32) {
+        let index = self.inputs.partition_point(|&t| t <= time);
+        if index == 0 {
+            return (self.translation[0], self.yaw[0]);
+        } else if index == self.inputs.len() {
+            return (self.translation[index - 1], self.yaw[index - 1]);
+        }
+        let (left, right) = (index - 1, index);
+        let p = (time - self.inputs[left]) / (self.inputs[right] - self.inputs[left]);
+        (self.translation[left].lerp(self.translation[right], p), mix(self.yaw[left], self.yaw[right], p))
+    }
+    /// The motion from `from` to `to`, as a translation in the space of the root at `from`, and a change of yaw
+    pub fn delta(&self, from: f32, to: f32) -> (Vec3, f32) {
+        let (from_translation, from_yaw) = self.sample(from);
+        let (to_translation, to_yaw) = self.sample(to);
+        (Quat::from_rotation_z(-from_yaw) * (to_translation - from_translation), to_yaw - from_yaw)
+    }
+    /// Scales the translation, i.e. for a skeleton with longer legs
+    pub fn scale(&mut self, scale: f32) {
+        for translation in &mut self.translation {
+            *translation *= scale;
+        }
+    }
+}
+
+/// Applies the motion `b` after the motion `a`
+fn chain_root_motion(a: (Vec3, f32), b: (Vec3, f32)) -> (Vec3, f32) {
+    (a.0 + Quat::from_rotation_z(a.1) * b.0, a.1 + b.1)
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct AnimationClip {
     pub id: String,
     pub tracks: Vec<AnimationTrack>,
     pub start: f32,
     pub end: f32,
+    pub events: Vec<AnimationEvent>,
+    pub root_motion: Option<AnimationRootMotion>,
 }
 impl AnimationClip {
     pub fn from_tracks(tracks: Vec<AnimationTrack>) -> Self {
         let end = tracks.iter().map(|x| ordered_float::OrderedFloat::from(x.duration())).max().unwrap().into();
-        Self { id: "".to_string(), tracks, start: 0., end }
+        Self { id: "".to_string(), tracks, start: 0., end, events: Vec::new(), root_motion: None }
     }
     pub fn duration(&self) -> f32 {
         self.end - self.start
     }
-    /// Merge tracks with Vec3Field outputs into Vec3 and Quat tracks
+    /// The events passed when playing from `from` to `to`, which may be backwards. `from` is excluded, so that
+    /// consecutive calls don't return the same event twice. The times are unwrapped; if the clip is looping, the
+    /// events are repeated every [Self::duration] seconds.
+    pub fn events_between(&self, from: f32, to: f32, looping: bool) -> Vec<&AnimationEvent> {
+        let duration = self.duration();
+        self.events
+            .iter()
+            .filter(|event| {
+                if looping && duration > 0. {
+                    // The closest repetition of the event after (or before) `from`
+                    if from <= to {
+                        let time = event.time + (((from - event.time) / duration).floor() + 1.) * duration;
+                        time <= to
+                    } else {
+                        let time = event.time + (((from - event.time) / duration).ceil() - 1.) * duration;
+                        time >= to
+                    }
+                } else if from <= to {
+                    from < event.time && event.time <= to
+                } else {
+                    to <= event.time && event.time < from
+                }
+            })
+            .collect()
+    }
+    /// The root motion when playing from `from` to `to`, which may be backwards, with unwrapped times like [Self::events_between].
+    /// Returns `None` if no root motion was extracted from the clip.
+    pub fn root_motion_between(&self, from: f32, to: f32, looping: bool) -> Option<(Vec3, f32)> {
+        let root_motion = self.root_motion.as_ref()?;
+        let duration = self.duration();
+        if !looping || duration <= 0. {
+            return Some(root_motion.delta(from, to));
+        }
+        let (from_loop, to_loop) = ((from / duration).floor(), (to / duration).floor());
+        let (from, to) = (from - from_loop * duration, to - to_loop * duration);
+        if from_loop == to_loop {
+            return Some(root_motion.delta(from, to));
+        }
+        // Play to the end of the loop, through any whole loops, and then from the start of the last one
+        let (end, start) = if to_loop > from_loop { (duration, 0.) } else { (0., duration) };
+        let mut res = root_motion.delta(from, end);
+        let whole_loop = root_motion.delta(start, end);
+        for _ in 1..(to_loop - from_loop).abs() as usize {
+            res = chain_root_motion(res, whole_loop);
+        }
+        Some(chain_root_motion(res, root_motion.delta(start, to)))
+    }
+    /// Merge tracks with Vec3Field outputs into Vec3 and Quat tracks. Tracks with other outputs are kept as they are.
     pub fn merge_field_tracks(&mut self) {
         let mut euler_rotation_tracks = HashMap::new();
         let mut translation_tracks = HashMap::new();
         let mut scale_tracks = HashMap::new();
+        let mut new_tracks = Vec::new();
         for track in self.tracks.iter() {
-            if track.outputs.component() == euler_rotation() {
+            if track.outputs.field().is_none() {
+                new_tracks.push(track.clone());
+            } else if track.outputs.component() == euler_rotation() {
                 let res_tracks = euler_rotation_tracks.entry(track.target.clone()).or_insert_with(HashMap::new);
                 res_tracks.insert(track.outputs.field().unwrap(), track.clone());
             } else if track.outputs.component() == translation() {
@@ -211,7 +329,6 @@ impl AnimationClip {
                 panic!("merge_field_tracks is only supported for clips with euler_rotation, translation and scale properties");
             }
         }
-        let mut new_tracks = Vec::new();
         for (target, tracks) in euler_rotation_tracks.into_iter() {
             new_tracks.push(merge_rotation_tracks(target, tracks));
         }
@@ -289,3 +406,41 @@ fn merge_rotation_tracks(target: AnimationTarget, tracks: HashMap<Vec3Field, Ani
     }
     AnimationTrack { target, inputs, outputs: AnimationOutputs::Quat { component: rotation(), data: outputs } }
 }
+
+#[test]
+fn test_animation_events_between() {
+    let event = |name: &str, time: f32| AnimationEvent { name: name.to_string(), time };
+    let clip = AnimationClip {
+        id: "".to_string(),
+        tracks: Vec::new(),
+        start: 0.,
+        end: 1.,
+        events: vec![event("a", 0.25), event("b", 0.75)],
+        root_motion: None,
+    };
+    let names = |events: Vec<&AnimationEvent>| events.into_iter().map(|x| x.name.clone()).collect::<Vec<_>>();
+    assert_eq!(names(clip.events_between(0., 0.5, false)), ["a"]);
+    assert_eq!(names(clip.events_between(0.25, 0.5, false)), Vec::<String>::new());
+    assert_eq!(names(clip.events_between(0.9, 1.3, true)), ["a"]);
+    assert_eq!(names(clip.events_between(1.9, 2.8, true)), ["a", "b"]);
+    assert_eq!(names(clip.events_between(1.3, 0.7, true)), ["a", "b"]);
+    assert_eq!(names(clip.events_between(1.2, 0.8, true)), Vec::<String>::new());
+    assert_eq!(names(clip.events_between(0.8, 0.2, false)), ["a", "b"]);
+}
+
+#[test]
+fn test_animation_root_motion_between() {
+    use glam::vec3;
+
+    let mut clip = AnimationClip { id: "".to_string(), tracks: Vec::new(), start: 0., end: 1., events: Vec::new(), root_motion: None };
+    assert_eq!(clip.root_motion_between(0., 1., false), None);
+    clip.root_motion = Some(AnimationRootMotion {
+        bind_id: "Hips".to_string(),
+        inputs: vec![0., 1.],
+        translation: vec![Vec3::ZERO, vec3(0., 2., 0.)],
+        yaw: vec![0., 0.],
+    });
+    assert_eq!(clip.root_motion_between(0.25, 0.75, false), Some((vec3(0., 1., 0.), 0.)));
+    assert_eq!(clip.root_motion_between(0.5, 2.5, true), Some((vec3(0., 4., 0.), 0.)));
+    assert_eq!(clip.root_motion_between(2.5, 0.5, true), Some((vec3(0., -4., 0.), 0.)));
+}
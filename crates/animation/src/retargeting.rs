@@ -2,11 +2,11 @@ use std::sync::Arc;
 
 use ambient_core::transform::{rotation, translation};
 use ambient_editor_derive::ElementEditor;
-use ambient_model::{Model, ModelFromUrl};
+use ambient_model::Model;
 use ambient_std::{
     asset_cache::{AssetCache, AssetKeepalive, AsyncAssetKey, AsyncAssetKeyExt},
     asset_url::{AnimationAssetType, ModelAssetType, TypedAssetUrl},
-    download_asset::AssetError,
+    download_asset::{AssetError, BytesFromUrl},
 };
 use anyhow::Context;
 use async_trait::async_trait;
@@ -51,8 +51,10 @@ impl AsyncAssetKey<Result<Arc<AnimationClip>, AssetError>> for AnimationClipReta
     async fn load(self, assets: AssetCache) -> Result<Arc<AnimationClip>, AssetError> {
         let clip_url: TypedAssetUrl<AnimationAssetType> =
             self.clip.abs().context(format!("Expected absolute url, got: {}", self.clip))?.into();
-        let anim_model =
-            ModelFromUrl(clip_url.model_crate().context("Invalid clip url")?.model()).get(&assets).await.context("Failed to load model")?;
+        let anim_model = ModelHierarchyFromUrl(clip_url.model_crate().context("Invalid clip url")?.model())
+            .get(&assets)
+            .await
+            .context("Failed to load model")?;
         let clip = AnimationClipFromUrl::new(clip_url.unwrap_abs(), true).get(&assets).await.context("No such clip")?;
         match self.translation_retargeting {
             AnimationRetargeting::None => Ok(clip),
@@ -64,7 +66,8 @@ impl AsyncAssetKey<Result<Arc<AnimationClip>, AssetError>> for AnimationClipReta
             AnimationRetargeting::AnimationScaled { normalize_hip } => {
                 let retarget_model_url =
                     self.retarget_model.context("No retarget_model specified")?.abs().context("Failed to resolve retarget url")?;
-                let retarget_model = ModelFromUrl(retarget_model_url.into()).get(&assets).await.context("Failed to load retarget model")?;
+                let retarget_model =
+                    ModelHierarchyFromUrl(retarget_model_url.into()).get(&assets).await.context("Failed to load retarget model")?;
                 let mut clip = (*clip).clone();
                 let anim_root = anim_model.roots()[0];
                 let _retarget_root = retarget_model.roots()[0];
@@ -94,28 +97,56 @@ impl AsyncAssetKey<Result<Arc<AnimationClip>, AssetError>> for AnimationClipReta
                         true
                     }
                 });
+                // The root moves as far as the root bone would have, had it not been extracted
+                if let Some(root_motion) = &mut clip.root_motion {
+                    if let Some(scale) = retarget_scale(&root_motion.bind_id, &anim_model, &retarget_model) {
+                        root_motion.scale(scale);
+                    }
+                }
                 Ok(Arc::new(clip))
             }
         }
     }
 }
-fn retarget_track(track: &mut AnimationTrack, anim_model: &Model, retarget_model: &Model) -> Option<()> {
-    let bind_id = track.target.bind_id().unwrap();
-    let original = anim_model.get_entity_id_by_bind_id(bind_id).unwrap();
+/// Only the hierarchy of the models is needed for retargeting, so their primitives aren't loaded. This also lets clips be
+/// retargeted on the server, which can't load them.
+#[derive(Debug, Clone)]
+struct ModelHierarchyFromUrl(TypedAssetUrl<ModelAssetType>);
+#[async_trait]
+impl AsyncAssetKey<Result<Arc<Model>, AssetError>> for ModelHierarchyFromUrl {
+    async fn load(self, assets: AssetCache) -> Result<Arc<Model>, AssetError> {
+        let url = self.0.abs().context(format!("ModelHierarchyFromUrl got relative url: {}", self.0))?;
+        let data = BytesFromUrl::new(url, true).get(&assets).await?;
+        Ok(Arc::new(Model::from_slice(&data)?))
+    }
+}
+
+/// The ratio between the length of the bone in the retarget model and in the model the animation was made for
+fn retarget_scale(bind_id: &str, anim_model: &Model, retarget_model: &Model) -> Option<f32> {
+    let original = anim_model.get_entity_id_by_bind_id(bind_id)?;
     let target = retarget_model.get_entity_id_by_bind_id(bind_id)?;
-    let original = anim_model.0.get(original, translation()).unwrap().length();
-    let target = anim_model.0.get(target, translation()).ok()?.length();
-    if target == 0. {
-        return Some(());
+    let original = anim_model.0.get(original, translation()).ok()?.length();
+    let target = retarget_model.0.get(target, translation()).ok()?.length();
+    if original == 0. || target == 0. {
+        return None;
     }
-    let scale = target / original;
+    Some(target / original)
+}
+
+fn retarget_track(track: &mut AnimationTrack, anim_model: &Model, retarget_model: &Model) -> Option<()> {
+    let bind_id = track.target.bind_id().unwrap();
+    retarget_model.get_entity_id_by_bind_id(bind_id)?;
+    let scale = match retarget_scale(bind_id, anim_model, retarget_model) {
+        Some(scale) => scale,
+        None => return Some(()),
+    };
     match &mut track.outputs {
         AnimationOutputs::Vec3 { data, .. } => {
             for v in data.iter_mut() {
                 *v *= scale;
             }
         }
-        AnimationOutputs::Quat { .. } => unreachable!(),
+        AnimationOutputs::Quat { .. } | AnimationOutputs::Weight { .. } => unreachable!(),
         AnimationOutputs::Vec3Field { data, .. } => {
             for v in data.iter_mut() {
                 *v *= scale;
@@ -0,0 +1,155 @@
+use std::collections::{HashMap, HashSet};
+
+use ambient_core::{
+    asset_cache, time,
+    transform::{rotation, scale, translation},
+};
+use ambient_ecs::{EntityId, World};
+use glam::{Mat4, Quat, Vec3};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{animation_controller, animation_graph_actions, AnimationOutput, BlendContext, Vec3Field};
+
+/// A node of the hierarchy of a model, for animating the model where it isn't spawned, such as on the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkeletonNode {
+    /// The animation bind id of the node, if it has one
+    pub bind_id: Option<String>,
+    /// The index of the parent of this node in the skeleton. Parents come before their children
+    pub parent: Option<usize>,
+    /// The base pose of the node, relative to its parent
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+/// The transforms of the nodes of `skeleton` in the space of the model, posed by the animations `id` is playing now. The
+/// animation graph of the entity is used if it has one, like on the clients. Nodes that aren't animated, or whose clips
+/// haven't loaded yet, keep their base pose.
+pub fn sample_skeleton(world: &World, id: EntityId, skeleton: &[SkeletonNode]) -> Result<Vec<Mat4>, String> {
+    let assets = world.resource(asset_cache()).clone();
+    // The animations are blended to placeholder entities, as the nodes aren't spawned
+    let nodes = skeleton.iter().map(|_| EntityId::new()).collect_vec();
+    let binder = skeleton.iter().zip(&nodes).filter_map(|(node, id)| Some((node.bind_id.clone()?, *id))).collect::<HashMap<_, _>>();
+    let ctx = BlendContext::new(world, id, &binder, &assets, *world.resource(time()));
+    let mut outputs = HashMap::new();
+    if let Ok(layers) = world.get_ref(id, animation_graph_actions()) {
+        ctx.blend_layers(layers, |mask| mask_nodes(skeleton, &nodes, mask), &mut outputs)?;
+    } else if let Ok(controller) = world.get_ref(id, animation_controller()) {
+        ctx.blend(&controller.actions, None, &mut outputs)?;
+    }
+
+    let indices = nodes.iter().enumerate().map(|(i, id)| (*id, i)).collect::<HashMap<_, _>>();
+    let mut poses = skeleton.iter().map(|node| (node.translation, node.rotation, node.scale)).collect_vec();
+    for output in outputs.into_values() {
+        let (pos, rot, scl) = match indices.get(&output.target) {
+            Some(i) => &mut poses[*i],
+            None => continue,
+        };
+        match output.value {
+            AnimationOutput::Vec3 { component, value } if component == translation() => *pos = value,
+            AnimationOutput::Vec3 { component, value } if component == scale() => *scl = value,
+            AnimationOutput::Quat { component, value } if component == rotation() => *rot = value,
+            AnimationOutput::Vec3Field { component, field, value } => {
+                let vector = if component == translation() {
+                    pos
+                } else if component == scale() {
+                    scl
+                } else {
+                    continue;
+                };
+                match field {
+                    Vec3Field::X => vector.x = value,
+                    Vec3Field::Y => vector.y = value,
+                    Vec3Field::Z => vector.z = value,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut transforms: Vec<Mat4> = Vec::with_capacity(skeleton.len());
+    for (node, (pos, rot, scl)) in skeleton.iter().zip(poses) {
+        let transform = Mat4::from_scale_rotation_translation(scl, rot, pos);
+        transforms.push(node.parent.map(|parent| transforms[parent] * transform).unwrap_or(transform));
+    }
+    Ok(transforms)
+}
+
+/// The placeholder entities of the nodes in `mask` and all of their descendants, or `None` if the mask is empty
+fn mask_nodes(skeleton: &[SkeletonNode], nodes: &[EntityId], mask: &[String]) -> Option<HashSet<EntityId>> {
+    if mask.is_empty() {
+        return None;
+    }
+    let mut masked = Vec::with_capacity(skeleton.len());
+    for node in skeleton {
+        let in_mask = node.bind_id.as_ref().map(|bind_id| mask.contains(bind_id)).unwrap_or(false);
+        masked.push(in_mask || node.parent.map(|parent| masked[parent]).unwrap_or(false));
+    }
+    Some(nodes.iter().zip(masked).filter(|(_, masked)| *masked).map(|(id, _)| *id).collect())
+}
+
+#[cfg(test)]
+fn node(bind_id: &str, parent: Option<usize>, translation: Vec3) -> SkeletonNode {
+    SkeletonNode { bind_id: Some(bind_id.to_string()), parent, translation, rotation: Quat::IDENTITY, scale: Vec3::ONE }
+}
+
+#[tokio::test]
+async fn test_sample_skeleton() {
+    use std::{f32::consts::FRAC_PI_2, sync::Arc, time::Duration};
+
+    use ambient_ecs::EntityData;
+    use ambient_std::asset_cache::AssetCache;
+    use glam::vec3;
+
+    use crate::{
+        AnimationAction, AnimationActionTime, AnimationClip, AnimationClipRef, AnimationController, AnimationGraphLayerActions,
+        AnimationOutputs, AnimationTarget, AnimationTrack,
+    };
+
+    ambient_core::init_all_components();
+    ambient_model::init_components();
+    crate::init_components();
+
+    let skeleton = vec![
+        SkeletonNode { bind_id: None, parent: None, translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: Vec3::splat(0.5) },
+        node("Hips", Some(0), vec3(0., 0., 2.)),
+        node("Spine", Some(1), vec3(0., 0., 1.)),
+        node("Head", Some(2), vec3(0., 0., 1.)),
+    ];
+    let bend = Quat::from_rotation_x(FRAC_PI_2);
+    let clip = AnimationClip::from_tracks(vec![AnimationTrack {
+        target: AnimationTarget::BinderId("Spine".to_string()),
+        inputs: vec![0., 1.],
+        outputs: AnimationOutputs::Quat { component: rotation(), data: vec![bend, bend] },
+    }]);
+    let actions = vec![AnimationAction {
+        clip: AnimationClipRef::Clip(Arc::new(clip)),
+        time: AnimationActionTime::Absolute { time: 0.5 },
+        looping: false,
+        weight: 1.,
+    }];
+
+    let mut world = World::new("test_sample_skeleton");
+    world.add_resource(asset_cache(), AssetCache::new(tokio::runtime::Handle::current()));
+    world.add_resource(time(), Duration::ZERO);
+    let positions = |transforms: Vec<Mat4>| transforms.iter().map(|transform| transform.w_axis.truncate()).collect_vec();
+    let close = |a: Vec<Vec3>, b: Vec<Vec3>| a.iter().zip(&b).all(|(a, b)| a.distance(*b) < 1e-5);
+
+    let base = vec![Vec3::ZERO, vec3(0., 0., 1.), vec3(0., 0., 1.5), vec3(0., 0., 2.)];
+    let id = EntityData::new().spawn(&mut world);
+    assert!(close(positions(sample_skeleton(&world, id, &skeleton).unwrap()), base.clone()));
+
+    // The children of the animated node follow it
+    let bent = vec![Vec3::ZERO, vec3(0., 0., 1.), vec3(0., 0., 1.5), vec3(0., -0.5, 1.5)];
+    world.add_component(id, animation_controller(), AnimationController { actions: actions.clone(), apply_base_pose: false }).unwrap();
+    assert!(close(positions(sample_skeleton(&world, id, &skeleton).unwrap()), bent.clone()));
+
+    // Only the nodes in the mask of a layer are animated by it
+    let layers = |mask: &str| vec![AnimationGraphLayerActions { mask: vec![mask.to_string()], weight: 1., actions: actions.clone() }];
+    world.add_component(id, animation_graph_actions(), layers("Hips")).unwrap();
+    assert!(close(positions(sample_skeleton(&world, id, &skeleton).unwrap()), bent));
+    world.set(id, animation_graph_actions(), layers("Head")).unwrap();
+    assert!(close(positions(sample_skeleton(&world, id, &skeleton).unwrap()), base));
+}
@@ -238,6 +238,21 @@ impl AssetCache {
         cache.insert(key.clone(), SyncAssetLoc { _key: key, content: Arc::new(Mutex::new(Some(Arc::new(asset) as Arc<dyn AssetHolder>))) });
     }
 
+    /// Expires all loaded assets whose key matches `should_invalidate`, so that they are loaded again the next time they are requested.
+    ///
+    /// Existing references to the assets are unaffected. Assets that are currently loading are not invalidated.
+    pub fn invalidate(&self, should_invalidate: impl Fn(&str) -> bool) {
+        let mut async_ = self.async_cache.lock();
+        for (key, asset) in &mut *async_ {
+            if matches!(asset.content, ContentState::Loaded { .. }) && should_invalidate(key) {
+                tracing::debug!("Invalidating asset: {key:?}");
+                asset.content = ContentState::Expired;
+                asset.keepalive_task = None;
+                self.timeline.lock().dropped(key);
+            }
+        }
+    }
+
     fn clean_up_dropped(&self) {
         let mut async_ = self.async_cache.lock();
         for (key, asset) in &mut *async_ {
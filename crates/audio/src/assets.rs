@@ -74,3 +74,27 @@ impl GetAssetType for VorbisTrack {
         AssetType::VorbisTrack
     }
 }
+
+/// The bytes of a vorbis file to be streamed with [`stream_vorbis`](crate::vorbis::stream_vorbis),
+/// shared by all the streams of the file
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct VorbisStreamFromUrl {
+    pub url: AbsAssetUrl,
+}
+
+#[async_trait]
+impl AsyncAssetKey<Result<Arc<[u8]>, Arc<Error>>> for VorbisStreamFromUrl {
+    async fn load(
+        self,
+        assets: ambient_std::asset_cache::AssetCache,
+    ) -> Result<Arc<[u8]>, Arc<Error>>
+    where
+        Self: 'async_trait,
+    {
+        BytesFromUrl::new(self.url.clone(), true)
+            .get(&assets)
+            .await
+            .map(|v| Arc::from(&v[..]))
+            .map_err(|e| Arc::new(e.into()))
+    }
+}
@@ -112,6 +112,43 @@ impl TransferFunction for Bpf {
     }
 }
 
+/// The state of a second order filter, for filtering frames one at a time
+#[derive(Debug, Clone)]
+pub struct Biquad {
+    pub c: BltCoeffs,
+    x1: Vec2,
+    x2: Vec2,
+    y1: Vec2,
+    y2: Vec2,
+}
+
+impl Biquad {
+    pub fn new(c: BltCoeffs) -> Self {
+        Self {
+            c,
+            x1: Vec2::ZERO,
+            x2: Vec2::ZERO,
+            y1: Vec2::ZERO,
+            y2: Vec2::ZERO,
+        }
+    }
+
+    pub fn process(&mut self, sample: Vec2) -> Vec2 {
+        let y = self.c.b0 * sample + self.c.b1 * self.x1 + self.c.b2 * self.x2
+            - self.c.a1 * self.y1
+            - self.c.a2 * self.y2;
+
+        // Slide
+        self.x2 = self.x1;
+        self.x1 = sample;
+
+        self.y2 = self.y1;
+        self.y1 = y;
+
+        y
+    }
+}
+
 pub trait TransferFunction {
     fn get_coeffs(&self, sample_freq: SampleRate) -> BltCoeffs;
 }
@@ -122,13 +159,9 @@ where
     Vh: for<'x> Value<'x, Item = H>,
 {
     source: S,
-    c: BltCoeffs,
+    biquad: Biquad,
     filter: Vh,
     prev_filter: H,
-    x1: Vec2,
-    x2: Vec2,
-    y1: Vec2,
-    y2: Vec2,
 }
 
 impl<S, H, Vh> BilinearTransform<S, H, Vh>
@@ -144,11 +177,7 @@ where
 
         Self {
             source,
-            c,
-            x1: Vec2::ZERO,
-            x2: Vec2::ZERO,
-            y1: Vec2::ZERO,
-            y2: Vec2::ZERO,
+            biquad: Biquad::new(c),
             prev_filter: f,
             filter,
         }
@@ -164,25 +193,14 @@ where
     fn next_sample(&mut self) -> Option<crate::Frame> {
         let filter = self.filter.get();
         if self.prev_filter != *filter {
-            self.c = filter.get_coeffs(self.sample_rate());
+            self.biquad.c = filter.get_coeffs(self.sample_rate());
             self.prev_filter = filter.clone();
         }
         drop(filter);
 
         let sample = self.source.next_sample()?;
 
-        let y = self.c.b0 * sample + self.c.b1 * self.x1 + self.c.b2 * self.x2
-            - self.c.a1 * self.y1
-            - self.c.a2 * self.y2;
-
-        // Slide
-        self.x2 = self.x1;
-        self.x1 = sample;
-
-        self.y2 = self.y1;
-        self.y1 = y;
-
-        Some(y)
+        Some(self.biquad.process(sample))
     }
 
     fn sample_rate(&self) -> SampleRate {
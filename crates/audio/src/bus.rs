@@ -0,0 +1,397 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{AudioMixer, Frame, ReverbParams, ReverbState, SampleRate, Source};
+
+/// The buses sounds are mixed in.
+///
+/// `Master` is played on the output, and all the other buses are mixed into it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Bus {
+    Master,
+    Music,
+    Sfx,
+    Voice,
+    Ui,
+}
+
+impl Bus {
+    pub const ALL: [Bus; 5] = [Bus::Master, Bus::Music, Bus::Sfx, Bus::Voice, Bus::Ui];
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::Sfx
+    }
+}
+
+/// How loud a bus is
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BusLevels {
+    pub gain: f32,
+    pub muted: bool,
+}
+
+impl BusLevels {
+    fn target_gain(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.gain
+        }
+    }
+}
+
+impl Default for BusLevels {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            muted: false,
+        }
+    }
+}
+
+/// The levels of many buses, which can be transitioned to at once
+pub type BusSnapshot = HashMap<Bus, BusLevels>;
+
+/// Lowers the gain of a bus while another bus plays, such as the music while someone speaks
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Ducking {
+    /// The bus which ducks this bus when it plays
+    pub trigger: Bus,
+    /// The gain of this bus while it's ducked
+    pub gain: f32,
+    /// The peak level of the trigger bus above which it ducks this bus
+    pub threshold: f32,
+    /// How long it takes to duck this bus
+    pub attack: Duration,
+    /// How long it takes for this bus to recover after the trigger bus is quiet
+    pub release: Duration,
+}
+
+/// An effect on the mixed output of a bus
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BusEffect {
+    LowPass { freq: f32, bandwidth: f32 },
+    HighPass { freq: f32, bandwidth: f32 },
+    BandPass { freq: f32, bandwidth: f32 },
+    Gain { gain: f32 },
+}
+
+impl BusEffect {
+    fn apply(&self, source: Box<dyn Source>) -> Box<dyn Source> {
+        match *self {
+            BusEffect::LowPass { freq, bandwidth } => Box::new(source.low_pass(freq, bandwidth)),
+            BusEffect::HighPass { freq, bandwidth } => Box::new(source.high_pass(freq, bandwidth)),
+            BusEffect::BandPass { freq, bandwidth } => Box::new(source.band_pass(freq, bandwidth)),
+            BusEffect::Gain { gain } => Box::new(source.gain(gain)),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct BusState {
+    levels: BusLevels,
+    /// How long it takes to go from the current gain to `levels`
+    transition: Duration,
+    /// Incremented when the levels change, which starts a new transition
+    levels_version: u64,
+    effects: Vec<BusEffect>,
+    effects_version: u64,
+    ducking: Option<Ducking>,
+    reverb: Option<ReverbParams>,
+    /// The peak level of the latest output of the bus
+    peak: f32,
+}
+
+/// The state of the buses of a mixer. This is shared with the outputs of the buses, and does not
+/// own their mixers, as that would make the mixers own themselves.
+pub(crate) type BusStates = Arc<Mutex<HashMap<Bus, BusState>>>;
+
+pub(crate) fn set_levels(states: &BusStates, bus: Bus, levels: BusLevels, transition: Duration) {
+    let mut states = states.lock();
+    let state = states.entry(bus).or_default();
+    state.levels = levels;
+    state.transition = transition;
+    state.levels_version += 1;
+}
+
+pub(crate) fn levels(states: &BusStates, bus: Bus) -> BusLevels {
+    states
+        .lock()
+        .get(&bus)
+        .map(|v| v.levels)
+        .unwrap_or_default()
+}
+
+pub(crate) fn set_effects(states: &BusStates, bus: Bus, effects: Vec<BusEffect>) {
+    let mut states = states.lock();
+    let state = states.entry(bus).or_default();
+    state.effects = effects;
+    state.effects_version += 1;
+}
+
+pub(crate) fn set_ducking(states: &BusStates, bus: Bus, ducking: Option<Ducking>) {
+    states.lock().entry(bus).or_default().ducking = ducking;
+}
+
+pub(crate) fn set_reverb(states: &BusStates, bus: Bus, reverb: Option<ReverbParams>) {
+    states.lock().entry(bus).or_default().reverb = reverb;
+}
+
+/// Plays the mixer of a bus with the levels, effects and ducking of the bus
+pub(crate) struct BusOutput {
+    bus: Bus,
+    mixer: AudioMixer,
+    states: BusStates,
+    /// The mixer with the effects of the bus applied
+    chain: Box<dyn Source>,
+    effects_version: u64,
+    levels_version: u64,
+    gain: f32,
+    target_gain: f32,
+    /// How much the gain changes every frame until it reaches the target
+    gain_step: f32,
+    duck_gain: f32,
+    reverb: Option<ReverbState>,
+    buffer: Vec<Frame>,
+}
+
+impl BusOutput {
+    pub(crate) fn new(bus: Bus, mixer: AudioMixer, states: BusStates) -> Self {
+        let (gain, levels_version) = {
+            let mut states = states.lock();
+            let state = states.entry(bus).or_default();
+            (state.levels.target_gain(), state.levels_version)
+        };
+
+        Self {
+            bus,
+            chain: Box::new(mixer.clone()),
+            mixer,
+            states,
+            // The effects are applied on the first output
+            effects_version: u64::MAX,
+            levels_version,
+            gain,
+            target_gain: gain,
+            gain_step: 0.0,
+            duck_gain: 1.0,
+            reverb: None,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Debug for BusOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BusOutput")
+            .field("bus", &self.bus)
+            .field("gain", &self.gain)
+            .field("duck_gain", &self.duck_gain)
+            .finish()
+    }
+}
+
+impl Source for BusOutput {
+    fn next_sample(&mut self) -> Option<Frame> {
+        let mut output = [Frame::ZERO];
+        self.sample_buffered(&mut output);
+        Some(output[0])
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.mixer.sample_rate()
+    }
+
+    fn sample_count(&self) -> Option<u64> {
+        None
+    }
+
+    fn sample_buffered(&mut self, output: &mut [Frame]) -> usize {
+        let sample_rate = self.sample_rate() as f32;
+
+        // The states are not locked while the mixer plays, as the buses mixed into this one lock
+        // them too
+        let (ducking, trigger_peak, reverb) = {
+            let mut states = self.states.lock();
+            let state = states.entry(self.bus).or_default();
+            if state.effects_version != self.effects_version {
+                self.effects_version = state.effects_version;
+                self.chain = state.effects.iter().fold(
+                    Box::new(self.mixer.clone()) as Box<dyn Source>,
+                    |source, effect| effect.apply(source),
+                );
+            }
+            if state.levels_version != self.levels_version {
+                self.levels_version = state.levels_version;
+                self.target_gain = state.levels.target_gain();
+                let frames = (state.transition.as_secs_f32() * sample_rate).max(1.0);
+                self.gain_step = (self.target_gain - self.gain) / frames;
+            }
+            let ducking = state.ducking;
+            let reverb = state.reverb;
+            let trigger_peak = ducking
+                .and_then(|ducking| states.get(&ducking.trigger))
+                .map(|trigger| trigger.peak)
+                .unwrap_or_default();
+            (ducking, trigger_peak, reverb)
+        };
+
+        self.buffer.clear();
+        self.buffer.resize(output.len(), Frame::ZERO);
+        self.chain.sample_buffered(&mut self.buffer);
+
+        match reverb {
+            Some(params) => {
+                let state = self
+                    .reverb
+                    .get_or_insert_with(|| ReverbState::new(sample_rate as SampleRate));
+                for frame in &mut self.buffer {
+                    *frame = state.process(*frame, &params);
+                }
+            }
+            None => self.reverb = None,
+        }
+
+        let (duck_target, duck_step) = match ducking {
+            Some(ducking) => {
+                let target = if trigger_peak > ducking.threshold {
+                    ducking.gain
+                } else {
+                    1.0
+                };
+                let time = if target < self.duck_gain {
+                    ducking.attack
+                } else {
+                    ducking.release
+                };
+                (target, 1.0 / (time.as_secs_f32() * sample_rate).max(1.0))
+            }
+            None => (1.0, 1.0),
+        };
+
+        let mut peak = 0.0f32;
+        for (out, frame) in output.iter_mut().zip(&self.buffer) {
+            if self.gain_step != 0.0 {
+                self.gain += self.gain_step;
+                if (self.gain_step > 0.0) == (self.gain >= self.target_gain) {
+                    self.gain = self.target_gain;
+                    self.gain_step = 0.0;
+                }
+            }
+            self.duck_gain += (duck_target - self.duck_gain).clamp(-duck_step, duck_step);
+
+            let frame = *frame * self.gain * self.duck_gain;
+            peak = peak.max(frame.abs().max_element());
+            *out += frame;
+        }
+
+        self.states.lock().entry(self.bus).or_default().peak = peak;
+
+        output.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::BufferedSource;
+
+    #[test]
+    fn bus_levels() {
+        let mut mixer = AudioMixer::new(4);
+        mixer.play_on(Bus::Music, BufferedSource::new(vec![1.0; 16], 1, 4));
+        assert_eq!(mixer.next_sample(), Some(Frame::splat(1.0)));
+
+        mixer.set_bus_levels(
+            Bus::Music,
+            BusLevels {
+                gain: 0.5,
+                muted: false,
+            },
+            Duration::ZERO,
+        );
+        assert_eq!(mixer.next_sample(), Some(Frame::splat(0.5)));
+
+        mixer.set_bus_levels(
+            Bus::Master,
+            BusLevels {
+                gain: 1.0,
+                muted: true,
+            },
+            Duration::ZERO,
+        );
+        assert_eq!(mixer.next_sample(), Some(Frame::splat(0.0)));
+    }
+
+    fn assert_close(a: &[f32], b: &[f32]) {
+        assert!(
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5),
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn snapshot() {
+        let mut mixer = AudioMixer::new(10);
+        mixer.play_on(Bus::Music, BufferedSource::new(vec![1.0; 16], 1, 10));
+        mixer.play_on(Bus::Sfx, BufferedSource::new(vec![1.0; 16], 1, 10));
+        assert_eq!(mixer.next_sample(), Some(Frame::splat(2.0)));
+
+        let quiet = BusLevels {
+            gain: 0.5,
+            muted: false,
+        };
+        let muted = BusLevels {
+            gain: 1.0,
+            muted: true,
+        };
+        let snapshot = BusSnapshot::from([(Bus::Music, quiet), (Bus::Sfx, muted)]);
+        mixer.apply_snapshot(&snapshot, Duration::from_millis(400));
+        assert_eq!(mixer.bus_levels(Bus::Music), quiet);
+        assert_eq!(mixer.bus_levels(Bus::Sfx), muted);
+        assert_eq!(mixer.bus_levels(Bus::Voice), BusLevels::default());
+
+        // All the buses fade to their levels over the transition, and stay there
+        let samples = (0..6)
+            .map(|_| mixer.next_sample().unwrap().x)
+            .collect::<Vec<_>>();
+        assert_close(&samples, &[1.625, 1.25, 0.875, 0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn ducking() {
+        let mut mixer = AudioMixer::new(10);
+        mixer.set_bus_ducking(
+            Bus::Music,
+            Some(Ducking {
+                trigger: Bus::Voice,
+                gain: 0.2,
+                threshold: 0.1,
+                attack: Duration::from_millis(400),
+                release: Duration::from_millis(800),
+            }),
+        );
+        mixer.play_on(Bus::Music, BufferedSource::new(vec![1.0; 64], 1, 10));
+        assert_eq!(mixer.next_sample(), Some(Frame::splat(1.0)));
+
+        // The music is ducked over the attack once the voice plays, as of the next frame
+        mixer.play_on(Bus::Voice, BufferedSource::new(vec![0.5; 8], 1, 10));
+        let music = (0..8)
+            .map(|_| mixer.next_sample().unwrap().x - 0.5)
+            .collect::<Vec<_>>();
+        assert_close(&music, &[1.0, 0.75, 0.5, 0.25, 0.2, 0.2, 0.2, 0.2]);
+
+        // And recovers over the release once the voice is quiet
+        let music = (0..9)
+            .map(|_| mixer.next_sample().unwrap().x)
+            .collect::<Vec<_>>();
+        assert_close(
+            &music,
+            &[0.2, 0.325, 0.45, 0.575, 0.7, 0.825, 0.95, 1.0, 1.0],
+        );
+    }
+}
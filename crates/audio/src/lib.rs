@@ -1,6 +1,8 @@
 mod assets;
+mod bus;
 mod error;
 mod mixer;
+mod music;
 // mod sink;
 mod stream;
 
@@ -18,8 +20,10 @@ pub mod vorbis;
 pub mod wav;
 
 pub use assets::*;
+pub use bus::{Bus, BusEffect, BusLevels, BusSnapshot, Ducking};
 pub use error::*;
 pub use mixer::*;
+pub use music::*;
 // pub use sink::*;
 pub use source::*;
 pub use spatial::*;
@@ -1,12 +1,12 @@
 use std::{
-    future::Future, sync::{Arc, Weak}, task::Poll, thread, time::Duration
+    collections::HashMap, future::Future, sync::{Arc, Weak}, task::Poll, thread, time::Duration
 };
 
 use parking_lot::Mutex;
 use slotmap::{new_key_type, SlotMap};
 
 use crate::{
-    signal::{AsyncSignal, BlockingSignal, Signal}, Frame, SampleConversion, SampleRate, Source
+    bus::{self, BusOutput, BusStates}, signal::{AsyncSignal, BlockingSignal, Signal}, Bus, BusEffect, BusLevels, BusSnapshot, Ducking, Frame, ReverbParams, SampleConversion, SampleRate, Source
 };
 
 new_key_type! {
@@ -99,6 +99,9 @@ struct AudioMixerInner {
     sample_rate: SampleRate,
     waiters: Mutex<SignalVec>,
     sources: Mutex<SlotMap<SoundId, PlayingSound>>,
+    bus_states: BusStates,
+    /// The mixers of the buses, which are created when they're first used
+    bus_mixers: Mutex<HashMap<Bus, AudioMixer>>,
 }
 
 impl std::fmt::Debug for AudioMixerInner {
@@ -117,6 +120,8 @@ impl AudioMixer {
                 sample_rate,
                 sources: Mutex::default(),
                 waiters: Default::default(),
+                bus_states: Default::default(),
+                bus_mixers: Default::default(),
             }),
         }
     }
@@ -148,6 +153,65 @@ impl AudioMixer {
         }
     }
 
+    /// Returns the mixer of a bus of this mixer.
+    ///
+    /// Sounds played on the returned mixer are affected by the levels, effects and ducking of the
+    /// bus. All buses except `Master` are mixed into `Master`.
+    pub fn bus(&self, bus: Bus) -> AudioMixer {
+        let mixer = {
+            let mut mixers = self.inner.bus_mixers.lock();
+            if let Some(mixer) = mixers.get(&bus) {
+                return mixer.clone();
+            }
+            let mixer = AudioMixer::new(self.inner.sample_rate);
+            mixers.insert(bus, mixer.clone());
+            mixer
+        };
+
+        let output = BusOutput::new(bus, mixer.clone(), self.inner.bus_states.clone());
+        match bus {
+            Bus::Master => self.play(output),
+            _ => self.bus(Bus::Master).play(output),
+        };
+
+        mixer
+    }
+
+    /// Play a source on a bus of the mixer
+    pub fn play_on<S: Source + 'static>(&self, bus: Bus, source: S) -> Sound {
+        self.bus(bus).play(source)
+    }
+
+    /// Changes the levels of a bus, fading to them over `transition`
+    pub fn set_bus_levels(&self, bus: Bus, levels: BusLevels, transition: Duration) {
+        bus::set_levels(&self.inner.bus_states, bus, levels, transition);
+    }
+
+    pub fn bus_levels(&self, bus: Bus) -> BusLevels {
+        bus::levels(&self.inner.bus_states, bus)
+    }
+
+    /// Changes the levels of all the buses in the snapshot, fading to them over `transition`
+    pub fn apply_snapshot(&self, snapshot: &BusSnapshot, transition: Duration) {
+        for (&bus, &levels) in snapshot {
+            self.set_bus_levels(bus, levels, transition);
+        }
+    }
+
+    /// Replaces the effects of a bus, which are applied in order to its mixed output
+    pub fn set_bus_effects(&self, bus: Bus, effects: Vec<BusEffect>) {
+        bus::set_effects(&self.inner.bus_states, bus, effects);
+    }
+
+    pub fn set_bus_ducking(&self, bus: Bus, ducking: Option<Ducking>) {
+        bus::set_ducking(&self.inner.bus_states, bus, ducking);
+    }
+
+    /// Sets the room whose reverb is added to the output of the bus
+    pub fn set_bus_reverb(&self, bus: Bus, reverb: Option<ReverbParams>) {
+        bus::set_reverb(&self.inner.bus_states, bus, reverb);
+    }
+
     fn notify_sound_waiters(&self, id: SoundId) {
         // Wake the wakers which are parked on this id, and remove them from the waiting list
         self.inner.waiters.lock().retain_mut(|(sound_id, signal)| {
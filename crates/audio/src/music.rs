@@ -0,0 +1,162 @@
+use std::{collections::VecDeque, fmt::Debug, sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+
+use crate::{Crossfade, Frame, SampleConversion, SampleRate, Source};
+
+#[derive(Default)]
+struct MusicPlayerState {
+    current: Option<Box<dyn Source>>,
+    queue: VecDeque<Box<dyn Source>>,
+    finished: bool,
+}
+
+/// Plays tracks one after the other, without gaps, and crossfades between them.
+///
+/// Plays silence while there is nothing to play, until it's finished. Clones share the same
+/// player.
+#[derive(Clone)]
+pub struct MusicPlayer {
+    sample_rate: SampleRate,
+    state: Arc<Mutex<MusicPlayerState>>,
+}
+
+impl Debug for MusicPlayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MusicPlayer")
+            .field("sample_rate", &self.sample_rate)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MusicPlayer {
+    pub fn new(sample_rate: SampleRate) -> Self {
+        Self {
+            sample_rate,
+            state: Default::default(),
+        }
+    }
+
+    fn convert<S: Source + 'static>(&self, track: S) -> Box<dyn Source> {
+        if track.sample_rate() == self.sample_rate {
+            Box::new(track)
+        } else {
+            Box::new(SampleConversion::new(track, self.sample_rate as _))
+        }
+    }
+
+    /// Plays a track now, instead of the current one
+    pub fn play<S: Source + 'static>(&self, track: S) {
+        let track = self.convert(track);
+        self.state.lock().current = Some(track);
+    }
+
+    /// Plays a track after the current one and the queued ones
+    pub fn queue<S: Source + 'static>(&self, track: S) {
+        let track = self.convert(track);
+        self.state.lock().queue.push_back(track);
+    }
+
+    /// Removes the tracks which were queued
+    pub fn clear_queue(&self) {
+        self.state.lock().queue.clear();
+    }
+
+    /// Fades from the current track to another one over `duration`, starting now
+    pub fn crossfade<S: Source + 'static>(&self, track: S, duration: Duration) {
+        let track = self.convert(track);
+        let mut state = self.state.lock();
+        state.current = Some(match state.current.take() {
+            // The current track is cut to the fade, so that the fade starts on the next sample
+            Some(current) if !duration.is_zero() => {
+                let duration = current
+                    .duration()
+                    .map_or(duration, |left| left.min(duration));
+                Box::new(Crossfade::new(current.slice(..duration), track, duration))
+            }
+            _ => track,
+        });
+    }
+
+    /// Whether a track is playing
+    pub fn is_playing(&self) -> bool {
+        self.state.lock().current.is_some()
+    }
+
+    /// Stops the player for good, which removes it from the mixer it's played on
+    pub fn finish(&self) {
+        let mut state = self.state.lock();
+        state.current = None;
+        state.queue.clear();
+        state.finished = true;
+    }
+}
+
+impl Source for MusicPlayer {
+    fn next_sample(&mut self) -> Option<Frame> {
+        let mut output = [Frame::ZERO];
+        if self.sample_buffered(&mut output) == 0 {
+            return None;
+        }
+        Some(output[0])
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    fn sample_count(&self) -> Option<u64> {
+        None
+    }
+
+    fn sample_buffered(&mut self, output: &mut [Frame]) -> usize {
+        let mut state = self.state.lock();
+        if state.finished {
+            return 0;
+        }
+
+        let mut written = 0;
+        while written < output.len() {
+            if state.current.is_none() {
+                state.current = state.queue.pop_front();
+            }
+            let Some(current) = &mut state.current else {
+                // Silence is played until there is something to play
+                break;
+            };
+            match current.next_sample() {
+                Some(frame) => {
+                    output[written] += frame;
+                    written += 1;
+                }
+                None => state.current = None,
+            }
+        }
+
+        output.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::BufferedSource;
+
+    #[test]
+    fn queue_and_crossfade() {
+        let player = MusicPlayer::new(4);
+        player.play(BufferedSource::new(vec![1.0; 2], 1, 4));
+        player.queue(BufferedSource::new(vec![2.0; 8], 1, 4));
+
+        let next = || player.clone().next_sample().unwrap().x;
+        // The queued track follows without a gap
+        assert_eq!([next(), next(), next()], [1.0, 1.0, 2.0]);
+
+        player.crossfade(
+            BufferedSource::new(vec![4.0; 8], 1, 4),
+            Duration::from_millis(500),
+        );
+        let next = || player.clone().next_sample().unwrap().x;
+        assert_eq!([next(), next(), next()], [2.0, 3.0, 4.0]);
+    }
+}
@@ -9,10 +9,12 @@ mod oscilloscope;
 mod pad_to;
 mod peek;
 mod repeat;
+mod reverb;
 mod sample_bufferer;
 mod sample_rate;
 mod slice;
 mod spatial;
+mod speed;
 pub mod streaming_source;
 mod uniform;
 use std::{
@@ -28,14 +30,16 @@ pub use mix::*;
 use parking_lot::Mutex;
 pub use peek::*;
 pub use repeat::*;
+pub use reverb::*;
 pub use sample_rate::*;
 pub use slice::*;
 pub use spatial::*;
+pub use speed::*;
 pub use uniform::*;
 
 use self::{history::History, mix::Mix, oscilloscope::Oscilloscope, pad_to::PadTo};
 use crate::{
-    blt::{BilinearTransform, Hpf, Lpf, TransferFunction}, hrtf::HrtfLib, value::{Constant, Value}, AudioEmitter, AudioListener, Frame, SampleRate
+    blt::{BilinearTransform, Bpf, Hpf, Lpf, TransferFunction}, hrtf::HrtfLib, value::{Constant, Value}, AudioEmitter, AudioListener, Frame, SampleRate
 };
 
 /// A source represents a continuous stream of stereo audio samples.
@@ -153,6 +157,23 @@ pub trait Source: Send {
         Gain::new(self, gain)
     }
 
+    /// Plays the source faster or slower, which also raises or lowers its pitch
+    fn speed(self, speed: f32) -> Speed<Self>
+    where
+        Self: Sized,
+    {
+        Speed::new(self, speed)
+    }
+
+    /// Adds the reverb of a room to the source
+    fn reverb<P>(self, params: P) -> Reverb<Self, P>
+    where
+        Self: Sized,
+        P: for<'x> Value<'x, Item = ReverbParams>,
+    {
+        Reverb::new(self, params)
+    }
+
     fn spatial<L, P>(self, hrtf_lib: &HrtfLib, listener: L, params: P) -> Spatial<Self, L, P>
     where
         Self: Sized,
@@ -176,6 +197,13 @@ pub trait Source: Send {
         BilinearTransform::new(self, Constant(Lpf { freq, bandwidth }))
     }
 
+    fn band_pass(self, freq: f32, bandwidth: f32) -> BilinearTransform<Self, Bpf, Constant<Bpf>>
+    where
+        Self: Sized,
+    {
+        BilinearTransform::new(self, Constant(Bpf { freq, bandwidth }))
+    }
+
     fn blt<V, H>(self, transfer: V) -> BilinearTransform<Self, H, V>
     where
         Self: Sized,
@@ -1,4 +1,4 @@
-use crate::Source;
+use crate::{Frame, SampleRate, Source, Uniform};
 
 #[derive(Debug, Clone)]
 pub struct Repeat<S> {
@@ -41,3 +41,108 @@ where
         self.source.sample_count()
     }
 }
+
+/// Repeats the sources made by a function, one after the other, `count` times or until the
+/// function returns None.
+///
+/// Unlike [`Repeat`], this doesn't require the source to be cloneable, and every repetition can be
+/// different. They are all converted to the sample rate of the first one.
+pub struct RepeatWith<F> {
+    make_source: F,
+    source: Option<Uniform<Box<dyn Source>>>,
+    sample_rate: SampleRate,
+    remaining: Option<u32>,
+}
+
+impl<F> RepeatWith<F>
+where
+    F: FnMut() -> Option<Box<dyn Source>>,
+{
+    pub fn new(mut make_source: F, count: Option<u32>) -> Self {
+        let source = match count {
+            Some(0) => None,
+            _ => make_source(),
+        };
+        let sample_rate = source.as_ref().map(|v| v.sample_rate()).unwrap_or(1);
+        Self {
+            make_source,
+            source: source.map(|v| Uniform::new(v, sample_rate)),
+            sample_rate,
+            remaining: count.map(|v| v.saturating_sub(1)),
+        }
+    }
+}
+
+impl<F> std::fmt::Debug for RepeatWith<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RepeatWith")
+            .field("sample_rate", &self.sample_rate)
+            .field("remaining", &self.remaining)
+            .finish()
+    }
+}
+
+impl<F> Source for RepeatWith<F>
+where
+    F: FnMut() -> Option<Box<dyn Source>> + Send,
+{
+    fn next_sample(&mut self) -> Option<Frame> {
+        if let Some(v) = self.source.as_mut()?.next_sample() {
+            return Some(v);
+        }
+
+        // Start the next repetition. An empty one ends the repeat, as it would never yield
+        self.source = match &mut self.remaining {
+            Some(0) => None,
+            Some(remaining) => {
+                *remaining -= 1;
+                (self.make_source)()
+            }
+            None => (self.make_source)(),
+        }
+        .map(|v| Uniform::new(v, self.sample_rate));
+        self.source.as_mut()?.next_sample()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    fn sample_count(&self) -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::BufferedSource;
+
+    #[test]
+    fn repeat_with() {
+        let mut n = 0.0;
+        let mut source = RepeatWith::new(
+            || {
+                n += 1.0;
+                Some(Box::new(BufferedSource::new(vec![n, n], 1, 2)) as Box<dyn Source>)
+            },
+            Some(3),
+        );
+
+        let samples = std::iter::from_fn(|| source.next_sample()).collect_vec();
+
+        assert_eq!(
+            samples,
+            [
+                Frame::splat(1.0),
+                Frame::splat(1.0),
+                Frame::splat(2.0),
+                Frame::splat(2.0),
+                Frame::splat(3.0),
+                Frame::splat(3.0)
+            ]
+        );
+    }
+}
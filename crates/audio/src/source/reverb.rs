@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{value::Value, Frame, SampleRate, Source};
+
+/// The lengths of the comb filters at 44.1 kHz, from Freeverb
+const COMB_LENGTHS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+/// The lengths of the all-pass filters at 44.1 kHz, from Freeverb
+const ALLPASS_LENGTHS: [usize; 4] = [556, 441, 341, 225];
+/// How much longer the filters of the right channel are, to decorrelate it from the left one
+const STEREO_SPREAD: usize = 23;
+const INPUT_GAIN: f32 = 0.015;
+const ALLPASS_FEEDBACK: f32 = 0.5;
+
+/// How a room sounds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReverbParams {
+    /// How large the room is, from 0 to 1, which sets how long the reverb lasts
+    pub room_size: f32,
+    /// How much high frequencies are absorbed by the room, from 0 to 1
+    pub damping: f32,
+    /// How loud the reverb is compared to the original sound, from 0 to 1
+    pub wet: f32,
+}
+
+impl Default for ReverbParams {
+    fn default() -> Self {
+        Self {
+            room_size: 0.5,
+            damping: 0.5,
+            wet: 0.0,
+        }
+    }
+}
+
+impl ReverbParams {
+    /// Interpolates between two rooms
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            room_size: self.room_size + (other.room_size - self.room_size) * t,
+            damping: self.damping + (other.damping - self.damping) * t,
+            wet: self.wet + (other.wet - self.wet) * t,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Comb {
+    buffer: Box<[f32]>,
+    index: usize,
+    filter_store: f32,
+}
+
+impl Comb {
+    fn new(len: usize) -> Self {
+        Self {
+            buffer: vec![0.0; len.max(1)].into_boxed_slice(),
+            index: 0,
+            filter_store: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.filter_store = output * (1.0 - damping) + self.filter_store * damping;
+        self.buffer[self.index] = input + self.filter_store * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Allpass {
+    buffer: Box<[f32]>,
+    index: usize,
+}
+
+impl Allpass {
+    fn new(len: usize) -> Self {
+        Self {
+            buffer: vec![0.0; len.max(1)].into_boxed_slice(),
+            index: 0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let buffered = self.buffer[self.index];
+        self.buffer[self.index] = input + buffered * ALLPASS_FEEDBACK;
+        self.index = (self.index + 1) % self.buffer.len();
+        buffered - input
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Channel {
+    combs: Vec<Comb>,
+    allpasses: Vec<Allpass>,
+}
+
+impl Channel {
+    fn new(sample_rate: SampleRate, spread: usize) -> Self {
+        let scale = |len: usize| (len + spread) * sample_rate as usize / 44100;
+        Self {
+            combs: COMB_LENGTHS
+                .iter()
+                .map(|&len| Comb::new(scale(len)))
+                .collect(),
+            allpasses: ALLPASS_LENGTHS
+                .iter()
+                .map(|&len| Allpass::new(scale(len)))
+                .collect(),
+        }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output: f32 = self
+            .combs
+            .iter_mut()
+            .map(|comb| comb.process(input, feedback, damping))
+            .sum();
+        self.allpasses
+            .iter_mut()
+            .fold(output, |output, allpass| allpass.process(output))
+    }
+}
+
+/// The state of an algorithmic reverb, for processing frames one at a time
+#[derive(Debug, Clone)]
+pub struct ReverbState {
+    left: Channel,
+    right: Channel,
+}
+
+impl ReverbState {
+    pub fn new(sample_rate: SampleRate) -> Self {
+        Self {
+            left: Channel::new(sample_rate, 0),
+            right: Channel::new(sample_rate, STEREO_SPREAD),
+        }
+    }
+
+    /// Returns the frame with the reverb of the room added to it
+    pub fn process(&mut self, frame: Frame, params: &ReverbParams) -> Frame {
+        let feedback = 0.7 + params.room_size.clamp(0.0, 1.0) * 0.28;
+        let damping = params.damping.clamp(0.0, 1.0) * 0.4;
+        let input = (frame.x + frame.y) * INPUT_GAIN;
+
+        let wet = Frame::new(
+            self.left.process(input, feedback, damping),
+            self.right.process(input, feedback, damping),
+        );
+
+        frame + wet * params.wet.clamp(0.0, 1.0)
+    }
+}
+
+/// Adds the reverb of a room to a source
+#[derive(Debug, Clone)]
+pub struct Reverb<S, P> {
+    source: S,
+    params: P,
+    state: ReverbState,
+}
+
+impl<S, P> Reverb<S, P>
+where
+    S: Source,
+    P: for<'x> Value<'x, Item = ReverbParams>,
+{
+    pub fn new(source: S, params: P) -> Self {
+        let state = ReverbState::new(source.sample_rate());
+        Self {
+            source,
+            params,
+            state,
+        }
+    }
+}
+
+impl<S, P> Source for Reverb<S, P>
+where
+    S: Source,
+    P: for<'x> Value<'x, Item = ReverbParams>,
+{
+    fn next_sample(&mut self) -> Option<Frame> {
+        let sample = self.source.next_sample()?;
+        let params = *self.params.get();
+        Some(self.state.process(sample, &params))
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.source.sample_rate()
+    }
+
+    fn sample_count(&self) -> Option<u64> {
+        self.source.sample_count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{value::Constant, BufferedSource};
+
+    #[test]
+    fn reverb_tail() {
+        let mut samples = vec![0.0; 44100];
+        samples[0] = 1.0;
+        let params = ReverbParams {
+            room_size: 0.8,
+            damping: 0.2,
+            wet: 1.0,
+        };
+        let mut source = Reverb::new(BufferedSource::new(samples, 1, 44100), Constant(params));
+
+        assert_eq!(source.next_sample(), Some(Frame::splat(1.0)));
+        // The impulse is heard again after the shortest comb filter has delayed it
+        let tail = std::iter::from_fn(|| source.next_sample())
+            .skip(COMB_LENGTHS[0])
+            .take(4410)
+            .map(|frame| frame.abs().max_element())
+            .fold(0.0, f32::max);
+        assert!(tail > 0.0);
+    }
+}
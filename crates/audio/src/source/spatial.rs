@@ -3,7 +3,7 @@ use std::{ops::Deref, time::Duration};
 use glam::Vec3;
 
 use crate::{
-    hrtf::{Hrtf, HrtfContext, HrtfLib}, value::Value, AudioEmitter, AudioListener, Frame, Source, MAX_ANGULAR_SPEED, MAX_SPEED
+    blt::{Biquad, Lpf, TransferFunction}, hrtf::{Hrtf, HrtfContext, HrtfLib}, occlusion_cutoff, value::Value, AudioEmitter, AudioListener, Frame, SampleRate, Source, MAX_ANGULAR_SPEED, MAX_SPEED
 };
 
 #[derive(Debug)]
@@ -17,10 +17,17 @@ pub struct Spatial<S, L, E> {
     cur: usize,
     listener: L,
     emitter: E,
+    /// Muffles the sound while the emitter is occluded
+    occlusion_filter: Biquad,
+    /// The occlusion the filter is set to, which follows the occlusion of the emitter over a few
+    /// blocks so that its cutoff doesn't jump
+    occlusion: f32,
 }
 
 const BLOCK_DURATION: Duration = Duration::from_millis(15);
 const INTERPOLATION_STEPS: u32 = 8;
+/// How much the occlusion of the filter changes every block
+const OCCLUSION_STEP: f32 = 0.2;
 
 impl<S, L, E> Spatial<S, L, E>
 where
@@ -33,11 +40,11 @@ where
         let block_len = (sample_rate as f32 * BLOCK_DURATION.as_secs_f32()).round() as _;
         let buf_len = block_len * INTERPOLATION_STEPS as usize;
 
-        let ctx = {
+        let (ctx, occlusion) = {
             let listener = listener.get();
             let emitter = emitter.get();
 
-            Self::calculate_hrtf_context(
+            let ctx = Self::calculate_hrtf_context(
                 listener
                     .deref()
                     .transform()
@@ -45,7 +52,8 @@ where
                     .transform_point3(emitter.pos),
                 &listener,
                 &emitter,
-            )
+            );
+            (ctx, emitter.occlusion.clamp(0.0, 1.0))
         };
 
         Self {
@@ -56,6 +64,17 @@ where
             len: 0,
             cur: 0,
             prev_to_source: ctx.to_source(),
+            occlusion_filter: Biquad::new(
+                Self::occlusion_filter(occlusion, sample_rate).get_coeffs(sample_rate),
+            ),
+            occlusion,
+        }
+    }
+
+    fn occlusion_filter(occlusion: f32, sample_rate: SampleRate) -> Lpf {
+        Lpf {
+            freq: occlusion_cutoff(occlusion, sample_rate),
+            bandwidth: 1.0,
         }
     }
 
@@ -87,7 +106,7 @@ where
             to_source,
             listener.ear_distance / 2.0,
             emitter.attenuation,
-            emitter.amplitude,
+            emitter.amplitude * emitter.occlusion_gain(),
         )
     }
 }
@@ -111,18 +130,35 @@ where
             self.cur += 1;
             Some(s)
         } else {
-            let ctx = {
+            let sample_rate = self.sample_rate();
+            let (ctx, target_occlusion) = {
                 let listener = self.listener.get();
                 let emitter = self.emitter.get();
 
                 let ctx = Self::calculate_hrtf_context(self.prev_to_source, &listener, &emitter);
                 self.prev_to_source = ctx.to_source();
 
-                ctx
+                (ctx, emitter.occlusion.clamp(0.0, 1.0))
             };
 
+            let occlusion = self.occlusion
+                + (target_occlusion - self.occlusion).clamp(-OCCLUSION_STEP, OCCLUSION_STEP);
+            if occlusion != self.occlusion {
+                self.occlusion_filter.c =
+                    Self::occlusion_filter(occlusion, sample_rate).get_coeffs(sample_rate);
+            }
+
             let new_len = self.hrtf.process(ctx, &mut self.output_buffer);
             self.len = new_len;
+
+            // The filter is only bypassed once its cutoff is back up to where it doesn't muffle
+            // the sound, so that it doesn't stop abruptly
+            if occlusion > 0.0 || self.occlusion > 0.0 {
+                for frame in &mut self.output_buffer[..new_len] {
+                    *frame = self.occlusion_filter.process(*frame);
+                }
+            }
+            self.occlusion = occlusion;
             self.cur = 1;
             if new_len == 0 {
                 return None;
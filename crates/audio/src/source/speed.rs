@@ -0,0 +1,41 @@
+use crate::{SampleRate, Source};
+
+/// Plays a source faster or slower by changing its sample rate, which also raises or lowers its pitch
+#[derive(Debug, Clone)]
+pub struct Speed<S> {
+    source: S,
+    sample_rate: SampleRate,
+}
+
+impl<S: Source> Speed<S> {
+    pub fn new(source: S, speed: f32) -> Self {
+        let sample_rate = ((source.sample_rate() as f32 * speed).round() as SampleRate).max(1);
+        Self {
+            source,
+            sample_rate,
+        }
+    }
+}
+
+impl<S> Source for Speed<S>
+where
+    S: Source,
+{
+    #[inline]
+    fn next_sample(&mut self) -> Option<crate::Frame> {
+        self.source.next_sample()
+    }
+
+    #[inline]
+    fn sample_buffered(&mut self, output: &mut [crate::Frame]) -> usize {
+        self.source.sample_buffered(output)
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.sample_rate
+    }
+
+    fn sample_count(&self) -> Option<u64> {
+        self.source.sample_count()
+    }
+}
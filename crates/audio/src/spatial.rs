@@ -3,8 +3,14 @@ use std::f32::consts::E;
 use glam::{Mat4, Vec3};
 use serde::{Deserialize, Serialize};
 
+use crate::SampleRate;
+
 /// The speed of sound in units/s
 pub const SPEED_OF_SOUND: f32 = 343.0;
+/// The cutoff frequency of the low-pass filter of a fully occluded emitter
+pub const OCCLUDED_CUTOFF: f32 = 500.0;
+/// The gain of a fully occluded emitter
+pub const OCCLUDED_GAIN: f32 = 0.3;
 /// The "maximum" speed of the source relative to the listener, in
 /// units/block, where block is `block_len / sample_rate ~= BLOCK_DURATION`.
 ///
@@ -23,6 +29,29 @@ pub struct AudioEmitter {
     pub amplitude: f32,
     pub pos: Vec3,
     pub attenuation: Attenuation,
+    /// How much of the sound is blocked between the emitter and the listener, from 0 to 1.
+    ///
+    /// Occluded sounds are quieter and muffled.
+    pub occlusion: f32,
+}
+
+impl AudioEmitter {
+    /// The gain of the emitter, given how occluded it is
+    pub fn occlusion_gain(&self) -> f32 {
+        1.0 + (OCCLUDED_GAIN - 1.0) * self.occlusion.clamp(0.0, 1.0)
+    }
+
+    /// The cutoff frequency of the low-pass filter of the emitter, given how occluded it is
+    pub fn occlusion_cutoff(&self, sample_rate: SampleRate) -> f32 {
+        occlusion_cutoff(self.occlusion, sample_rate)
+    }
+}
+
+/// The cutoff frequency of the low-pass filter of an emitter which is `occlusion` occluded
+pub fn occlusion_cutoff(occlusion: f32, sample_rate: SampleRate) -> f32 {
+    let open_cutoff = sample_rate as f32 * 0.45;
+    // The cutoff is interpolated in octaves, as that's how pitch is heard
+    open_cutoff * (OCCLUDED_CUTOFF / open_cutoff).powf(occlusion.clamp(0.0, 1.0))
 }
 
 impl Default for AudioEmitter {
@@ -31,6 +60,7 @@ impl Default for AudioEmitter {
             amplitude: 1.0,
             pos: Default::default(),
             attenuation: Default::default(),
+            occlusion: 0.0,
         }
     }
 }
@@ -1,10 +1,20 @@
-use std::{io::Cursor, sync::Arc};
+use std::{io::Cursor, sync::Arc, thread, time::Duration};
 
 use derivative::Derivative;
 use itertools::Itertools;
 use lewton::inside_ogg::OggStreamReader;
+use serde::{Deserialize, Serialize};
 
-use crate::{ChannelCount, Error, Frame, Result, SampleRate, Source};
+use crate::{
+    source::streaming_source::StreamingSource, ChannelCount, Error, Frame, Result, SampleRate,
+    Source,
+};
+
+/// How many decoded packets are buffered ahead of a streamed track
+const STREAM_BUFFERED_PACKETS: usize = 64;
+/// How many frames further back a stream seeks again, when it lands past where it should be.
+/// Doubles with each try
+const SEEK_STEP: u64 = 4096;
 
 /// A packet of multi-channel interleaved samples
 struct FramedSamples {
@@ -160,3 +170,278 @@ impl Source for VorbisDecodeStream {
         Some(self.decoded_len as _)
     }
 }
+
+/// Where a streamed track loops
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct LoopPoints {
+    /// Where the track jumps back to, from its start
+    pub start: Duration,
+    /// Where the track jumps back from, or the end of the track if there is none
+    pub end: Option<Duration>,
+}
+
+/// Decodes a vorbis stream on a background thread, a packet at a time, instead of decoding it
+/// whole. The track loops back to `loop_points.start` when it reaches their end, down to the
+/// sample.
+///
+/// Plays silence if the decoding falls behind.
+pub fn stream_vorbis(
+    bytes: Arc<[u8]>,
+    loop_points: Option<LoopPoints>,
+) -> Result<StreamingSource<VorbisChunks>> {
+    let (chunks, sample_rate) = spawn_decoder(bytes, loop_points)?;
+    Ok(StreamingSource::new(chunks, sample_rate))
+}
+
+/// Starts decoding a vorbis stream on its thread, and returns the frames it decodes along with
+/// their sample rate
+fn spawn_decoder(
+    bytes: Arc<[u8]>,
+    loop_points: Option<LoopPoints>,
+) -> Result<(VorbisChunks, SampleRate)> {
+    let streamer = OggStreamReader::new(Cursor::new(bytes.clone()))?;
+    let sample_rate: SampleRate = streamer.ident_hdr.audio_sample_rate as _;
+    let to_frames = |dur: Duration| (sample_rate * dur.as_nanos() as u64 / 1_000_000_000) as usize;
+    let loop_frames =
+        loop_points.map(|points| (to_frames(points.start), points.end.map(to_frames)));
+
+    let (tx, rx) = flume::bounded(STREAM_BUFFERED_PACKETS);
+    thread::Builder::new()
+        .name("vorbis_stream".into())
+        .spawn(move || {
+            let mut streamer = streamer;
+            // The position in the track of the next decoded frame
+            let mut pos = 0;
+            // Frames before this are dropped, after looping back
+            let mut skip_to = 0;
+            // Whether anything has been played since the start or the latest loop
+            let mut played = false;
+            // The frames decoded while seeking, which are played before the next packet
+            let mut seeked = None;
+            loop {
+                let packet = match seeked.take() {
+                    Some(frames) => Ok(Some(frames)),
+                    None => read_packet(&mut streamer),
+                };
+                let mut packet = match packet {
+                    Ok(Some(packet)) => packet,
+                    // Without loop points, the stream ends with the track
+                    Ok(None) if loop_frames.is_none() => return,
+                    Ok(None) => Vec::new(),
+                    Err(err) => {
+                        tracing::error!("Failed to decode vorbis stream: {err}");
+                        return;
+                    }
+                };
+                let end = pos + packet.len();
+
+                let loop_end = loop_frames.and_then(|(_, end)| end);
+                let ended = packet.is_empty() || loop_end.map_or(false, |loop_end| end >= loop_end);
+                if let Some(loop_end) = loop_end {
+                    packet.truncate(loop_end.saturating_sub(pos));
+                }
+                let packet = packet.split_off(skip_to.saturating_sub(pos).min(packet.len()));
+                pos = end;
+
+                if !packet.is_empty() {
+                    played = true;
+                    if tx.send(packet).is_err() {
+                        // The stream has been dropped
+                        return;
+                    }
+                }
+
+                if let (true, Some((start, _))) = (ended, loop_frames) {
+                    if !played {
+                        // The loop is empty, so the track would loop forever without playing
+                        return;
+                    }
+                    match seek(&mut streamer, &bytes, start) {
+                        Ok((seeked_pos, frames)) => {
+                            pos = seeked_pos;
+                            seeked = Some(frames).filter(|frames| !frames.is_empty());
+                        }
+                        Err(err) => {
+                            tracing::error!("Failed to seek vorbis stream: {err}");
+                            return;
+                        }
+                    }
+                    skip_to = start;
+                    played = false;
+                }
+            }
+        })
+        .expect("Failed to spawn vorbis stream thread");
+
+    // Wait for the first packet, so that the track doesn't start with silence
+    let chunk = rx.recv().unwrap_or_default();
+
+    Ok((
+        VorbisChunks {
+            rx,
+            chunk,
+            cursor: 0,
+        },
+        sample_rate,
+    ))
+}
+
+type StreamReader = OggStreamReader<Cursor<Arc<[u8]>>>;
+
+fn read_packet(streamer: &mut StreamReader) -> Result<Option<Vec<Frame>>> {
+    match streamer.read_dec_packet_generic::<FramedSamples>()? {
+        Some(packet) => Ok(Some(packet.samples?)),
+        None => Ok(None),
+    }
+}
+
+/// Seeks the stream to at most `frame`. Returns the position of the frames decoded next, and the
+/// frames which were decoded to find it.
+///
+/// Vorbis only records the position at the end of each page, and seeks to a page, so the
+/// position is known once a page has been decoded. Seeking goes further back until the page
+/// starts before `frame`, and decodes the track from its start as a last resort.
+fn seek(
+    streamer: &mut StreamReader,
+    bytes: &Arc<[u8]>,
+    frame: usize,
+) -> Result<(usize, Vec<Frame>)> {
+    let mut target = frame as u64;
+    let mut step = SEEK_STEP;
+    while target > 0 {
+        streamer.seek_absgp_pg(target)?;
+        let mut frames = Vec::new();
+        while let Some(packet) = read_packet(streamer)? {
+            frames.extend(packet);
+            // The position is set once the last packet of a page has been read
+            if let Some(page_end) = streamer.get_last_absgp() {
+                let start = (page_end as usize).saturating_sub(frames.len());
+                if start <= frame {
+                    return Ok((start, frames));
+                }
+                break;
+            }
+        }
+        target = target.saturating_sub(step);
+        step *= 2;
+    }
+
+    *streamer = OggStreamReader::new(Cursor::new(bytes.clone()))?;
+    Ok((0, Vec::new()))
+}
+
+/// The frames decoded by the thread of a vorbis stream
+pub struct VorbisChunks {
+    rx: flume::Receiver<Vec<Frame>>,
+    chunk: Vec<Frame>,
+    cursor: usize,
+}
+
+impl Iterator for VorbisChunks {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        while self.cursor >= self.chunk.len() {
+            match self.rx.try_recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.cursor = 0;
+                }
+                Err(flume::TryRecvError::Empty) => return Some(Frame::ZERO),
+                Err(flume::TryRecvError::Disconnected) => return None,
+            }
+        }
+
+        let frame = self.chunk[self.cursor];
+        self.cursor += 1;
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        f32::consts::TAU,
+        num::{NonZeroU32, NonZeroU8},
+    };
+
+    use vorbis_rs::{VorbisBitrateManagementStrategy, VorbisEncoderBuilder};
+
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 44100;
+    /// The period of the tone, in frames. The loop is a whole number of periods long, so that the
+    /// tone goes on smoothly across the seam
+    const PERIOD: usize = 100;
+
+    fn tone(frame: usize) -> f32 {
+        0.5 * (TAU * (frame % PERIOD) as f32 / PERIOD as f32).sin()
+    }
+
+    fn encode_tone(frames: usize) -> Arc<[u8]> {
+        let samples = (0..frames).map(tone).collect_vec();
+        let mut bytes = Vec::new();
+        let mut builder = VorbisEncoderBuilder::new(
+            NonZeroU32::new(SAMPLE_RATE).unwrap(),
+            NonZeroU8::new(1).unwrap(),
+            &mut bytes,
+        )
+        .unwrap();
+        builder.bitrate_management_strategy(VorbisBitrateManagementStrategy::QualityVbr {
+            target_quality: 1.,
+        });
+        let mut encoder = builder.build().unwrap();
+        encoder.encode_audio_block([&samples[..]]).unwrap();
+        encoder.finish().unwrap();
+        bytes.into()
+    }
+
+    /// Plays `frames` frames of a stream, waiting for the decoder instead of playing silence when
+    /// it falls behind
+    fn play(mut chunks: VorbisChunks, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .map(|_| {
+                while chunks.cursor >= chunks.chunk.len() {
+                    chunks.chunk = chunks.rx.recv().expect("The stream ended");
+                    chunks.cursor = 0;
+                }
+                chunks.next().unwrap().x
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loops_without_gaps() {
+        let bytes = encode_tone(SAMPLE_RATE as usize * 3);
+        let (start, end) = (SAMPLE_RATE as usize, SAMPLE_RATE as usize * 2);
+        let loop_points = LoopPoints {
+            start: Duration::from_secs(1),
+            end: Some(Duration::from_secs(2)),
+        };
+        let (chunks, sample_rate) = spawn_decoder(bytes, Some(loop_points)).unwrap();
+        assert_eq!(sample_rate, SAMPLE_RATE as SampleRate);
+
+        // Three times through the loop
+        let loop_len = end - start;
+        let played = play(chunks, end + loop_len * 2);
+        for seam in [end, end + loop_len] {
+            for frame in seam - 2 * PERIOD..seam + 2 * PERIOD {
+                // Each frame after the seam is the one from the start of the loop
+                let expected = tone(if frame < seam {
+                    frame
+                } else {
+                    frame - loop_len
+                });
+                assert!(
+                    (played[frame] - expected).abs() < 0.05,
+                    "Frame {frame} is {}, instead of {expected}",
+                    played[frame]
+                );
+            }
+            // And the loop is the same every time
+            for frame in seam..seam + loop_len / 10 {
+                assert!((played[frame] - played[frame - loop_len]).abs() < 1e-4);
+            }
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// The version of the files written by the asset pipelines. Bump this whenever the format of an output changes
+/// without a new version of Ambient, such as the encoding of animation clips, so that existing caches are discarded.
+pub const PIPELINE_FORMAT_VERSION: u32 = 4;
+
+/// A content-hash based record of what the asset pipelines were last run with, stored in `build/build_cache.json`.
+///
+/// Each `pipeline.json` is keyed by its path relative to `assets/`. A pipeline is considered up to date if its
+/// config and all files in its directory hash to the same values as last time, in which case it is skipped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BuildCache {
+    /// The version of Ambient that wrote this cache. The cache is discarded when this changes.
+    pub version: String,
+    /// The [`PIPELINE_FORMAT_VERSION`] of the outputs. The cache is discarded when this changes.
+    #[serde(default)]
+    pub format_version: u32,
+    /// Content hashes of input files, keyed by path relative to `assets/`. Used to avoid re-hashing unmodified files.
+    pub files: BTreeMap<String, FileHash>,
+    pub pipelines: BTreeMap<String, PipelineCacheEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileHash {
+    pub size: u64,
+    /// Modification time, in nanoseconds since the unix epoch
+    pub modified: u64,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PipelineCacheEntry {
+    /// The hash of the `pipeline.json` itself
+    pub config: String,
+    /// All files the pipeline could read, relative to `assets/`, and their hashes
+    pub inputs: BTreeMap<String, String>,
+    /// All files written by the pipeline, relative to `build/assets/`
+    pub outputs: BTreeSet<String>,
+}
+impl PipelineCacheEntry {
+    /// The directory of the `pipeline.json`, relative to `assets/`. The pipeline processes the files in this directory.
+    pub fn root(pipeline_path: &str) -> &str {
+        pipeline_path.rsplit_once('/').map(|(root, _)| root).unwrap_or("")
+    }
+    pub fn is_up_to_date(&self, previous: &PipelineCacheEntry) -> bool {
+        self.config == previous.config && self.inputs == previous.inputs
+    }
+}
+
+/// Returns true if `path` is in the directory `root`; all paths are relative and `/`-separated
+pub fn is_in_directory(path: &str, root: &str) -> bool {
+    root.is_empty() || path.strip_prefix(root).map(|rest| rest.starts_with('/')).unwrap_or(false)
+}
+
+pub fn hash_bytes(data: &[u8]) -> String {
+    hex::encode(ring::digest::digest(&ring::digest::SHA256, data))
+}
+
+impl BuildCache {
+    pub const FILE_NAME: &'static str = "build_cache.json";
+
+    pub fn new() -> Self {
+        Self { version: env!("CARGO_PKG_VERSION").to_string(), format_version: PIPELINE_FORMAT_VERSION, ..Default::default() }
+    }
+
+    /// Loads the cache from the build directory. Returns an empty cache if there is none, or if it can't be used.
+    pub fn load(build_path: &Path) -> Self {
+        let cache = std::fs::read(build_path.join(Self::FILE_NAME))
+            .ok()
+            .and_then(|data| serde_json::from_slice::<BuildCache>(&data).map_err(|err| log::warn!("Invalid build cache: {err}")).ok());
+        match cache {
+            Some(cache) if cache.version == env!("CARGO_PKG_VERSION") && cache.format_version == PIPELINE_FORMAT_VERSION => cache,
+            _ => Self::new(),
+        }
+    }
+
+    pub fn save(&self, build_path: &Path) -> anyhow::Result<()> {
+        std::fs::write(build_path.join(Self::FILE_NAME), serde_json::to_vec_pretty(self)?).context("Failed to write build cache")
+    }
+
+    /// Returns the content hash of a file, relative to `assets_path`. Files that have the same size and
+    /// modification time as when they were last hashed are not read again.
+    pub fn hash_file(&mut self, assets_path: &Path, path: &str) -> anyhow::Result<String> {
+        let full_path = assets_path.join(path);
+        let metadata = std::fs::metadata(&full_path).with_context(|| format!("Failed to stat {full_path:?}"))?;
+        let size = metadata.len();
+        let modified = metadata.modified()?.duration_since(UNIX_EPOCH)?.as_nanos() as u64;
+        if let Some(file) = self.files.get(path) {
+            if file.size == size && file.modified == modified {
+                return Ok(file.hash.clone());
+            }
+        }
+        let hash = hash_bytes(&std::fs::read(&full_path).with_context(|| format!("Failed to read {full_path:?}"))?);
+        self.files.insert(path.to_string(), FileHash { size, modified, hash: hash.clone() });
+        Ok(hash)
+    }
+
+    /// Hashes the config and inputs of every pipeline in `pipeline_paths`. `files` are all files in `assets/`.
+    pub fn hash_pipelines(
+        &mut self,
+        assets_path: &Path,
+        files: &[String],
+        pipeline_paths: &[String],
+    ) -> anyhow::Result<BTreeMap<String, PipelineCacheEntry>> {
+        let mut pipelines = BTreeMap::new();
+        for pipeline_path in pipeline_paths {
+            let root = PipelineCacheEntry::root(pipeline_path);
+            let mut entry = PipelineCacheEntry { config: self.hash_file(assets_path, pipeline_path)?, ..Default::default() };
+            for file in files.iter().filter(|file| is_in_directory(file, root)) {
+                entry.inputs.insert(file.clone(), self.hash_file(assets_path, file)?);
+            }
+            pipelines.insert(pipeline_path.clone(), entry);
+        }
+        // Forget files that no longer exist
+        let files = files.iter().collect::<HashSet<_>>();
+        self.files.retain(|path, _| files.contains(path));
+        Ok(pipelines)
+    }
+
+    /// Assigns each written file to the pipeline with the deepest directory that contains it
+    pub fn attribute_outputs<'a>(
+        pipeline_paths: impl Iterator<Item = &'a String> + Clone,
+        written: &[String],
+    ) -> BTreeMap<String, BTreeSet<String>> {
+        let mut outputs = BTreeMap::<String, BTreeSet<String>>::new();
+        for file in written {
+            let owner = pipeline_paths
+                .clone()
+                .filter(|pipeline_path| is_in_directory(file, PipelineCacheEntry::root(pipeline_path)))
+                .max_by_key(|pipeline_path| PipelineCacheEntry::root(pipeline_path).len());
+            if let Some(owner) = owner {
+                outputs.entry(owner.clone()).or_default().insert(file.clone());
+            }
+        }
+        outputs
+    }
+}
+
+/// Removes files that were written to `build/assets/` by a previous build
+pub fn remove_outputs<'a>(build_assets_path: &Path, outputs: impl IntoIterator<Item = &'a String>) {
+    for output in outputs {
+        let path = build_assets_path.join(output);
+        match std::fs::remove_file(&path) {
+            Ok(()) => log::info!("Removed stale output {output}"),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => log::warn!("Failed to remove stale output {path:?}: {err}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_in_directory() {
+        assert!(is_in_directory("models/a.glb", ""));
+        assert!(is_in_directory("models/a.glb", "models"));
+        assert!(!is_in_directory("models2/a.glb", "models"));
+        assert!(!is_in_directory("models", "models"));
+    }
+
+    #[test]
+    fn test_attribute_outputs() {
+        let pipelines = vec!["pipeline.json".to_string(), "models/pipeline.json".to_string()];
+        let written = vec!["models/a/models/main.json".to_string(), "music.ogg".to_string()];
+        let outputs = BuildCache::attribute_outputs(pipelines.iter(), &written);
+        assert_eq!(outputs["models/pipeline.json"], BTreeSet::from_iter(["models/a/models/main.json".to_string()]));
+        assert_eq!(outputs["pipeline.json"], BTreeSet::from_iter(["music.ogg".to_string()]));
+    }
+
+    #[test]
+    fn test_up_to_date() {
+        let entry = PipelineCacheEntry {
+            config: "a".to_string(),
+            inputs: BTreeMap::from_iter([("pipeline.json".to_string(), "a".to_string())]),
+            outputs: Default::default(),
+        };
+        assert!(entry.is_up_to_date(&entry.clone()));
+        let mut changed = entry.clone();
+        changed.inputs.insert("b.glb".to_string(), "b".to_string());
+        assert!(!changed.is_up_to_date(&entry));
+        let failed = PipelineCacheEntry { config: String::new(), ..entry.clone() };
+        assert!(!entry.is_up_to_date(&failed));
+    }
+
+    #[test]
+    fn test_load_discards_other_format_versions() {
+        let build_path = std::env::temp_dir().join(format!("ambient_build_cache_test_{}", std::process::id()));
+        std::fs::create_dir_all(&build_path).unwrap();
+
+        let mut cache = BuildCache::new();
+        cache.pipelines.insert("pipeline.json".to_string(), Default::default());
+        cache.save(&build_path).unwrap();
+        assert_eq!(BuildCache::load(&build_path).pipelines.len(), 1);
+
+        cache.format_version = PIPELINE_FORMAT_VERSION - 1;
+        cache.save(&build_path).unwrap();
+        assert!(BuildCache::load(&build_path).pipelines.is_empty());
+
+        std::fs::remove_dir_all(&build_path).unwrap();
+    }
+}
@@ -0,0 +1,265 @@
+use std::{io::Cursor, path::Path};
+
+use ambient_model_import::{fbx::FbxDoc, ModelTransform};
+use ambient_project::Manifest as ProjectManifest;
+use ambient_std::asset_url::AssetUrl;
+use itertools::Itertools;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::pipelines::{
+    materials::{MaterialsImporter, PipelinePbrMaterial},
+    Pipeline, PipelineConfig,
+};
+
+/// The result of validating a project with [`check`]. Serializes to the JSON report printed by `ambient check`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CheckReport {
+    pub diagnostics: Vec<Diagnostic>,
+}
+impl CheckReport {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.severity == Severity::Error)
+    }
+    pub fn log(&self) {
+        for d in &self.diagnostics {
+            let location = match (d.line, d.column) {
+                (Some(line), Some(column)) => format!("{}:{line}:{column}", d.file),
+                (Some(line), None) => format!("{}:{line}", d.file),
+                _ => d.file.clone(),
+            };
+            match d.severity {
+                Severity::Error => log::error!("{location}: {}", d.message),
+                Severity::Warning => log::warn!("{location}: {}", d.message),
+            }
+        }
+    }
+    fn push(&mut self, severity: Severity, file: &str, location: Option<(usize, usize)>, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            file: file.to_string(),
+            line: location.map(|(line, _)| line),
+            column: location.map(|(_, column)| column),
+            message: message.into(),
+        });
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The file the problem was found in, relative to the project
+    pub file: String,
+    /// 1-based line number, if known
+    pub line: Option<usize>,
+    /// 1-based column number, if known
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+/// Validates the `ambient.toml` and all `pipeline.json` files of the project at `path`, without building anything.
+///
+/// This checks that they parse, and that the files, material URLs and node names referenced by the pipelines exist.
+pub fn check(path: &Path) -> CheckReport {
+    let mut report = CheckReport::default();
+    check_manifest(path, &mut report);
+
+    let assets_path = path.join("assets");
+    let files = WalkDir::new(&assets_path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.path().strip_prefix(&assets_path).unwrap().components().map(|c| c.as_os_str().to_string_lossy()).join("/"))
+        .sorted()
+        .collect_vec();
+    for pipeline_path in files.iter().filter(|file| file.ends_with("pipeline.json")) {
+        let file = format!("assets/{pipeline_path}");
+        match std::fs::read_to_string(assets_path.join(pipeline_path)) {
+            Ok(text) => check_pipeline_file(&assets_path, &files, pipeline_path, &file, &text, &mut report),
+            Err(err) => report.push(Severity::Error, &file, None, format!("Failed to read file: {err}")),
+        }
+    }
+    report
+}
+
+fn check_manifest(path: &Path, report: &mut CheckReport) {
+    const FILE: &str = "ambient.toml";
+    let text = match std::fs::read_to_string(path.join(FILE)) {
+        Ok(text) => text,
+        Err(err) => return report.push(Severity::Error, FILE, None, format!("Failed to read project manifest: {err}")),
+    };
+    let manifest = match ProjectManifest::parse(&text) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            let location = err.span().map(|span| line_column(&text, span.start));
+            return report.push(Severity::Error, FILE, location, err.message());
+        }
+    };
+    // Pipelines can contain these components, so they have to be registered before the pipelines are parsed
+    match manifest.all_defined_components(false) {
+        Ok(components) => ambient_ecs::ComponentRegistry::get_mut().add_external(components),
+        Err(err) => report.push(Severity::Error, FILE, None, err),
+    }
+    if let Err(err) = manifest.all_defined_migrations(false) {
+        report.push(Severity::Error, FILE, None, err);
+    }
+}
+
+fn check_pipeline_file(assets_path: &Path, files: &[String], pipeline_path: &str, file: &str, text: &str, report: &mut CheckReport) {
+    let pipelines = if text.trim_start().starts_with('[') {
+        serde_json::from_str::<Vec<Pipeline>>(text)
+    } else {
+        serde_json::from_str::<Pipeline>(text).map(|pipeline| vec![pipeline])
+    };
+    let pipelines = match pipelines {
+        Ok(pipelines) => pipelines,
+        Err(err) => return report.push(Severity::Error, file, Some((err.line(), err.column())), strip_location(&err.to_string())),
+    };
+
+    let root = pipeline_path.rsplit_once('/').map(|(root, _)| format!("{root}/")).unwrap_or_default();
+    for pipeline in &pipelines {
+        let mut sources = Vec::new();
+        for source in &pipeline.sources {
+            match glob::Pattern::new(source) {
+                Ok(pattern) => sources.push(pattern),
+                Err(err) => {
+                    report.push(Severity::Error, file, find_string(text, source), format!("Invalid source pattern {source:?}: {err}"))
+                }
+            }
+        }
+        let inputs = files
+            .iter()
+            .filter_map(|path| path.strip_prefix(&root))
+            .filter(|path| sources.is_empty() || sources.iter().any(|pattern| pattern.matches(path)))
+            .collect_vec();
+        for (source, pattern) in pipeline.sources.iter().zip(&sources) {
+            if !inputs.iter().any(|path| pattern.matches(path)) {
+                report.push(
+                    Severity::Warning,
+                    file,
+                    find_string(text, source),
+                    format!("Source pattern {source:?} does not match any files"),
+                );
+            }
+        }
+
+        match &pipeline.pipeline {
+            PipelineConfig::Models(config) => {
+                for material in config.material_overrides.iter().map(|o| &o.material) {
+                    check_material(files, &root, material, file, text, report);
+                }
+                for transform in &config.transforms {
+                    if let ModelTransform::SetRoot { name } = transform {
+                        check_node_name(assets_path, &root, &inputs, name, file, text, report);
+                    }
+                }
+            }
+            PipelineConfig::Materials(config) => {
+                if let MaterialsImporter::Single(material) = &*config.importer {
+                    check_material(files, &root, material, file, text, report);
+                }
+            }
+            PipelineConfig::Audio(_) | PipelineConfig::AnimationGraphs(_) => {}
+        }
+    }
+}
+
+/// Relative material URLs are resolved against the directory of the pipeline
+fn check_material(files: &[String], root: &str, material: &PipelinePbrMaterial, file: &str, text: &str, report: &mut CheckReport) {
+    let urls = [&material.base_color, &material.opacity, &material.normalmap, &material.metallic_roughness, &material.specular];
+    for url in urls.into_iter().flatten() {
+        if let AssetUrl::Relative(path) = url {
+            let path = path.normalize();
+            if !files.iter().any(|f| f.strip_prefix(root) == Some(path.as_str())) {
+                report.push(Severity::Error, file, find_string(text, url.path()), format!("Material references missing file {url}"));
+            }
+        }
+    }
+}
+
+/// Node names can only be checked for glTF and FBX models; other formats are not parsed
+fn check_node_name(assets_path: &Path, root: &str, inputs: &[&str], name: &str, file: &str, text: &str, report: &mut CheckReport) {
+    let mut checked_any = false;
+    for input in inputs.iter().filter(|path| [".glb", ".gltf", ".fbx"].iter().any(|extension| path.ends_with(extension))) {
+        let model_path = assets_path.join(format!("{root}{input}"));
+        let node_names = match std::fs::read(&model_path).map_err(anyhow::Error::from).and_then(|data| model_node_names(input, data)) {
+            Ok(node_names) => node_names,
+            Err(err) => {
+                report.push(Severity::Error, &format!("assets/{root}{input}"), None, format!("Failed to parse model: {err}"));
+                continue;
+            }
+        };
+        checked_any = true;
+        if !node_names.iter().any(|node_name| node_name == name) {
+            report.push(Severity::Error, file, find_string(text, name), format!("SetRoot node {name:?} does not exist in model {input}"));
+        }
+    }
+    if !checked_any {
+        log::debug!("No glTF or FBX models to check SetRoot node {name:?} against in {file}");
+    }
+}
+
+fn model_node_names(path: &str, data: Vec<u8>) -> anyhow::Result<Vec<String>> {
+    if path.ends_with(".fbx") {
+        Ok(FbxDoc::from_reader(Cursor::new(data))?.node_names().map(|name| name.to_string()).collect())
+    } else {
+        Ok(gltf::Gltf::from_slice(&data)?.nodes().filter_map(|node| node.name().map(|name| name.to_string())).collect())
+    }
+}
+
+/// Converts a byte offset into a 1-based line and column
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map(|l| l.chars().count()).unwrap_or_default() + 1;
+    (line, column)
+}
+
+/// The location of the first occurrence of `value` as a JSON string in `text`
+fn find_string(text: &str, value: &str) -> Option<(usize, usize)> {
+    text.find(&serde_json::to_string(value).ok()?).map(|offset| line_column(text, offset))
+}
+
+/// serde_json appends " at line X column Y" to its errors, which is redundant in the report
+fn strip_location(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(i) => message[..i].to_string(),
+        None => message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locations() {
+        let text = "{\n  \"sources\": [\"*.glb\"]\n}";
+        assert_eq!(find_string(text, "*.glb"), Some((2, 15)));
+        assert_eq!(find_string(text, "*.fbx"), None);
+        assert_eq!(line_column(text, 0), (1, 1));
+    }
+
+    #[test]
+    fn test_invalid_pipeline() {
+        let mut report = CheckReport::default();
+        let text = "{\n  \"pipeline\": {\n    \"type\": \"Modles\"\n  }\n}";
+        check_pipeline_file(Path::new("assets"), &[], "pipeline.json", "assets/pipeline.json", text, &mut report);
+        assert!(report.has_errors());
+        assert!(report.diagnostics[0].line.is_some());
+    }
+
+    #[test]
+    fn test_model_node_names() {
+        let gltf = br#"{"asset": {"version": "2.0"}, "nodes": [{"name": "Root"}, {}]}"#.to_vec();
+        assert_eq!(model_node_names("model.gltf", gltf).unwrap(), vec!["Root".to_string()]);
+        assert!(model_node_names("model.fbx", b"not an fbx".to_vec()).is_err());
+    }
+}
@@ -1,17 +1,27 @@
 use std::{
+    collections::BTreeMap,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
 };
 
 use ambient_asset_cache::{AssetCache, SyncAssetKeyExt};
 use ambient_physics::physx::{Physics, PhysicsKey};
 use ambient_project::Manifest as ProjectManifest;
 use ambient_std::asset_url::AbsAssetUrl;
+use cache::{remove_outputs, BuildCache, PipelineCacheEntry};
 use futures::FutureExt;
 use itertools::Itertools;
+use notify::{RecursiveMode, Watcher};
+use parking_lot::Mutex;
 use pipelines::{FileCollection, ProcessCtx, ProcessCtxKey};
 use walkdir::WalkDir;
 
+pub mod cache;
+pub mod check;
 pub mod pipelines;
 
 /// This takes the path to an Ambient project and builds it. An Ambient project is expected to
@@ -21,7 +31,9 @@ pub mod pipelines;
 /// src/**  This is where you store Rust source files
 /// build  This is the output directory, and is created when building
 /// ambient.toml  This is a metadata file to describe the project
-pub async fn build(physics: Physics, _assets: &AssetCache, path: PathBuf, manifest: &ProjectManifest) {
+///
+/// Pipelines whose inputs haven't changed since the last build are skipped, unless `clean` is set.
+pub async fn build(physics: Physics, _assets: &AssetCache, path: PathBuf, manifest: &ProjectManifest, clean: bool) {
     log::info!(
         "Building project `{}` ({})",
         manifest.project.id,
@@ -29,23 +41,125 @@ pub async fn build(physics: Physics, _assets: &AssetCache, path: PathBuf, manife
     );
 
     ambient_ecs::ComponentRegistry::get_mut().add_external(manifest.all_defined_components(false).unwrap());
+    ambient_ecs::ComponentRegistry::get_mut().add_migrations(manifest.all_defined_migrations(false).unwrap());
 
     let build_path = path.join("build");
     let assets_path = path.join("assets");
 
     std::fs::create_dir_all(&build_path).unwrap();
-    build_assets(physics, &assets_path, &build_path).await;
+    build_assets(physics, &assets_path, &build_path, clean).await;
     build_scripts(&path, manifest, &build_path).await.unwrap();
 }
 
-async fn build_assets(physics: Physics, assets_path: &Path, build_path: &Path) {
-    let files = WalkDir::new(assets_path)
+/// How long to wait for more changes after a file has changed before rebuilding, as editors often write files in several steps
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+/// How often the `assets/` directory is scanned for changes when it can't be watched
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watches the `assets/` directory of the project at `path`, and rebuilds the assets whenever it changes.
+///
+/// `on_change` is called after each rebuild with the files that were written, relative to `build/`.
+pub async fn watch(physics: Physics, path: PathBuf, on_change: impl Fn(Vec<String>) + Send + Sync) {
+    let build_path = path.join("build");
+    let assets_path = path.join("assets");
+
+    let (tx, rx) = flume::unbounded();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if matches!(event, Ok(event) if !event.kind.is_access()) {
+            tx.send(()).ok();
+        }
+    })
+    .and_then(|mut watcher| watcher.watch(&assets_path, RecursiveMode::Recursive).map(|_| watcher));
+    // The watcher stops when it's dropped
+    let _watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(err) => {
+            log::warn!("Failed to watch {assets_path:?} for changes, polling it instead: {err}");
+            return poll(physics, &assets_path, &build_path, on_change).await;
+        }
+    };
+
+    log::info!("Watching {assets_path:?} for changes");
+    while rx.recv_async().await.is_ok() {
+        tokio::time::sleep(WATCH_DEBOUNCE).await;
+        rx.drain();
+        rebuild(physics.clone(), &assets_path, &build_path, &on_change).await;
+    }
+}
+
+/// The fallback of [`watch`] for platforms and file systems which don't report changes, such as some network drives
+async fn poll(physics: Physics, assets_path: &Path, build_path: &Path, on_change: impl Fn(Vec<String>) + Send + Sync) {
+    let mut snapshot = snapshot_files(assets_path);
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let new_snapshot = snapshot_files(assets_path);
+        if new_snapshot == snapshot {
+            continue;
+        }
+        snapshot = new_snapshot;
+        rebuild(physics.clone(), assets_path, build_path, &on_change).await;
+    }
+}
+
+async fn rebuild(physics: Physics, assets_path: &Path, build_path: &Path, on_change: &(impl Fn(Vec<String>) + Send + Sync)) {
+    log::info!("Assets changed, rebuilding");
+    let written = build_assets(physics, assets_path, build_path, false).await;
+    if !written.is_empty() {
+        on_change(written.into_iter().map(|path| format!("assets/{path}")).collect());
+    }
+}
+
+/// The size and modification time of every file in `path`
+fn snapshot_files(path: &Path) -> BTreeMap<PathBuf, (u64, Option<SystemTime>)> {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok().filter(|x| x.is_file()).map(|x| (e.into_path(), (x.len(), x.modified().ok()))))
+        .collect()
+}
+
+/// Runs the pipelines that need to be rebuilt, and returns the files that were written, relative to `build/assets/`
+async fn build_assets(physics: Physics, assets_path: &Path, build_path: &Path, clean: bool) -> Vec<String> {
+    let build_assets_path = build_path.join("assets");
+    let mut cache = if clean {
+        if build_assets_path.exists() {
+            std::fs::remove_dir_all(&build_assets_path).unwrap();
+        }
+        BuildCache::new()
+    } else {
+        BuildCache::load(build_path)
+    };
+
+    let paths = WalkDir::new(assets_path)
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.metadata().map(|x| x.is_file()).unwrap_or(false))
-        .map(|x| AbsAssetUrl::from_file_path(x.into_path()))
+        .map(|x| x.into_path())
         .collect_vec();
+    let relative_paths = paths
+        .iter()
+        .map(|path| path.strip_prefix(assets_path).unwrap().components().map(|c| c.as_os_str().to_string_lossy()).join("/"))
+        .collect_vec();
+    let pipeline_paths = relative_paths.iter().filter(|path| path.ends_with("pipeline.json")).cloned().collect_vec();
+    let pipelines = cache.hash_pipelines(assets_path, &relative_paths, &pipeline_paths).unwrap();
+
+    // Clean up after pipelines that were removed, and find the ones that need to be rebuilt
+    let removed = cache.pipelines.keys().filter(|path| !pipelines.contains_key(*path)).cloned().collect_vec();
+    for path in removed {
+        let entry = cache.pipelines.remove(&path).unwrap();
+        remove_outputs(&build_assets_path, &entry.outputs);
+    }
+    let dirty = pipelines
+        .iter()
+        .filter(|(path, entry)| cache.pipelines.get(*path).map(|previous| !entry.is_up_to_date(previous)).unwrap_or(true))
+        .map(|(path, _)| path.clone())
+        .collect_vec();
+    log::info!("Building {} pipelines, skipping {} unchanged pipelines", dirty.len(), pipelines.len() - dirty.len());
+
+    let files = paths.into_iter().map(AbsAssetUrl::from_file_path).collect_vec();
     let assets = AssetCache::new_with_config(tokio::runtime::Handle::current(), None);
+    let written = Arc::new(Mutex::new(Vec::new()));
+    let had_errors = Arc::new(AtomicBool::new(false));
     PhysicsKey.insert(&assets, physics);
     let ctx = ProcessCtx {
         assets: assets.clone(),
@@ -56,7 +170,9 @@ async fn build_assets(physics: Physics, assets_path: &Path, build_path: &Path) {
         package_name: "".to_string(),
         write_file: Arc::new({
             let build_path = build_path.to_owned();
+            let written = written.clone();
             move |path, contents| {
+                written.lock().push(path.clone());
                 let path = build_path.join("assets").join(path);
                 async move {
                     std::fs::create_dir_all(path.parent().unwrap()).unwrap();
@@ -70,13 +186,45 @@ async fn build_assets(physics: Physics, assets_path: &Path, build_path: &Path) {
             log::info!("{}", msg);
             async {}.boxed()
         }),
-        on_error: Arc::new(|err| {
-            log::error!("{:?}", err);
-            async {}.boxed()
+        on_error: Arc::new({
+            let had_errors = had_errors.clone();
+            move |err| {
+                log::error!("{:?}", err);
+                had_errors.store(true, Ordering::SeqCst);
+                async {}.boxed()
+            }
         }),
     };
     ProcessCtxKey.insert(&ctx.assets, ctx.clone());
-    pipelines::process_pipelines(&ctx).await;
+    let dirty_files = dirty.iter().map(|path| AbsAssetUrl::from_file_path(assets_path.join(path))).collect_vec();
+    pipelines::process_pipeline_files(&ctx, &dirty_files).await;
+
+    // Record the outputs of the rebuilt pipelines, and remove the outputs they no longer produce
+    let written = written.lock().iter().unique().cloned().collect_vec();
+    let mut outputs = BuildCache::attribute_outputs(pipelines.keys(), &written);
+    for (path, mut entry) in pipelines {
+        let new_outputs = outputs.remove(&path).unwrap_or_default();
+        match cache.pipelines.get(&path) {
+            Some(previous) if !dirty.contains(&path) => {
+                entry.outputs = previous.outputs.union(&new_outputs).cloned().collect();
+            }
+            previous => {
+                if let Some(previous) = previous {
+                    remove_outputs(&build_assets_path, previous.outputs.difference(&new_outputs));
+                }
+                entry.outputs = new_outputs;
+            }
+        }
+        // If anything failed, we don't know which pipeline it was, so make sure all rebuilt pipelines are retried next time
+        if had_errors.load(Ordering::SeqCst) && dirty.contains(&path) {
+            entry = PipelineCacheEntry { config: String::new(), ..entry };
+        }
+        cache.pipelines.insert(path, entry);
+    }
+    if let Err(err) = cache.save(build_path) {
+        log::warn!("{:?}", err);
+    }
+    written
 }
 
 async fn build_scripts(path: &Path, manifest: &ProjectManifest, build_path: &Path) -> anyhow::Result<()> {
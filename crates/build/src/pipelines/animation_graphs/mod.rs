@@ -0,0 +1,46 @@
+use ambient_animation::AnimationGraph;
+use ambient_std::asset_url::AssetType;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tracing::{info_span, Instrument};
+
+use super::{
+    context::PipelineCtx,
+    out_asset::{asset_id_from_url, OutAsset, OutAssetContent, OutAssetPreview},
+};
+
+/// Animation graphs are authored as JSON files with this suffix
+pub const ANIMATION_GRAPH_SUFFIX: &str = ".animgraph.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnimationGraphsPipeline {}
+
+pub async fn pipeline(ctx: &PipelineCtx, _config: AnimationGraphsPipeline) -> Vec<OutAsset> {
+    ctx.process_files(
+        |file| file.path().as_str().ends_with(ANIMATION_GRAPH_SUFFIX),
+        move |ctx, file| async move {
+            let graph =
+                file.download_json::<AnimationGraph>(ctx.assets()).await.with_context(|| format!("Invalid animation graph {file}"))?;
+            graph.validate().with_context(|| format!("Invalid animation graph {file}"))?;
+
+            // Clip urls stay relative to the graph, so they point to the clips built by the Models pipeline
+            let rel_path = ctx.in_root().relative_path(file.path());
+            let content_url = ctx.write_file(&rel_path, serde_json::to_vec_pretty(&graph)?).await;
+
+            let name = file.path().file_name().unwrap().trim_end_matches(ANIMATION_GRAPH_SUFFIX).to_string();
+            Ok(vec![OutAsset {
+                id: asset_id_from_url(&file),
+                type_: AssetType::AnimationGraph,
+                hidden: false,
+                name,
+                tags: Vec::new(),
+                categories: Default::default(),
+                preview: OutAssetPreview::None,
+                content: OutAssetContent::Content(content_url),
+                source: Some(file.clone()),
+            }])
+        },
+    )
+    .instrument(info_span!("animation_graphs_pipeline"))
+    .await
+}
@@ -1,117 +1,163 @@
-use std::process::Stdio;
-
-use ambient_std::asset_url::AssetType;
+use ambient_std::asset_url::{AbsAssetUrl, AssetType};
 use ambient_world_audio::AudioNode;
+pub use ambient_world_audio::SOUND_GRAPH_EXTENSION;
 use anyhow::Context;
-use futures::FutureExt;
-use tokio::io::{AsyncRead, AsyncReadExt};
+use serde::{Deserialize, Serialize};
 use tracing::{info_span, Instrument};
 
+use self::transcode::AudioBuffer;
 use super::{
     context::PipelineCtx,
     out_asset::{asset_id_from_url, OutAsset, OutAssetContent, OutAssetPreview},
 };
 
-pub const SOUND_GRAPH_EXTENSION: &str = "sgr";
-
-pub async fn pipeline(ctx: &PipelineCtx) -> Vec<OutAsset> {
-    ctx.process_files(
-        |file| matches!(file.extension().as_deref(), Some("ogg") | Some("wav") | Some("mp3")),
-        |ctx, file| async move {
-            let contents = file.download_bytes(ctx.assets()).await?;
+pub mod transcode;
 
-            let filename = file.path().file_name().unwrap().to_string();
+fn default_quality() -> f32 {
+    0.5
+}
 
-            let rel_path = ctx.in_root().relative_path(file.path());
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioPipeline {
+    /// Resample the audio to this sample rate, in Hz. The original sample rate is kept if not specified.
+    pub sample_rate: Option<u32>,
+    /// Mix all channels down to a single channel.
+    pub mono: bool,
+    /// Normalize the loudness (RMS level) of the audio to this level, in dBFS (e.g. `-16.0`).
+    /// The gain is limited so that the audio never clips.
+    pub normalize_loudness: Option<f32>,
+    /// Remove silence at the start of the audio. Samples quieter than this level, in dBFS (e.g. `-60.0`), are considered silent.
+    pub trim_leading_silence: Option<f32>,
+    /// The quality of the Ogg Vorbis encoding, from -0.2 (lowest) to 1.0 (highest). Defaults to 0.5.
+    #[serde(default = "default_quality")]
+    pub quality: f32,
+    /// Decode the audio while it plays instead of all at once, which suits long tracks such as music.
+    pub stream: bool,
+    /// Loop the audio back to this time, in seconds, when it reaches `loop_end` or its end. The audio is streamed if it loops.
+    pub loop_start: Option<f32>,
+    /// Loop the audio back to `loop_start` when it reaches this time, in seconds. The audio is streamed if it loops.
+    pub loop_end: Option<f32>,
+}
+impl Default for AudioPipeline {
+    fn default() -> Self {
+        Self {
+            sample_rate: None,
+            mono: false,
+            normalize_loudness: None,
+            trim_leading_silence: None,
+            quality: default_quality(),
+            stream: false,
+            loop_start: None,
+            loop_end: None,
+        }
+    }
+}
+impl AudioPipeline {
+    /// Ogg Vorbis files are passed through unchanged unless they need to be processed
+    fn needs_processing(&self) -> bool {
+        self.sample_rate.is_some() || self.mono || self.normalize_loudness.is_some() || self.trim_leading_silence.is_some()
+    }
+    fn process(&self, mut audio: AudioBuffer) -> anyhow::Result<Vec<u8>> {
+        if let Some(threshold) = self.trim_leading_silence {
+            audio.trim_leading_silence(threshold);
+        }
+        if self.mono {
+            audio.downmix_to_mono();
+        }
+        if let Some(sample_rate) = self.sample_rate {
+            audio.resample(sample_rate);
+        }
+        if let Some(target) = self.normalize_loudness {
+            audio.normalize_loudness(target);
+        }
+        audio.encode_vorbis(self.quality)
+    }
+    /// The sound graph which plays the processed audio
+    fn root_node(&self, url: String) -> AudioNode {
+        if self.stream || self.loop_start.is_some() || self.loop_end.is_some() {
+            AudioNode::Stream { url, loop_start: self.loop_start, loop_end: self.loop_end }
+        } else {
+            AudioNode::Vorbis { url }
+        }
+    }
+}
 
-            let content_url = match file.extension().as_deref() {
-                Some("ogg") => ctx.write_file(&rel_path, contents).await,
-                ext @ Some("wav" | "mp3") => {
-                    tracing::info!("Processing {ext:?} file");
-                    // Make sure to take the contents, to avoid having both the input and output in
-                    // memory at once
-                    let contents = ffmpeg_convert(std::io::Cursor::new(contents)).await?;
-                    ctx.write_file(rel_path.with_extension("ogg"), contents).await
+pub async fn pipeline(ctx: &PipelineCtx, config: AudioPipeline) -> Vec<OutAsset> {
+    ctx.process_files(
+        |file| matches!(file.extension().as_deref(), Some("ogg" | "wav" | "mp3" | "flac" | SOUND_GRAPH_EXTENSION)),
+        move |ctx, file| {
+            let config = config.clone();
+            async move {
+                let contents = file.download_bytes(ctx.assets()).await?;
+
+                let filename = file.path().file_name().unwrap().to_string();
+
+                let rel_path = ctx.in_root().relative_path(file.path());
+
+                if file.extension().as_deref() == Some(SOUND_GRAPH_EXTENSION) {
+                    // The urls of authored graphs are relative to where the graph is written
+                    let mut root_node: AudioNode =
+                        serde_json::from_slice(&contents).with_context(|| format!("Invalid sound graph {file}"))?;
+                    root_node.resolve_urls(&ctx.out_root().push(&rel_path)?)?;
+                    let graph_url = ctx.write_file(&rel_path, save_audio_graph(root_node)?).await;
+                    return Ok(vec![sound_graph_asset(&file, filename, graph_url)]);
                 }
-                other => anyhow::bail!("Audio filetype {:?} is not yet supported", other.unwrap_or_default()),
-            };
-
-            let root_node = AudioNode::Vorbis { url: content_url.to_string() };
-            let graph_url = ctx.write_file(&rel_path.with_extension("SOUND_GRAPH_EXTENSION"), save_audio_graph(root_node).unwrap()).await;
 
-            Ok(vec![
-                OutAsset {
-                    id: asset_id_from_url(&file),
-                    type_: AssetType::VorbisTrack,
-                    hidden: false,
-                    name: filename.clone(),
-                    tags: Vec::new(),
-                    categories: Default::default(),
-                    preview: OutAssetPreview::None,
-                    content: OutAssetContent::Content(content_url),
-                    source: Some(file.clone()),
-                },
-                OutAsset {
-                    id: asset_id_from_url(&file.push("graph").unwrap()),
-                    type_: AssetType::SoundGraph,
-                    hidden: false,
-                    name: filename,
-                    tags: Vec::new(),
-                    categories: Default::default(),
-                    preview: OutAssetPreview::None,
-                    content: OutAssetContent::Content(graph_url),
-                    source: None,
-                },
-            ])
+                let content_url = match file.extension() {
+                    Some(ext) if ext == "ogg" && !config.needs_processing() => ctx.write_file(&rel_path, contents).await,
+                    Some(ext) => {
+                        tracing::info!("Processing {ext:?} file");
+                        let config = config.clone();
+                        let contents = tokio::task::spawn_blocking(move || {
+                            let audio = AudioBuffer::decode(contents, &ext)?;
+                            config.process(audio)
+                        })
+                        .await?
+                        .with_context(|| format!("Failed to transcode {file}"))?;
+                        ctx.write_file(rel_path.with_extension("ogg"), contents).await
+                    }
+                    None => anyhow::bail!("Audio file {file} has no extension"),
+                };
+
+                let root_node = config.root_node(content_url.to_string());
+                let graph_url = ctx.write_file(&rel_path.with_extension(SOUND_GRAPH_EXTENSION), save_audio_graph(root_node).unwrap()).await;
+
+                Ok(vec![
+                    OutAsset {
+                        id: asset_id_from_url(&file),
+                        type_: AssetType::VorbisTrack,
+                        hidden: false,
+                        name: filename.clone(),
+                        tags: Vec::new(),
+                        categories: Default::default(),
+                        preview: OutAssetPreview::None,
+                        content: OutAssetContent::Content(content_url),
+                        source: Some(file.clone()),
+                    },
+                    sound_graph_asset(&file.push("graph").unwrap(), filename, graph_url),
+                ])
+            }
         },
     )
     .instrument(info_span!("audio_pipeline"))
     .await
 }
 
-fn save_audio_graph(root: AudioNode) -> anyhow::Result<Vec<u8>> {
-    Ok(serde_json::to_string_pretty(&root).context("Invalid sound graph")?.into_bytes())
-}
-
-#[tracing::instrument(level = "info", skip(input))]
-async fn ffmpeg_convert<A>(input: A) -> anyhow::Result<Vec<u8>>
-where
-    A: 'static + Send + AsyncRead,
-{
-    let mut child = tokio::process::Command::new("ffmpeg")
-        .args(["-i", "pipe:", "-f", "ogg", "pipe:1"])
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .context("Failed to execute ffmpeg")?;
-
-    tracing::info!("Writing to stdin");
-
-    let mut stdin = child.stdin.take().expect("no stdin");
-    let mut stdout = child.stdout.take().expect("no stdout");
-
-    let input = tokio::task::spawn(async move {
-        tokio::pin!(input);
-        tokio::io::copy(&mut input, &mut stdin).await.context("Failed to write to stdin")
-    })
-    .map(|v| v.unwrap());
-
-    let output = async move {
-        let mut output = Vec::new();
-        stdout.read_to_end(&mut output).await.unwrap();
-        Ok(output)
-    };
-
-    let status = async { child.wait().await.context("Failed to wait for ffmpeg") };
-
-    tracing::info!("Waiting for ffmpeg to complete");
-    let (_, output, status) = tokio::try_join!(input, output, status)?;
-
-    if !status.success() {
-        anyhow::bail!("FFMPEG conversion failed")
+fn sound_graph_asset(id_url: &AbsAssetUrl, name: String, graph_url: AbsAssetUrl) -> OutAsset {
+    OutAsset {
+        id: asset_id_from_url(id_url),
+        type_: AssetType::SoundGraph,
+        hidden: false,
+        name,
+        tags: Vec::new(),
+        categories: Default::default(),
+        preview: OutAssetPreview::None,
+        content: OutAssetContent::Content(graph_url),
+        source: None,
     }
+}
 
-    tracing::info!("Converted to vorbis of {} kb", output.len() as f32 / 1000.0);
-
-    Ok(output)
+fn save_audio_graph(root: AudioNode) -> anyhow::Result<Vec<u8>> {
+    Ok(serde_json::to_string_pretty(&root).context("Invalid sound graph")?.into_bytes())
 }
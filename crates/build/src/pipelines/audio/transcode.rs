@@ -0,0 +1,228 @@
+use std::{
+    f64::consts::PI,
+    io::Cursor,
+    num::{NonZeroU32, NonZeroU8},
+};
+
+use anyhow::Context;
+use symphonia::core::{
+    audio::SampleBuffer, codecs::DecoderOptions, codecs::CODEC_TYPE_NULL, errors::Error as SymphoniaError, formats::FormatOptions,
+    io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+};
+use vorbis_rs::{VorbisBitrateManagementStrategy, VorbisEncoderBuilder};
+
+/// The number of frames passed to the vorbis encoder at a time
+const ENCODE_BLOCK_SIZE: usize = 4096;
+/// The number of zero crossings on each side of the resampling filter
+const RESAMPLE_ZERO_CROSSINGS: usize = 16;
+
+/// Decoded audio, with one buffer of samples per channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioBuffer {
+    pub sample_rate: u32,
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl AudioBuffer {
+    /// Decodes a WAV, MP3, FLAC or Ogg Vorbis file
+    pub fn decode(data: Vec<u8>, extension: &str) -> anyhow::Result<Self> {
+        let stream = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+        let mut hint = Hint::new();
+        hint.with_extension(extension);
+        let probed = symphonia::default::get_probe()
+            .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+            .context("Unsupported audio format")?;
+        let mut format = probed.format;
+        let track = format.tracks().iter().find(|track| track.codec_params.codec != CODEC_TYPE_NULL).context("No audio track found")?;
+        let track_id = track.id;
+        let sample_rate = track.codec_params.sample_rate.context("Unknown sample rate")?;
+        let mut decoder =
+            symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default()).context("Unsupported audio codec")?;
+
+        let mut channels: Vec<Vec<f32>> = Vec::new();
+        loop {
+            let packet = match format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err).context("Failed to read audio packet"),
+            };
+            if packet.track_id() != track_id {
+                continue;
+            }
+            let decoded = match decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(SymphoniaError::DecodeError(err)) => {
+                    log::warn!("Skipping undecodable audio packet: {err}");
+                    continue;
+                }
+                Err(err) => return Err(err).context("Failed to decode audio"),
+            };
+            let spec = *decoded.spec();
+            let frames = decoded.frames();
+            let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+            samples.copy_planar_ref(decoded);
+            let channel_count = spec.channels.count();
+            channels.resize_with(channel_count, Vec::new);
+            for (i, channel) in channels.iter_mut().enumerate() {
+                channel.extend_from_slice(&samples.samples()[i * frames..(i + 1) * frames]);
+            }
+        }
+        if channels.is_empty() {
+            anyhow::bail!("Audio file contains no samples");
+        }
+        Ok(Self { sample_rate, channels })
+    }
+
+    pub fn frames(&self) -> usize {
+        self.channels.first().map(|channel| channel.len()).unwrap_or_default()
+    }
+
+    /// Averages all channels into a single one
+    pub fn downmix_to_mono(&mut self) {
+        if self.channels.len() <= 1 {
+            return;
+        }
+        let count = self.channels.len() as f32;
+        let mono = (0..self.frames()).map(|i| self.channels.iter().map(|channel| channel[i]).sum::<f32>() / count).collect();
+        self.channels = vec![mono];
+    }
+
+    /// Resamples all channels to `sample_rate`, using windowed sinc interpolation. When downsampling, frequencies
+    /// above the new Nyquist frequency are filtered out first, so that they don't alias.
+    pub fn resample(&mut self, sample_rate: u32) {
+        if sample_rate == self.sample_rate || self.frames() == 0 {
+            return;
+        }
+        let ratio = self.sample_rate as f64 / sample_rate as f64;
+        let cutoff = (1. / ratio).min(1.);
+        // The filter is widened as the cutoff is lowered, to keep the same number of zero crossings
+        let half_width = RESAMPLE_ZERO_CROSSINGS as f64 / cutoff;
+        let frames = ((self.frames() as f64) / ratio).round() as usize;
+        for channel in &mut self.channels {
+            let last = channel.len() - 1;
+            *channel = (0..frames)
+                .map(|i| {
+                    let center = i as f64 * ratio;
+                    let start = (center - half_width).ceil().max(0.) as usize;
+                    let end = ((center + half_width).floor() as usize).min(last);
+                    let (sum, weights) = (start..=end).fold((0., 0.), |(sum, weights), j| {
+                        let x = j as f64 - center;
+                        let weight = cutoff * sinc(x * cutoff) * blackman(x / half_width);
+                        (sum + channel[j] as f64 * weight, weights + weight)
+                    });
+                    // Normalizing by the sum of the weights keeps the gain at 1, including at the edges
+                    if weights == 0. {
+                        0.
+                    } else {
+                        (sum / weights) as f32
+                    }
+                })
+                .collect();
+        }
+        self.sample_rate = sample_rate;
+    }
+
+    /// Applies a gain so that the RMS level of the audio matches `target_db` (in dBFS), without clipping
+    pub fn normalize_loudness(&mut self, target_db: f32) {
+        let samples = self.channels.iter().flatten();
+        let count = self.frames() * self.channels.len();
+        if count == 0 {
+            return;
+        }
+        let rms = (samples.clone().map(|x| x * x).sum::<f32>() / count as f32).sqrt();
+        let peak = samples.fold(0f32, |peak, x| peak.max(x.abs()));
+        if rms <= 0. {
+            return;
+        }
+        let gain = (db_to_amplitude(target_db) / rms).min(1. / peak);
+        for sample in self.channels.iter_mut().flatten() {
+            *sample *= gain;
+        }
+    }
+
+    /// Removes all frames at the start where every channel is quieter than `threshold_db` (in dBFS)
+    pub fn trim_leading_silence(&mut self, threshold_db: f32) {
+        let threshold = db_to_amplitude(threshold_db);
+        let start = (0..self.frames()).find(|&i| self.channels.iter().any(|channel| channel[i].abs() > threshold)).unwrap_or(self.frames());
+        for channel in &mut self.channels {
+            channel.drain(..start);
+        }
+    }
+
+    /// Encodes the audio as Ogg Vorbis. `quality` ranges from -0.2 (lowest) to 1.0 (highest).
+    pub fn encode_vorbis(&self, quality: f32) -> anyhow::Result<Vec<u8>> {
+        let sample_rate = NonZeroU32::new(self.sample_rate).context("Invalid sample rate")?;
+        let channels = NonZeroU8::new(u8::try_from(self.channels.len()).context("Too many channels")?).context("No channels")?;
+        let mut output = Vec::new();
+        let mut builder = VorbisEncoderBuilder::new(sample_rate, channels, &mut output)?;
+        builder.bitrate_management_strategy(VorbisBitrateManagementStrategy::QualityVbr { target_quality: quality });
+        let mut encoder = builder.build()?;
+        for start in (0..self.frames()).step_by(ENCODE_BLOCK_SIZE) {
+            let end = (start + ENCODE_BLOCK_SIZE).min(self.frames());
+            encoder.encode_audio_block(self.channels.iter().map(|channel| &channel[start..end]).collect::<Vec<_>>())?;
+        }
+        encoder.finish()?;
+        Ok(output)
+    }
+}
+
+fn db_to_amplitude(db: f32) -> f32 {
+    10f32.powf(db / 20.)
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0. {
+        1.
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// The Blackman window, for `t` from -1 to 1
+fn blackman(t: f64) -> f64 {
+    0.42 + 0.5 * (PI * t).cos() + 0.08 * (2. * PI * t).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downmix_and_resample() {
+        let mut audio = AudioBuffer { sample_rate: 4, channels: vec![vec![0., 1., 0., 1.], vec![1., 1., 0., 0.]] };
+        audio.downmix_to_mono();
+        assert_eq!(audio.channels, vec![vec![0.5, 1., 0., 0.5]]);
+        audio.resample(8);
+        assert_eq!(audio.sample_rate, 8);
+        assert_eq!(audio.frames(), 8);
+        // Upsampling keeps the original samples
+        for (i, sample) in [0.5, 1., 0., 0.5].into_iter().enumerate() {
+            assert!((audio.channels[0][i * 2] - sample).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_resample_without_aliasing() {
+        // A tone at the Nyquist frequency can't be represented at half the sample rate, and should be filtered out
+        let mut audio = AudioBuffer { sample_rate: 8, channels: vec![(0..256).map(|i| if i % 2 == 0 { 1. } else { -1. }).collect()] };
+        audio.resample(4);
+        assert_eq!(audio.frames(), 128);
+        assert!(audio.channels[0][32..96].iter().all(|sample| sample.abs() < 0.01));
+
+        // While a constant signal is unchanged
+        let mut audio = AudioBuffer { sample_rate: 8, channels: vec![vec![0.5; 256]] };
+        audio.resample(3);
+        assert!(audio.channels[0].iter().all(|sample| (sample - 0.5).abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_trim_and_normalize() {
+        let mut audio = AudioBuffer { sample_rate: 1, channels: vec![vec![0., 0.0001, 0.25, -0.25]] };
+        audio.trim_leading_silence(-60.);
+        assert_eq!(audio.channels, vec![vec![0.25, -0.25]]);
+        audio.normalize_loudness(0.);
+        assert_eq!(audio.channels, vec![vec![1., -1.]]);
+        audio.normalize_loudness(-6.0206);
+        assert!((audio.channels[0][0] - 0.5).abs() < 0.001);
+    }
+}
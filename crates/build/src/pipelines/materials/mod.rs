@@ -5,6 +5,7 @@ use ambient_decals::decal;
 use ambient_ecs::EntityData;
 use ambient_model_import::{
     model_crate::{cap_texture_size, ModelCrate},
+    texture_compression::{compress_texture, TextureKind},
     ModelTextureSize,
 };
 use ambient_physics::collider::{collider, collider_type};
@@ -47,16 +48,21 @@ pub struct MaterialsPipeline {
     /// Whether or not decal prefabs should be created for each of these materials.
     #[serde(default)]
     pub output_decals: bool,
+    /// Whether or not the textures should also be written as block compressed (BC7, BC5 or BC4) KTX2 textures with
+    /// precomputed mipmaps, which are used instead of the uncompressed ones where the GPU supports them.
+    #[serde(default)]
+    pub compress_textures: bool,
 }
 
 pub async fn pipeline(ctx: &PipelineCtx, config: MaterialsPipeline) -> Vec<OutAsset> {
+    let compress_textures = config.compress_textures;
     let materials = match *config.importer.clone() {
         MaterialsImporter::Single(mat) => {
             ctx.process_single(move |ctx| async move {
                 let name = mat.name.as_ref().or(mat.source.as_ref()).unwrap().to_string();
 
                 let mat_out_url = ctx.out_root().join(ctx.pipeline_path())?.as_directory();
-                let material = mat.to_mat(&ctx, &ctx.in_root(), &mat_out_url).await?;
+                let material = mat.to_mat(&ctx, &ctx.in_root(), &mat_out_url, compress_textures).await?;
                 let base_color_url = material.base_color.clone().unwrap().resolve(&mat_out_url).unwrap();
                 let base_color = ImageFromUrl { url: base_color_url }.get(ctx.assets()).await?;
                 let mat_url = ctx.write_file(ctx.pipeline_path().join("mat.json"), serde_json::to_vec(&material).unwrap()).await;
@@ -151,28 +157,60 @@ pub struct PipelinePbrMaterial {
     pub specular_exponent: Option<f32>,
 }
 impl PipelinePbrMaterial {
-    pub async fn to_mat(&self, ctx: &PipelineCtx, source_root: &AbsAssetUrl, out_root: &AbsAssetUrl) -> anyhow::Result<PbrMaterialFromUrl> {
-        let pipe_image = |path: &Option<AssetUrl>| -> BoxFuture<'_, anyhow::Result<Option<AssetUrl>>> {
+    pub async fn to_mat(
+        &self,
+        ctx: &PipelineCtx,
+        source_root: &AbsAssetUrl,
+        out_root: &AbsAssetUrl,
+        compress_textures: bool,
+    ) -> anyhow::Result<PbrMaterialFromUrl> {
+        let compress = |kind: TextureKind| compress_textures.then_some(kind);
+        let pipe_image = |path: &Option<AssetUrl>, kind: TextureKind| -> BoxFuture<'_, anyhow::Result<Option<AssetUrl>>> {
             let source_root = source_root.clone();
             let path = path.clone();
             let ctx = ctx.clone();
             async move {
                 if let Some(path) = path {
-                    Ok(Some(AssetUrl::from(PipeImage::resolve(&ctx, path.resolve(&source_root).unwrap()).get(ctx.assets()).await?)))
+                    Ok(Some(AssetUrl::from(
+                        PipeImage::resolve(&ctx, path.resolve(&source_root).unwrap()).compress(compress(kind)).get(ctx.assets()).await?,
+                    )))
                 } else {
                     Ok(None)
                 }
             }
             .boxed()
         };
+        let (base_color, opacity) = match (&self.base_color, &self.opacity) {
+            // Compressed textures can't be combined when loaded, so the opacity is moved into the alpha of the base color
+            (Some(base_color), Some(opacity)) if compress_textures => (
+                Some(
+                    PipeImage::resolve(ctx, base_color.resolve(source_root).unwrap())
+                        .with_opacity(ctx.get_downloadable_url(&opacity.resolve(source_root).unwrap())?.clone())
+                        .compress(compress(TextureKind::BaseColor))
+                        .get(ctx.assets())
+                        .await?
+                        .into(),
+                ),
+                None,
+            ),
+            _ => {
+                (pipe_image(&self.base_color, TextureKind::BaseColor).await?, pipe_image(&self.opacity, TextureKind::SingleChannel).await?)
+            }
+        };
         Ok(PbrMaterialFromUrl {
             name: self.name.clone(),
             source: self.source.clone(),
-            base_color: pipe_image(&self.base_color).await?,
-            opacity: pipe_image(&self.opacity).await?,
-            normalmap: pipe_image(&self.normalmap).await?,
+            base_color,
+            opacity,
+            normalmap: pipe_image(&self.normalmap, TextureKind::Normal).await?,
             metallic_roughness: if let Some(url) = &self.metallic_roughness {
-                Some(PipeImage::resolve(ctx, url.resolve(source_root).unwrap()).get(ctx.assets()).await?.into())
+                Some(
+                    PipeImage::resolve(ctx, url.resolve(source_root).unwrap())
+                        .compress(compress(TextureKind::MetallicRoughness))
+                        .get(ctx.assets())
+                        .await?
+                        .into(),
+                )
             } else if let Some(specular) = &self.specular {
                 let specular_exponent = self.specular_exponent.unwrap_or(1.);
                 Some(
@@ -186,6 +224,7 @@ impl PipelinePbrMaterial {
                                 p[3] = 255;
                             }
                         })
+                        .compress(compress(TextureKind::MetallicRoughness))
                         .get(ctx.assets())
                         .await?
                         .into(),
@@ -245,13 +284,25 @@ pub struct PipeImage {
     second_source: Option<AbsAssetUrl>,
     transform: Option<Box<dyn ImageTransformer>>,
     cap_texture_sizes: Option<ModelTextureSize>,
+    compress: Option<TextureKind>,
 }
 impl PipeImage {
     pub fn resolve(ctx: &PipelineCtx, source: AbsAssetUrl) -> Self {
         Self::new(ctx.get_downloadable_url(&source).unwrap().clone())
     }
     pub fn new(source: AbsAssetUrl) -> Self {
-        PipeImage { source, second_source: None, transform: None, cap_texture_sizes: None }
+        PipeImage { source, second_source: None, transform: None, cap_texture_sizes: None, compress: None }
+    }
+    /// Moves the opacity map into the alpha channel of this image
+    pub fn with_opacity(mut self, opacity: AbsAssetUrl) -> Self {
+        self.second_source = Some(opacity);
+        self.transform("opacity", |image, opacity| {
+            if let Some(opacity) = opacity {
+                for (color, alpha) in image.pixels_mut().zip(opacity.pixels()) {
+                    color[3] = alpha[0];
+                }
+            }
+        })
     }
     pub fn transform<F: Fn(&mut RgbaImage, Option<&RgbaImage>) + Sync + Send + 'static>(
         mut self,
@@ -265,6 +316,12 @@ impl PipeImage {
         self.cap_texture_sizes = cap_texture_sizes;
         self
     }
+    /// Also writes a block compressed `.ktx2` version of the image, and returns its url instead of the `.png` one.
+    /// The `.png` is kept as a fallback for when compressed textures aren't supported.
+    pub fn compress(mut self, kind: Option<TextureKind>) -> Self {
+        self.compress = kind;
+        self
+    }
 }
 #[async_trait]
 impl AsyncAssetKey<AssetResult<Arc<AbsAssetUrl>>> for PipeImage {
@@ -288,7 +345,7 @@ impl AsyncAssetKey<AssetResult<Arc<AbsAssetUrl>>> for PipeImage {
         };
         let path = ctx.in_root.relative_path(self.source.path());
         let mut data = Cursor::new(Vec::new());
-        tokio::task::block_in_place(|| {
+        let compressed = tokio::task::block_in_place(|| {
             if let Some(transform) = &self.transform {
                 transform.transform(&mut image, second_image.as_deref());
                 extension = format!("{}.png", transform.name());
@@ -297,8 +354,14 @@ impl AsyncAssetKey<AssetResult<Arc<AbsAssetUrl>>> for PipeImage {
                 cap_texture_size(&mut image, size.size());
             }
             image.write_to(&mut data, ImageOutputFormat::Png).unwrap();
+            self.compress.and_then(|kind| compress_texture(&image, kind))
         });
-        Ok(Arc::new((ctx.write_file)(path.with_extension(extension).to_string(), data.into_inner()).await))
+        let url = (ctx.write_file)(path.with_extension(&extension).to_string(), data.into_inner()).await;
+        if let Some(compressed) = compressed {
+            let extension = format!("{}ktx2", extension.strip_suffix("png").unwrap());
+            return Ok(Arc::new((ctx.write_file)(path.with_extension(extension).to_string(), compressed.to_bytes()).await));
+        }
+        Ok(Arc::new(url))
     }
 }
 
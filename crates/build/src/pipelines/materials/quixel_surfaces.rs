@@ -16,7 +16,8 @@ use super::{
 };
 use crate::pipelines::out_asset::asset_id_from_url;
 
-pub async fn pipeline(ctx: &PipelineCtx, _config: MaterialsPipeline) -> Vec<OutAsset> {
+pub async fn pipeline(ctx: &PipelineCtx, config: MaterialsPipeline) -> Vec<OutAsset> {
+    let compress_textures = config.compress_textures;
     ctx.process_files(
         |file| {
             file.extension() == Some("json".to_string())
@@ -30,6 +31,9 @@ pub async fn pipeline(ctx: &PipelineCtx, _config: MaterialsPipeline) -> Vec<OutA
             let surface = QuixelSurfaceDef::from_quixel_json(&ctx, &quixel_id, &quixel_json, &in_root_url);
             let mut asset_crate = ModelCrate::new();
             surface.write_to_asset_crate(ctx.assets(), &mut asset_crate).await;
+            if compress_textures {
+                tokio::task::block_in_place(|| asset_crate.compress_textures());
+            }
 
             let tags =
                 quixel_json["tags"].as_array().unwrap().iter().map(|x| x.as_str().unwrap().to_string().to_case(Case::Title)).collect_vec();
@@ -6,11 +6,13 @@ use anyhow::Context;
 use context::PipelineCtx;
 use futures::{future::BoxFuture, StreamExt};
 use image::ImageFormat;
+use itertools::Itertools;
 use out_asset::{OutAsset, OutAssetContent, OutAssetPreview};
 use serde::{Deserialize, Serialize};
 
-use self::{materials::MaterialsPipeline, models::ModelsPipeline};
+use self::{animation_graphs::AnimationGraphsPipeline, audio::AudioPipeline, materials::MaterialsPipeline, models::ModelsPipeline};
 
+pub mod animation_graphs;
 pub mod audio;
 pub mod context;
 pub mod materials;
@@ -27,8 +29,11 @@ pub enum PipelineConfig {
     /// Will import specific materials without needing to be part of a model.
     Materials(MaterialsPipeline),
     /// The audio asset pipeline.
-    /// Will import supported audio file formats and produce Ogg Vorbis files to be used by the runtime.
-    Audio,
+    /// Will import supported audio file formats (WAV, MP3, FLAC and Ogg Vorbis) and produce Ogg Vorbis files to be used by the runtime.
+    Audio(AudioPipeline),
+    /// The animation graphs pipeline.
+    /// Will import animation state machines from `.animgraph.json` files, to be used with the `animation_graph` component.
+    AnimationGraphs(AnimationGraphsPipeline),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,7 +58,8 @@ impl Pipeline {
         let mut assets = match &self.pipeline {
             PipelineConfig::Models(config) => models::pipeline(&ctx, config.clone()).await,
             PipelineConfig::Materials(config) => materials::pipeline(&ctx, config.clone()).await,
-            PipelineConfig::Audio => audio::pipeline(&ctx).await,
+            PipelineConfig::Audio(config) => audio::pipeline(&ctx, config.clone()).await,
+            PipelineConfig::AnimationGraphs(config) => animation_graphs::pipeline(&ctx, config.clone()).await,
         };
         for asset in &mut assets {
             asset.tags.extend(self.tags.clone());
@@ -68,6 +74,12 @@ impl Pipeline {
 }
 
 pub async fn process_pipelines(ctx: &ProcessCtx) -> Vec<OutAsset> {
+    let pipeline_files = ctx.files.0.iter().filter(|file| file.0.path().ends_with("pipeline.json")).cloned().collect_vec();
+    process_pipeline_files(ctx, &pipeline_files).await
+}
+
+/// Processes only the given `pipeline.json` files; all files in `ctx.files` are still available to the pipelines
+pub async fn process_pipeline_files(ctx: &ProcessCtx, pipeline_files: &[AbsAssetUrl]) -> Vec<OutAsset> {
     log::info!("Processing pipeline with out_root={}", ctx.out_root);
 
     #[derive(Debug, Clone, Deserialize)]
@@ -85,14 +97,15 @@ pub async fn process_pipelines(ctx: &ProcessCtx) -> Vec<OutAsset> {
         }
     }
 
-    futures::stream::iter(ctx.files.0.iter())
+    futures::stream::iter(pipeline_files.iter())
         .filter_map(|file| async move {
-            let pipelines: PipelineOneOrMany = if file.0.path().ends_with("pipeline.json") {
-                file.download_json(&ctx.assets).await.unwrap()
-            } else {
-                return None;
-            };
-            Some((file, pipelines.into_vec()))
+            match file.download_json::<PipelineOneOrMany>(&ctx.assets).await {
+                Ok(pipelines) => Some((file, pipelines.into_vec())),
+                Err(err) => {
+                    (ctx.on_error)(err.context(format!("Invalid pipeline {file}; run `ambient check` for details"))).await;
+                    None
+                }
+            }
         })
         .flat_map(|(file, pipelines)| {
             futures::stream::iter(pipelines.into_iter().enumerate().map(|(i, pipeline)| {
@@ -154,6 +167,8 @@ impl FileCollection {
 }
 
 pub async fn download_image(assets: &AssetCache, url: &AbsAssetUrl) -> anyhow::Result<image::DynamicImage> {
+    // Compressed textures are always written next to an uncompressed version
+    let url = &if url.extension_is("ktx2") { url.with_extension("png") } else { url.clone() };
     let data = url.download_bytes(assets).await?;
     if let Some(format) = url.extension().as_ref().and_then(ImageFormat::from_extension) {
         Ok(image::load_from_memory_with_format(&data, format).with_context(|| format!("Failed to load image {url}"))?)
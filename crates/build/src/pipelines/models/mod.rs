@@ -1,9 +1,12 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
 use ambient_core::hierarchy::children;
 use ambient_ecs::EntityData;
-use ambient_model_import::{model_crate::ModelCrate, MaterialFilter, ModelTextureSize, ModelTransform, TextureResolver};
-use ambient_physics::collider::{collider_type, ColliderType};
+use ambient_model_import::{lod::GenerateLods, model_crate::ModelCrate, MaterialFilter, ModelTextureSize, ModelTransform, TextureResolver};
+use ambient_physics::{
+    collider::{collider_type, ColliderType},
+    ragdoll::RagdollBoneOverride,
+};
 use ambient_std::asset_url::AssetType;
 use futures::FutureExt;
 use relative_path::RelativePath;
@@ -49,6 +52,15 @@ pub async fn pipeline(ctx: &PipelineCtx, config: ModelsPipeline) -> Vec<OutAsset
 fn true_value() -> bool {
     true
 }
+fn max_hulls_value() -> u32 {
+    16
+}
+fn resolution_value() -> u32 {
+    64
+}
+fn radius_scale_value() -> f32 {
+    0.25
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelsPipeline {
@@ -67,6 +79,10 @@ pub struct ModelsPipeline {
     collider_type: ColliderType,
     /// Whether or not this mesh should have its texture sizes capped.
     cap_texture_sizes: Option<ModelTextureSize>,
+    /// Whether or not the textures should also be written as block compressed (BC7, BC5 or BC4) KTX2 textures with
+    /// precomputed mipmaps, which are used instead of the uncompressed ones where the GPU supports them.
+    #[serde(default)]
+    compress_textures: bool,
     /// Treats all assets in the pipeline as variations, and outputs a single asset which is a collection of all assets.
     /// Most useful for grass and other entities whose individual identity is not important.
     #[serde(default)]
@@ -86,13 +102,26 @@ pub struct ModelsPipeline {
     prefab_components: EntityData,
     /// If specified, a list of overrides to use for the materials for the mesh.
     #[serde(default)]
-    material_overrides: Vec<MaterialOverride>,
+    pub material_overrides: Vec<MaterialOverride>,
     /// If specified, a list of transformations to apply to this model. This can be used
     /// to correct coordinate space differences between your asset source and the runtime.
     ///
     /// These will be applied in sequence.
     #[serde(default)]
-    transforms: Vec<ModelTransform>,
+    pub transforms: Vec<ModelTransform>,
+    /// If specified, simplified versions of the meshes will be generated and used as lower levels of detail.
+    #[serde(default)]
+    generate_lods: Option<GenerateLods>,
+    /// If specified, the horizontal translation and yaw of the bone with this bind id (i.e. `Hips`) are extracted from the animations
+    /// as root motion, which moves the animated entity instead of the bone.
+    #[serde(default)]
+    extract_root_motion: Option<String>,
+    /// If specified, a ragdoll is generated from the skin of the model, which the prefab can switch to with `ragdoll_active`.
+    #[serde(default)]
+    ragdoll: Option<Ragdoll>,
+    /// Make the directional lights of the model a `sun`, so that they light the scene when it is spawned. Off by default.
+    #[serde(default)]
+    directional_lights_as_sun: bool,
 }
 impl ModelsPipeline {
     pub async fn apply(
@@ -105,19 +134,41 @@ impl ModelsPipeline {
             transform.apply(model_crate);
         }
         for mat in &self.material_overrides {
-            let material =
-                mat.material.to_mat(ctx, &ctx.in_root(), &ctx.out_root().push(out_model_path.as_ref().join("materials"))?).await?;
+            let material = mat
+                .material
+                .to_mat(ctx, &ctx.in_root(), &ctx.out_root().push(out_model_path.as_ref().join("materials"))?, self.compress_textures)
+                .await?;
             model_crate.override_material(&mat.filter, material);
         }
         if let Some(max_size) = self.cap_texture_sizes {
             model_crate.cap_texture_sizes(max_size.size());
         }
+        if self.compress_textures {
+            tokio::task::block_in_place(|| model_crate.compress_textures());
+        }
+        if let Some(generate_lods) = &self.generate_lods {
+            model_crate.generate_lods(&generate_lods.ratios(), generate_lods.cutoffs.clone());
+        }
+        if self.directional_lights_as_sun {
+            model_crate.make_directional_lights_suns();
+        }
         model_crate.finalize_model();
+        if let Some(root) = &self.extract_root_motion {
+            model_crate.extract_root_motion(root)?;
+        }
+        if let Some(ragdoll) = &self.ragdoll {
+            model_crate.create_ragdoll(ragdoll.radius_scale, &ragdoll.overrides)?;
+        }
         match self.collider {
             Collider::None => {}
             Collider::FromModel { flip_normals, reverse_indices } => {
                 model_crate.create_collider_from_model(&ctx.process_ctx.assets, flip_normals, reverse_indices).unwrap();
             }
+            Collider::ConvexDecomposition { max_hulls, resolution } => {
+                tokio::task::block_in_place(|| {
+                    model_crate.create_convex_decomposition_collider(&ctx.process_ctx.assets, max_hulls, resolution)
+                })?;
+            }
             Collider::Character { radius, height } => model_crate.create_character_collider(radius, height),
         }
         model_crate.add_component_to_prefab(collider_type(), self.collider_type);
@@ -166,6 +217,17 @@ pub enum Collider {
         #[serde(default = "true_value")]
         reverse_indices: bool,
     },
+    /// Approximate the model with a set of convex hulls, computed when building.
+    /// Unlike `FromModel`, this can be used for `Dynamic` colliders.
+    ConvexDecomposition {
+        /// The maximum number of convex hulls. Defaults to 16.
+        #[serde(default = "max_hulls_value")]
+        max_hulls: u32,
+        /// The number of voxels along the longest axis of the model that the decomposition works with.
+        /// Higher values are more accurate, but slower to compute. Defaults to 64.
+        #[serde(default = "resolution_value")]
+        resolution: u32,
+    },
     /// Use a spherical character collider.
     Character {
         /// The radius of the collider.
@@ -175,6 +237,17 @@ pub enum Collider {
     },
 }
 
+/// A ragdoll with a capsule for each joint of the skin of a model, pointing towards the joint's children.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ragdoll {
+    /// The radius of the capsules relative to their length. Defaults to 0.25.
+    #[serde(default = "radius_scale_value")]
+    radius_scale: f32,
+    /// Changes to the generated bones, by their bind id (i.e. `LeftHand`).
+    #[serde(default)]
+    overrides: HashMap<String, RagdollBoneOverride>,
+}
+
 fn create_texture_resolver(ctx: &PipelineCtx) -> TextureResolver {
     let ctx = ctx.clone();
     Arc::new(move |path| {
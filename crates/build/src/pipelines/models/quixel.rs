@@ -1,5 +1,7 @@
 use ambient_asset_cache::AsyncAssetKeyExt;
-use ambient_model_import::{fbx::FbxDoc, MaterialFilter, ModelImportPipeline, ModelImportTransform, ModelTransform};
+use ambient_model_import::{
+    fbx::FbxDoc, texture_compression::TextureKind, MaterialFilter, ModelImportPipeline, ModelImportTransform, ModelTransform,
+};
 use ambient_renderer::materials::pbr_material::PbrMaterialFromUrl;
 use ambient_std::asset_url::{AbsAssetUrl, AssetType, AssetUrl};
 use convert_case::{Case, Casing};
@@ -118,7 +120,7 @@ pub async fn object_pipelines_from_quixel_json(
         }
     }
 
-    let pipe_image = |ending: &str| -> BoxFuture<'_, anyhow::Result<AssetUrl>> {
+    let pipe_image = |ending: &str, kind: TextureKind| -> BoxFuture<'_, anyhow::Result<AssetUrl>> {
         let ctx = ctx.clone();
         let in_root_url = in_root_url.clone();
         let config = config.clone();
@@ -126,21 +128,68 @@ pub async fn object_pipelines_from_quixel_json(
         async move {
             let pattern = format!("{}**/*{}", in_root_url.as_directory().path(), ending);
             let file = ctx.files.find_file_res(&pattern)?.clone();
-            Ok(AssetUrl::from(PipeImage::new(file).cap_texture_size(config.cap_texture_sizes).get(ctx.assets()).await?))
+            Ok(AssetUrl::from(
+                PipeImage::new(file)
+                    .cap_texture_size(config.cap_texture_sizes)
+                    .compress(config.compress_textures.then_some(kind))
+                    .get(ctx.assets())
+                    .await?,
+            ))
+        }
+        .boxed()
+    };
+    let pipe_image_opt = |ending: Option<String>, kind: TextureKind| -> BoxFuture<'_, anyhow::Result<Option<AssetUrl>>> {
+        async move {
+            if let Some(ending) = ending {
+                Ok(Some(pipe_image(&ending, kind).await?))
+            } else {
+                Ok(None)
+            }
+        }
+        .boxed()
+    };
+    // Returns the base color and opacity. Compressed textures can't be combined when loaded, so the opacity is
+    // moved into the alpha of the base color when compressing
+    let pipe_base_color = |albedo: Option<String>,
+                           opacity: Option<String>|
+     -> BoxFuture<'_, anyhow::Result<(Option<AssetUrl>, Option<AssetUrl>)>> {
+        let ctx = ctx.clone();
+        let in_root_url = in_root_url.clone();
+        let config = config.clone();
+        async move {
+            match (albedo, opacity) {
+                (Some(albedo), Some(opacity)) if config.compress_textures => {
+                    let find_file =
+                        |ending: &str| ctx.files.find_file_res(format!("{}**/*{}", in_root_url.as_directory().path(), ending)).cloned();
+                    let base_color = PipeImage::new(find_file(&albedo)?)
+                        .with_opacity(find_file(&opacity)?)
+                        .cap_texture_size(config.cap_texture_sizes)
+                        .compress(Some(TextureKind::BaseColor))
+                        .get(ctx.assets())
+                        .await?;
+                    Ok((Some(AssetUrl::from(base_color)), None))
+                }
+                (albedo, opacity) => {
+                    Ok((pipe_image_opt(albedo, TextureKind::BaseColor).await?, pipe_image_opt(opacity, TextureKind::SingleChannel).await?))
+                }
+            }
         }
         .boxed()
     };
     match get_path(quixel, vec!["semanticTags", "asset_type"]).unwrap().as_str().unwrap() as &str {
         "3D asset" => {
+            let (base_color, opacity) = pipe_base_color(
+                Some(format!("{}_Albedo.jpg", quixel_id.resolution)),
+                if quixel_has_opacity(quixel).unwrap_or(false) { Some(format!("{}_Opacity.jpg", quixel_id.resolution)) } else { None },
+            )
+            .await?;
             let material = PbrMaterialFromUrl {
-                base_color: Some(pipe_image(&format!("{}_Albedo.jpg", quixel_id.resolution)).await?),
-                opacity: if quixel_has_opacity(quixel).unwrap_or(false) {
-                    Some(pipe_image(&format!("{}_Opacity.jpg", quixel_id.resolution)).await?)
-                } else {
-                    None
-                },
-                normalmap: Some(pipe_image(&format!("{}_Normal_LOD0.jpg", quixel_id.resolution)).await?),
-                metallic_roughness: Some(pipe_image(&format!("{}_Roughness.jpg", quixel_id.resolution)).await?),
+                base_color,
+                opacity,
+                normalmap: Some(pipe_image(&format!("{}_Normal_LOD0.jpg", quixel_id.resolution), TextureKind::Normal).await?),
+                metallic_roughness: Some(
+                    pipe_image(&format!("{}_Roughness.jpg", quixel_id.resolution), TextureKind::MetallicRoughness).await?,
+                ),
                 roughness: 1.0,
                 metallic: 0.2,
                 ..Default::default()
@@ -226,16 +275,6 @@ pub async fn object_pipelines_from_quixel_json(
                     None
                 }
             };
-            let pipe_image_opt = |ending: Option<String>| -> BoxFuture<'_, anyhow::Result<Option<AssetUrl>>> {
-                async move {
-                    if let Some(ending) = ending {
-                        Ok(Some(pipe_image(&ending).await?))
-                    } else {
-                        Ok(None)
-                    }
-                }
-                .boxed()
-            };
             let pipe_mr_image = |ending: Option<String>| -> BoxFuture<'_, anyhow::Result<Option<AssetUrl>>> {
                 let config = config.clone();
                 let in_root_url = in_root_url.clone();
@@ -246,6 +285,7 @@ pub async fn object_pipelines_from_quixel_json(
                             PipeImage::new(ctx.get_downloadable_url(&in_root_url.push(ending).unwrap()).unwrap().clone())
                                 .transform("mr", |img, _| rougness_to_mr(img))
                                 .cap_texture_size(config.cap_texture_sizes)
+                                .compress(config.compress_textures.then_some(TextureKind::MetallicRoughness))
                                 .get(ctx.assets())
                                 .await?,
                         )))
@@ -256,10 +296,16 @@ pub async fn object_pipelines_from_quixel_json(
                 .boxed()
             };
 
+            let (base_color, opacity) = pipe_base_color(
+                get_map_v1("maps", "Albedo").or_else(|| get_map_v2("components", "Albedo")),
+                get_map_v1("maps", "Opacity").or_else(|| get_map_v2("components", "Opacity")),
+            )
+            .await?;
             let atlas = PbrMaterialFromUrl {
-                base_color: pipe_image_opt(get_map_v1("maps", "Albedo").or_else(|| get_map_v2("components", "Albedo"))).await?,
-                opacity: pipe_image_opt(get_map_v1("maps", "Opacity").or_else(|| get_map_v2("components", "Opacity"))).await?,
-                normalmap: pipe_image_opt(get_map_v1("maps", "Normal").or_else(|| get_map_v2("components", "Normal"))).await?,
+                base_color,
+                opacity,
+                normalmap: pipe_image_opt(get_map_v1("maps", "Normal").or_else(|| get_map_v2("components", "Normal")), TextureKind::Normal)
+                    .await?,
                 alpha_cutoff: Some(0.5),
                 metallic_roughness: pipe_mr_image(get_map_v1("maps", "Roughness").or_else(|| get_map_v2("components", "Roughness")))
                     .await?,
@@ -268,10 +314,11 @@ pub async fn object_pipelines_from_quixel_json(
                 ..Default::default()
             }
             .relative_path_from(out_materials_url);
+            let (base_color, opacity) = pipe_base_color(get_map_v1("billboards", "Albedo"), get_map_v1("billboards", "Opacity")).await?;
             let billboard = PbrMaterialFromUrl {
-                base_color: pipe_image_opt(get_map_v1("billboards", "Albedo")).await?,
-                opacity: pipe_image_opt(get_map_v1("billboards", "Opacity")).await?,
-                normalmap: pipe_image_opt(get_map_v1("billboards", "Normal")).await?,
+                base_color,
+                opacity,
+                normalmap: pipe_image_opt(get_map_v1("billboards", "Normal"), TextureKind::Normal).await?,
                 alpha_cutoff: Some(0.5),
                 metallic_roughness: pipe_mr_image(get_map_v1("maps", "Roughness")).await?,
                 metallic: 0.2,
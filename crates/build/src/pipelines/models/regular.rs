@@ -1,6 +1,10 @@
+use std::collections::HashMap;
+
+use ambient_animation::AnimationEvent;
 use ambient_model_import::{model_crate::ModelCrate, MODEL_EXTENSIONS};
-use ambient_std::asset_url::AssetType;
+use ambient_std::asset_url::{AbsAssetUrl, AssetType};
 use anyhow::Context;
+use itertools::Itertools;
 
 use super::{
     super::{
@@ -28,6 +32,11 @@ pub async fn pipeline(ctx: &PipelineCtx, config: ModelsPipeline) -> Vec<OutAsset
                 model_crate.model_mut().set_name(file.path().file_name().unwrap());
                 model_crate.create_prefab_from_model();
 
+                let events_file = file.add_extension("events.json");
+                if ctx.files.has_input_file(&events_file) {
+                    add_animation_events(&ctx, &mut model_crate, &events_file).await?;
+                }
+
                 let out_model_path = ctx.in_root().relative_path(file.path());
                 config.apply(&ctx, &mut model_crate, &out_model_path).await?;
 
@@ -68,3 +77,22 @@ pub async fn pipeline(ctx: &PipelineCtx, config: ModelsPipeline) -> Vec<OutAsset
     )
     .await
 }
+
+/// Adds the events of a `<model>.events.json` file next to the model to its animations. The file maps
+/// the id of each animation to its events.
+async fn add_animation_events(ctx: &PipelineCtx, model_crate: &mut ModelCrate, events_file: &AbsAssetUrl) -> anyhow::Result<()> {
+    let events = events_file
+        .download_json::<HashMap<String, Vec<AnimationEvent>>>(ctx.assets())
+        .await
+        .with_context(|| format!("Invalid animation events {events_file}"))?;
+    for (anim, events) in events {
+        if !model_crate.animations.content.contains_key(&anim) {
+            let anims = model_crate.animations.content.keys().sorted().join(", ");
+            anyhow::bail!("No animation {anim:?} for the events in {events_file}, the animations are: {anims}");
+        }
+        let clip = model_crate.animations.content.get_mut(&anim).unwrap();
+        clip.events.extend(events);
+        clip.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+    Ok(())
+}
@@ -10,6 +10,7 @@ use ambient_model::{pbr_renderer_primitives_from_url, Model, PbrRenderPrimitiveF
 use ambient_model_import::{
     dotdot_path,
     model_crate::{cap_texture_size, ModelCrate},
+    texture_compression::{compress_texture, TextureKind},
     ModelImportPipeline, ModelImportTransform, ModelTransform, RelativePathBufExt,
 };
 use ambient_renderer::{
@@ -293,28 +294,40 @@ impl UnityMaterials {
             } else {
                 None
             };
-            let get_image = |image_and_file: Option<(image::RgbaImage, AbsAssetUrl)>| {
+            let get_image = |image_and_file: Option<(image::RgbaImage, AbsAssetUrl)>, kind: TextureKind| {
                 if let Some((mut image, file)) = image_and_file {
                     let out_image_path = self.ctx.in_root().relative_path(file.path()).prejoin("materials").with_extension("png");
                     let ctx = self.ctx.clone();
                     let config = config.clone();
                     async move {
                         let mut data = Cursor::new(Vec::new());
-                        tokio::task::block_in_place(|| {
+                        let compressed = tokio::task::block_in_place(|| {
                             if let Some(size) = config.cap_texture_sizes {
                                 cap_texture_size(&mut image, size.size());
                             }
                             image.write_to(&mut data, ImageOutputFormat::Png).unwrap();
+                            if config.compress_textures {
+                                compress_texture(&image, kind)
+                            } else {
+                                None
+                            }
                         });
-                        Some(ctx.write_file(&out_image_path, data.into_inner()).await)
+                        let url = ctx.write_file(&out_image_path, data.into_inner()).await;
+                        if let Some(compressed) = compressed {
+                            return Some(ctx.write_file(out_image_path.with_extension("ktx2"), compressed.to_bytes()).await);
+                        }
+                        Some(url)
                     }
                     .boxed()
                 } else {
                     async move { None as Option<AbsAssetUrl> }.boxed()
                 }
             };
-            let (base_color, normalmap, metallic_roughness) =
-                futures::join!(get_image(base_color), get_image(normalmap), get_image(metallic_roughness));
+            let (base_color, normalmap, metallic_roughness) = futures::join!(
+                get_image(base_color, TextureKind::BaseColor),
+                get_image(normalmap, TextureKind::Normal),
+                get_image(metallic_roughness, TextureKind::MetallicRoughness)
+            );
             let mat = PbrMaterialFromUrl {
                 name: Some(name.to_string()),
                 source: None,
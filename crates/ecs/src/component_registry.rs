@@ -43,6 +43,7 @@ pub struct ExternalComponentDesc {
 pub struct ExternalComponentAttributes {
     pub name: Option<String>,
     pub description: Option<String>,
+    pub version: u32,
     pub flags: ExternalComponentFlagAttributes,
 }
 impl ExternalComponentAttributes {
@@ -50,6 +51,7 @@ impl ExternalComponentAttributes {
         Self {
             name: desc.attribute::<Name>().map(|n| n.0.clone()),
             description: desc.attribute::<Description>().map(|n| n.0.clone()),
+            version: desc.attribute::<ComponentVersion>().map(|v| v.0).unwrap_or_default(),
             flags: ExternalComponentFlagAttributes::from_existing_component(desc),
         }
     }
@@ -106,6 +108,7 @@ pub struct ComponentRegistry {
     pub(crate) components: Vec<RegistryComponent>,
     pub component_paths: HashMap<String, u32>,
     pub next_index: u32,
+    pub migrations: Vec<ComponentMigration>,
 
     /// Handlers are called with a write-lock on ComponentRegistry, which will result in deadlock if your operation
     /// requires a read-lock on ComponentRegistry. Consider deferring your operation to a later time.
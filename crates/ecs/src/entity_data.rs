@@ -1,5 +1,6 @@
 use std::{
     self,
+    cmp::Ordering,
     fmt::{self, Debug},
     iter::Flatten,
 };
@@ -13,7 +14,10 @@ use serde::{
 };
 
 use super::{with_component_registry, Component, ComponentValue, ECSError, EntityId, World};
-use crate::{ComponentDesc, ComponentEntry, ComponentSet, ECSDeserializationWarnings, Serializable};
+use crate::{
+    migration::StoredEntity, split_versioned_component_path, versioned_component_path, ComponentDesc, ComponentEntry, ComponentSet,
+    ComponentVersion, ECSDeserializationWarnings, ECSMigrationReport, Serializable,
+};
 
 #[derive(Clone)]
 pub struct EntityData {
@@ -196,13 +200,14 @@ impl Serialize for EntityData {
         for entry in self.content.iter() {
             if let Some(ser) = entry.attribute::<Serializable>() {
                 let value = ser.serialize(entry);
-                map.serialize_entry(&entry.desc().path(), &value).expect("Bincode does not support #[serde(flatten)]");
+                map.serialize_entry(&versioned_component_path(entry.desc()), &value).expect("Bincode does not support #[serde(flatten)]");
             }
         }
         map.end()
     }
 }
 
+/// Doesn't apply the component migrations, use [`DeserEntityDataWithWarnings`] for stored data
 impl<'de> Deserialize<'de> for EntityData {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -223,7 +228,8 @@ impl<'de> Deserialize<'de> for EntityData {
             {
                 let mut res = EntityData::new();
                 while let Some(key) = map.next_key::<String>()? {
-                    let desc = with_component_registry(|r| r.get_by_path(&key))
+                    let (path, _) = split_versioned_component_path(&key);
+                    let desc = with_component_registry(|r| r.get_by_path(path))
                         .ok_or_else(|| de::Error::custom(format!("No such component: {key}")))?;
 
                     let ser = desc
@@ -250,6 +256,7 @@ impl<'de> Deserialize<'de> for DeserEntityDataWithWarnings {
     {
         struct EntityDataVisitor {
             warnings: ECSDeserializationWarnings,
+            migrated: ECSMigrationReport,
         }
 
         impl<'de> Visitor<'de> for EntityDataVisitor {
@@ -263,8 +270,18 @@ impl<'de> Deserialize<'de> for DeserEntityDataWithWarnings {
             where
                 V: MapAccess<'de>,
             {
-                let mut res = EntityData::new();
+                let mut stored = StoredEntity::new();
                 while let Some((key, value)) = map.next_entry::<String, serde_json::Value>()? {
+                    let (path, version) = split_versioned_component_path(&key);
+                    stored.insert(path.to_string(), (version, value));
+                }
+
+                let (migrated, errors) = with_component_registry(|r| r.migrate(&mut stored));
+                self.migrated.extend(migrated.into_iter().map(|(key, description)| (EntityId::null(), key, description)));
+                self.warnings.extend(errors.into_iter().map(|(key, err)| (EntityId::null(), key, format!("Migration failed: {err}"))));
+
+                let mut res = EntityData::new();
+                for (key, (version, value)) in stored {
                     let desc = with_component_registry(|r| r.get_by_path(&key));
                     let desc = match desc {
                         Some(desc) => desc,
@@ -275,6 +292,22 @@ impl<'de> Deserialize<'de> for DeserEntityDataWithWarnings {
                         }
                     };
 
+                    // The data is still loaded, but it may not have the layout of the current version
+                    let current_version = desc.attribute::<ComponentVersion>().map(|v| v.0).unwrap_or_default();
+                    match version.cmp(&current_version) {
+                        Ordering::Less => self.warnings.push((
+                            EntityId::null(),
+                            key.clone(),
+                            format!("Stored with version {version}, and no migration converts it to the current version {current_version}"),
+                        )),
+                        Ordering::Greater => self.warnings.push((
+                            EntityId::null(),
+                            key.clone(),
+                            format!("Stored with version {version}, which is newer than the current version {current_version}"),
+                        )),
+                        Ordering::Equal => {}
+                    }
+
                     let ser: Result<_, V::Error> = desc
                         .attribute::<Serializable>()
                         .ok_or_else(|| de::Error::custom(format!("Component {desc:?} is not deserializable")));
@@ -299,19 +332,20 @@ impl<'de> Deserialize<'de> for DeserEntityDataWithWarnings {
                     res.set_entry(value);
                 }
 
-                Ok(DeserEntityDataWithWarnings { entity: res, warnings: self.warnings })
+                Ok(DeserEntityDataWithWarnings { entity: res, warnings: self.warnings, migrated: self.migrated })
             }
         }
 
-        deserializer.deserialize_map(EntityDataVisitor { warnings: Default::default() })
+        deserializer.deserialize_map(EntityDataVisitor { warnings: Default::default(), migrated: Default::default() })
     }
 }
 
 /// Use this struct while de-serializing an EntityData to also get warnings
-/// about missing/bad components. Only works with serde_json
+/// about missing/bad components, and to apply the registered component migrations. Only works with serde_json
 pub struct DeserEntityDataWithWarnings {
     pub entity: EntityData,
     pub warnings: ECSDeserializationWarnings,
+    pub migrated: ECSMigrationReport,
 }
 
 impl IntoIterator for EntityData {
@@ -37,6 +37,7 @@ mod entity_data;
 mod events;
 mod index;
 mod location;
+mod migration;
 mod primitive_component;
 mod query;
 mod serialization;
@@ -51,6 +52,7 @@ pub use entity_data::*;
 pub use events::*;
 pub use index::*;
 pub use location::*;
+pub use migration::*;
 pub use primitive_component::*;
 pub use query::*;
 pub use serialization::*;
@@ -153,8 +155,9 @@ impl World {
         Self::from_slice(&content)
     }
     pub fn from_slice(content: &[u8]) -> anyhow::Result<Self> {
-        let DeserWorldWithWarnings { world, warnings } = serde_json::from_slice(content)?;
+        let DeserWorldWithWarnings { world, warnings, migrated } = serde_json::from_slice(content)?;
         warnings.log_warnings();
+        migrated.log_migrations();
         Ok(world)
     }
     pub fn spawn(&mut self, entity_data: EntityData) -> EntityId {
@@ -0,0 +1,341 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    AttributeConstructor, AttributeStore, ComponentAttribute, ComponentDesc, ComponentRegistry, ComponentValue, EntityId,
+    PrimitiveComponentContainerType, PrimitiveComponentType,
+};
+
+/// The version of the data layout of a component. Stored components are written with their version,
+/// so that [`ComponentMigration`]s can be applied when data from an older version is loaded.
+///
+/// Components without this attribute are version 0, and are stored without a version suffix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ComponentVersion(pub u32);
+impl ComponentAttribute for ComponentVersion {}
+impl<T: ComponentValue> AttributeConstructor<T, u32> for ComponentVersion {
+    fn construct(store: &mut AttributeStore, value: u32) {
+        store.set(Self(value))
+    }
+}
+
+/// Returns the key a component is stored under, i.e. `path` for unversioned components and `path@version` otherwise
+pub fn versioned_component_path(desc: ComponentDesc) -> String {
+    match desc.attribute::<ComponentVersion>().map(|v| v.0).unwrap_or_default() {
+        0 => desc.path(),
+        version => format!("{}@{}", desc.path(), version),
+    }
+}
+
+/// Splits a stored key into the component path and the version it was stored with
+pub fn split_versioned_component_path(key: &str) -> (&str, u32) {
+    match key.rsplit_once('@') {
+        Some((path, version)) => match version.parse() {
+            Ok(version) => (path, version),
+            Err(_) => (key, 0),
+        },
+        None => (key, 0),
+    }
+}
+
+/// Describes how to upgrade stored component data after a component has been renamed or retyped.
+///
+/// Migrations are applied in the order they were registered when loading entities through
+/// [`crate::DeserEntityDataWithWarnings`] or [`crate::DeserWorldWithWarnings`]. The plain `Deserialize`
+/// implementations of [`crate::EntityData`] and [`crate::World`] don't apply them, as they are also used
+/// for formats which aren't self-describing, such as bincode.
+///
+/// Components which are still older or newer than their current [`ComponentVersion`] after migrating are
+/// loaded as they are, with a deserialization warning, so that a missing `Convert` migration is noticed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type")]
+pub enum ComponentMigration {
+    /// Moves data stored under the `from` path to the `to` path.
+    Rename { from: String, to: String },
+    /// Converts data of `component` that was stored with a version lower than `version` from `from_type`
+    /// to the current type of the component.
+    Convert { component: String, from_type: PrimitiveComponentType, version: u32 },
+    /// Splits a vector component (`Vec2`, `Vec3`, `Vec4`, `Quat`) into one component per element. Non-vector values are copied to all targets.
+    Split { from: String, to: Vec<String> },
+    /// Merges several scalar components into a single vector component. Missing elements are set to zero.
+    Merge { from: Vec<String>, to: String },
+}
+impl ComponentMigration {
+    /// Applies this migration to the components of a single entity
+    fn apply(&self, registry: &ComponentRegistry, entity: &mut StoredEntity) -> Result<Option<String>, String> {
+        match self {
+            ComponentMigration::Rename { from, to } => {
+                if let Some(stored) = entity.remove(from) {
+                    entity.insert(to.clone(), stored);
+                    Ok(Some(format!("Renamed {from} to {to}")))
+                } else {
+                    Ok(None)
+                }
+            }
+            ComponentMigration::Convert { component, from_type, version } => {
+                let (stored_version, value) = match entity.get_mut(component) {
+                    Some((stored_version, value)) if *stored_version < *version => (stored_version, value),
+                    _ => return Ok(None),
+                };
+                let to_type = registry
+                    .get_by_path(component)
+                    .and_then(|desc| registry.get_primitive_component(desc.index()))
+                    .ok_or_else(|| format!("Component {component} is not a primitive component"))?
+                    .ty;
+                *value = convert_value(value.clone(), *from_type, to_type)?;
+                *stored_version = *version;
+                Ok(Some(format!("Converted {component} from {from_type:?} to {to_type:?}")))
+            }
+            ComponentMigration::Split { from, to } => {
+                let value = match entity.remove(from) {
+                    Some((_, value)) => value,
+                    None => return Ok(None),
+                };
+                for (i, target) in to.iter().enumerate() {
+                    let element = match &value {
+                        Value::Array(elements) => elements.get(i).cloned().unwrap_or_else(|| Value::from(0.0)),
+                        value => value.clone(),
+                    };
+                    entity.insert(target.clone(), (current_version(registry, target), element));
+                }
+                Ok(Some(format!("Split {from} into {}", to.join(", "))))
+            }
+            ComponentMigration::Merge { from, to } => {
+                if !from.iter().any(|path| entity.contains_key(path)) {
+                    return Ok(None);
+                }
+                let elements =
+                    from.iter().map(|path| entity.remove(path).map(|(_, value)| value).unwrap_or_else(|| Value::from(0.0))).collect();
+                entity.insert(to.clone(), (current_version(registry, to), Value::Array(elements)));
+                Ok(Some(format!("Merged {} into {to}", from.join(", "))))
+            }
+        }
+    }
+}
+
+/// The version of the component at `path`, which split and merged data is written with, as it already has the current layout
+fn current_version(registry: &ComponentRegistry, path: &str) -> u32 {
+    registry.get_by_path(path).and_then(|desc| desc.attribute::<ComponentVersion>()).map(|v| v.0).unwrap_or_default()
+}
+
+/// The components of an entity as they were stored, keyed by path, along with the version they were stored with
+pub(crate) type StoredEntity = BTreeMap<String, (u32, Value)>;
+
+thread_local! {
+    static SCOPED_MIGRATIONS: RefCell<Option<Vec<ComponentMigration>>> = RefCell::new(None);
+}
+
+/// Runs `f` with `migrations` applied to the entities deserialized on this thread, instead of the migrations
+/// registered in the [`ComponentRegistry`]
+pub fn with_migrations<R>(migrations: Vec<ComponentMigration>, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<Vec<ComponentMigration>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED_MIGRATIONS.with(|scoped| *scoped.borrow_mut() = self.0.take());
+        }
+    }
+
+    let _restore = Restore(SCOPED_MIGRATIONS.with(|scoped| scoped.replace(Some(migrations))));
+    f()
+}
+
+impl ComponentRegistry {
+    pub fn add_migrations(&mut self, migrations: Vec<ComponentMigration>) {
+        self.migrations.extend(migrations);
+    }
+
+    /// Applies all registered migrations, or the ones of the enclosing [`with_migrations`], to the stored components of an entity.
+    ///
+    /// Returns a description of each applied migration, and the migrations that failed.
+    pub(crate) fn migrate(&self, entity: &mut StoredEntity) -> (Vec<(String, String)>, Vec<(String, String)>) {
+        SCOPED_MIGRATIONS.with(|scoped| {
+            let scoped = scoped.borrow();
+            let migrations = scoped.as_deref().unwrap_or(&self.migrations);
+
+            let mut migrated = Vec::new();
+            let mut errors = Vec::new();
+            for migration in migrations {
+                let key = match migration {
+                    ComponentMigration::Rename { from, .. } | ComponentMigration::Split { from, .. } => from.clone(),
+                    ComponentMigration::Convert { component, .. } => component.clone(),
+                    ComponentMigration::Merge { to, .. } => to.clone(),
+                };
+                match migration.apply(self, entity) {
+                    Ok(Some(description)) => migrated.push((key, description)),
+                    Ok(None) => {}
+                    Err(err) => errors.push((key, err)),
+                }
+            }
+            (migrated, errors)
+        })
+    }
+}
+
+/// Converts a json value of the primitive type `from` to the primitive type `to`
+pub fn convert_value(value: Value, from: PrimitiveComponentType, to: PrimitiveComponentType) -> Result<Value, String> {
+    use PrimitiveComponentContainerType as C;
+
+    if from == to {
+        return Ok(value);
+    }
+    match (from.decompose_container_type(), to.decompose_container_type()) {
+        (None, None) => convert_primitive_value(value, from, to),
+        (Some((C::Vec, from)), Some((C::Vec, to))) => match value {
+            Value::Array(elements) => {
+                Ok(Value::Array(elements.into_iter().map(|v| convert_primitive_value(v, from, to)).collect::<Result<_, _>>()?))
+            }
+            value => Err(format!("Expected an array, got {value}")),
+        },
+        (Some((C::Option, from)), Some((C::Option, to))) => match value {
+            Value::Null => Ok(Value::Null),
+            value => convert_primitive_value(value, from, to),
+        },
+        (None, Some((C::Vec, to))) => Ok(Value::Array(vec![convert_primitive_value(value, from, to)?])),
+        (None, Some((C::Option, to))) => convert_primitive_value(value, from, to),
+        (Some((C::Option, from)), None) => match value {
+            Value::Null => Err(format!("Cannot convert an empty Option to {to:?}")),
+            value => convert_primitive_value(value, from, to),
+        },
+        (Some((C::Vec, from)), None) => match value {
+            Value::Array(elements) if !elements.is_empty() => convert_primitive_value(elements.into_iter().next().unwrap(), from, to),
+            value => Err(format!("Cannot convert {value} to {to:?}")),
+        },
+        _ => Err(format!("Cannot convert {from:?} to {to:?}")),
+    }
+}
+
+fn convert_primitive_value(value: Value, from: PrimitiveComponentType, to: PrimitiveComponentType) -> Result<Value, String> {
+    use PrimitiveComponentType as P;
+
+    fn vector_len(ty: PrimitiveComponentType) -> Option<usize> {
+        match ty {
+            P::Vec2 => Some(2),
+            P::Vec3 => Some(3),
+            P::Vec4 | P::Quat => Some(4),
+            _ => None,
+        }
+    }
+    fn as_number(value: &Value) -> Option<f64> {
+        match value {
+            Value::Number(n) => n.as_f64(),
+            Value::Bool(b) => Some(if *b { 1. } else { 0. }),
+            _ => None,
+        }
+    }
+    fn number_to(value: f64, to: PrimitiveComponentType) -> Option<Value> {
+        Some(match to {
+            P::Bool => Value::Bool(value != 0.),
+            P::I32 => Value::from(value.round() as i32),
+            P::U32 => Value::from(value.round().max(0.) as u32),
+            P::U64 => Value::from(value.round().max(0.) as u64),
+            P::F32 | P::F64 => Value::from(value),
+            _ => {
+                let len = vector_len(to)?;
+                Value::Array(vec![Value::from(value); len])
+            }
+        })
+    }
+
+    if from == to {
+        return Ok(value);
+    }
+    let err = format!("Cannot convert {value} from {from:?} to {to:?}");
+    match (from, to) {
+        (_, P::String) => Ok(Value::String(match value {
+            Value::String(s) => s,
+            value => value.to_string(),
+        })),
+        (P::String, _) => {
+            let parsed: Value = match &value {
+                Value::String(s) => serde_json::from_str(s).map_err(|_| err)?,
+                _ => return Err(err),
+            };
+            let parsed_ty = match &parsed {
+                Value::Bool(_) => P::Bool,
+                Value::Array(elements) => match elements.len() {
+                    2 => P::Vec2,
+                    3 => P::Vec3,
+                    _ => P::Vec4,
+                },
+                _ => P::F64,
+            };
+            convert_primitive_value(parsed, parsed_ty, to)
+        }
+        (P::Bool | P::I32 | P::U32 | P::U64 | P::F32 | P::F64, _) => as_number(&value).and_then(|value| number_to(value, to)).ok_or(err),
+        (from, to) if vector_len(from).is_some() && vector_len(to).is_some() => match value {
+            Value::Array(mut elements) => {
+                elements.resize(vector_len(to).unwrap(), Value::from(0.0));
+                Ok(Value::Array(elements))
+            }
+            _ => Err(err),
+        },
+        (from, to) if vector_len(from).is_some() => match &value {
+            Value::Array(elements) => elements.first().and_then(as_number).and_then(|value| number_to(value, to)).ok_or(err),
+            _ => Err(err),
+        },
+        _ => Err(err),
+    }
+}
+
+/// A record of all migrations that were applied while deserializing
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ECSMigrationReport {
+    pub migrated: Vec<(EntityId, String, String)>,
+}
+
+impl std::ops::DerefMut for ECSMigrationReport {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.migrated
+    }
+}
+
+impl std::ops::Deref for ECSMigrationReport {
+    type Target = Vec<(EntityId, String, String)>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.migrated
+    }
+}
+impl ECSMigrationReport {
+    pub fn log_migrations(&self) {
+        if !self.migrated.is_empty() {
+            log::info!("{} components migrated", self.migrated.len());
+            for (id, comp, description) in self.migrated.iter().take(10) {
+                log::info!("Migrated component {} {}: {}", id, comp, description);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use PrimitiveComponentType as P;
+
+    #[test]
+    fn test_split_versioned_component_path() {
+        assert_eq!(split_versioned_component_path("core::test::a"), ("core::test::a", 0));
+        assert_eq!(split_versioned_component_path("core::test::a@3"), ("core::test::a", 3));
+        assert_eq!(split_versioned_component_path("core::test::a@x"), ("core::test::a@x", 0));
+    }
+
+    #[test]
+    fn test_convert_value() {
+        assert_eq!(convert_value(json!(3), P::I32, P::F32), Ok(json!(3.0)));
+        assert_eq!(convert_value(json!(2.6), P::F32, P::I32), Ok(json!(3)));
+        assert_eq!(convert_value(json!(1), P::U32, P::Bool), Ok(json!(true)));
+        assert_eq!(convert_value(json!(5), P::I32, P::String), Ok(json!("5")));
+        assert_eq!(convert_value(json!("5"), P::String, P::U32), Ok(json!(5)));
+        assert_eq!(convert_value(json!([1.0, 2.0]), P::Vec2, P::Vec3), Ok(json!([1.0, 2.0, 0.0])));
+        assert_eq!(convert_value(json!(2.0), P::F32, P::Vec3), Ok(json!([2.0, 2.0, 2.0])));
+        assert_eq!(convert_value(json!(4), P::I32, P::VecF32), Ok(json!([4.0])));
+        assert_eq!(convert_value(json!([1, 2]), P::VecI32, P::VecF32), Ok(json!([1.0, 2.0])));
+        assert_eq!(convert_value(json!(null), P::OptionI32, P::OptionF32), Ok(json!(null)));
+        assert!(convert_value(json!("hello"), P::String, P::F32).is_err());
+        assert!(convert_value(json!(null), P::OptionI32, P::I32).is_err());
+    }
+}
@@ -5,7 +5,7 @@ use once_cell::sync::Lazy;
 use paste::paste;
 
 use crate::{
-    AttributeConstructor, AttributeStore, ComponentDesc, ComponentRegistry, ComponentVTable, Description, EntityId,
+    AttributeConstructor, AttributeStore, ComponentDesc, ComponentRegistry, ComponentVTable, ComponentVersion, Description, EntityId,
     ExternalComponentAttributes, Name,
 };
 
@@ -41,6 +41,9 @@ macro_rules! build_attribute_registration {
         if let Some(description) = $attributes.description {
             <Description as AttributeConstructor<$type, _>>::construct(&mut $store, &description);
         }
+        if $attributes.version > 0 {
+            <ComponentVersion as AttributeConstructor<$type, _>>::construct(&mut $store, $attributes.version);
+        }
         $attributes.flags.construct_for_store::<$type>(&mut $store);
 
         static VTABLE: &ComponentVTable<$type> = &ComponentVTable::construct_external();
@@ -7,7 +7,9 @@ use serde::{
     Deserialize, Deserializer, Serialize, Serializer,
 };
 
-use crate::{dont_store, query, DeserEntityDataWithWarnings, EntityData, EntityId, Serializable, World};
+use crate::{
+    dont_store, query, versioned_component_path, DeserEntityDataWithWarnings, ECSMigrationReport, EntityData, EntityId, Serializable, World,
+};
 
 impl Serialize for World {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
@@ -32,7 +34,7 @@ impl<'a> Serialize for SerWorldEntity<'a> {
         for comp in comps {
             if let Some(ser) = comp.attribute::<Serializable>() {
                 let value = self.world.get_entry(self.id, comp).unwrap();
-                entity.serialize_entry(&comp.path(), ser.serialize(&value))?;
+                entity.serialize_entry(&versioned_component_path(comp), ser.serialize(&value))?;
             }
         }
         entity.end()
@@ -70,10 +72,12 @@ impl<'de> Deserialize<'de> for World {
 }
 
 /// Use this struct while de-serializing a World to also get warnings
-/// about missing/bad components. Only works with json.
+/// about missing/bad components, and a report of the component migrations
+/// that were applied. Only works with json.
 pub struct DeserWorldWithWarnings {
     pub world: World,
     pub warnings: ECSDeserializationWarnings,
+    pub migrated: ECSMigrationReport,
 }
 
 impl<'de> Deserialize<'de> for DeserWorldWithWarnings {
@@ -94,11 +98,15 @@ impl<'de> Deserialize<'de> for DeserWorldWithWarnings {
             where
                 V: MapAccess<'de>,
             {
-                let mut res =
-                    DeserWorldWithWarnings { world: World::new_with_config_internal("deserialized", false), warnings: Default::default() };
+                let mut res = DeserWorldWithWarnings {
+                    world: World::new_with_config_internal("deserialized", false),
+                    warnings: Default::default(),
+                    migrated: Default::default(),
+                };
                 while let Some((id, entity)) = map.next_entry::<EntityId, DeserEntityDataWithWarnings>()? {
                     res.world.spawn_with_id(id, entity.entity);
                     res.warnings.warnings.extend(entity.warnings.warnings.into_iter().map(|(_, key, err)| (id, key, err)));
+                    res.migrated.migrated.extend(entity.migrated.migrated.into_iter().map(|(_, key, description)| (id, key, description)));
                 }
                 Ok(res)
             }
@@ -148,6 +156,10 @@ mod test {
         ser_test3: String,
         @[Serializable]
         ser_test4: String,
+        @[Serializable, ComponentVersion[1]]
+        ser_test5: f32,
+        @[Serializable]
+        ser_test6: glam::Vec2,
     });
 
     fn init() {
@@ -205,4 +217,52 @@ mod test {
 
         assert!(serde_json::from_str::<World>(source).is_err());
     }
+
+    #[test]
+    pub fn test_deserialize_migrated_world() {
+        init();
+        let migrations = vec![
+            ComponentMigration::Rename { from: "core::test::old_ser_test3".to_string(), to: "core::test::ser_test3".to_string() },
+            ComponentMigration::Convert {
+                component: "core::test::ser_test5".to_string(),
+                from_type: PrimitiveComponentType::I32,
+                version: 1,
+            },
+            ComponentMigration::Merge {
+                from: vec!["core::test::old_x".to_string(), "core::test::old_y".to_string()],
+                to: "core::test::ser_test6".to_string(),
+            },
+        ];
+        let source =
+            r#"{"L9wH6h4qgcNBfRv2Rv2FIQ":{"core::test::old_ser_test3":"hello","core::test::ser_test5":3,"core::test::old_x":1.0}}"#;
+
+        // The migrations are scoped to this test, so that they don't apply to the others running in the same process
+        let deser: DeserWorldWithWarnings = with_migrations(migrations, || serde_json::from_str(source).unwrap());
+        let id = EntityId::from_str("L9wH6h4qgcNBfRv2Rv2FIQ").unwrap();
+        assert!(deser.warnings.warnings.is_empty());
+        assert_eq!(deser.migrated.migrated.len(), 3);
+        assert_eq!(deser.world.get_ref(id, ser_test3()).unwrap(), "hello");
+        assert_eq!(deser.world.get(id, ser_test5()).unwrap(), 3.);
+        assert_eq!(deser.world.get(id, ser_test6()).unwrap(), glam::vec2(1., 0.));
+
+        let ser = serde_json::to_string(&deser.world).unwrap();
+        assert!(ser.contains(r#""core::test::ser_test5@1":3.0"#));
+        let deser: World = serde_json::from_str(&ser).unwrap();
+        assert_eq!(deser.get(id, ser_test5()).unwrap(), 3.);
+    }
+
+    #[test]
+    pub fn test_deserialize_unmigrated_world() {
+        init();
+        let source = r#"{"L9wH6h4qgcNBfRv2Rv2FIQ":{"core::test::ser_test5":3,"core::test::ser_test4@2":"hello"}}"#;
+
+        let deser: DeserWorldWithWarnings = with_migrations(vec![], || serde_json::from_str(source).unwrap());
+        let id = EntityId::from_str("L9wH6h4qgcNBfRv2Rv2FIQ").unwrap();
+        assert!(deser.migrated.migrated.is_empty());
+        let mut warnings = deser.warnings.warnings.iter().map(|(_, key, _)| key.as_str()).collect::<Vec<_>>();
+        warnings.sort();
+        assert_eq!(warnings, ["core::test::ser_test4", "core::test::ser_test5"]);
+        assert_eq!(deser.world.get(id, ser_test5()).unwrap(), 3.);
+        assert_eq!(deser.world.get_ref(id, ser_test4()).unwrap(), "hello");
+    }
 }
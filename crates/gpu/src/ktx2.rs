@@ -0,0 +1,158 @@
+//! A minimal reader and writer for [KTX2](https://registry.khronos.org/KTX/specs/2.0/ktxspec.v2.html) containers.
+//!
+//! Only what the asset pipeline produces is supported: a single 2D image with a full mip chain, in one of the
+//! block compressed formats below, without supercompression.
+
+use anyhow::Context;
+use byteorder::{ByteOrder, LittleEndian};
+
+const IDENTIFIER: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+const HEADER_SIZE: usize = 80;
+const LEVEL_INDEX_ENTRY_SIZE: usize = 24;
+const LEVEL_ALIGNMENT: usize = 16;
+
+/// A block compressed texture and its mip chain, as stored in a `.ktx2` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ktx2Texture {
+    pub format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    /// The data of each mip level, starting with the full size one.
+    pub levels: Vec<Vec<u8>>,
+}
+impl Ktx2Texture {
+    pub fn is_supported_format(format: wgpu::TextureFormat) -> bool {
+        vk_format(format).is_some()
+    }
+    /// The data of all mip levels, in the order expected by [crate::texture::Texture::new_with_data].
+    pub fn data(&self) -> Vec<u8> {
+        self.levels.concat()
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let vk_format = vk_format(self.format).expect("Unsupported KTX2 format");
+        let dfd = data_format_descriptor(self.format);
+        let level_count = self.levels.len();
+        let dfd_offset = HEADER_SIZE + level_count * LEVEL_INDEX_ENTRY_SIZE;
+
+        // Mip levels are stored smallest first
+        let mut data = Vec::new();
+        let mut level_offsets = vec![0; level_count];
+        let mut offset = dfd_offset + dfd.len();
+        for (i, level) in self.levels.iter().enumerate().rev() {
+            let padding = (LEVEL_ALIGNMENT - offset % LEVEL_ALIGNMENT) % LEVEL_ALIGNMENT;
+            data.resize(data.len() + padding, 0);
+            offset += padding;
+            level_offsets[i] = offset;
+            data.extend_from_slice(level);
+            offset += level.len();
+        }
+
+        let mut res = vec![0; dfd_offset];
+        res[0..12].copy_from_slice(&IDENTIFIER);
+        let header = [vk_format, 1, self.width, self.height, 0, 0, 1, level_count as u32, 0];
+        LittleEndian::write_u32_into(&header, &mut res[12..48]);
+        LittleEndian::write_u32_into(&[dfd_offset as u32, dfd.len() as u32, 0, 0], &mut res[48..64]);
+        LittleEndian::write_u64_into(&[0, 0], &mut res[64..80]);
+        for (i, level) in self.levels.iter().enumerate() {
+            let entry = HEADER_SIZE + i * LEVEL_INDEX_ENTRY_SIZE;
+            LittleEndian::write_u64_into(
+                &[level_offsets[i] as u64, level.len() as u64, level.len() as u64],
+                &mut res[entry..entry + LEVEL_INDEX_ENTRY_SIZE],
+            );
+        }
+        res.extend_from_slice(&dfd);
+        res.extend_from_slice(&data);
+        res
+    }
+    pub fn from_bytes(data: &[u8]) -> anyhow::Result<Self> {
+        if data.len() < HEADER_SIZE || data[0..12] != IDENTIFIER {
+            anyhow::bail!("Not a KTX2 file");
+        }
+        let mut header = [0; 9];
+        LittleEndian::read_u32_into(&data[12..48], &mut header);
+        let [vk_format, _type_size, width, height, depth, layer_count, face_count, level_count, supercompression] = header;
+        let format = wgpu_format(vk_format).with_context(|| format!("Unsupported KTX2 vkFormat {vk_format}"))?;
+        if depth != 0 || layer_count != 0 || face_count != 1 {
+            anyhow::bail!("Only 2D KTX2 textures are supported");
+        }
+        if supercompression != 0 {
+            anyhow::bail!("Supercompressed KTX2 textures are not supported");
+        }
+        let level_count = level_count.max(1) as usize;
+        let levels = (0..level_count)
+            .map(|i| {
+                let entry = HEADER_SIZE + i * LEVEL_INDEX_ENTRY_SIZE;
+                let entry = data.get(entry..entry + LEVEL_INDEX_ENTRY_SIZE).context("Truncated KTX2 level index")?;
+                let offset = LittleEndian::read_u64(&entry[0..8]) as usize;
+                let length = LittleEndian::read_u64(&entry[8..16]) as usize;
+                Ok(data.get(offset..offset + length).context("Truncated KTX2 level data")?.to_vec())
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self { format, width, height, levels })
+    }
+}
+
+fn vk_format(format: wgpu::TextureFormat) -> Option<u32> {
+    match format {
+        wgpu::TextureFormat::Bc4RUnorm => Some(139),
+        wgpu::TextureFormat::Bc5RgUnorm => Some(141),
+        wgpu::TextureFormat::Bc7RgbaUnorm => Some(145),
+        wgpu::TextureFormat::Bc7RgbaUnormSrgb => Some(146),
+        _ => None,
+    }
+}
+fn wgpu_format(vk_format: u32) -> Option<wgpu::TextureFormat> {
+    match vk_format {
+        139 => Some(wgpu::TextureFormat::Bc4RUnorm),
+        141 => Some(wgpu::TextureFormat::Bc5RgUnorm),
+        145 => Some(wgpu::TextureFormat::Bc7RgbaUnorm),
+        146 => Some(wgpu::TextureFormat::Bc7RgbaUnormSrgb),
+        _ => None,
+    }
+}
+
+/// Builds the Khronos basic data format descriptor for one of the supported formats.
+fn data_format_descriptor(format: wgpu::TextureFormat) -> Vec<u8> {
+    // (color model, bytes per block, sample channels)
+    let (color_model, block_size, channels): (u32, u32, &[u32]) = match format {
+        wgpu::TextureFormat::Bc4RUnorm => (131, 8, &[0]),
+        wgpu::TextureFormat::Bc5RgUnorm => (132, 16, &[0, 1]),
+        _ => (134, 16, &[0]),
+    };
+    let transfer_function = if format.describe().srgb { 2 } else { 1 };
+    let block_bits = block_size * 8 / channels.len() as u32;
+    let mut words = vec![
+        0,
+        2 | ((24 + 16 * channels.len() as u32) << 16),
+        color_model | (1 << 8) | (transfer_function << 16),
+        3 | (3 << 8),
+        block_size,
+        0,
+    ];
+    for (i, channel) in channels.iter().enumerate() {
+        words.extend([(i as u32 * block_bits) | ((block_bits - 1) << 16) | (channel << 24), 0, 0, u32::MAX]);
+    }
+    words.insert(0, 4 * (words.len() as u32 + 1));
+    let mut res = vec![0; words.len() * 4];
+    LittleEndian::write_u32_into(&words, &mut res);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ktx2_roundtrip() {
+        let texture = Ktx2Texture {
+            format: wgpu::TextureFormat::Bc7RgbaUnormSrgb,
+            width: 8,
+            height: 8,
+            levels: vec![(0..64).collect(), (64..80).collect()],
+        };
+        let bytes = texture.to_bytes();
+        assert_eq!(bytes[0..12], IDENTIFIER);
+        assert_eq!(Ktx2Texture::from_bytes(&bytes).unwrap(), texture);
+        assert!(Ktx2Texture::from_bytes(&bytes[12..]).is_err());
+    }
+}
@@ -2,6 +2,7 @@ pub mod blit;
 pub mod fill;
 pub mod gpu;
 pub mod gpu_run;
+pub mod ktx2;
 pub mod mesh_buffer;
 pub mod mipmap;
 pub mod multi_buffer;
@@ -1,4 +1,5 @@
 use std::{
+    future::Future,
     ops::Range,
     sync::{
         atomic::{AtomicUsize, Ordering},
@@ -10,13 +11,13 @@ use ambient_std::{
     asset_cache::{AssetCache, AsyncAssetKey, AsyncAssetKeyExt, SyncAssetKey, SyncAssetKeyExt},
     asset_url::AbsAssetUrl,
     download_asset::{AssetResult, MeshFromUrl},
-    mesh::Mesh,
+    mesh::{Mesh, MorphTarget},
 };
 use async_trait::async_trait;
-use glam::{UVec4, Vec2, Vec4};
+use glam::{UVec4, Vec2, Vec3, Vec4, Vec4Swizzles};
 use itertools::Itertools;
 use parking_lot::Mutex;
-use wgpu::RenderPass;
+use wgpu::{BufferAsyncError, RenderPass};
 
 use crate::{
     gpu::{Gpu, GpuKey},
@@ -100,6 +101,7 @@ impl AsyncAssetKey<AssetResult<Arc<GpuMesh>>> for GpuMeshFromUrl {
 pub struct MeshBuffer {
     gpu: Arc<Gpu>,
     pub metadata_buffer: TypedBuffer<MeshMetadata>,
+    /// The positions of each mesh, followed by the position offsets and the normal offsets of each of its morph targets
     pub position_buffer: AttributeBuffer<Vec4>, // Vec4 instead of Vec3 because of alignment (16)
     pub normal_buffer: AttributeBuffer<Vec4>,
     pub tangent_buffer: AttributeBuffer<Vec4>,
@@ -177,6 +179,7 @@ impl MeshBuffer {
         }
     }
     pub fn insert(&mut self, mesh: &Mesh) -> Arc<GpuMesh> {
+        let vertex_count = mesh.positions.as_ref().map(|x| x.len()).unwrap_or_default();
         let metadata = MeshMetadata {
             position_offset: self.position_buffer.front.len() as u32,
             normal_offset: self.normal_buffer.front.len() as u32,
@@ -186,12 +189,25 @@ impl MeshBuffer {
             weight_offset: self.weight_buffer.front.len() as u32,
             index_offset: self.index_buffer.front.len() as u32,
             index_count: mesh.indices.as_ref().map(|x| x.len()).unwrap_or_default() as u32,
+            vertex_count: vertex_count as u32,
+            morph_target_count: mesh.morph_targets.len() as u32,
         };
 
         let mut internal_mesh = InternalMesh { metadata, ..Default::default() };
         if let Some(positions) = &mesh.positions {
+            // Every morph target is padded to the vertex count, so that the shader can find them from the end of the positions
+            fn padded(values: &[Vec3], count: usize) -> impl Iterator<Item = Vec4> + '_ {
+                (0..count).map(move |i| values.get(i).copied().unwrap_or_default().extend(0.))
+            }
+            let positions = positions
+                .iter()
+                .map(|p| p.extend(0.))
+                .chain(mesh.morph_targets.iter().flat_map(|target| {
+                    padded(&target.positions, vertex_count).chain(padded(target.normals.as_deref().unwrap_or_default(), vertex_count))
+                }))
+                .collect_vec();
             self.position_buffer.front.resize(self.position_buffer.front.len() + positions.len() as u64, true);
-            self.position_buffer.front.write(metadata.position_offset as u64, &positions.iter().map(|p| p.extend(0.)).collect_vec());
+            self.position_buffer.front.write(metadata.position_offset as u64, &positions);
             internal_mesh.position_count = positions.len() as u64;
         }
         if let Some(normals) = &mesh.normals {
@@ -298,6 +314,8 @@ impl MeshBuffer {
         for (index, mesh) in update_meshes_sorted {
             self.meshes[index].as_mut().unwrap().metadata = MeshMetadata {
                 index_count: mesh.index_count as u32,
+                vertex_count: mesh.metadata.vertex_count,
+                morph_target_count: mesh.metadata.morph_target_count,
                 position_offset: base_offset.position_offset + cursor.position_offset,
                 normal_offset: base_offset.normal_offset + cursor.normal_offset,
                 tangent_offset: base_offset.tangent_offset + cursor.tangent_offset,
@@ -358,6 +376,64 @@ impl MeshBuffer {
     pub fn get_mesh_metadata(&self, mesh: &GpuMesh) -> &MeshMetadata {
         &self.meshes[mesh.index as usize].as_ref().unwrap().metadata
     }
+    /// Reads `mesh` back from the gpu. Only the first set of texture coordinates is stored on the gpu, and colors aren't stored at all.
+    ///
+    /// The copies are issued right away, so the returned future doesn't need to hold on to the mesh buffer.
+    pub fn read_mesh(&self, mesh: &GpuMesh) -> impl Future<Output = Result<Mesh, BufferAsyncError>> + 'static {
+        let internal = self.meshes[mesh.index as usize].clone().unwrap();
+        let metadata = internal.metadata;
+        fn range(offset: u32, count: u64) -> Range<u64> {
+            offset as u64..offset as u64 + count
+        }
+        let positions = self.position_buffer.front.read_staged(range(metadata.position_offset, internal.position_count));
+        let normals = self.normal_buffer.front.read_staged(range(metadata.normal_offset, internal.normal_count));
+        let tangents = self.tangent_buffer.front.read_staged(range(metadata.tangent_offset, internal.tangent_count));
+        let texcoords = self.texcoord0_buffer.front.read_staged(range(metadata.texcoord0_offset, internal.texcoord0_count));
+        let joints = self.joint_buffer.front.read_staged(range(metadata.joint_offset, internal.joint_count));
+        let weights = self.weight_buffer.front.read_staged(range(metadata.weight_offset, internal.weight_count));
+        let indices = self.index_buffer.front.read_staged(range(metadata.index_offset, internal.index_count));
+        let name = mesh.name.clone();
+        async move {
+            fn xyz(values: Vec<Vec4>) -> Vec<Vec3> {
+                values.into_iter().map(|v| v.xyz()).collect()
+            }
+            fn non_empty<T>(values: Vec<T>) -> Option<Vec<T>> {
+                if values.is_empty() {
+                    None
+                } else {
+                    Some(values)
+                }
+            }
+            let vertex_count = metadata.vertex_count as usize;
+            let mut positions = positions.await?;
+            let morphs = positions.split_off(vertex_count.min(positions.len()));
+            let morph_targets = if vertex_count == 0 {
+                Vec::new()
+            } else {
+                morphs
+                    .chunks_exact(vertex_count * 2)
+                    .enumerate()
+                    .map(|(i, target)| MorphTarget {
+                        name: i.to_string(),
+                        positions: target[..vertex_count].iter().map(|v| v.xyz()).collect(),
+                        normals: Some(target[vertex_count..].iter().map(|v| v.xyz()).collect()),
+                    })
+                    .collect()
+            };
+            Ok(Mesh {
+                name,
+                positions: non_empty(xyz(positions)),
+                colors: None,
+                normals: non_empty(xyz(normals.await?)),
+                tangents: non_empty(xyz(tangents.await?)),
+                texcoords: non_empty(texcoords.await?).into_iter().collect(),
+                joint_indices: non_empty(joints.await?),
+                joint_weights: non_empty(weights.await?),
+                indices: non_empty(indices.await?),
+                morph_targets,
+            })
+        }
+    }
     pub fn size(&self) -> u64 {
         self.metadata_buffer.size()
             + self.position_buffer.front.size()
@@ -397,6 +473,9 @@ pub struct MeshMetadata {
     pub index_offset: u32,
 
     pub index_count: u32,
+    /// The number of positions; the morph targets follow them in the position buffer
+    pub vertex_count: u32,
+    pub morph_target_count: u32,
 }
 
 #[derive(Debug, Clone, Default)]
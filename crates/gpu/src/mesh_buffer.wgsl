@@ -9,6 +9,8 @@ struct MeshMetadata {
     index_offset: u32,
 
     index_count: u32,
+    vertex_count: u32,
+    morph_target_count: u32,
 };
 
 
@@ -22,6 +22,7 @@ use wgpu::util::DeviceExt;
 use super::{
     fill::FillerKey,
     gpu::{Gpu, GpuKey},
+    ktx2::Ktx2Texture,
     mipmap::generate_mipmaps,
 };
 
@@ -51,14 +52,16 @@ impl Texture {
     }
 
     fn size_in_bytes_from_desc(descriptor: &wgpu::TextureDescriptor) -> u64 {
-        let mut mip_size = (descriptor.size.width as u64 * descriptor.size.height as u64 * descriptor.size.depth_or_array_layers as u64)
-            * descriptor.format.describe().block_size as u64;
-        let mut size_in_bytes = mip_size;
-        for _ in 1..descriptor.mip_level_count {
-            mip_size /= 2;
-            size_in_bytes += mip_size;
-        }
-        size_in_bytes
+        let info = descriptor.format.describe();
+        let (block_width, block_height) = (info.block_dimensions.0 as u64, info.block_dimensions.1 as u64);
+        (0..descriptor.mip_level_count)
+            .map(|level| {
+                let width = (descriptor.size.width as u64 >> level).max(1);
+                let height = (descriptor.size.height as u64 >> level).max(1);
+                let blocks = ((width + block_width - 1) / block_width) * ((height + block_height - 1) / block_height);
+                blocks * descriptor.size.depth_or_array_layers as u64 * info.block_size as u64
+            })
+            .sum()
     }
 
     pub fn new(gpu: Arc<Gpu>, descriptor: &wgpu::TextureDescriptor) -> Self {
@@ -149,6 +152,28 @@ impl Texture {
             &img.into_vec(),
         )
     }
+    /// Uploads a block compressed texture and all of its mip levels. `srgb` picks between the sRGB and linear
+    /// variant of the format, where the format has both
+    pub fn from_ktx2(gpu: Arc<Gpu>, texture: &Ktx2Texture, srgb: bool, label: wgpu::Label) -> Self {
+        let format = match texture.format {
+            wgpu::TextureFormat::Bc7RgbaUnorm | wgpu::TextureFormat::Bc7RgbaUnormSrgb if srgb => wgpu::TextureFormat::Bc7RgbaUnormSrgb,
+            wgpu::TextureFormat::Bc7RgbaUnorm | wgpu::TextureFormat::Bc7RgbaUnormSrgb => wgpu::TextureFormat::Bc7RgbaUnorm,
+            format => format,
+        };
+        Self::new_with_data(
+            gpu,
+            &wgpu::TextureDescriptor {
+                size: wgpu::Extent3d { width: texture.width, height: texture.height, depth_or_array_layers: 1 },
+                mip_level_count: texture.levels.len() as u32,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                label,
+            },
+            &texture.data(),
+        )
+    }
     /// This will automatically resize the images to the largest size if they're not the same size
     pub fn array_rgba8_mipmapped(assets: AssetCache, label: Option<&str>, mut data: Vec<RgbaImage>, format: wgpu::TextureFormat) -> Self {
         let gpu = GpuKey.get(&assets);
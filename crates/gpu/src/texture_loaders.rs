@@ -1,7 +1,7 @@
 use std::{borrow::Cow, fmt, io::Cursor, sync::Arc};
 
 use ambient_std::{
-    asset_cache::{AssetCache, AsyncAssetKey, AsyncAssetKeyExt},
+    asset_cache::{AssetCache, AsyncAssetKey, AsyncAssetKeyExt, SyncAssetKeyExt},
     asset_url::AbsAssetUrl,
     download_asset::{AssetError, AssetResult, BytesFromUrl},
     CowStr,
@@ -10,7 +10,7 @@ use async_trait::async_trait;
 use futures::future::join_all;
 use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
 
-use crate::texture::Texture;
+use crate::{gpu::GpuKey, ktx2::Ktx2Texture, texture::Texture};
 
 #[derive(Debug, Clone)]
 pub struct ImageFromUrl {
@@ -34,7 +34,18 @@ impl AsyncAssetKey<Result<Arc<image::RgbaImage>, AssetError>> for Rgba8ImageFrom
     }
 }
 
+/// Block compressed `.ktx2` textures are written next to an uncompressed `.png` of the same image, which is used
+/// whenever the texture has to be decoded on the CPU, or when the adapter doesn't support the compressed format.
+fn uncompressed_url(url: AbsAssetUrl) -> AbsAssetUrl {
+    if url.extension_is("ktx2") {
+        url.with_extension("png")
+    } else {
+        url
+    }
+}
+
 async fn image_from_url(assets: AssetCache, url: AbsAssetUrl) -> Result<DynamicImage, AssetError> {
+    let url = uncompressed_url(url);
     let data = BytesFromUrl::new(url.clone(), true).get(&assets).await?;
 
     let extension = url.extension().context("No extension")?;
@@ -58,6 +69,17 @@ impl AsyncAssetKey<Result<Arc<Texture>, AssetError>> for TextureFromUrl {
     }
     #[tracing::instrument(level = "info", name = "texture_from_url")]
     async fn load(self, assets: AssetCache) -> Result<Arc<Texture>, AssetError> {
+        if self.url.extension_is("ktx2") {
+            let gpu = GpuKey.get(&assets);
+            if gpu.device.features().contains(wgpu::Features::TEXTURE_COMPRESSION_BC) {
+                let data = BytesFromUrl::new(self.url.clone(), true).get(&assets).await?;
+                return tokio::task::block_in_place(|| -> Result<Arc<Texture>, AssetError> {
+                    let texture = Ktx2Texture::from_bytes(&data).with_context(|| format!("Failed to load texture {}", self.url))?;
+                    let srgb = self.format.describe().srgb;
+                    Ok(Arc::new(Texture::from_ktx2(gpu, &texture, srgb, Some(&self.url.to_string()))))
+                });
+            }
+        }
         let image = image_from_url(assets.clone(), self.url.clone()).await?;
         tokio::task::block_in_place(|| Ok(Arc::new(Texture::from_image_mipmapped(assets, image, self.format, Some(&self.url.to_string())))))
     }
@@ -1,5 +1,5 @@
 use std::{
-    marker::PhantomData, ops::{DerefMut, RangeBounds}, sync::{
+    future::Future, marker::PhantomData, ops::{DerefMut, Range, RangeBounds}, sync::{
         atomic::{AtomicUsize, Ordering}, Arc
     }
 };
@@ -145,6 +145,34 @@ impl UntypedBuffer {
             Self::read_buf(&self.gpu, &self.buffer, bounds).await
         }
     }
+    /// Copies `range` (in bytes) to a staging buffer right away, and returns a future which reads it back.
+    ///
+    /// Unlike [`UntypedBuffer::read`], the future doesn't borrow this buffer, so the buffer can be resized or written to
+    /// (or its lock released) while waiting for the data.
+    pub fn read_staged(&self, range: Range<BufferAddress>) -> impl Future<Output = Result<Vec<u8>, BufferAsyncError>> + 'static {
+        let gpu = self.gpu.clone();
+        let size = range.end.saturating_sub(range.start);
+        let staging_buffer = if size > 0 {
+            let mut encoder = gpu.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            let staging_buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            encoder.copy_buffer_to_buffer(&self.buffer, range.start, &staging_buffer, 0, size);
+            gpu.queue.submit(Some(encoder.finish()));
+            Some(staging_buffer)
+        } else {
+            None
+        };
+        async move {
+            match staging_buffer {
+                Some(staging_buffer) => Self::read_buf(&gpu, &staging_buffer, ..).await,
+                None => Ok(Vec::new()),
+            }
+        }
+    }
     async fn read_buf(gpu: &Gpu, buf: &wgpu::Buffer, range: impl RangeBounds<BufferAddress>) -> Result<Vec<u8>, BufferAsyncError> {
         let slice = buf.slice(range);
         let (tx, value) = tokio::sync::oneshot::channel();
@@ -230,6 +258,12 @@ impl<T: bytemuck::Pod> TypedBuffer<T> {
         Ok(bytemuck::cast_slice(&data).to_vec())
     }
 
+    /// Reads a range of items, see [`UntypedBuffer::read_staged`]
+    pub fn read_staged(&self, range: Range<u64>) -> impl Future<Output = Result<Vec<T>, BufferAsyncError>> + 'static {
+        let data = self.buffer.read_staged(range.start * self.item_size..range.end * self.item_size);
+        async move { Ok(data.await?.chunks_exact(std::mem::size_of::<T>()).map(bytemuck::pod_read_unaligned).collect()) }
+    }
+
     pub fn buffer(&self) -> &wgpu::Buffer {
         &self.buffer.buffer
     }
@@ -153,6 +153,7 @@ impl<'a> From<&'a CuboidMesh> for Mesh {
             joint_indices: None,
             joint_weights: None,
             indices: Some(indices),
+            morph_targets: Vec::new(),
         };
         if cuboid.tangents {
             mesh.create_tangents();
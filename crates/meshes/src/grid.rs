@@ -67,6 +67,7 @@ impl From<&GridMesh> for Mesh {
             indices: Some(indices),
             joint_weights: None,
             joint_indices: None,
+            morph_targets: Vec::new(),
         };
         mesh.create_tangents();
         mesh
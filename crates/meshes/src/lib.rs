@@ -88,6 +88,7 @@ pub fn triangle() -> Mesh {
         joint_indices: None,
         joint_weights: None,
         indices: None,
+        morph_targets: Vec::new(),
     }
 }
 
@@ -114,6 +115,7 @@ pub fn pentagon() -> Mesh {
         joint_indices: None,
         joint_weights: None,
         indices: Some(vec![0, 1, 4, 1, 2, 4, 2, 3, 4]),
+        morph_targets: Vec::new(),
     }
 }
 
@@ -155,6 +157,7 @@ impl From<QuadMesh> for Mesh {
             joint_indices: None,
             joint_weights: None,
             indices: Some(vec![0, 1, 2, 1, 3, 2]),
+            morph_targets: Vec::new(),
         };
         mesh.create_tangents();
         mesh
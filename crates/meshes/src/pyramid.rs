@@ -141,6 +141,7 @@ impl From<&PyramidMesh> for Mesh {
             joint_indices: None,
             joint_weights: None,
             indices: Some(indices),
+            morph_targets: Vec::new(),
         };
         mesh.create_tangents();
         mesh
@@ -147,6 +147,7 @@ fn import_sync(buffer: &[u8], model_crate: &mut ModelCrate, extension: &str) ->
             joint_indices: None,
             joint_weights: None,
             indices: Some(mesh.faces.iter().flat_map(|f| f.0.clone()).collect()),
+            morph_targets: Vec::new(),
         };
         model_crate.meshes.insert(i.to_string(), out_mesh);
     }
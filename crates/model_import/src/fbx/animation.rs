@@ -2,6 +2,7 @@ use std::collections::HashMap;
 
 use ambient_animation::{animation_bind_id_from_name, AnimationClip, AnimationOutputs, AnimationTarget, AnimationTrack, Vec3Field};
 use ambient_core::transform::{euler_rotation, scale, translation};
+use ambient_renderer::skinning::morph_weights;
 use fbxcel::tree::v7400::NodeHandle;
 use itertools::Itertools;
 use ordered_float::OrderedFloat;
@@ -17,6 +18,8 @@ pub fn get_animations(doc: &FbxDoc) -> HashMap<String, AnimationClip> {
         .map(|stack| {
             let mut clip = AnimationClip {
                 id: stack.name.clone(),
+                events: Vec::new(),
+                root_motion: None,
                 tracks: stack
                     .layers
                     .iter()
@@ -24,7 +27,7 @@ pub fn get_animations(doc: &FbxDoc) -> HashMap<String, AnimationClip> {
                         let layer = doc.animation_layers.get(layer_id).unwrap();
                         layer.curve_nodes.iter().flat_map(|curve_node_id| {
                             let curve_node = doc.animation_curve_nodes.get(curve_node_id).unwrap();
-                            curve_node.outputs.iter().flat_map(|(output_id, property)| {
+                            let model_tracks = curve_node.outputs.iter().flat_map(|(output_id, property)| {
                                 curve_node
                                     .curves
                                     .iter()
@@ -60,7 +63,31 @@ pub fn get_animations(doc: &FbxDoc) -> HashMap<String, AnimationClip> {
                                     })
                                     .collect_vec()
                                     .into_iter()
-                            })
+                            });
+                            let weight_tracks = curve_node
+                                .channel_outputs
+                                .iter()
+                                .filter_map(|channel_id| blend_shape_channel_target(doc, *channel_id))
+                                .flat_map(|(node_name, index)| {
+                                    curve_node
+                                        .curves
+                                        .values()
+                                        .map(|curve_id| {
+                                            let curve = doc.animation_curves.get(curve_id).unwrap();
+                                            AnimationTrack {
+                                                target: AnimationTarget::BinderId(animation_bind_id_from_name(&node_name)),
+                                                inputs: curve.key_time.iter().map(|time| *time as f32 / FBX_TIME).collect(),
+                                                outputs: AnimationOutputs::Weight {
+                                                    component: morph_weights(),
+                                                    index,
+                                                    // DeformPercent goes from 0 to 100
+                                                    data: curve.key_value_float.iter().map(|v| v / 100.).collect(),
+                                                },
+                                            }
+                                        })
+                                        .collect_vec()
+                                });
+                            model_tracks.chain(weight_tracks)
                         })
                     })
                     .collect(),
@@ -103,6 +130,16 @@ pub fn get_animations(doc: &FbxDoc) -> HashMap<String, AnimationClip> {
         .collect()
 }
 
+/// The name of the model node and the index of the morph target that a blend shape channel is applied to
+fn blend_shape_channel_target(doc: &FbxDoc, channel_id: i64) -> Option<(String, usize)> {
+    doc.geometries.values().find_map(|geo| {
+        let channels = geo.blend_shape_channels(&doc.blend_shapes, &doc.blend_shape_channels);
+        let index = channels.iter().position(|channel| channel.id == channel_id)?;
+        let model = doc.models.values().find(|model| model.geometries.contains(&geo.id))?;
+        Some((model.node_name.clone(), index))
+    })
+}
+
 #[derive(Debug)]
 pub struct FbxAnimationStack {
     pub id: i64,
@@ -139,11 +176,13 @@ pub struct FbxAnimationCurveNode {
     pub id: i64,
     pub curves: HashMap<String, i64>,
     pub outputs: Vec<(i64, String)>,
+    /// Blend shape channels whose DeformPercent is animated by this node
+    pub channel_outputs: Vec<i64>,
 }
 impl FbxAnimationCurveNode {
     pub fn from_node(node: NodeHandle) -> Self {
         let id = node.attributes()[0].get_i64().unwrap();
-        Self { id, curves: HashMap::new(), outputs: Vec::new() }
+        Self { id, curves: HashMap::new(), outputs: Vec::new(), channel_outputs: Vec::new() }
     }
 }
 #[derive(Debug)]
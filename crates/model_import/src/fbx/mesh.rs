@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use ambient_std::mesh::Mesh;
+use ambient_std::mesh::{Mesh, MorphTarget};
 use fbxcel::tree::v7400::NodeHandle;
 use glam::{uvec4, vec2, vec3, vec4, Mat4, Vec2, Vec3};
 use indexmap::IndexMap;
@@ -103,6 +103,7 @@ pub struct FbxGeometry {
     uvs: Vec<FbxLayerElementUV>,
     materials: Option<FbxLayerElementMaterial>,
     pub skin: Option<i64>,
+    pub blend_shape: Option<i64>,
 }
 impl FbxGeometry {
     pub fn from_node(node: NodeHandle, _: &FbxGlobalSettings) -> Self {
@@ -140,9 +141,27 @@ impl FbxGeometry {
             uvs: node.children().filter_map(FbxLayerElementUV::from_node).sorted_by_key(|x| x.channel).collect(),
             materials: materials_container_node.map(FbxLayerElementMaterial::from_node),
             skin: None,
+            blend_shape: None,
         }
     }
-    pub fn to_cpu_meshes(&self, skins: &IndexMap<i64, FbxSkin>, clusters: &HashMap<i64, FbxCluster>) -> Vec<Mesh> {
+    /// The blend shape channels of this geometry, in the order of its morph targets
+    pub fn blend_shape_channels<'a>(
+        &self,
+        blend_shapes: &HashMap<i64, FbxBlendShape>,
+        channels: &'a HashMap<i64, FbxBlendShapeChannel>,
+    ) -> Vec<&'a FbxBlendShapeChannel> {
+        self.blend_shape
+            .and_then(|id| blend_shapes.get(&id))
+            .map(|blend_shape| blend_shape.channels.iter().filter_map(|id| channels.get(id)).collect())
+            .unwrap_or_default()
+    }
+    pub fn to_cpu_meshes(
+        &self,
+        skins: &IndexMap<i64, FbxSkin>,
+        clusters: &HashMap<i64, FbxCluster>,
+        channels: &[&FbxBlendShapeChannel],
+        shapes: &HashMap<i64, FbxShape>,
+    ) -> Vec<Mesh> {
         // FBX is a bit complicated; there is a "merged" list of vertices in the self.vertices field (positions),
         // but other properties (such as normals) may require them to be unmerged, since one corner can have multiple
         // normals. This code handles both cases; when a vertex can be shared by multiple faces it will be, and when
@@ -163,6 +182,19 @@ impl FbxGeometry {
             }
         }
 
+        // The offset of each control point for each morph target. Channels with in-between shapes use their last (full) shape
+        let control_point_offsets = channels
+            .iter()
+            .map(|channel| {
+                channel
+                    .shapes
+                    .last()
+                    .and_then(|id| shapes.get(id))
+                    .map(|shape| shape.control_point_offsets(self.vertices.len()))
+                    .unwrap_or_else(|| vec![Vec3::ZERO; self.vertices.len()])
+            })
+            .collect_vec();
+
         // The polygon_vertices represent all vertices for all polygons, so they may be reduntant. For instance, a triangluated
         // quad will have 3+3=6 polygon vertices, but at a later step they may get merged into just 4 vertices
         let polygon_vertices = self
@@ -172,6 +204,7 @@ impl FbxGeometry {
             .map(|(polygon_vertex_index, &vertex_index)| {
                 let vertex_index = if vertex_index >= 0 { vertex_index } else { -vertex_index - 1 } as usize;
                 IntermediateVertex {
+                    control_point: vertex_index,
                     position: self.vertices[vertex_index],
                     normal: self.normals.as_ref().map(|normals| match normals.info_type {
                         FbxMappingInformationType::ByPolygonVertex => normals.normals[polygon_vertex_index],
@@ -316,6 +349,15 @@ impl FbxGeometry {
                         None
                     },
                     indices: Some(indices),
+                    morph_targets: channels
+                        .iter()
+                        .zip(&control_point_offsets)
+                        .map(|(channel, offsets)| MorphTarget {
+                            name: channel.name.clone(),
+                            positions: final_vertices.iter().map(|v| offsets[v.control_point]).collect(),
+                            normals: None,
+                        })
+                        .collect(),
                 };
                 mesh.try_ensure_tangents();
                 mesh
@@ -330,6 +372,7 @@ fn read_vec3(p: &[f64]) -> Vec3 {
 
 #[derive(PartialEq, Clone, Default, Debug)]
 struct IntermediateVertex {
+    control_point: usize,
     position: Vec3,
     normal: Option<Vec3>,
     tangent: Option<Vec3>,
@@ -439,3 +482,68 @@ impl FbxCluster {
         }
     }
 }
+
+#[derive(Debug)]
+pub struct FbxBlendShape {
+    pub id: i64,
+    pub channels: Vec<i64>,
+}
+impl FbxBlendShape {
+    pub fn from_node(node: NodeHandle) -> Self {
+        let id = node.attributes()[0].get_i64().unwrap();
+        Self { id, channels: Vec::new() }
+    }
+}
+
+#[derive(Debug)]
+pub struct FbxBlendShapeChannel {
+    pub id: i64,
+    pub name: String,
+    /// The weight of the channel, in percent
+    pub deform_percent: f32,
+    pub shapes: Vec<i64>,
+}
+impl FbxBlendShapeChannel {
+    pub fn from_node(node: NodeHandle) -> Self {
+        let id = node.attributes()[0].get_i64().unwrap();
+        let name = node.attributes()[1].get_string().unwrap().split('\u{0}').next().unwrap();
+        let deform_percent = node.children().find(|node| node.name() == "DeformPercent");
+        Self {
+            id,
+            name: name.to_string(),
+            deform_percent: deform_percent.and_then(|node| node.attributes()[0].get_f64()).unwrap_or_default() as f32,
+            shapes: Vec::new(),
+        }
+    }
+}
+
+/// The target geometry of a blend shape channel, stored as offsets of some of the control points of the base geometry
+#[derive(Debug)]
+pub struct FbxShape {
+    pub id: i64,
+    indexes: Vec<i32>,
+    vertices: Vec<Vec3>,
+}
+impl FbxShape {
+    pub fn from_node(node: NodeHandle) -> Self {
+        let id = node.attributes()[0].get_i64().unwrap();
+        let indexes = node.children().find(|node| node.name() == "Indexes");
+        let vertices = node.children().find(|node| node.name() == "Vertices");
+        Self {
+            id,
+            indexes: indexes.map(|indexes| indexes.attributes()[0].get_arr_i32().unwrap().to_vec()).unwrap_or_default(),
+            vertices: vertices
+                .map(|vertices| vertices.attributes()[0].get_arr_f64().unwrap().chunks(3).map(read_vec3).collect())
+                .unwrap_or_default(),
+        }
+    }
+    fn control_point_offsets(&self, control_point_count: usize) -> Vec<Vec3> {
+        let mut offsets = vec![Vec3::ZERO; control_point_count];
+        for (&index, &offset) in self.indexes.iter().zip(&self.vertices) {
+            if let Some(value) = offsets.get_mut(index as usize) {
+                *value = offset;
+            }
+        }
+        offsets
+    }
+}
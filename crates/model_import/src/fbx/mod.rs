@@ -1,5 +1,6 @@
 use std::{
     collections::HashMap,
+    f32::consts::FRAC_PI_2,
     io::{Cursor, Read, Seek},
     sync::Arc,
 };
@@ -18,7 +19,7 @@ use fbxcel::tree::{
     v7400::{NodeHandle, Tree},
 };
 use futures::future::join_all;
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Quat, Vec3};
 use indexmap::IndexMap;
 use itertools::Itertools;
 use relative_path::RelativePathBuf;
@@ -26,10 +27,14 @@ use relative_path::RelativePathBuf;
 use self::{
     animation::{FbxAnimationCurve, FbxAnimationCurveNode, FbxAnimationLayer, FbxAnimationStack},
     material::{FbxMaterial, FbxTexture, FbxVideo},
-    mesh::{FbxCluster, FbxGeometry, FbxSkin},
-    model::FbxModel,
+    mesh::{FbxBlendShape, FbxBlendShapeChannel, FbxCluster, FbxGeometry, FbxShape, FbxSkin},
+    model::{FbxCamera, FbxLight, FbxModel},
+};
+use crate::{
+    lights_cameras::{attach_to_node, camera_entity_data},
+    model_crate::ModelCrate,
+    TextureResolver,
 };
-use crate::{model_crate::ModelCrate, TextureResolver};
 
 mod animation;
 mod material;
@@ -61,7 +66,8 @@ pub async fn import_from_fbx_reader(
             let mut n_meshes = HashMap::new();
 
             for (id, geo) in doc.geometries.iter() {
-                let meshes = geo.to_cpu_meshes(&doc.skins, &doc.clusters);
+                let channels = geo.blend_shape_channels(&doc.blend_shapes, &doc.blend_shape_channels);
+                let meshes = geo.to_cpu_meshes(&doc.skins, &doc.clusters, &channels, &doc.shapes);
                 n_meshes.insert(*id, meshes.len());
                 for (index, mesh) in meshes.into_iter().enumerate() {
                     asset_crate.meshes.insert(format!("{id}_{index}"), mesh);
@@ -104,6 +110,24 @@ pub async fn import_from_fbx_reader(
                         .unwrap();
                 }
             }
+            // FBX cameras look along X and lights point along -Y, whereas Ambient's look along Z and point along X
+            for model in doc.models.values() {
+                let id = *entities.get(&model.id).unwrap();
+                if let Some(camera) = model.camera.and_then(|camera| doc.cameras.get(&camera)) {
+                    match camera.to_projection() {
+                        Some(projection) => {
+                            let ed = camera_entity_data(&projection).set(ambient_core::name(), model.node_name.clone());
+                            attach_to_node(&mut world, id, Quat::from_rotation_y(FRAC_PI_2), ed);
+                        }
+                        None => log::warn!("Skipping orthographic camera {:?}, as they are not supported for FBX", model.node_name),
+                    }
+                }
+                if let Some(light) = model.light.and_then(|light| doc.lights.get(&light)) {
+                    let ed = light.to_model_light().to_entity_data().set(ambient_core::name(), model.node_name.clone());
+                    attach_to_node(&mut world, id, Quat::from_rotation_z(-FRAC_PI_2), ed);
+                }
+            }
+
             let mut skins = Vec::new();
             for skin in doc.skins.values() {
                 skins.push(ModelSkin {
@@ -136,8 +160,13 @@ pub struct FbxDoc {
     pub videos: HashMap<i64, FbxVideo>,
 
     pub geometries: HashMap<i64, FbxGeometry>,
+    pub lights: HashMap<i64, FbxLight>,
+    pub cameras: HashMap<i64, FbxCamera>,
     pub skins: IndexMap<i64, FbxSkin>,
     pub clusters: HashMap<i64, FbxCluster>,
+    pub blend_shapes: HashMap<i64, FbxBlendShape>,
+    pub blend_shape_channels: HashMap<i64, FbxBlendShapeChannel>,
+    pub shapes: HashMap<i64, FbxShape>,
 
     pub animation_stacks: HashMap<i64, FbxAnimationStack>,
     pub animation_layers: HashMap<i64, FbxAnimationLayer>,
@@ -149,12 +178,18 @@ pub struct FbxDoc {
 impl FbxDoc {
     pub async fn from_url(assets: &AssetCache, url: &AbsAssetUrl) -> anyhow::Result<Self> {
         let content = url.download_bytes(assets).await?;
-        let cursor = Cursor::new(&*content);
-        match AnyTree::from_seekable_reader(cursor).context("Failed to load tree")? {
+        Self::from_reader(Cursor::new(&*content))
+    }
+    pub fn from_reader(reader: impl Read + Seek) -> anyhow::Result<Self> {
+        match AnyTree::from_seekable_reader(reader).context("Failed to load tree")? {
             AnyTree::V7400(_, tree, _) => Ok(Self::from_tree(tree)),
             _ => Err(anyhow::anyhow!("Unsupported fbx format (not 7.4)")),
         }
     }
+    /// The names of all nodes in the document, which become the names of the nodes of the model
+    pub fn node_names(&self) -> impl Iterator<Item = &str> {
+        self.models.values().map(|model| model.node_name.as_str())
+    }
     fn from_tree(tree: Tree) -> Self {
         let mut doc = Self {
             global_settings: FbxGlobalSettings::new(tree.root()),
@@ -165,8 +200,13 @@ impl FbxDoc {
             videos: HashMap::new(),
 
             geometries: HashMap::new(),
+            lights: HashMap::new(),
+            cameras: HashMap::new(),
             skins: IndexMap::new(),
             clusters: HashMap::new(),
+            blend_shapes: HashMap::new(),
+            blend_shape_channels: HashMap::new(),
+            shapes: HashMap::new(),
 
             animation_stacks: HashMap::new(),
             animation_layers: HashMap::new(),
@@ -198,10 +238,25 @@ impl FbxDoc {
                     doc.videos.insert(video.id, video);
                 }
 
+                "Geometry" if node.attributes()[2].get_string() == Some("Shape") => {
+                    let shape = FbxShape::from_node(node);
+                    doc.shapes.insert(shape.id, shape);
+                }
                 "Geometry" => {
                     let geo = FbxGeometry::from_node(node, &doc.global_settings);
                     doc.geometries.insert(geo.id, geo);
                 }
+                "NodeAttribute" => match node.attributes()[2].get_string() {
+                    Some("Light") => {
+                        let light = FbxLight::from_node(node);
+                        doc.lights.insert(light.id, light);
+                    }
+                    Some("Camera") => {
+                        let camera = FbxCamera::from_node(node);
+                        doc.cameras.insert(camera.id, camera);
+                    }
+                    _ => {}
+                },
                 "Deformer" => match node.attributes()[2].get_string().unwrap() {
                     "Skin" => {
                         let skin = FbxSkin::from_node(node);
@@ -211,6 +266,14 @@ impl FbxDoc {
                         let cluster = FbxCluster::from_node(node);
                         doc.clusters.insert(cluster.id, cluster);
                     }
+                    "BlendShape" => {
+                        let blend_shape = FbxBlendShape::from_node(node);
+                        doc.blend_shapes.insert(blend_shape.id, blend_shape);
+                    }
+                    "BlendShapeChannel" => {
+                        let channel = FbxBlendShapeChannel::from_node(node);
+                        doc.blend_shape_channels.insert(channel.id, channel);
+                    }
                     _ => panic!("Unrecognized type: {}", node.attributes()[2].get_string().unwrap()),
                 },
 
@@ -247,6 +310,11 @@ impl FbxDoc {
                     id,
                     match node.name() {
                         "Deformer" => node.attributes()[2].get_string().unwrap().to_string(),
+                        "Geometry" if node.attributes()[2].get_string() == Some("Shape") => "Shape".to_string(),
+                        "NodeAttribute" => match node.attributes()[2].get_string() {
+                            Some(class @ ("Light" | "Camera")) => class.to_string(),
+                            _ => node.name().to_string(),
+                        },
                         _ => node.name().to_string(),
                     },
                 )
@@ -258,6 +326,8 @@ impl FbxDoc {
                 match (to_type as &str, from_type as &str) {
                     ("Geometry", "Model") => doc.models.get_mut(&from).unwrap().geometries.push(to),
                     ("Material", "Model") => doc.models.get_mut(&from).unwrap().materials.push(to),
+                    ("Light", "Model") => doc.models.get_mut(&from).unwrap().light = Some(to),
+                    ("Camera", "Model") => doc.models.get_mut(&from).unwrap().camera = Some(to),
                     ("Texture", "Material") => match property.as_ref().map(|x| x as &str) {
                         Some("DiffuseColor") => doc.materials.get_mut(&from).unwrap().diffuse_color_texture = Some(to),
                         Some("TransparencyFactor") => doc.materials.get_mut(&from).unwrap().alpha_texture = Some(to),
@@ -280,6 +350,9 @@ impl FbxDoc {
                     ("Cluster", "Skin") => doc.skins.get_mut(&from).unwrap().clusters.push(to),
                     ("Skin", "Geometry") => doc.geometries.get_mut(&from).unwrap().skin = Some(to),
                     ("Model", "Cluster") => doc.clusters.get_mut(&from).unwrap().bone_id = Some(to),
+                    ("BlendShape", "Geometry") => doc.geometries.get_mut(&from).unwrap().blend_shape = Some(to),
+                    ("BlendShapeChannel", "BlendShape") => doc.blend_shapes.get_mut(&from).unwrap().channels.push(to),
+                    ("Shape", "BlendShapeChannel") => doc.blend_shape_channels.get_mut(&from).unwrap().shapes.push(to),
 
                     ("AnimationLayer", "AnimationStack") => doc.animation_stacks.get_mut(&from).unwrap().layers.push(to),
                     ("AnimationCurveNode", "AnimationLayer") => doc.animation_layers.get_mut(&from).unwrap().curve_nodes.push(to),
@@ -289,6 +362,9 @@ impl FbxDoc {
                     ("AnimationCurveNode", "Model") => {
                         doc.animation_curve_nodes.get_mut(&to).unwrap().outputs.push((from, property.as_ref().unwrap().to_string()));
                     }
+                    ("AnimationCurveNode", "BlendShapeChannel") => {
+                        doc.animation_curve_nodes.get_mut(&to).unwrap().channel_outputs.push(from);
+                    }
                     _ => {}
                 }
             }
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use ambient_core::{
+    camera::Projection,
     hierarchy::children,
     name,
     transform::{
@@ -10,13 +11,17 @@ use ambient_core::{
 };
 use ambient_ecs::{EntityData, EntityId, World};
 use ambient_model::{model_skin_ix, pbr_renderer_primitives_from_url, PbrRenderPrimitiveFromUrl};
-use ambient_renderer::double_sided;
+use ambient_renderer::{double_sided, skinning::morph_weights};
 use fbxcel::tree::v7400::NodeHandle;
 use glam::{vec3, EulerRot, Mat4, Quat, Vec3};
 use itertools::Itertools;
 
 use super::FbxDoc;
-use crate::{dotdot_path, model_crate::ModelCrate};
+use crate::{
+    dotdot_path,
+    lights_cameras::{LightKind, ModelLight},
+    model_crate::ModelCrate,
+};
 
 #[derive(Debug)]
 pub struct FbxModel {
@@ -40,6 +45,8 @@ pub struct FbxModel {
 
     pub geometries: Vec<i64>,
     pub materials: Vec<i64>,
+    pub light: Option<i64>,
+    pub camera: Option<i64>,
     pub parent: Option<i64>,
     pub children: Vec<i64>,
     pub is_root: bool,
@@ -79,6 +86,8 @@ impl FbxModel {
 
             geometries: Default::default(),
             materials: Default::default(),
+            light: None,
+            camera: None,
             parent: None,
             children: Default::default(),
             is_root: true,
@@ -176,6 +185,12 @@ impl FbxModel {
             if let Some(skin) = doc.geometries.get(&geo).and_then(|geo| geo.skin).and_then(|id| doc.skins.get_index_of(&id)) {
                 out_node.set_self(model_skin_ix(), skin);
             }
+            if let Some(geo) = doc.geometries.get(&geo) {
+                let channels = geo.blend_shape_channels(&doc.blend_shapes, &doc.blend_shape_channels);
+                if !channels.is_empty() {
+                    out_node.set_self(morph_weights(), channels.iter().map(|channel| channel.deform_percent / 100.).collect());
+                }
+            }
             if self.geometric_translation.is_some() || self.geometric_rotation.is_some() || self.geometric_scale.is_some() {
                 out_node.set_self(
                     mesh_to_local(),
@@ -191,6 +206,117 @@ impl FbxModel {
         entities.insert(self.id, out_node.spawn(world));
     }
 }
+
+/// A `NodeAttribute` of the `Light` class. FBX lights point along -Y.
+#[derive(Debug)]
+pub struct FbxLight {
+    pub id: i64,
+    light_type: i32,
+    color: Vec3,
+    intensity: f32,
+    /// The full angles of the cone, in degrees
+    inner_angle: f32,
+    outer_angle: f32,
+    far_attenuation_end: Option<f32>,
+}
+impl FbxLight {
+    pub fn from_node(node: NodeHandle) -> Self {
+        let id = node.attributes()[0].get_i64().unwrap();
+        let mut light =
+            Self { id, light_type: 0, color: Vec3::ONE, intensity: 100., inner_angle: 0., outer_angle: 45., far_attenuation_end: None };
+        let mut enable_far_attenuation = false;
+        if let Some(props) = node.children().find(|node| node.name() == "Properties70") {
+            for prop in props.children() {
+                match prop.attributes()[0].get_string().unwrap() {
+                    "LightType" => light.light_type = prop.attributes()[4].get_i32().unwrap(),
+                    "Color" => light.color = prop_vec3(prop),
+                    "Intensity" => light.intensity = prop.attributes()[4].get_f64().unwrap() as f32,
+                    "InnerAngle" => light.inner_angle = prop.attributes()[4].get_f64().unwrap() as f32,
+                    "OuterAngle" => light.outer_angle = prop.attributes()[4].get_f64().unwrap() as f32,
+                    "EnableFarAttenuation" => enable_far_attenuation = prop.attributes()[4].get_i32().unwrap() != 0,
+                    "FarAttenuationEnd" => light.far_attenuation_end = Some(prop.attributes()[4].get_f64().unwrap() as f32),
+                    _ => {}
+                }
+            }
+        }
+        if !enable_far_attenuation {
+            light.far_attenuation_end = None;
+        }
+        light
+    }
+    pub fn to_model_light(&self) -> ModelLight {
+        let kind = match self.light_type {
+            1 => LightKind::Directional,
+            2 => LightKind::Spot { inner_angle: (self.inner_angle / 2.).to_radians(), outer_angle: (self.outer_angle / 2.).to_radians() },
+            // Area and volume lights are approximated with point lights
+            _ => LightKind::Point,
+        };
+        // FBX intensities aren't in physical units, and 100 is the default, which is used as a `light_diffuse` of 1
+        ModelLight { kind, color: self.color * self.intensity / 100., range: self.far_attenuation_end }
+    }
+}
+
+/// A `NodeAttribute` of the `Camera` class. FBX cameras look along X, with Y up.
+#[derive(Debug)]
+pub struct FbxCamera {
+    pub id: i64,
+    orthographic: bool,
+    aperture_mode: i32,
+    /// In degrees. Depending on the aperture mode, this is either the horizontal or the vertical field of view
+    field_of_view: f32,
+    field_of_view_y: Option<f32>,
+    aspect_width: f32,
+    aspect_height: f32,
+    near_plane: f32,
+    far_plane: f32,
+}
+impl FbxCamera {
+    pub fn from_node(node: NodeHandle) -> Self {
+        let id = node.attributes()[0].get_i64().unwrap();
+        let mut camera = Self {
+            id,
+            orthographic: false,
+            aperture_mode: 2,
+            field_of_view: 40.,
+            field_of_view_y: None,
+            aspect_width: 320.,
+            aspect_height: 200.,
+            near_plane: 10.,
+            far_plane: 4000.,
+        };
+        if let Some(props) = node.children().find(|node| node.name() == "Properties70") {
+            for prop in props.children() {
+                match prop.attributes()[0].get_string().unwrap() {
+                    "CameraProjectionType" => camera.orthographic = prop.attributes()[4].get_i32().unwrap() == 1,
+                    "ApertureMode" => camera.aperture_mode = prop.attributes()[4].get_i32().unwrap(),
+                    "FieldOfView" => camera.field_of_view = prop.attributes()[4].get_f64().unwrap() as f32,
+                    "FieldOfViewY" => camera.field_of_view_y = Some(prop.attributes()[4].get_f64().unwrap() as f32),
+                    "AspectWidth" => camera.aspect_width = prop.attributes()[4].get_f64().unwrap() as f32,
+                    "AspectHeight" => camera.aspect_height = prop.attributes()[4].get_f64().unwrap() as f32,
+                    "NearPlane" => camera.near_plane = prop.attributes()[4].get_f64().unwrap() as f32,
+                    "FarPlane" => camera.far_plane = prop.attributes()[4].get_f64().unwrap() as f32,
+                    _ => {}
+                }
+            }
+        }
+        camera
+    }
+    /// Orthographic cameras aren't supported, as their size can't be reliably derived from the file
+    pub fn to_projection(&self) -> Option<Projection> {
+        if self.orthographic {
+            return None;
+        }
+        let aspect_ratio = self.aspect_width / self.aspect_height;
+        let fovy = match (self.field_of_view_y, self.aperture_mode) {
+            (Some(fovy), _) => fovy.to_radians(),
+            // Horizontal
+            (None, 1) => 2. * ((self.field_of_view.to_radians() / 2.).tan() / aspect_ratio).atan(),
+            _ => self.field_of_view.to_radians(),
+        };
+        Some(Projection::Perspective { fovy, aspect_ratio, near: self.near_plane, far: self.far_plane })
+    }
+}
+
 fn prop_vec3(prop: NodeHandle) -> Vec3 {
     vec3(
         prop.attributes()[4].get_f64().unwrap() as f32,
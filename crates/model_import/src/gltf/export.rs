@@ -0,0 +1,796 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    io::Cursor,
+};
+
+use ambient_animation::{animation_bind_id_from_name, AnimationClip, AnimationEvent, AnimationOutputs, AnimationTarget};
+use ambient_core::{
+    asset_cache,
+    hierarchy::{children, parent},
+    name,
+    transform::{local_to_parent, local_to_world, mesh_to_local, rotation, scale, translation, TransformSystem},
+};
+use ambient_ecs::{EntityId, FrameEvent, System, World};
+use ambient_gpu::mesh_buffer::MeshBufferKey;
+use ambient_model::{animation_bind_id, model_skin_ix, model_skins, pbr_renderer_primitives_from_url};
+use ambient_renderer::{
+    materials::pbr_material::{PbrMaterial, PbrMaterialFromUrl},
+    primitives,
+    skinning::{inverse_bind_matrices, joints, morph_weights},
+    Material,
+};
+use ambient_std::{asset_cache::SyncAssetKeyExt, asset_url::AssetUrl, mesh::Mesh};
+use anyhow::Context;
+use futures::{future::BoxFuture, FutureExt, TryFutureExt};
+use glam::{Mat4, Vec3, Vec4};
+use image::{ImageOutputFormat, RgbaImage};
+use itertools::Itertools;
+use serde_json::{json, Value};
+
+use crate::model_crate::ModelCrate;
+
+const FLOAT: u32 = 5126;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Exports the main model of `model_crate` (its node hierarchy, meshes, PBR materials, skins and animations) to a binary glTF file.
+///
+/// The transform of the model (i.e. the conversion to Z up when it was normalized on import) is put on a root node,
+/// so that a normalized glTF model ends up with the same coordinates as the file it was imported from.
+pub fn export_model_crate(model_crate: &ModelCrate) -> anyhow::Result<Vec<u8>> {
+    let model = model_crate.models.content.get(ModelCrate::MAIN).context("Model crate has no model")?;
+    let mut world = model.0.clone();
+    let roots = world.resource_opt(children()).cloned().unwrap_or_default();
+    let ids = collect_hierarchy(&world, &roots);
+    // Finalized models don't store their matrices, so they're recalculated here
+    for &id in &ids {
+        if !world.has_component(id, local_to_world()) {
+            world.add_component(id, local_to_world(), Mat4::IDENTITY).unwrap();
+        }
+        if world.has_component(id, parent()) && !world.has_component(id, local_to_parent()) {
+            world.add_component(id, local_to_parent(), Mat4::IDENTITY).unwrap();
+        }
+    }
+    TransformSystem::new().run(&mut world, &FrameEvent);
+
+    let index_of: HashMap<EntityId, usize> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+    let mut scene = ExportScene {
+        roots: roots.iter().filter_map(|id| index_of.get(id).copied()).collect(),
+        root_transform: model.get_transform().unwrap_or_default(),
+        ..Default::default()
+    };
+
+    let skins = world
+        .resource_opt(model_skins())
+        .into_iter()
+        .flatten()
+        .map(|skin| {
+            let joints = skin.joints.iter().map(|joint| index_of.get(joint).copied()).collect::<Option<Vec<_>>>()?;
+            scene.skins.push(ExportSkin { joints, inverse_bind_matrices: skin.inverse_bind_matrices.to_vec() });
+            Some(scene.skins.len() - 1)
+        })
+        .collect_vec();
+
+    let mut mesh_indices = HashMap::<String, usize>::new();
+    let mut material_indices = HashMap::<String, usize>::new();
+    let mut image_indices = HashMap::new();
+    for &id in &ids {
+        let mut node = ExportNode {
+            name: world.get_ref(id, name()).ok().cloned(),
+            transform: if world.has_component(id, parent()) { world.get(id, local_to_parent()) } else { world.get(id, local_to_world()) }
+                .unwrap_or_default(),
+            children: world
+                .get_ref(id, children())
+                .map(|childs| childs.iter().filter_map(|child| index_of.get(child).copied()).collect())
+                .unwrap_or_default(),
+            mesh_to_local: world.get(id, mesh_to_local()).unwrap_or_default(),
+            morph_weights: world.get_ref(id, morph_weights()).ok().cloned(),
+            skin: world.get(id, model_skin_ix()).ok().and_then(|ix| skins.get(ix).copied().flatten()),
+            ..Default::default()
+        };
+        for primitive in world.get_ref(id, pbr_renderer_primitives_from_url()).into_iter().flatten().filter(|p| p.lod == 0) {
+            let mesh = match mesh_indices.get(primitive.mesh.path()) {
+                Some(&index) => index,
+                None => {
+                    let mesh = model_crate
+                        .meshes
+                        .get_by_path(primitive.mesh.path())
+                        .with_context(|| format!("Missing mesh {}", primitive.mesh))?;
+                    scene.meshes.push(mesh.clone());
+                    mesh_indices.insert(primitive.mesh.path().to_string(), scene.meshes.len() - 1);
+                    scene.meshes.len() - 1
+                }
+            };
+            let material = match &primitive.material {
+                Some(url) => Some(match material_indices.get(url.path()) {
+                    Some(&index) => index,
+                    None => {
+                        let material = model_crate.materials.get_by_path(url.path()).with_context(|| format!("Missing material {url}"))?;
+                        let material = ExportMaterial::from_url(material, model_crate, &mut scene.images, &mut image_indices);
+                        scene.materials.push(material);
+                        material_indices.insert(url.path().to_string(), scene.materials.len() - 1);
+                        scene.materials.len() - 1
+                    }
+                }),
+                None => None,
+            };
+            node.primitives.push((mesh, material));
+        }
+        scene.nodes.push(node);
+    }
+
+    // Tracks are bound the same way as when the model is animated
+    let mut bind_ids = HashMap::new();
+    for (i, &id) in ids.iter().enumerate().rev() {
+        let bind_id = match world.get_ref(id, animation_bind_id()) {
+            Ok(bind_id) => bind_id.clone(),
+            Err(_) => match world.get_ref(id, name()) {
+                Ok(name) => animation_bind_id_from_name(name),
+                Err(_) => continue,
+            },
+        };
+        bind_ids.insert(bind_id, i);
+    }
+    for (id, clip) in model_crate.animations.content.iter().sorted_by(|a, b| a.0.cmp(b.0)) {
+        let name = if clip.id.is_empty() { id.clone() } else { clip.id.clone() };
+        let animation = ExportAnimation::from_clip(name, clip, &scene.nodes, |target| match target {
+            AnimationTarget::BinderId(bind_id) => bind_ids.get(bind_id).copied(),
+            AnimationTarget::Entity(id) => index_of.get(id).copied(),
+        });
+        scene.animations.push(animation);
+    }
+
+    Ok(scene.write_glb())
+}
+
+/// Exports the hierarchy under `root` in a running `world` to a binary glTF file.
+///
+/// The entities are read right away; only reading the meshes back from the gpu happens in the returned future.
+/// Textures can't be read back from the gpu, so only the factors of the materials are exported. Animations aren't exported.
+pub fn export_world_subtree(world: &World, root: EntityId) -> impl Future<Output = anyhow::Result<Vec<u8>>> + 'static {
+    let snapshot = snapshot_world_subtree(world, root);
+    async move {
+        let (mut scene, meshes) = snapshot?;
+        scene.meshes = futures::future::try_join_all(meshes).await?;
+        Ok(scene.write_glb())
+    }
+}
+
+type MeshReadback = BoxFuture<'static, anyhow::Result<Mesh>>;
+
+fn snapshot_world_subtree(world: &World, root: EntityId) -> anyhow::Result<(ExportScene, Vec<MeshReadback>)> {
+    if !world.exists(root) {
+        anyhow::bail!("Entity {root} does not exist");
+    }
+    let ids = collect_hierarchy(world, &[root]);
+    let index_of: HashMap<EntityId, usize> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+    let mut scene = ExportScene { roots: vec![0], ..Default::default() };
+
+    let mut gpu_meshes = Vec::new();
+    let mut mesh_indices = HashMap::new();
+    let mut material_indices = HashMap::new();
+    for &id in &ids {
+        let mut node = ExportNode {
+            name: world.get_ref(id, name()).ok().cloned(),
+            transform: local_transform(world, id, id == root),
+            children: world
+                .get_ref(id, children())
+                .map(|childs| childs.iter().filter_map(|child| index_of.get(child).copied()).collect())
+                .unwrap_or_default(),
+            mesh_to_local: world.get(id, mesh_to_local()).unwrap_or_default(),
+            morph_weights: world.get_ref(id, morph_weights()).ok().cloned(),
+            ..Default::default()
+        };
+        for primitive in world.get_ref(id, primitives()).into_iter().flatten().filter(|p| p.lod == 0) {
+            let mesh = *mesh_indices.entry(primitive.mesh.index()).or_insert_with(|| {
+                gpu_meshes.push(primitive.mesh.clone());
+                gpu_meshes.len() - 1
+            });
+            let material = *material_indices.entry(primitive.material.id().to_string()).or_insert_with(|| {
+                scene.materials.push(ExportMaterial::from_renderer(&**primitive.material));
+                scene.materials.len() - 1
+            });
+            node.primitives.push((mesh, Some(material)));
+        }
+        if let (Ok(joints), Ok(inverse_bind_matrices)) = (world.get_ref(id, joints()), world.get_ref(id, inverse_bind_matrices())) {
+            match joints.iter().map(|joint| index_of.get(joint).copied()).collect::<Option<Vec<_>>>() {
+                Some(joints) => {
+                    scene.skins.push(ExportSkin { joints, inverse_bind_matrices: inverse_bind_matrices.to_vec() });
+                    node.skin = Some(scene.skins.len() - 1);
+                }
+                None => log::warn!("Not exporting the skin of {id}, as some of its joints are outside of the exported hierarchy"),
+            }
+        }
+        scene.nodes.push(node);
+    }
+
+    let assets = world.resource(asset_cache()).clone();
+    let mesh_buffer = MeshBufferKey.get(&assets);
+    let mesh_buffer = mesh_buffer.lock();
+    let meshes = gpu_meshes.iter().map(|mesh| mesh_buffer.read_mesh(mesh).err_into::<anyhow::Error>().boxed()).collect();
+    Ok((scene, meshes))
+}
+
+/// The transform of `id` relative to its parent, or to the world for the root of the export
+fn local_transform(world: &World, id: EntityId, is_root: bool) -> Mat4 {
+    let ltw = world.get(id, local_to_world()).unwrap_or_default();
+    if is_root {
+        return ltw;
+    }
+    match world.get(id, local_to_parent()) {
+        Ok(ltp) => ltp,
+        Err(_) => match world.get(id, parent()).and_then(|parent| world.get(parent, local_to_world())) {
+            Ok(parent_ltw) => parent_ltw.inverse() * ltw,
+            Err(_) => ltw,
+        },
+    }
+}
+
+/// Lists the entities in the hierarchies under `roots`, with parents before their children
+fn collect_hierarchy(world: &World, roots: &[EntityId]) -> Vec<EntityId> {
+    let mut ids = Vec::new();
+    let mut stack = roots.iter().rev().copied().collect_vec();
+    while let Some(id) = stack.pop() {
+        if !world.exists(id) {
+            continue;
+        }
+        ids.push(id);
+        if let Ok(childs) = world.get_ref(id, children()) {
+            stack.extend(childs.iter().rev());
+        }
+    }
+    ids
+}
+
+/// Converts between Ambient's Z up and glTF's Y up
+fn swap_y_z() -> Mat4 {
+    Mat4::from_cols(Vec4::X, Vec4::Z, Vec4::Y, Vec4::W)
+}
+
+/// A hierarchy in Ambient's conventions, which are converted to glTF's when it's written
+#[derive(Default)]
+struct ExportScene {
+    nodes: Vec<ExportNode>,
+    roots: Vec<usize>,
+    /// Applied on top of the roots
+    root_transform: Mat4,
+    meshes: Vec<Mesh>,
+    materials: Vec<ExportMaterial>,
+    images: Vec<RgbaImage>,
+    skins: Vec<ExportSkin>,
+    animations: Vec<ExportAnimation>,
+}
+
+#[derive(Default)]
+struct ExportNode {
+    name: Option<String>,
+    transform: Mat4,
+    children: Vec<usize>,
+    /// Mesh and material indices
+    primitives: Vec<(usize, Option<usize>)>,
+    mesh_to_local: Mat4,
+    morph_weights: Option<Vec<f32>>,
+    skin: Option<usize>,
+}
+
+struct ExportMaterial {
+    name: Option<String>,
+    base_color_factor: Vec4,
+    emissive_factor: Vec3,
+    metallic: f32,
+    roughness: f32,
+    alpha_cutoff: Option<f32>,
+    transparent: bool,
+    double_sided: bool,
+    base_color: Option<usize>,
+    normalmap: Option<usize>,
+    /// In glTF's layout; roughness in green and metallic in blue
+    metallic_roughness: Option<usize>,
+}
+impl ExportMaterial {
+    fn from_url(
+        material: &PbrMaterialFromUrl,
+        model_crate: &ModelCrate,
+        images: &mut Vec<RgbaImage>,
+        image_indices: &mut HashMap<(String, bool), usize>,
+    ) -> Self {
+        let mut push_image = |url: &Option<AssetUrl>, metallic_roughness: bool| {
+            let url = url.as_ref()?;
+            let key = (url.path().to_string(), metallic_roughness);
+            if let Some(&index) = image_indices.get(&key) {
+                return Some(index);
+            }
+            let image = model_crate.images.get_by_path(url.path()).or_else(|| {
+                // Compressed textures are exported from the uncompressed image they were made from
+                model_crate.images.content.get(&model_crate.compressed_images.loc.id_from_path(url.path())?)
+            });
+            let mut image = match image {
+                Some(image) => image.clone(),
+                None => {
+                    log::warn!("Not exporting missing image {url}");
+                    return None;
+                }
+            };
+            // Ambient stores metallic in red, see the glTF importer
+            if metallic_roughness {
+                for p in image.pixels_mut() {
+                    p[2] = p[0];
+                    p[0] = 0;
+                }
+            }
+            images.push(image);
+            image_indices.insert(key, images.len() - 1);
+            Some(images.len() - 1)
+        };
+        Self {
+            name: material.name.clone(),
+            base_color_factor: material.base_color_factor.unwrap_or(Vec4::ONE),
+            emissive_factor: material.emissive_factor.unwrap_or_default().truncate(),
+            metallic: material.metallic,
+            roughness: material.roughness,
+            alpha_cutoff: material.alpha_cutoff,
+            transparent: material.transparent.unwrap_or_default(),
+            double_sided: material.double_sided.unwrap_or_default(),
+            base_color: push_image(&material.base_color, false),
+            normalmap: push_image(&material.normalmap, false),
+            metallic_roughness: push_image(&material.metallic_roughness, true),
+        }
+    }
+    fn from_renderer(material: &dyn Material) -> Self {
+        let params = material.downcast_ref::<PbrMaterial>().map(|pbr| pbr.config.params).unwrap_or_default();
+        Self {
+            name: Some(material.name().to_string()),
+            base_color_factor: params.base_color_factor,
+            emissive_factor: params.emissive_factor.truncate(),
+            metallic: params.metallic,
+            roughness: params.roughness,
+            alpha_cutoff: None,
+            transparent: material.transparent().unwrap_or_default(),
+            double_sided: material.double_sided().unwrap_or_default(),
+            base_color: None,
+            normalmap: None,
+            metallic_roughness: None,
+        }
+    }
+}
+
+struct ExportSkin {
+    joints: Vec<usize>,
+    inverse_bind_matrices: Vec<Mat4>,
+}
+
+struct ExportAnimation {
+    name: String,
+    channels: Vec<ExportChannel>,
+    events: Vec<AnimationEvent>,
+}
+struct ExportChannel {
+    node: usize,
+    path: &'static str,
+    inputs: Vec<f32>,
+    outputs: Vec<f32>,
+    accessor_type: &'static str,
+}
+impl ExportAnimation {
+    /// Only transforms and morph weights can be exported; other tracks are skipped
+    fn from_clip(name: String, clip: &AnimationClip, nodes: &[ExportNode], node_of: impl Fn(&AnimationTarget) -> Option<usize>) -> Self {
+        let mut channels = Vec::new();
+        let mut weight_tracks = BTreeMap::<usize, Vec<_>>::new();
+        for track in &clip.tracks {
+            let node = match node_of(&track.target) {
+                Some(node) => node,
+                None => continue,
+            };
+            let channel = |path: &'static str, outputs: Vec<f32>, accessor_type: &'static str| ExportChannel {
+                node,
+                path,
+                inputs: track.inputs.clone(),
+                outputs,
+                accessor_type,
+            };
+            match &track.outputs {
+                AnimationOutputs::Vec3 { component, data } if *component == translation() => {
+                    channels.push(channel("translation", data.iter().flat_map(|v| v.to_array()).collect(), "VEC3"));
+                }
+                AnimationOutputs::Vec3 { component, data } if *component == scale() => {
+                    channels.push(channel("scale", data.iter().flat_map(|v| v.to_array()).collect(), "VEC3"));
+                }
+                AnimationOutputs::Quat { component, data } if *component == rotation() => {
+                    channels.push(channel("rotation", data.iter().flat_map(|v| v.to_array()).collect(), "VEC4"));
+                }
+                AnimationOutputs::Weight { index, data, .. } => weight_tracks.entry(node).or_default().push((*index, &track.inputs, data)),
+                _ => {}
+            }
+        }
+        // glTF animates all the weights of a node in one channel, so the tracks have to share their keyframes
+        for (node, tracks) in weight_tracks {
+            let inputs = tracks[0].1;
+            if tracks.iter().any(|(_, track_inputs, data)| *track_inputs != inputs || data.len() != inputs.len()) {
+                log::warn!("Not exporting the morph weight animation of node {node}, as its tracks have different keyframes");
+                continue;
+            }
+            let base = nodes[node].morph_weights.clone().unwrap_or_default();
+            let count = tracks.iter().map(|(index, ..)| index + 1).max().unwrap_or_default().max(base.len());
+            let mut outputs = (0..inputs.len()).flat_map(|_| (0..count).map(|i| base.get(i).copied().unwrap_or_default())).collect_vec();
+            for (index, _, data) in tracks {
+                for (frame, weight) in data.iter().enumerate() {
+                    outputs[frame * count + index] = *weight;
+                }
+            }
+            channels.push(ExportChannel { node, path: "weights", inputs: inputs.clone(), outputs, accessor_type: "SCALAR" });
+        }
+        Self { name, channels, events: clip.events.clone() }
+    }
+}
+
+impl ExportScene {
+    fn write_glb(&self) -> Vec<u8> {
+        let mut writer = GlbWriter::default();
+
+        let images = self.images.iter().map(|image| writer.push_image(image)).collect_vec();
+        let textures = (0..images.len()).map(|source| json!({ "source": source })).collect_vec();
+        let materials = self.materials.iter().map(|material| material.to_json()).collect_vec();
+
+        let mesh_primitives = self.meshes.iter().map(|mesh| writer.push_mesh(mesh)).collect_vec();
+        let mut meshes = Vec::new();
+        let mut mesh_indices = HashMap::new();
+        let mut nodes = self.nodes.iter().map(|node| node.to_json()).collect_vec();
+        let mut mesh_nodes = (0..self.nodes.len()).collect_vec();
+        for (i, node) in self.nodes.iter().enumerate() {
+            let primitives = node
+                .primitives
+                .iter()
+                .filter_map(|(mesh, material)| {
+                    let mut primitive = mesh_primitives[*mesh].clone()?;
+                    if let Some(material) = material {
+                        primitive["material"] = json!(material);
+                    }
+                    Some(primitive)
+                })
+                .collect_vec();
+            if primitives.is_empty() {
+                continue;
+            }
+            let mesh = *mesh_indices.entry(&node.primitives).or_insert_with(|| {
+                let mut mesh = json!({ "primitives": primitives });
+                let first_mesh = &self.meshes[node.primitives[0].0];
+                if !first_mesh.morph_targets.is_empty() {
+                    mesh["extras"] = json!({ "targetNames": first_mesh.morph_targets.iter().map(|t| &t.name).collect_vec() });
+                }
+                meshes.push(mesh);
+                meshes.len() - 1
+            });
+            // glTF meshes can't be offset from their node, so an offset is put on a child node instead
+            let mesh_node = if node.mesh_to_local.abs_diff_eq(Mat4::IDENTITY, 1e-6) {
+                i
+            } else {
+                nodes.push(json!({ "matrix": node.mesh_to_local.to_cols_array() }));
+                let mesh_node = nodes.len() - 1;
+                let children = nodes[i].as_object_mut().unwrap().entry("children").or_insert_with(|| json!([]));
+                children.as_array_mut().unwrap().push(json!(mesh_node));
+                mesh_node
+            };
+            mesh_nodes[i] = mesh_node;
+            nodes[mesh_node]["mesh"] = json!(mesh);
+            if let Some(weights) = &node.morph_weights {
+                nodes[mesh_node]["weights"] = json!(weights);
+            }
+            if let Some(skin) = node.skin {
+                nodes[mesh_node]["skin"] = json!(skin);
+            }
+        }
+
+        let skins = self
+            .skins
+            .iter()
+            .map(|skin| {
+                let matrices = skin.inverse_bind_matrices.iter().flat_map(|m| m.to_cols_array()).collect_vec();
+                json!({ "joints": skin.joints, "inverseBindMatrices": writer.push_floats(&matrices, "MAT4", None) })
+            })
+            .collect_vec();
+
+        let animations = self
+            .animations
+            .iter()
+            .map(|animation| {
+                let mut samplers = Vec::new();
+                let mut channels = Vec::new();
+                for channel in animation.channels.iter().filter(|channel| !channel.inputs.is_empty()) {
+                    let input = writer.push_floats(&channel.inputs, "SCALAR", None);
+                    let (min, max) = channel.inputs.iter().fold((f32::MAX, f32::MIN), |(min, max), &t| (min.min(t), max.max(t)));
+                    writer.accessors[input]["min"] = json!([min]);
+                    writer.accessors[input]["max"] = json!([max]);
+                    let output = writer.push_floats(&channel.outputs, channel.accessor_type, None);
+                    // Weights belong to the node with the mesh
+                    let node = if channel.path == "weights" { mesh_nodes[channel.node] } else { channel.node };
+                    samplers.push(json!({ "input": input, "output": output, "interpolation": "LINEAR" }));
+                    channels.push(json!({ "sampler": samplers.len() - 1, "target": { "node": node, "path": channel.path } }));
+                }
+                let mut res = json!({ "name": animation.name, "samplers": samplers, "channels": channels });
+                if !animation.events.is_empty() {
+                    res["extras"] = json!({ "events": animation.events });
+                }
+                res
+            })
+            .filter(|animation| !animation["channels"].as_array().unwrap().is_empty())
+            .collect_vec();
+
+        let root_transform = swap_y_z() * self.root_transform;
+        let roots = if root_transform.abs_diff_eq(Mat4::IDENTITY, 1e-6) {
+            self.roots.clone()
+        } else {
+            nodes.push(json!({ "matrix": root_transform.to_cols_array(), "children": self.roots }));
+            vec![nodes.len() - 1]
+        };
+
+        writer.pad_buffer();
+        let mut root = json!({
+            "asset": { "version": "2.0", "generator": "Ambient" },
+            "scene": 0,
+            "scenes": [{ "nodes": roots }],
+        });
+        if !writer.buffer.is_empty() {
+            root["buffers"] = json!([{ "byteLength": writer.buffer.len() }]);
+        }
+        // glTF doesn't allow empty arrays
+        for (key, values) in [
+            ("bufferViews", std::mem::take(&mut writer.buffer_views)),
+            ("accessors", std::mem::take(&mut writer.accessors)),
+            ("images", images),
+            ("textures", textures),
+            ("materials", materials),
+            ("meshes", meshes),
+            ("nodes", nodes),
+            ("skins", skins),
+            ("animations", animations),
+        ] {
+            if !values.is_empty() {
+                root[key] = Value::Array(values);
+            }
+        }
+        glb_container(&serde_json::to_vec(&root).unwrap(), &writer.buffer)
+    }
+}
+
+impl ExportNode {
+    fn to_json(&self) -> Value {
+        let (scale, rotation, translation) = self.transform.to_scale_rotation_translation();
+        let mut node = if rotation.is_finite() && scale.is_finite() {
+            json!({ "translation": translation.to_array(), "rotation": rotation.to_array(), "scale": scale.to_array() })
+        } else {
+            json!({ "matrix": self.transform.to_cols_array() })
+        };
+        if let Some(name) = &self.name {
+            node["name"] = json!(name);
+        }
+        if !self.children.is_empty() {
+            node["children"] = json!(self.children);
+        }
+        node
+    }
+}
+
+impl ExportMaterial {
+    fn to_json(&self) -> Value {
+        let mut pbr = json!({
+            "baseColorFactor": self.base_color_factor.to_array(),
+            "metallicFactor": self.metallic,
+            "roughnessFactor": self.roughness,
+        });
+        if let Some(texture) = self.base_color {
+            pbr["baseColorTexture"] = json!({ "index": texture });
+        }
+        if let Some(texture) = self.metallic_roughness {
+            pbr["metallicRoughnessTexture"] = json!({ "index": texture });
+        }
+        let mut material = json!({
+            "pbrMetallicRoughness": pbr,
+            "emissiveFactor": self.emissive_factor.to_array(),
+            "doubleSided": self.double_sided,
+        });
+        if let Some(name) = &self.name {
+            material["name"] = json!(name);
+        }
+        if let Some(texture) = self.normalmap {
+            material["normalTexture"] = json!({ "index": texture });
+        }
+        match (self.transparent, self.alpha_cutoff) {
+            (true, _) => material["alphaMode"] = json!("BLEND"),
+            (false, Some(cutoff)) => {
+                material["alphaMode"] = json!("MASK");
+                material["alphaCutoff"] = json!(cutoff);
+            }
+            (false, None) => material["alphaMode"] = json!("OPAQUE"),
+        }
+        material
+    }
+}
+
+/// Collects the binary data of the file, along with the buffer views and accessors which describe it
+#[derive(Default)]
+struct GlbWriter {
+    buffer: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+impl GlbWriter {
+    fn pad_buffer(&mut self) {
+        self.buffer.resize((self.buffer.len() + 3) / 4 * 4, 0);
+    }
+    fn push_view(&mut self, data: &[u8], target: Option<u32>) -> usize {
+        self.pad_buffer();
+        let mut view = json!({ "buffer": 0, "byteOffset": self.buffer.len(), "byteLength": data.len() });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+        self.buffer.extend_from_slice(data);
+        self.buffer_views.push(view);
+        self.buffer_views.len() - 1
+    }
+    fn push_accessor(&mut self, data: &[u8], count: usize, component_type: u32, accessor_type: &str, target: Option<u32>) -> usize {
+        let view = self.push_view(data, target);
+        self.accessors.push(json!({ "bufferView": view, "componentType": component_type, "count": count, "type": accessor_type }));
+        self.accessors.len() - 1
+    }
+    fn push_floats(&mut self, values: &[f32], accessor_type: &str, target: Option<u32>) -> usize {
+        let components = match accessor_type {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" => 4,
+            "MAT4" => 16,
+            _ => unreachable!(),
+        };
+        self.push_accessor(bytemuck::cast_slice(values), values.len() / components, FLOAT, accessor_type, target)
+    }
+    /// Positions need bounds in glTF
+    fn push_positions(&mut self, positions: &[Vec3]) -> usize {
+        let accessor = self.push_floats(bytemuck::cast_slice(positions), "VEC3", Some(ARRAY_BUFFER));
+        let min = positions.iter().fold(Vec3::splat(f32::MAX), |min, p| min.min(*p));
+        let max = positions.iter().fold(Vec3::splat(f32::MIN), |max, p| max.max(*p));
+        self.accessors[accessor]["min"] = json!(min.to_array());
+        self.accessors[accessor]["max"] = json!(max.to_array());
+        accessor
+    }
+    fn push_image(&mut self, image: &RgbaImage) -> Value {
+        let mut data = Cursor::new(Vec::new());
+        image.write_to(&mut data, ImageOutputFormat::Png).unwrap();
+        let view = self.push_view(&data.into_inner(), None);
+        json!({ "bufferView": view, "mimeType": "image/png" })
+    }
+    /// Returns the glTF primitive of the mesh, without a material. Meshes without positions can't be exported.
+    fn push_mesh(&mut self, mesh: &Mesh) -> Option<Value> {
+        let positions = mesh.positions.as_ref().filter(|positions| !positions.is_empty())?;
+        let vertex_count = positions.len();
+        let complete = |len: usize| len == vertex_count;
+
+        let mut primitive = json!({ "attributes": { "POSITION": self.push_positions(positions) } });
+        let mut attribute = |name: &str, accessor: usize| primitive["attributes"][name] = json!(accessor);
+        if let Some(normals) = mesh.normals.as_ref().filter(|v| complete(v.len())) {
+            attribute("NORMAL", self.push_floats(bytemuck::cast_slice(normals), "VEC3", Some(ARRAY_BUFFER)));
+        }
+        if let Some(tangents) = mesh.tangents.as_ref().filter(|v| complete(v.len())) {
+            let tangents = tangents.iter().flat_map(|t| t.extend(1.).to_array()).collect_vec();
+            attribute("TANGENT", self.push_floats(&tangents, "VEC4", Some(ARRAY_BUFFER)));
+        }
+        for (i, texcoords) in mesh.texcoords.iter().enumerate().filter(|(_, v)| complete(v.len())) {
+            attribute(&format!("TEXCOORD_{i}"), self.push_floats(bytemuck::cast_slice(texcoords), "VEC2", Some(ARRAY_BUFFER)));
+        }
+        if let Some(colors) = mesh.colors.as_ref().filter(|v| complete(v.len())) {
+            let colors = colors.iter().flat_map(|c| c.to_array()).collect_vec();
+            attribute("COLOR_0", self.push_floats(&colors, "VEC4", Some(ARRAY_BUFFER)));
+        }
+        if let Some(joints) = mesh.joint_indices.as_ref().filter(|v| complete(v.len())) {
+            let joints = joints.iter().flat_map(|j| j.to_array().map(|x| x as u16)).collect_vec();
+            attribute(
+                "JOINTS_0",
+                self.push_accessor(bytemuck::cast_slice(&joints), vertex_count, UNSIGNED_SHORT, "VEC4", Some(ARRAY_BUFFER)),
+            );
+        }
+        if let Some(weights) = mesh.joint_weights.as_ref().filter(|v| complete(v.len())) {
+            let weights = weights.iter().flat_map(|w| w.to_array()).collect_vec();
+            attribute("WEIGHTS_0", self.push_floats(&weights, "VEC4", Some(ARRAY_BUFFER)));
+        }
+        // The importer flips the winding of the triangles, so they're flipped back
+        if let Some(indices) = &mesh.indices {
+            let indices = indices.chunks_exact(3).flat_map(|t| [t[0], t[2], t[1]]).collect_vec();
+            primitive["indices"] = json!(self.push_accessor(
+                bytemuck::cast_slice(&indices),
+                indices.len(),
+                UNSIGNED_INT,
+                "SCALAR",
+                Some(ELEMENT_ARRAY_BUFFER)
+            ));
+        }
+        if !mesh.morph_targets.is_empty() {
+            let targets = mesh
+                .morph_targets
+                .iter()
+                .map(|target| {
+                    let mut json = json!({ "POSITION": self.push_positions(&target.positions) });
+                    if let Some(normals) = target.normals.as_ref().filter(|v| complete(v.len())) {
+                        json["NORMAL"] = json!(self.push_floats(bytemuck::cast_slice(normals), "VEC3", Some(ARRAY_BUFFER)));
+                    }
+                    json
+                })
+                .collect_vec();
+            primitive["targets"] = json!(targets);
+        }
+        Some(primitive)
+    }
+}
+
+/// Puts the JSON and binary chunks into a GLB container, padding both to 4 bytes
+fn glb_container(json: &[u8], bin: &[u8]) -> Vec<u8> {
+    fn chunk(out: &mut Vec<u8>, chunk_type: u32, data: &[u8], padding: u8) {
+        let len = (data.len() + 3) / 4 * 4;
+        out.extend_from_slice(&(len as u32).to_le_bytes());
+        out.extend_from_slice(&chunk_type.to_le_bytes());
+        out.extend_from_slice(data);
+        out.resize(out.len() + len - data.len(), padding);
+    }
+    let mut out = Vec::new();
+    out.extend_from_slice(b"glTF");
+    out.extend_from_slice(&2u32.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    chunk(&mut out, 0x4E4F534A, json, b' ');
+    if !bin.is_empty() {
+        chunk(&mut out, 0x004E4942, bin, 0);
+    }
+    let len = out.len() as u32;
+    out[8..12].copy_from_slice(&len.to_le_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use ambient_ecs::EntityData;
+    use ambient_model::{Model, PbrRenderPrimitiveFromUrl};
+    use glam::vec3;
+
+    use super::*;
+    use crate::dotdot_path;
+
+    #[test]
+    fn test_export_model_crate() {
+        ambient_app::init_all_components();
+        let mut model_crate = ModelCrate::new();
+        let mesh = Mesh {
+            name: "triangle".to_string(),
+            positions: Some(vec![vec3(0., 0., 0.), vec3(1., 0., 0.), vec3(0., 1., 0.)]),
+            colors: None,
+            normals: None,
+            tangents: None,
+            texcoords: Vec::new(),
+            joint_indices: None,
+            joint_weights: None,
+            indices: Some(vec![0, 1, 2]),
+            morph_targets: Vec::new(),
+        };
+        let mesh_path = model_crate.meshes.insert("triangle", mesh).path;
+        let primitive = PbrRenderPrimitiveFromUrl { mesh: dotdot_path(mesh_path).into(), material: None, lod: 0 };
+        let mut world = World::new("test");
+        let node = EntityData::new()
+            .set(name(), "node".to_string())
+            .set(translation(), vec3(1., 2., 3.))
+            .set(pbr_renderer_primitives_from_url(), vec![primitive])
+            .spawn(&mut world);
+        world.add_resource(children(), vec![node]);
+        model_crate.models.insert(ModelCrate::MAIN, Model(world));
+
+        let glb = export_model_crate(&model_crate).unwrap();
+        let (document, buffers, _) = gltf::import_slice(&glb).unwrap();
+        // The model isn't normalized, so the conversion to Y up is put on a root node
+        let root = document.scenes().next().unwrap().nodes().next().unwrap();
+        let node = root.children().next().unwrap();
+        assert_eq!(node.name(), Some("node"));
+        assert_eq!(node.transform().decomposed().0, [1., 2., 3.]);
+        let primitive = node.mesh().unwrap().primitives().next().unwrap();
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        assert_eq!(reader.read_positions().unwrap().count(), 3);
+        assert_eq!(reader.read_indices().unwrap().into_u32().collect_vec(), vec![0, 2, 1]);
+    }
+}
@@ -1,24 +1,40 @@
-use std::sync::Arc;
+use std::{
+    f32::consts::{FRAC_PI_2, PI},
+    sync::Arc,
+};
 
-use ambient_animation::{animation_bind_id_from_name, AnimationClip, AnimationOutputs, AnimationTarget, AnimationTrack};
+use ambient_animation::{animation_bind_id_from_name, AnimationClip, AnimationEvent, AnimationOutputs, AnimationTarget, AnimationTrack};
 use ambient_core::{
     bounding::local_bounding_aabb,
+    camera::{OrthographicRect, Projection},
     hierarchy::{children, parent},
     name,
     transform::{local_to_parent, local_to_world, rotation, scale, translation},
 };
 use ambient_ecs::{EntityData, World};
 use ambient_model::{model_skin_ix, model_skins, pbr_renderer_primitives_from_url, Model, ModelSkin, PbrRenderPrimitiveFromUrl};
-use ambient_renderer::materials::pbr_material::PbrMaterialFromUrl;
-use ambient_std::{asset_cache::AssetCache, asset_url::AbsAssetUrl, mesh::Mesh, shapes::AABB};
+use ambient_renderer::{materials::pbr_material::PbrMaterialFromUrl, skinning::morph_weights};
+use ambient_std::{
+    asset_cache::AssetCache,
+    asset_url::AbsAssetUrl,
+    mesh::{Mesh, MorphTarget},
+    shapes::AABB,
+};
+use anyhow::Context;
 use glam::{uvec4, Mat4, Quat, UVec4, Vec2, Vec3, Vec4, Vec4Swizzles};
-use gltf::animation::util::ReadOutputs;
+use gltf::{animation::util::ReadOutputs, khr_lights_punctual::Kind};
 use itertools::Itertools;
 use relative_path::RelativePathBuf;
+use serde::Deserialize;
 
 use self::gltf_import::GltfImport;
-use crate::{dotdot_path, model_crate::ModelCrate};
+use crate::{
+    dotdot_path,
+    lights_cameras::{attach_to_node, camera_entity_data, LightKind, ModelLight},
+    model_crate::ModelCrate,
+};
 
+pub mod export;
 mod gltf_import;
 
 pub async fn import_url(assets: &AssetCache, url: &AbsAssetUrl, asset_crate: &mut ModelCrate) -> anyhow::Result<RelativePathBuf> {
@@ -40,10 +56,23 @@ pub async fn import(import: &GltfImport, asset_crate: &mut ModelCrate) -> anyhow
                 texcoords.push(tc.into_f32().map(|x| x.into()).collect::<Vec<Vec2>>());
             }
 
+            let positions = reader.read_positions().map(|v| v.map(|x| x.into()).collect::<Vec<Vec3>>());
+            let vertex_count = positions.as_ref().map(|x| x.len()).unwrap_or_default();
+            // glTF doesn't name morph targets, so they're named by their index
+            let morph_targets = reader
+                .read_morph_targets()
+                .enumerate()
+                .map(|(i, (target_positions, target_normals, _))| MorphTarget {
+                    name: i.to_string(),
+                    positions: target_positions.map(|v| v.map(|x| x.into()).collect()).unwrap_or_else(|| vec![Vec3::ZERO; vertex_count]),
+                    normals: target_normals.map(|v| v.map(|x| x.into()).collect()),
+                })
+                .collect_vec();
+
             let flip_indices = true;
             let mut cpu_mesh = Mesh {
                 name: format!("{}:{}:{}", import.name, mesh.index(), primitive.index()),
-                positions: reader.read_positions().map(|v| v.map(|x| x.into()).collect::<Vec<Vec3>>()),
+                positions,
                 normals: reader.read_normals().map(|v| v.map(|x| x.into()).collect::<Vec<Vec3>>()),
                 tangents: reader.read_tangents().map(|v| v.map(|x| Vec4::from(x).xyz()).collect::<Vec<Vec3>>()),
                 texcoords,
@@ -67,6 +96,7 @@ pub async fn import(import: &GltfImport, asset_crate: &mut ModelCrate) -> anyhow
                         v.into_u32().collect::<Vec<u32>>()
                     }
                 }),
+                morph_targets,
             };
             cpu_mesh.try_ensure_tangents();
             let path = asset_crate.meshes.insert(&format!("{}{}_{}", name_(mesh.name()), mesh.index(), primitive.index()), cpu_mesh).path;
@@ -78,41 +108,63 @@ pub async fn import(import: &GltfImport, asset_crate: &mut ModelCrate) -> anyhow
         let tracks = animation
             .channels()
             .into_iter()
-            .map(|channel| {
+            .flat_map(|channel| {
                 let reader = channel.reader(|buffer| Some(&import.buffers[buffer.index()]));
                 let target = AnimationTarget::BinderId(animation_bind_id_from_name(channel.target().node().name().unwrap_or("")));
-                let inputs = reader.read_inputs().unwrap().collect();
+                let inputs: Vec<f32> = reader.read_inputs().unwrap().collect();
                 match reader.read_outputs() {
-                    Some(ReadOutputs::Translations(data)) => AnimationTrack {
+                    Some(ReadOutputs::Translations(data)) => vec![AnimationTrack {
                         target,
                         inputs,
                         outputs: AnimationOutputs::Vec3 {
                             component: translation(),
                             data: data.into_iter().map(|v| Vec3::from_slice(&v)).collect(),
                         },
-                    },
-                    Some(ReadOutputs::Scales(data)) => AnimationTrack {
+                    }],
+                    Some(ReadOutputs::Scales(data)) => vec![AnimationTrack {
                         target,
                         inputs,
                         outputs: AnimationOutputs::Vec3 {
                             component: scale(),
                             data: data.into_iter().map(|v| Vec3::from_slice(&v)).collect(),
                         },
-                    },
-                    Some(ReadOutputs::Rotations(data)) => AnimationTrack {
+                    }],
+                    Some(ReadOutputs::Rotations(data)) => vec![AnimationTrack {
                         target,
                         inputs,
                         outputs: AnimationOutputs::Quat {
                             component: rotation(),
                             data: data.into_f32().into_iter().map(|v| Quat::from_slice(&v)).collect(),
                         },
-                    },
-                    _ => unimplemented!(),
+                    }],
+                    // The weights of all morph targets are stored together per keyframe, so they're split into one track per target
+                    Some(ReadOutputs::MorphTargetWeights(data)) => {
+                        let data = data.into_f32().collect_vec();
+                        let target_count = if inputs.is_empty() { 0 } else { data.len() / inputs.len() };
+                        (0..target_count)
+                            .map(|index| AnimationTrack {
+                                target: target.clone(),
+                                inputs: inputs.clone(),
+                                outputs: AnimationOutputs::Weight {
+                                    component: morph_weights(),
+                                    index,
+                                    data: data.iter().skip(index).step_by(target_count).copied().collect(),
+                                },
+                            })
+                            .collect_vec()
+                    }
+                    None => Vec::new(),
                 }
             })
             .collect();
         let mut animation_clip = AnimationClip::from_tracks(tracks);
         animation_clip.id = animation.name().unwrap_or("").to_string();
+        if let Some(extras) = animation.extras() {
+            let extras: AnimationExtras = serde_json::from_str(extras.get())
+                .with_context(|| format!("Invalid extras on animation {:?}", animation.name().unwrap_or("")))?;
+            animation_clip.events = extras.events;
+            animation_clip.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+        }
         asset_crate.animations.insert(&format!("{}{}", name_(animation.name()), index), animation_clip);
     }
 
@@ -238,6 +290,13 @@ pub async fn import(import: &GltfImport, asset_crate: &mut ModelCrate) -> anyhow
                 if let Some(aabb) = AABB::unions(&aabbs) {
                     ed.set_self(local_bounding_aabb(), aabb);
                 }
+
+                let morph_target_count = mesh_.primitives().map(|primitive| primitive.morph_targets().count()).max().unwrap_or_default();
+                if morph_target_count > 0 {
+                    let mut weights = node.weights().or_else(|| mesh_.weights()).map(|w| w.to_vec()).unwrap_or_default();
+                    weights.resize(morph_target_count, 0.);
+                    ed.set_self(morph_weights(), weights);
+                }
             }
 
             if let Some(skin) = node.skin() {
@@ -272,9 +331,113 @@ pub async fn import(import: &GltfImport, asset_crate: &mut ModelCrate) -> anyhow
             world.add_component(*id, children(), childs).unwrap();
         }
     }
+    // glTF cameras look along -Z and lights point along -Z, whereas Ambient's look along Z and point along X
+    for (id, node) in nodes.iter().zip(import.document.nodes()) {
+        if let Some(camera) = node.camera() {
+            let projection = match camera.projection() {
+                gltf::camera::Projection::Orthographic(ortho) => Projection::Orthographic {
+                    rect: OrthographicRect { left: -ortho.xmag(), right: ortho.xmag(), top: ortho.ymag(), bottom: -ortho.ymag() },
+                    near: ortho.znear(),
+                    far: ortho.zfar(),
+                },
+                gltf::camera::Projection::Perspective(persp) => {
+                    let (fovy, aspect_ratio, near) = (persp.yfov(), persp.aspect_ratio().unwrap_or(1.), persp.znear());
+                    match persp.zfar() {
+                        Some(far) => Projection::Perspective { fovy, aspect_ratio, near, far },
+                        None => Projection::PerspectiveInfiniteReverse { fovy, aspect_ratio, near },
+                    }
+                }
+            };
+            let mut ed = camera_entity_data(&projection);
+            if let Some(camera_name) = camera.name().or(node.name()) {
+                ed.set_self(name(), camera_name.to_string());
+            }
+            attach_to_node(&mut world, *id, Quat::from_rotation_y(PI), ed);
+        }
+        if let Some(light) = node.light() {
+            let kind = match light.kind() {
+                Kind::Directional => LightKind::Directional,
+                Kind::Point => LightKind::Point,
+                Kind::Spot { inner_cone_angle, outer_cone_angle } => {
+                    LightKind::Spot { inner_angle: inner_cone_angle, outer_angle: outer_cone_angle }
+                }
+            };
+            let mut ed = ModelLight::from_photometric(kind, Vec3::from(light.color()), light.intensity(), light.range()).to_entity_data();
+            if let Some(light_name) = light.name().or(node.name()) {
+                ed.set_self(name(), light_name.to_string());
+            }
+            attach_to_node(&mut world, *id, Quat::from_rotation_y(FRAC_PI_2), ed);
+        }
+    }
     let roots = import.document.scenes().flat_map(|s| s.nodes().map(|x| nodes[x.index()])).collect_vec();
     world.add_resource(children(), roots);
     world.add_resource(name(), import.name.to_string());
 
     Ok(asset_crate.models.insert(ModelCrate::MAIN, Model(world)).path)
 }
+
+/// Animation events are stored in the extras of a glTF animation, as `{ "events": [{ "name": "footstep", "time": 0.5 }] }`.
+#[derive(Deserialize)]
+struct AnimationExtras {
+    #[serde(default)]
+    events: Vec<AnimationEvent>,
+}
+
+#[cfg(test)]
+mod tests {
+    use ambient_core::camera::{far, fovy, near};
+    use ambient_ecs::query;
+    use ambient_renderer::{light_diffuse, point_light};
+    use glam::vec3;
+
+    use super::*;
+
+    const LIGHT_AND_CAMERA: &str = r#"{
+        "asset": { "version": "2.0" },
+        "extensionsUsed": ["KHR_lights_punctual"],
+        "extensions": { "KHR_lights_punctual": { "lights": [{ "type": "point", "color": [1, 0.5, 0], "intensity": 683 }] } },
+        "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.8, "znear": 0.1, "zfar": 100 } }],
+        "nodes": [
+            { "name": "lamp", "extensions": { "KHR_lights_punctual": { "light": 0 } } },
+            { "name": "eye", "camera": 0 }
+        ],
+        "scenes": [{ "nodes": [0, 1] }],
+        "scene": 0
+    }"#;
+
+    #[tokio::test]
+    async fn test_import_light() {
+        ambient_app::init_all_components();
+        let gltf = GltfImport::from_slice("test".to_string(), false, LIGHT_AND_CAMERA).unwrap();
+        let mut model_crate = ModelCrate::new();
+        import(&gltf, &mut model_crate).await.unwrap();
+        let world = model_crate.model_world();
+
+        let (id, (&diffuse, &rot)) = query((light_diffuse(), rotation())).incl(point_light()).iter(world, None).next().unwrap();
+        assert_eq!(world.get_ref(id, name()).unwrap(), "lamp");
+        // 683 candela is 4π W
+        assert!(diffuse.abs_diff_eq(vec3(1., 0.5, 0.) * 4. * PI, 1e-4));
+        // glTF lights point along -Z
+        assert!((rot * Vec3::X).abs_diff_eq(-Vec3::Z, 1e-6));
+        assert_eq!(world.get(id, scale()).unwrap(), Vec3::ONE);
+    }
+
+    #[tokio::test]
+    async fn test_import_camera() {
+        ambient_app::init_all_components();
+        let gltf = GltfImport::from_slice("test".to_string(), false, LIGHT_AND_CAMERA).unwrap();
+        let mut model_crate = ModelCrate::new();
+        import(&gltf, &mut model_crate).await.unwrap();
+        let world = model_crate.model_world();
+
+        let (id, (&fovy, &rot)) = query((fovy(), rotation())).iter(world, None).next().unwrap();
+        assert_eq!(world.get_ref(id, name()).unwrap(), "eye");
+        assert_eq!(fovy, 0.8);
+        assert_eq!(world.get(id, near()).unwrap(), 0.1);
+        assert_eq!(world.get(id, far()).unwrap(), 100.);
+        // glTF cameras look along -Z with Y up, and the camera is rotated rather than mirrored to match
+        assert!((rot * Vec3::Z).abs_diff_eq(-Vec3::Z, 1e-6));
+        assert!((rot * Vec3::Y).abs_diff_eq(Vec3::Y, 1e-6));
+        assert_eq!(world.get(id, scale()).unwrap(), Vec3::ONE);
+    }
+}
@@ -21,7 +21,10 @@ use serde::{Deserialize, Serialize};
 pub mod assimp;
 pub mod fbx;
 pub mod gltf;
+mod lights_cameras;
+pub mod lod;
 pub mod model_crate;
+pub mod texture_compression;
 
 pub type TextureResolver = Arc<dyn Fn(String) -> futures::future::BoxFuture<'static, Option<RgbaImage>> + Sync + Send>;
 
@@ -259,7 +262,7 @@ impl ModelTransform {
                                 AnimationOutputs::Vec3 { component, data } => {
                                     AnimationOutputs::Vec3 { component: *component, data: data.iter().map(|x| *x * *anim_scale).collect() }
                                 }
-                                AnimationOutputs::Quat { component: _, data: _ } => unreachable!(),
+                                AnimationOutputs::Quat { .. } | AnimationOutputs::Weight { .. } => unreachable!(),
                                 AnimationOutputs::Vec3Field { component, field, data } => AnimationOutputs::Vec3Field {
                                     component: *component,
                                     field: *field,
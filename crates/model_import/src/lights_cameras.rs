@@ -0,0 +1,95 @@
+use std::f32::consts::PI;
+
+use ambient_core::{
+    camera::{aspect_ratio_from_window, projection, projection_view, Projection},
+    hierarchy::{children, parent},
+    main_scene,
+    transform::{inv_local_to_world, local_to_parent, local_to_world, rotation, scale, translation},
+};
+use ambient_ecs::{EntityData, EntityId, World};
+use ambient_renderer::{
+    directional_light, light_diffuse, light_range, point_light, spot_light, spot_light_inner_angle, spot_light_outer_angle,
+};
+use glam::{Quat, Vec3};
+
+/// The luminous efficacy used to convert photometric units to radiometric ones
+const LUMENS_PER_WATT: f32 = 683.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    Directional,
+    Point,
+    /// The angles are measured from the direction of the light, in radians
+    Spot {
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+/// A light read from a model file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelLight {
+    pub kind: LightKind,
+    /// The color of the light, multiplied by its irradiance in W/m² (directional) or its power in W (point and spot)
+    pub color: Vec3,
+    pub range: Option<f32>,
+}
+impl ModelLight {
+    /// Converts a light with photometric units, where the intensity is the illuminance in lux for directional lights,
+    /// and the luminous intensity in candela for point and spot lights
+    pub fn from_photometric(kind: LightKind, color: Vec3, intensity: f32, range: Option<f32>) -> Self {
+        let radiometric = match kind {
+            LightKind::Directional => intensity / LUMENS_PER_WATT,
+            // Spot lights are treated as point lights that are masked by their cone, so their power is the same
+            LightKind::Point | LightKind::Spot { .. } => intensity * 4. * PI / LUMENS_PER_WATT,
+        };
+        Self { kind, color: color * radiometric, range }
+    }
+    /// Ambient lights point along their X axis. Directional lights only light the scene once they're made a `sun`
+    pub fn to_entity_data(&self) -> EntityData {
+        let mut ed = EntityData::new().set(light_diffuse(), self.color);
+        match self.kind {
+            LightKind::Directional => ed.set_self(directional_light(), ()),
+            LightKind::Point => ed.set_self(point_light(), ()),
+            LightKind::Spot { inner_angle, outer_angle } => {
+                ed.set_self(spot_light(), ());
+                ed.set_self(spot_light_inner_angle(), inner_angle);
+                ed.set_self(spot_light_outer_angle(), outer_angle);
+            }
+        }
+        if let Some(range) = self.range {
+            ed.set_self(light_range(), range);
+        }
+        ed
+    }
+}
+
+/// Ambient cameras look along their Z axis, with Y up
+pub fn camera_entity_data(camera_projection: &Projection) -> EntityData {
+    let mut ed =
+        camera_projection.to_entity_data().set_default(inv_local_to_world()).set_default(projection()).set_default(projection_view());
+    if !matches!(camera_projection, Projection::Orthographic { .. }) {
+        ed.set_self(aspect_ratio_from_window(), ());
+    }
+    ed
+}
+
+/// Lights and cameras use different axis conventions in every format, so instead of putting them on the node itself,
+/// they're put on a child of it, which rotates from the conventions of the format to Ambient's.
+/// This is always a rotation, as a mirrored camera would flip the winding of everything it renders
+pub fn attach_to_node(world: &mut World, node: EntityId, rot: Quat, data: EntityData) -> EntityId {
+    let id = data
+        .set(translation(), Vec3::ZERO)
+        .set(rotation(), rot)
+        .set(scale(), Vec3::ONE)
+        .set_default(local_to_world())
+        .set_default(local_to_parent())
+        .set(parent(), node)
+        .set(main_scene(), ())
+        .spawn(world);
+    match world.get_mut(node, children()) {
+        Ok(childs) => childs.push(id),
+        Err(_) => world.add_component(node, children(), vec![id]).unwrap(),
+    }
+    id
+}
@@ -0,0 +1,132 @@
+use ambient_std::mesh::{Mesh, MorphTarget};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// Automatically generate lower levels of detail for the meshes of a model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerateLods {
+    /// The number of levels to generate, in addition to the original mesh.
+    pub levels: usize,
+    /// The target fraction of triangles to keep for each generated level, relative to the original mesh.
+    /// Defaults to halving the triangle count for every level (i.e. `[0.5, 0.25, ...]`). If fewer ratios than `levels`
+    /// are given, the remaining levels keep halving the triangle count of the last given one.
+    #[serde(default)]
+    pub ratios: Option<Vec<f32>>,
+    /// The screen sizes (as a fraction of the screen height) at which to switch to the next level, starting with the original mesh.
+    /// Spread out evenly down to 4% of the screen by default.
+    #[serde(default)]
+    pub cutoffs: Option<Vec<f32>>,
+}
+impl GenerateLods {
+    pub fn ratios(&self) -> Vec<f32> {
+        let given = self.ratios.as_deref().unwrap_or_default();
+        if given.len() > self.levels {
+            log::warn!("{} LOD ratios were given for {} levels, the extra ratios are ignored", given.len(), self.levels);
+        }
+        let mut ratio = 1.;
+        (0..self.levels)
+            .map(|level| {
+                ratio = given.get(level).copied().unwrap_or(ratio * 0.5);
+                ratio
+            })
+            .collect()
+    }
+}
+
+/// Reduces the number of triangles of `mesh` to roughly `ratio` times the original.
+///
+/// Only the index buffer is simplified, so vertices keep all of their attributes, including skin weights and morph targets.
+/// Vertices that share a position but differ in other attributes (i.e. UV seams) are kept apart, which preserves the seams.
+pub fn simplify_mesh(mesh: &Mesh, ratio: f32) -> Option<Mesh> {
+    let positions = mesh.positions.as_ref()?;
+    let indices = mesh.indices.as_ref()?;
+    if indices.is_empty() {
+        return None;
+    }
+    let vertices = meshopt::VertexDataAdapter::new(meshopt::typed_to_bytes(positions), std::mem::size_of::<Vec3>(), 0).ok()?;
+    let target_count = ((indices.len() as f32 * ratio) as usize / 3 * 3).max(3);
+    let simplified = meshopt::simplify(indices, &vertices, target_count, 0.01);
+    if simplified.is_empty() {
+        return None;
+    }
+    Some(compact_vertices(mesh, simplified))
+}
+
+/// Creates a mesh from the vertices of `mesh` that are used by `indices`
+fn compact_vertices(mesh: &Mesh, mut indices: Vec<u32>) -> Mesh {
+    let vertex_count = mesh.positions.as_ref().map(|p| p.len()).unwrap_or_default();
+    let mut remap = vec![u32::MAX; vertex_count];
+    let mut used = Vec::new();
+    for index in &mut indices {
+        let new_index = &mut remap[*index as usize];
+        if *new_index == u32::MAX {
+            *new_index = used.len() as u32;
+            used.push(*index);
+        }
+        *index = *new_index;
+    }
+    fn pick<T: Copy>(values: &Option<Vec<T>>, used: &[u32]) -> Option<Vec<T>> {
+        values.as_ref().map(|values| used.iter().map(|&i| values[i as usize]).collect())
+    }
+    Mesh {
+        name: mesh.name.clone(),
+        positions: pick(&mesh.positions, &used),
+        colors: pick(&mesh.colors, &used),
+        normals: pick(&mesh.normals, &used),
+        tangents: pick(&mesh.tangents, &used),
+        texcoords: mesh.texcoords.iter().map(|texcoords| used.iter().map(|&i| texcoords[i as usize]).collect()).collect(),
+        joint_indices: pick(&mesh.joint_indices, &used),
+        joint_weights: pick(&mesh.joint_weights, &used),
+        indices: Some(indices),
+        morph_targets: mesh
+            .morph_targets
+            .iter()
+            .map(|target| MorphTarget {
+                name: target.name.clone(),
+                positions: used.iter().map(|&i| target.positions[i as usize]).collect(),
+                normals: pick(&target.normals, &used),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec2, vec3};
+
+    use super::*;
+
+    #[test]
+    fn test_compact_vertices() {
+        let mesh = Mesh {
+            name: "quad".to_string(),
+            positions: Some(vec![vec3(0., 0., 0.), vec3(1., 0., 0.), vec3(0., 1., 0.), vec3(1., 1., 0.)]),
+            colors: None,
+            normals: None,
+            tangents: None,
+            texcoords: vec![vec![vec2(0., 0.), vec2(1., 0.), vec2(0., 1.), vec2(1., 1.)]],
+            joint_indices: None,
+            joint_weights: None,
+            indices: Some(vec![0, 1, 2, 1, 3, 2]),
+            morph_targets: Vec::new(),
+        };
+        let compacted = compact_vertices(&mesh, vec![1, 3, 2]);
+        assert_eq!(compacted.indices, Some(vec![0, 1, 2]));
+        assert_eq!(compacted.positions, Some(vec![vec3(1., 0., 0.), vec3(1., 1., 0.), vec3(0., 1., 0.)]));
+        assert_eq!(compacted.texcoords, vec![vec![vec2(1., 0.), vec2(1., 1.), vec2(0., 1.)]]);
+    }
+
+    #[test]
+    fn test_default_ratios() {
+        let config = GenerateLods { levels: 2, ratios: None, cutoffs: None };
+        assert_eq!(config.ratios(), vec![0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_missing_ratios() {
+        let config = GenerateLods { levels: 3, ratios: Some(vec![0.6]), cutoffs: None };
+        assert_eq!(config.ratios(), vec![0.6, 0.3, 0.15]);
+        let config = GenerateLods { levels: 1, ratios: Some(vec![0.6, 0.3]), cutoffs: None };
+        assert_eq!(config.ratios(), vec![0.6]);
+    }
+}
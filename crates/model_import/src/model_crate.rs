@@ -1,13 +1,23 @@
-use std::{collections::HashMap, io::Cursor, path::PathBuf, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::{PI, TAU},
+    io::Cursor,
+    path::PathBuf,
+    sync::Arc,
+};
 
-use ambient_animation::{animation_bind_id_from_name, AnimationClip};
+use ambient_animation::{
+    animation_bind_id_from_name, AnimationClip, AnimationOutput, AnimationOutputs, AnimationRootMotion, AnimationTarget, AnimationTrack,
+    AnimationTrackInterpolator, SkeletonNode,
+};
 use ambient_core::{
     bounding::local_bounding_aabb,
     hierarchy::children,
     name,
-    transform::{local_to_parent, local_to_world, mesh_to_local, TransformSystem},
+    transform::{local_to_parent, local_to_world, mesh_to_local, rotation, scale, translation, TransformSystem},
 };
-use ambient_ecs::{query, query_mut, Component, ComponentValue, EntityData, EntityId, FrameEvent, System, World};
+use ambient_ecs::{query, query_mut, Component, ComponentDesc, ComponentValue, EntityData, EntityId, FrameEvent, System, World};
+use ambient_gpu::ktx2::Ktx2Texture;
 use ambient_model::{
     animation_bind_id, model_from_url, model_skin_ix, model_skins, pbr_renderer_primitives_from_url, Model, PbrRenderPrimitiveFromUrl,
 };
@@ -15,11 +25,13 @@ use ambient_physics::{
     collider::{character_controller_height, character_controller_radius, collider, ColliderDef, ColliderFromUrls},
     mesh::PhysxGeometryFromUrl,
     physx::PhysicsKey,
+    ragdoll::{ragdoll_from_url, RagdollBone, RagdollBoneOverride, RagdollDef},
 };
 use ambient_renderer::{
-    double_sided,
+    directional_light, double_sided,
     lod::{gpu_lod, lod_cutoffs},
     materials::pbr_material::PbrMaterialFromUrl,
+    sun,
 };
 use ambient_std::{
     asset_cache::{AssetCache, SyncAssetKeyExt},
@@ -30,14 +42,22 @@ use ambient_std::{
 };
 use anyhow::Context;
 use futures::FutureExt;
-use glam::{Mat4, Vec3};
+use glam::{vec3, Mat4, Quat, Vec3};
 use image::{ImageOutputFormat, RgbaImage};
 use itertools::Itertools;
 use ordered_float::Float;
+use parry3d::{
+    math::Point,
+    transformation::vhacd::{VHACDParameters, VHACD},
+};
 use physxx::{PxConvexFlag, PxConvexMeshDesc, PxDefaultMemoryOutputStream, PxMeshFlag, PxTriangleMeshDesc};
 use relative_path::RelativePathBuf;
 
-use crate::{dotdot_path, MaterialFilter, TextureResolver};
+use crate::{
+    dotdot_path,
+    texture_compression::{compress_texture, TextureKind},
+    MaterialFilter, TextureResolver,
+};
 
 #[derive(Debug, Clone)]
 pub struct AssetLoc {
@@ -96,10 +116,13 @@ pub struct ModelCrate {
     pub meshes: AssetMap<Mesh>,
     pub animations: AssetMap<AnimationClip>,
     pub images: AssetMap<image::RgbaImage>,
+    /// Block compressed versions of the images, see [ModelCrate::compress_textures].
+    pub compressed_images: AssetMap<Ktx2Texture>,
     pub materials: AssetMap<PbrMaterialFromUrl>,
     pub px_triangle_meshes: AssetMap<Vec<u8>>,
     pub px_convex_meshes: AssetMap<Vec<u8>>,
     pub colliders: AssetMap<ColliderFromUrls>,
+    pub ragdolls: AssetMap<RagdollDef>,
 }
 impl ModelCrate {
     pub fn new() -> Self {
@@ -113,10 +136,12 @@ impl ModelCrate {
                 v.write_to(&mut data, ImageOutputFormat::Png).unwrap();
                 data.into_inner()
             }),
+            compressed_images: AssetMap::new("images", "ktx2", |v| v.to_bytes()),
             materials: AssetMap::new("materials", "json", |v| serde_json::to_vec(v).unwrap()),
             px_triangle_meshes: AssetMap::new("px_triangle_meshes", "pxtm", |v| v.clone()),
             px_convex_meshes: AssetMap::new("px_convex_meshes", "pxcm", |v| v.clone()),
             colliders: AssetMap::new("colliders", "json", |v| serde_json::to_vec(v).unwrap()),
+            ragdolls: AssetMap::new("ragdolls", "json", |v| serde_json::to_vec(v).unwrap()),
         }
     }
     pub async fn local_import(assets: &AssetCache, url: &AbsAssetUrl, normalize: bool, force_assimp: bool) -> anyhow::Result<Model> {
@@ -159,10 +184,12 @@ impl ModelCrate {
             self.meshes.to_items().into_iter(),
             self.animations.to_items().into_iter(),
             self.images.to_items().into_iter(),
+            self.compressed_images.to_items().into_iter(),
             self.materials.to_items().into_iter(),
             self.px_triangle_meshes.to_items().into_iter(),
             self.px_convex_meshes.to_items().into_iter(),
             self.colliders.to_items().into_iter(),
+            self.ragdolls.to_items().into_iter(),
         ]
         .into_iter()
         .flatten()
@@ -242,11 +269,7 @@ impl ModelCrate {
         Ok(())
     }
     pub fn merge_mesh_lods(&mut self, cutoffs: Option<Vec<f32>>, lods: Vec<ModelNodeRef>) {
-        let default_min_screen_size = 0.04; // i.e. 4%
-        let lod_step = (1. / default_min_screen_size).powf(1. / (lods.len() - 1) as f32);
-        let mut cutoffs = cutoffs.unwrap_or_else(|| (0..lods.len()).map(|i| 1. / lod_step.powi(i as i32)).collect_vec());
-        cutoffs.resize(20, 0.);
-        let cutoffs: [f32; 20] = cutoffs.try_into().unwrap();
+        let cutoffs = lod_cutoffs_or_default(cutoffs, lods.len());
 
         let lod_0_node = lods[0].get_node_id();
         let lod_0_world = lods[0].world();
@@ -297,6 +320,50 @@ impl ModelCrate {
         world.add_resource(children(), vec![root]);
         self.models.insert(ModelCrate::MAIN, Model(world));
     }
+    /// Adds simplified versions of the meshes of all nodes as additional LOD levels, one for each of the `ratios`.
+    ///
+    /// The nodes keep their place in the hierarchy and their skins, so this also works for animated models.
+    /// Nodes that already have multiple LOD levels are left as they are.
+    pub fn generate_lods(&mut self, ratios: &[f32], cutoffs: Option<Vec<f32>>) {
+        if ratios.is_empty() {
+            return;
+        }
+        let cutoffs = lod_cutoffs_or_default(cutoffs, ratios.len() + 1);
+        let world = &mut self.models.content.get_mut(ModelCrate::MAIN).unwrap().0;
+        let mut simplified_meshes = HashMap::<(String, usize), Option<RelativePathBuf>>::new();
+        for (node, primitives) in query(pbr_renderer_primitives_from_url()).collect_cloned(world, None) {
+            if primitives.iter().any(|primitive| primitive.lod > 0) {
+                continue;
+            }
+            let mut lod_primitives = primitives.clone();
+            for (i, ratio) in ratios.iter().enumerate() {
+                let lod = i + 1;
+                for primitive in &primitives {
+                    let mesh_id = match self.meshes.loc.id_from_path(primitive.mesh.path()) {
+                        Some(id) => id,
+                        None => continue,
+                    };
+                    let mesh_path = simplified_meshes
+                        .entry((mesh_id.clone(), lod))
+                        .or_insert_with(|| {
+                            let mesh = crate::lod::simplify_mesh(self.meshes.content.get(&mesh_id)?, *ratio)?;
+                            Some(self.meshes.insert(format!("{mesh_id}_lod{lod}"), mesh).path)
+                        })
+                        .clone();
+                    if let Some(mesh_path) = mesh_path {
+                        lod_primitives.push(PbrRenderPrimitiveFromUrl {
+                            mesh: dotdot_path(mesh_path).into(),
+                            material: primitive.material.clone(),
+                            lod,
+                        });
+                    }
+                }
+            }
+            world.set(node, pbr_renderer_primitives_from_url(), lod_primitives).unwrap();
+            world.add_component(node, lod_cutoffs(), cutoffs).unwrap();
+            world.add_component(node, gpu_lod(), ()).unwrap();
+        }
+    }
     pub fn merge_unity_style_mesh_lods(&mut self, source: &ModelCrate, cutoffs: Option<Vec<f32>>) {
         let mut lods = source.model_world().resource(children()).clone();
         lods.sort_by_key(|id| {
@@ -425,6 +492,54 @@ impl ModelCrate {
             cap_texture_size(image, max_size);
         }
     }
+    /// Encodes the textures of the materials as block compressed KTX2 textures with precomputed mip chains, and
+    /// points the materials to them. The uncompressed images are kept, and are used when the compressed textures
+    /// aren't supported.
+    pub fn compress_textures(&mut self) {
+        for material in self.materials.content.values_mut() {
+            // Compressed textures can't be combined when loaded, so the opacity is moved into the alpha of the base color
+            if let (Some(base_color), Some(opacity)) = (&material.base_color, &material.opacity) {
+                if let (Some(base_color_id), Some(opacity_id)) =
+                    (self.images.loc.id_from_path(base_color.path()), self.images.loc.id_from_path(opacity.path()))
+                {
+                    let merged_id = format!("{base_color_id}-{opacity_id}");
+                    if !self.images.content.contains_key(&merged_id) {
+                        if let (Some(base_color), Some(opacity)) =
+                            (self.images.content.get(&base_color_id), self.images.content.get(&opacity_id))
+                        {
+                            let mut merged = base_color.clone();
+                            for (color, alpha) in merged.pixels_mut().zip(opacity.pixels()) {
+                                color[3] = alpha[0];
+                            }
+                            self.images.insert(merged_id.clone(), merged);
+                        }
+                    }
+                    if self.images.content.contains_key(&merged_id) {
+                        material.base_color = Some(dotdot_path(self.images.loc.path(&merged_id)).into());
+                        material.opacity = None;
+                    }
+                }
+            }
+            for (url, kind) in [
+                (&mut material.base_color, TextureKind::BaseColor),
+                (&mut material.normalmap, TextureKind::Normal),
+                (&mut material.metallic_roughness, TextureKind::MetallicRoughness),
+                (&mut material.opacity, TextureKind::SingleChannel),
+            ] {
+                let id = match url.as_ref().and_then(|url| self.images.loc.id_from_path(url.path())) {
+                    Some(id) => id,
+                    None => continue,
+                };
+                if !self.compressed_images.content.contains_key(&id) {
+                    match self.images.content.get(&id).and_then(|image| compress_texture(image, kind)) {
+                        Some(texture) => self.compressed_images.insert(id.clone(), texture),
+                        None => continue,
+                    };
+                }
+                *url = Some(dotdot_path(self.compressed_images.loc.path(&id)).into());
+            }
+        }
+    }
     pub fn update_transforms(&mut self) {
         TransformSystem::new().run(self.model_world_mut(), &FrameEvent);
     }
@@ -434,6 +549,103 @@ impl ModelCrate {
             world.add_component(id, animation_bind_id(), animation_bind_id_from_name(&name)).unwrap();
         }
     }
+    /// Makes the directional lights of the model a `sun`, so that they light the scene when the model is spawned
+    pub fn make_directional_lights_suns(&mut self) {
+        let world = self.model_world_mut();
+        for id in query(()).incl(directional_light()).iter(world, None).map(|(id, _)| id).collect_vec() {
+            world.add_component(id, sun(), 0.).unwrap();
+        }
+    }
+    /// Moves the horizontal translation and the yaw of the bone with the bind id `root` out of the poses of the animations, and
+    /// into their root motion, so that they move the animated entity instead. Must be called after [Self::finalize_model].
+    pub fn extract_root_motion(&mut self, root: &str) -> anyhow::Result<()> {
+        let model = self.model();
+        let root_id = model.get_entity_id_by_bind_id(root).with_context(|| format!("The model has no bone with the bind id {root:?}"))?;
+        let parents = query(children())
+            .iter(&model.0, None)
+            .flat_map(|(id, children)| children.iter().map(move |child| (*child, id)))
+            .collect::<HashMap<_, _>>();
+        let mut parent_transform = Mat4::IDENTITY;
+        let mut node = parents.get(&root_id).copied();
+        while let Some(id) = node {
+            let node_transform = Mat4::from_scale_rotation_translation(
+                model.0.get(id, scale()).unwrap_or(Vec3::ONE),
+                model.0.get(id, rotation()).unwrap_or_default(),
+                model.0.get(id, translation()).unwrap_or_default(),
+            );
+            parent_transform = node_transform * parent_transform;
+            node = parents.get(&id).copied();
+        }
+        let parent_transform = model.get_transform().unwrap_or_default() * parent_transform;
+        let (_, parent_rotation, _) = parent_transform.to_scale_rotation_translation();
+        let inv_parent_transform = parent_transform.inverse();
+        let base_translation = model.0.get(root_id, translation()).unwrap_or_default();
+        let base_rotation = model.0.get(root_id, rotation()).unwrap_or_default();
+
+        for clip in self.animations.content.values_mut() {
+            let root_track = |component: ComponentDesc| {
+                clip.tracks.iter().position(|track| track.target.bind_id() == Some(root) && track.outputs.component() == component)
+            };
+            let (translation_track, rotation_track) = (root_track(translation().desc()), root_track(rotation().desc()));
+            if translation_track.is_none() && rotation_track.is_none() {
+                continue;
+            }
+            let mut inputs =
+                [translation_track, rotation_track].into_iter().flatten().flat_map(|i| clip.tracks[i].inputs.clone()).collect_vec();
+            inputs.sort_by(|a, b| a.total_cmp(b));
+            inputs.dedup();
+
+            let mut root_motion =
+                AnimationRootMotion { bind_id: root.to_string(), inputs: inputs.clone(), translation: Vec::new(), yaw: Vec::new() };
+            let (mut translations, mut rotations) = (Vec::new(), Vec::new());
+            let mut first = None;
+            for &time in &inputs {
+                let pos = match translation_track.map(|i| AnimationTrackInterpolator::new().value(&clip.tracks[i], time)) {
+                    Some(AnimationOutput::Vec3 { value, .. }) => value,
+                    _ => base_translation,
+                };
+                let rot = match rotation_track.map(|i| AnimationTrackInterpolator::new().value(&clip.tracks[i], time)) {
+                    Some(AnimationOutput::Quat { value, .. }) => value,
+                    _ => base_rotation,
+                };
+                // In the space of the model
+                let (pos, rot) = (parent_transform.transform_point3(pos), parent_rotation * rot);
+                let (first_pos, first_rot) = *first.get_or_insert((pos, rot));
+
+                let motion_translation = (pos - first_pos) * vec3(1., 1., 0.);
+                let twist = rot * first_rot.inverse();
+                let mut yaw = 2. * twist.z.atan2(twist.w);
+                // The yaw is kept continuous, so that it can be interpolated
+                if let Some(&previous) = root_motion.yaw.last() {
+                    yaw = previous + (yaw - previous + PI).rem_euclid(TAU) - PI;
+                }
+                let unyaw = Quat::from_rotation_z(-yaw);
+                translations.push(inv_parent_transform.transform_point3(unyaw * (pos - motion_translation)));
+                rotations.push(parent_rotation.inverse() * unyaw * rot);
+                root_motion.translation.push(motion_translation);
+                root_motion.yaw.push(yaw);
+            }
+
+            let target = AnimationTarget::BinderId(root.to_string());
+            clip.tracks.retain(|track| {
+                track.target != target || (track.outputs.component() != translation() && track.outputs.component() != rotation())
+            });
+            clip.tracks.push(AnimationTrack {
+                target: target.clone(),
+                inputs: inputs.clone(),
+                outputs: AnimationOutputs::Vec3 { component: translation(), data: translations },
+            });
+            if rotation_track.is_some() {
+                clip.tracks.push(AnimationTrack {
+                    target,
+                    inputs,
+                    outputs: AnimationOutputs::Quat { component: rotation(), data: rotations },
+                });
+            }
+            clip.root_motion = Some(root_motion);
+        }
+        Ok(())
+    }
     pub fn finalize_model(&mut self) {
         self.update_transforms();
         self.update_node_primitive_aabbs_from_cpu_meshes();
@@ -539,7 +751,192 @@ impl ModelCrate {
                 }
             }
         }
-        let obj_collider = self.colliders.insert(ModelCrate::MAIN.to_string(), ColliderFromUrls { convex, concave: triangle });
+        self.set_prefab_collider(ColliderFromUrls { convex, concave: triangle });
+        Ok(())
+    }
+    /// Approximates the model with at most `max_hulls` convex hulls, using V-HACD on a voxel grid with `resolution`
+    /// voxels along its longest axis. Unlike [ModelCrate::create_collider_from_model], the resulting collider can be
+    /// used for dynamic objects, as PhysX only supports triangle meshes for static ones.
+    pub fn create_convex_decomposition_collider(&mut self, assets: &AssetCache, max_hulls: u32, resolution: u32) -> anyhow::Result<()> {
+        self.update_transforms();
+        let physics = PhysicsKey.get(assets);
+        let world_transform = self.model().get_transform().unwrap_or_default();
+        let mut points = Vec::new();
+        let mut triangles = Vec::new();
+        for (id, prims) in query(pbr_renderer_primitives_from_url()).collect_cloned(self.model_world(), None) {
+            let ltw = self.model_world().get(id, local_to_world()).unwrap_or_default();
+            let mtl = self.model_world().get(id, mesh_to_local()).unwrap_or_default();
+            let transform = world_transform * ltw * mtl;
+            if let Some(max_lod) = prims.iter().map(|x| x.lod).max() {
+                // Only use the "max" lod for colliders
+                for primitive in prims.into_iter().filter(|x| x.lod == max_lod) {
+                    let mesh = self.meshes.get_by_path(primitive.mesh.path()).context("Missing mesh")?;
+                    if let (Some(positions), Some(indices)) = (&mesh.positions, &mesh.indices) {
+                        let offset = points.len() as u32;
+                        points.extend(positions.iter().map(|&p| {
+                            let p = transform.transform_point3(p);
+                            Point::new(p.x, p.y, p.z)
+                        }));
+                        triangles.extend(indices.chunks_exact(3).map(|t| [offset + t[0], offset + t[1], offset + t[2]]));
+                    }
+                }
+            }
+        }
+        if triangles.is_empty() {
+            anyhow::bail!("The model has no triangles to decompose");
+        }
+        let params = VHACDParameters { resolution, max_convex_hulls: max_hulls, ..Default::default() };
+        let decomposition = VHACD::decompose(&params, &points, &triangles, false);
+        let mut hulls = Vec::new();
+        for (i, (hull_points, _)) in decomposition.compute_convex_hulls(params.convex_hull_downsampling).into_iter().enumerate() {
+            let desc = PxConvexMeshDesc {
+                points: hull_points.iter().map(|p| Vec3::new(p.x, p.y, p.z)).collect_vec(),
+                indices: None,
+                vertex_limit: None,
+                flags: Some(PxConvexFlag::COMPUTE_CONVEX),
+            };
+            let stream = PxDefaultMemoryOutputStream::new();
+            let mut res = physxx::PxConvexMeshCookingResult::Success;
+            if !physics.cooking.cook_convex_mesh(&desc, &stream, &mut res) {
+                log::error!("Failed to cook convex hull {i}: {:?}", res);
+                continue;
+            }
+            let path = self.px_convex_meshes.insert(format!("{}_hull_{i}", ModelCrate::MAIN), stream.get_data()).path;
+            hulls.push((Mat4::IDENTITY, PhysxGeometryFromUrl(dotdot_path(path).into())));
+        }
+        if hulls.is_empty() {
+            anyhow::bail!("Failed to create any convex hulls");
+        }
+        // The hulls are used both for the physics shapes and for the convex shapes
+        self.set_prefab_collider(ColliderFromUrls { concave: hulls.clone(), convex: hulls });
+        Ok(())
+    }
+    /// Creates a ragdoll with a capsule for each joint of the model's skins, and adds it to the prefab. The capsules point
+    /// towards the children of the joints, and are `radius_scale` times as thick as they're long unless `overrides` says
+    /// otherwise. Must be called after [Self::finalize_model].
+    pub fn create_ragdoll(&mut self, radius_scale: f32, overrides: &HashMap<String, RagdollBoneOverride>) -> anyhow::Result<()> {
+        self.update_transforms();
+        let model = self.model();
+        let world_transform = model.get_transform().unwrap_or_default();
+        let joints = model.skins().into_iter().flatten().flat_map(|skin| skin.joints.iter().copied()).collect::<HashSet<_>>();
+        anyhow::ensure!(!joints.is_empty(), "The model has no skinned joints to create a ragdoll from");
+        let parents = query(children())
+            .iter(&model.0, None)
+            .flat_map(|(id, children)| children.iter().map(move |child| (*child, id)))
+            .collect::<HashMap<_, _>>();
+        let transform = |id: EntityId| world_transform * model.0.get(id, local_to_world()).unwrap_or_default();
+
+        // Depth first, so that parents come before their children
+        let mut nodes_in_order = Vec::new();
+        let mut stack = model.roots().into_iter().rev().collect_vec();
+        while let Some(id) = stack.pop() {
+            nodes_in_order.push(id);
+            if let Ok(children) = model.0.get_ref(id, children()) {
+                stack.extend(children.iter().rev());
+            }
+        }
+        let joints_in_order = nodes_in_order.iter().copied().filter(|id| joints.contains(id)).collect_vec();
+
+        let mut bones: Vec<RagdollBone> = Vec::new();
+        let mut bone_indices = HashMap::new();
+        for id in joints_in_order {
+            let bind_id = match model.0.get_ref(id, animation_bind_id()) {
+                Ok(bind_id) => bind_id.clone(),
+                Err(_) => continue,
+            };
+            let bone_override = overrides.get(&bind_id).cloned().unwrap_or_default();
+            if bone_override.exclude {
+                continue;
+            }
+            let mut ancestor = parents.get(&id).copied();
+            while let Some(node) = ancestor {
+                if bone_indices.contains_key(&node) {
+                    break;
+                }
+                ancestor = parents.get(&node).copied();
+            }
+            let parent = ancestor.map(|node| bone_indices[&node]);
+            // An articulation only has one root
+            if parent.is_none() && !bones.is_empty() {
+                continue;
+            }
+
+            let bone_transform = transform(id);
+            let (_, rot, pos) = bone_transform.to_scale_rotation_translation();
+            let ends = model
+                .0
+                .get_ref(id, children())
+                .into_iter()
+                .flatten()
+                .filter(|child| joints.contains(child))
+                .map(|child| transform(*child).w_axis.truncate())
+                .collect_vec();
+            let (direction, length) = if !ends.is_empty() {
+                let end = ends.iter().sum::<Vec3>() / ends.len() as f32;
+                (end - pos, (end - pos).length())
+            } else if let Some(parent) = parent {
+                // Leaves, such as heads and hands, continue in the direction of their parent
+                let parent = &bones[parent];
+                (parent.transform.to_scale_rotation_translation().1 * parent.direction, parent.length / 2.)
+            } else {
+                (Vec3::Z, 0.)
+            };
+            let length = bone_override.length.unwrap_or(length);
+            if length <= 0. {
+                continue;
+            }
+            let (twist_min, twist_max) = bone_override.twist_limit.unwrap_or((-30., 30.));
+            bones.push(RagdollBone {
+                bind_id,
+                node: 0,
+                parent,
+                transform: bone_transform,
+                direction: (rot.inverse() * direction).try_normalize().unwrap_or(Vec3::X),
+                length,
+                radius: bone_override.radius.unwrap_or(length * radius_scale),
+                density: bone_override.density.unwrap_or(1.),
+                swing_limit: bone_override.swing_limit.unwrap_or(45.).to_radians(),
+                twist_limit: (twist_min.to_radians(), twist_max.to_radians()),
+            });
+            bone_indices.insert(id, bones.len() - 1);
+        }
+        anyhow::ensure!(!bones.is_empty(), "None of the joints of the model could be used for the ragdoll");
+
+        // The nodes from the roots to the bones, so that the bones can be posed by the animations where the model isn't spawned
+        let mut in_skeleton = HashSet::new();
+        for id in bone_indices.keys() {
+            let mut node = Some(*id);
+            while let Some(id) = node {
+                if !in_skeleton.insert(id) {
+                    break;
+                }
+                node = parents.get(&id).copied();
+            }
+        }
+        let node_transform = |id: EntityId| model.0.get(id, local_to_world()).unwrap_or_default();
+        let mut skeleton: Vec<SkeletonNode> = Vec::new();
+        let mut node_indices = HashMap::new();
+        for id in nodes_in_order.into_iter().filter(|id| in_skeleton.contains(id)) {
+            let parent = parents.get(&id).copied();
+            let local = match parent {
+                Some(parent) => node_transform(parent).inverse() * node_transform(id),
+                None => node_transform(id),
+            };
+            let (scale, rotation, translation) = local.to_scale_rotation_translation();
+            let bind_id = model.0.get_ref(id, animation_bind_id()).ok().cloned();
+            skeleton.push(SkeletonNode { bind_id, parent: parent.map(|parent| node_indices[&parent]), translation, rotation, scale });
+            node_indices.insert(id, skeleton.len() - 1);
+        }
+        for (id, index) in bone_indices {
+            bones[index].node = node_indices[&id];
+        }
+
+        let ragdoll = self.ragdolls.insert(ModelCrate::MAIN, RagdollDef { transform: world_transform, skeleton, bones });
+        self.add_component_to_prefab(ragdoll_from_url(), dotdot_path(ragdoll.path).into());
+        Ok(())
+    }
+    fn set_prefab_collider(&mut self, collider_from_urls: ColliderFromUrls) {
+        let obj_collider = self.colliders.insert(ModelCrate::MAIN.to_string(), collider_from_urls);
         let prefab = self.prefab_world_mut();
         prefab
             .add_component(
@@ -548,7 +945,6 @@ impl ModelCrate {
                 ColliderDef::Asset { collider: dotdot_path(obj_collider.path).into() },
             )
             .unwrap();
-        Ok(())
     }
 }
 pub struct AssetItem {
@@ -580,6 +976,15 @@ impl<'a> ModelNodeRef<'a> {
     }
 }
 
+/// Uses the given screen size cutoffs, or spreads `lod_count` levels out evenly down to 4% of the screen
+fn lod_cutoffs_or_default(cutoffs: Option<Vec<f32>>, lod_count: usize) -> [f32; 20] {
+    let default_min_screen_size = 0.04; // i.e. 4%
+    let lod_step = (1. / default_min_screen_size).powf(1. / (lod_count.max(2) - 1) as f32);
+    let mut cutoffs = cutoffs.unwrap_or_else(|| (0..lod_count).map(|i| 1. / lod_step.powi(i as i32)).collect_vec());
+    cutoffs.resize(20, 0.);
+    cutoffs.try_into().unwrap()
+}
+
 pub fn physx_triangle_mesh_desc_from_mesh(mesh: &Mesh, flip_normals: bool, reverse_indices: bool) -> Option<PxTriangleMeshDesc> {
     let mut desc = PxTriangleMeshDesc {
         points: mesh.positions.clone()?,
@@ -597,3 +1002,43 @@ pub fn physx_triangle_mesh_desc_from_mesh(mesh: &Mesh, flip_normals: bool, rever
     }
     Some(desc)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Appends a closed box to the mesh
+    fn push_cuboid(mesh: &mut Mesh, min: Vec3, max: Vec3) {
+        let positions = mesh.positions.get_or_insert_with(Vec::new);
+        let offset = positions.len() as u32;
+        positions.extend((0..8).map(|i| {
+            vec3(if i & 1 == 0 { min.x } else { max.x }, if i & 2 == 0 { min.y } else { max.y }, if i & 4 == 0 { min.z } else { max.z })
+        }));
+        let indices = [0, 2, 1, 1, 2, 3, 4, 5, 6, 5, 7, 6, 0, 1, 4, 1, 5, 4, 2, 6, 3, 3, 6, 7, 0, 4, 2, 2, 4, 6, 1, 3, 5, 3, 7, 5];
+        mesh.indices.get_or_insert_with(Vec::new).extend(indices.iter().map(|i| offset + i));
+    }
+
+    #[tokio::test]
+    async fn test_convex_decomposition_of_concave_mesh() {
+        ambient_app::init_all_components();
+        let assets = AssetCache::new(tokio::runtime::Handle::current());
+        let mut model_crate = ModelCrate::new();
+
+        // An L shape, which a single convex hull would fill in
+        let mut mesh = Mesh { name: "l_shape".to_string(), ..Default::default() };
+        push_cuboid(&mut mesh, vec3(0., 0., 0.), vec3(3., 1., 1.));
+        push_cuboid(&mut mesh, vec3(0., 1., 0.), vec3(1., 3., 1.));
+        let mesh_path = model_crate.meshes.insert("l_shape", mesh).path;
+        let mut world = World::new("test");
+        let primitive = PbrRenderPrimitiveFromUrl { mesh: dotdot_path(mesh_path).into(), material: None, lod: 0 };
+        let node = EntityData::new().set(pbr_renderer_primitives_from_url(), vec![primitive]).spawn(&mut world);
+        world.add_resource(children(), vec![node]);
+        model_crate.models.insert(ModelCrate::MAIN, Model(world));
+        model_crate.create_prefab_from_model();
+
+        model_crate.create_convex_decomposition_collider(&assets, 16, 64).unwrap();
+        assert!(model_crate.px_convex_meshes.content.len() > 1);
+        let prefab = model_crate.prefab_world();
+        assert!(prefab.has_component(prefab.resource(children())[0], collider()));
+    }
+}
@@ -0,0 +1,110 @@
+use ambient_gpu::ktx2::Ktx2Texture;
+use glam::Vec3;
+use image::{imageops::FilterType, Rgba, RgbaImage};
+use intel_tex_2::{bc4, bc5, bc7, RSurface, RgSurface, RgbaSurface};
+
+/// What a texture is used for, which decides the block compression format it's encoded with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureKind {
+    /// sRGB color with alpha, encoded as BC7.
+    BaseColor,
+    /// A tangent space normal map. Only x and y are stored, as BC5; z is reconstructed in the shader.
+    Normal,
+    /// Metallic in the red channel and roughness in the green channel, encoded as BC5.
+    MetallicRoughness,
+    /// A single channel texture stored in red, such as an opacity map, encoded as BC4.
+    SingleChannel,
+}
+impl TextureKind {
+    pub fn format(&self) -> wgpu::TextureFormat {
+        match self {
+            TextureKind::BaseColor => wgpu::TextureFormat::Bc7RgbaUnormSrgb,
+            TextureKind::Normal | TextureKind::MetallicRoughness => wgpu::TextureFormat::Bc5RgUnorm,
+            TextureKind::SingleChannel => wgpu::TextureFormat::Bc4RUnorm,
+        }
+    }
+}
+
+/// Encodes the image and its full mip chain as a block compressed texture, on the CPU. Returns `None` for empty images.
+///
+/// Block compressed textures need to be a multiple of the 4x4 block size, so other images are scaled up to the next
+/// multiple of 4 first, which keeps their texture coordinates intact.
+pub fn compress_texture(image: &RgbaImage, kind: TextureKind) -> Option<Ktx2Texture> {
+    if image.width() == 0 || image.height() == 0 {
+        return None;
+    }
+    let (width, height) = ((image.width() + 3) / 4 * 4, (image.height() + 3) / 4 * 4);
+    let image = if image.dimensions() != (width, height) {
+        log::warn!(
+            "Scaling a {}x{} texture up to {width}x{height} to block compress it, as its size isn't a multiple of 4",
+            image.width(),
+            image.height()
+        );
+        let mut scaled = image::imageops::resize(image, width, height, FilterType::Triangle);
+        if kind == TextureKind::Normal {
+            renormalize(&mut scaled);
+        }
+        scaled
+    } else {
+        image.clone()
+    };
+    let mut levels = vec![compress_level(&image, kind)];
+    let mut level = image;
+    while level.width() > 1 || level.height() > 1 {
+        level = image::imageops::resize(&level, (level.width() / 2).max(1), (level.height() / 2).max(1), FilterType::Triangle);
+        if kind == TextureKind::Normal {
+            renormalize(&mut level);
+        }
+        levels.push(compress_level(&pad_to_blocks(&level), kind));
+    }
+    Some(Ktx2Texture { format: kind.format(), width, height, levels })
+}
+
+fn compress_level(image: &RgbaImage, kind: TextureKind) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    match kind {
+        TextureKind::BaseColor => {
+            let settings = if image.pixels().all(|p| p[3] == 255) { bc7::opaque_basic_settings() } else { bc7::alpha_basic_settings() };
+            bc7::compress_blocks(&settings, &RgbaSurface { data: image.as_raw(), width, height, stride: width * 4 })
+        }
+        TextureKind::Normal | TextureKind::MetallicRoughness => {
+            let data = image.pixels().flat_map(|p| [p[0], p[1]]).collect::<Vec<_>>();
+            bc5::compress_blocks(&RgSurface { data: &data, width, height, stride: width * 2 })
+        }
+        TextureKind::SingleChannel => {
+            let data = image.pixels().map(|p| p[0]).collect::<Vec<_>>();
+            bc4::compress_blocks(&RSurface { data: &data, width, height, stride: width })
+        }
+    }
+}
+
+/// The smallest mip levels are smaller than a block; they're padded by repeating their edges.
+fn pad_to_blocks(image: &RgbaImage) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    RgbaImage::from_fn((width + 3) / 4 * 4, (height + 3) / 4 * 4, |x, y| *image.get_pixel(x.min(width - 1), y.min(height - 1)))
+}
+
+/// Averaging normals shortens them, so downsampled normal maps are normalized again.
+fn renormalize(image: &mut RgbaImage) {
+    for p in image.pixels_mut() {
+        let normal = (Vec3::new(p[0] as f32, p[1] as f32, p[2] as f32) / 255. * 2. - 1.).normalize_or_zero();
+        let encoded = (normal * 0.5 + 0.5) * 255.;
+        *p = Rgba([encoded.x.round() as u8, encoded.y.round() as u8, encoded.z.round() as u8, p[3]]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_unaligned_texture() {
+        let image = RgbaImage::from_pixel(6, 5, Rgba([128, 128, 255, 255]));
+        let texture = compress_texture(&image, TextureKind::Normal).unwrap();
+        assert_eq!((texture.width, texture.height), (8, 8));
+        // 8x8, 4x4, 2x2 and 1x1, where the last two are padded to a single block
+        assert_eq!(texture.levels.iter().map(|level| level.len()).collect::<Vec<_>>(), vec![4 * 16, 16, 16, 16]);
+
+        assert!(compress_texture(&RgbaImage::new(0, 4), TextureKind::BaseColor).is_none());
+    }
+}
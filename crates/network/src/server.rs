@@ -97,8 +97,10 @@ impl WorldInstance {
     pub fn player_count(&self) -> usize {
         query((player(),)).iter(&self.world, None).count()
     }
-    pub fn step(&mut self, time: Duration) {
+    /// Runs a tick of the systems, where `dtime` is the time since the previous tick
+    pub fn step(&mut self, time: Duration, dtime: Duration) {
         self.world.set(self.world.resource_entity(), ambient_core::time(), time).unwrap();
+        self.world.set(self.world.resource_entity(), ambient_core::dtime(), dtime.as_secs_f32()).unwrap();
         self.systems.run(&mut self.world, &FrameEvent);
         self.world.next_frame();
     }
@@ -158,10 +160,10 @@ impl ServerState {
         Self { instances, players: Default::default(), create_server_systems, create_on_forking_systems, create_shutdown_systems }
     }
 
-    pub fn step(&mut self) {
+    pub fn step(&mut self, dtime: Duration) {
         let time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
         for instance in self.instances.values_mut() {
-            instance.step(time);
+            instance.step(time, dtime);
         }
     }
     pub fn broadcast_diffs(&mut self) {
@@ -251,8 +253,11 @@ impl GameServer {
         )));
 
         let mut fps_counter = FpsCounter::new();
-        let mut sim_interval = interval(Duration::from_secs_f32(1. / 60.));
+        let tick_duration = Duration::from_secs_f32(1. / 60.);
+        let mut sim_interval = interval(tick_duration);
         sim_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // Ticks are delayed when they take too long, so their duration is measured instead of assumed
+        let mut last_tick: Option<Instant> = None;
 
         let mut inactivity_interval = interval(Duration::from_secs_f32(5.));
         let mut last_active = Instant::now();
@@ -277,11 +282,14 @@ impl GameServer {
                 }
                 _ = sim_interval.tick() => {
                     fps_counter.frame_start();
+                    let now = Instant::now();
+                    let dtime = last_tick.map_or(tick_duration, |last| now.duration_since(last));
+                    last_tick = Some(now);
                     let mut state = state.lock();
                     tokio::task::block_in_place(|| {
                         profiling::finish_frame!();
                         profiling::scope!("sim_tick");
-                        state.step();
+                        state.step(dtime);
                         state.broadcast_diffs();
                         if let Some(sample) = fps_counter.frame_end() {
                             for instance in state.instances.values() {
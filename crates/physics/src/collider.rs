@@ -15,17 +15,17 @@ use ambient_ui::Editable;
 use anyhow::Context;
 use async_trait::async_trait;
 use futures::future::try_join_all;
-use glam::{vec3, Mat4, Quat, Vec3};
+use glam::{vec3, Mat4, Quat, Vec2, Vec3};
 use itertools::Itertools;
 use physxx::{
-    AsPxActor, AsPxRigidActor, PxActor, PxActorFlag, PxBase, PxBoxGeometry, PxControllerDesc, PxControllerShapeDesc, PxConvexMeshGeometry, PxGeometry, PxMaterial, PxMeshScale, PxPlaneGeometry, PxRigidActor, PxRigidBody, PxRigidBodyFlag, PxRigidDynamicRef, PxRigidStaticRef, PxShape, PxShapeFlag, PxSphereGeometry, PxTransform, PxTriangleMeshGeometry, PxUserData
+    AsPxActor, AsPxRigidActor, PxActor, PxActorFlag, PxBase, PxBoxGeometry, PxControllerDesc, PxControllerShapeDesc, PxConvexFlag, PxConvexMesh, PxConvexMeshDesc, PxConvexMeshGeometry, PxGeometry, PxMaterial, PxMeshScale, PxPlaneGeometry, PxRigidActor, PxRigidBody, PxRigidBodyFlag, PxRigidDynamicRef, PxRigidStaticRef, PxShape, PxShapeFlag, PxSphereGeometry, PxTransform, PxTriangleMeshGeometry, PxUserData
 };
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    main_controller_manager, make_physics_static, mesh::{PhysxGeometry, PhysxGeometryFromUrl}, physx::{
-        angular_velocity, character_controller, contact_offset, linear_velocity, physics, physics_controlled, physics_shape, rest_offset, rigid_actor, Physics
-    }, wood_physics_material, ColliderScene, PxActorUserData, PxShapeUserData, PxWoodMaterialKey
+    helpers::scaled_capsule, main_controller_manager, make_physics_static, mesh::{PhysxGeometry, PhysxGeometryFromUrl}, physx::{
+        angular_velocity, character_controller, contact_offset, linear_velocity, physics, physics_controlled, physics_shape, rest_offset, rigid_actor, Physics, PhysicsKey
+    }, trigger, wood_physics_material, ColliderScene, PxActorUserData, PxShapeUserData, PxWoodMaterialKey
 };
 
 fn one() -> f32 {
@@ -51,6 +51,24 @@ components!("physics", {
         Description["If attached, this entity will have a sphere physics collider.\nThe value corresponds to the radius of the sphere."]
     ]
     sphere_collider: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Capsule collider"],
+        Description["If attached, this entity will have a capsule physics collider along the Z axis.\n`x` is the radius of the capsule, and `y` is half the height of its cylindrical part."]
+    ]
+    capsule_collider: Vec2,
+    @[
+        Debuggable, Networked, Store,
+        Name["Cylinder collider"],
+        Description["If attached, this entity will have a cylinder physics collider along the Z axis, approximated with a convex hull.\n`x` is the radius of the cylinder, and `y` is half its height."]
+    ]
+    cylinder_collider: Vec2,
+    @[
+        Debuggable, Networked, Store,
+        Name["Convex hull collider"],
+        Description["If attached, this entity will have a physics collider which is the convex hull of these points.\nAt most 255 points are used."]
+    ]
+    convex_hull_collider: Vec<Vec3>,
     @[
         Debuggable, Networked, Store,
         Name["Collider from URL"],
@@ -78,6 +96,12 @@ components!("physics", {
         Description["If attached, and this entity is dynamic, this entity will also be kinematic (i.e. unable to be affected by other entities motion). Otherwise, it will receive forces normally."]
     ]
     kinematic: (),
+    @[
+        Debuggable, Networked, Store,
+        Name["Trigger area"],
+        Description["If attached, this entity's collider will be a trigger area, which doesn't collide with anything.\nThe `core/trigger_enter` and `core/trigger_exit` events are sent when other entities enter or exit it."]
+    ]
+    trigger_area: (),
     @[
         Debuggable, MakeDefault[one], Editable, Networked, Store,
         Name["Mass"],
@@ -168,6 +192,23 @@ pub fn server_systems() -> SystemGroup {
                     world.add_component(id, collider(), ColliderDef::Box { size, center: Vec3::ZERO }).unwrap();
                 }
             }),
+            query(capsule_collider().changed()).to_system(|q, world, qs, _| {
+                for (id, size) in changed_or_missing(q, world, qs, collider()) {
+                    let def = ColliderDef::Capsule { radius: size.x, half_height: size.y, axis: ColliderAxis::Z, center: Vec3::ZERO };
+                    world.add_component(id, collider(), def).unwrap();
+                }
+            }),
+            query(cylinder_collider().changed()).to_system(|q, world, qs, _| {
+                for (id, size) in changed_or_missing(q, world, qs, collider()) {
+                    let def = ColliderDef::Cylinder { radius: size.x, half_height: size.y, axis: ColliderAxis::Z, center: Vec3::ZERO };
+                    world.add_component(id, collider(), def).unwrap();
+                }
+            }),
+            query(convex_hull_collider().changed()).to_system(|q, world, qs, _| {
+                for (id, points) in changed_or_missing(q, world, qs, collider()) {
+                    world.add_component(id, collider(), ColliderDef::ConvexHull { points }).unwrap();
+                }
+            }),
             query(collider_from_url().changed()).to_system(|q, world, qs, _| {
                 for (id, url) in changed_or_missing(q, world, qs, collider()) {
                     tracing::debug!("Loading collider: {url:#?}");
@@ -184,6 +225,11 @@ pub fn server_systems() -> SystemGroup {
                     world.add_component(id, collider_type(), if dynamic { ColliderType::Dynamic } else { ColliderType::Static }).unwrap();
                 }
             }),
+            query(trigger_area()).spawned().to_system(|q, world, qs, _| {
+                for (id, _) in q.collect_cloned(world, qs) {
+                    world.add_component(id, collider_type(), ColliderType::TriggerArea).unwrap();
+                }
+            }),
             query((character_controller_height().changed(), character_controller_radius().changed(), translation())).to_system(
                 |q, world, qs, _| {
                     let all = changed_or_missing(q, world, qs, character_controller());
@@ -301,6 +347,21 @@ pub fn server_systems() -> SystemGroup {
                         for shape in actor.get_shapes() {
                             actor.detach_shape(&shape, false);
                         }
+                        if collider_type == ColliderType::TriggerArea {
+                            let count = shapes.len();
+                            shapes.retain(|shape| trigger::is_trigger_geometry(shape.get_geometry_type()));
+                            if shapes.len() < count {
+                                log::error!(
+                                    "Trigger area {id} has {} triangle mesh, height field or plane shapes, which can't be used for trigger areas; they are ignored",
+                                    count - shapes.len()
+                                );
+                            }
+                            if shapes.is_empty() {
+                                actor.as_actor().remove_user_data::<PxActorUserData>();
+                                actor.release();
+                                return;
+                            }
+                        }
                         let coff = world.get(id, contact_offset()).ok();
                         let roff = world.get(id, rest_offset()).ok();
                         for shape in shapes.iter_mut() {
@@ -379,6 +440,84 @@ pub enum ColliderDef {
         center: Vec3,
     },
     Plane,
+    /// A capsule made of a cylinder of `half_height` with hemispheres of `radius` on its ends
+    Capsule {
+        #[serde(default = "one_value")]
+        radius: f32,
+        #[serde(default = "one_value")]
+        half_height: f32,
+        #[serde(default)]
+        axis: ColliderAxis,
+        #[serde(default = "vec3_zero_value")]
+        center: Vec3,
+    },
+    /// PhysX doesn't have cylinders, so they're the convex hulls of their ends
+    Cylinder {
+        #[serde(default = "one_value")]
+        radius: f32,
+        #[serde(default = "one_value")]
+        half_height: f32,
+        #[serde(default)]
+        axis: ColliderAxis,
+        #[serde(default = "vec3_zero_value")]
+        center: Vec3,
+    },
+    ConvexHull {
+        points: Vec<Vec3>,
+    },
+}
+
+/// The axis a capsule or cylinder collider is aligned with
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ElementEditor)]
+pub enum ColliderAxis {
+    X,
+    Y,
+    Z,
+}
+impl ColliderAxis {
+    /// The rotation from the X axis, which is the axis of PhysX capsules
+    fn rotation(&self) -> Quat {
+        match self {
+            ColliderAxis::X => Quat::IDENTITY,
+            ColliderAxis::Y => Quat::from_rotation_z(PI / 2.),
+            ColliderAxis::Z => Quat::from_rotation_y(-PI / 2.),
+        }
+    }
+}
+impl Default for ColliderAxis {
+    fn default() -> Self {
+        Self::Z
+    }
+}
+
+/// The number of points on each end of a cylinder collider
+const CYLINDER_SEGMENTS: usize = 16;
+
+/// Cooks the convex hull of `points` once, for all the shapes of a collider
+fn cook_convex_hull(physics: &Physics, points: Vec<Vec3>) -> Option<PxConvexMesh> {
+    let desc = PxConvexMeshDesc { points, indices: None, vertex_limit: None, flags: Some(PxConvexFlag::COMPUTE_CONVEX) };
+    match PxConvexMesh::from_desc(physics.physics, physics.cooking, desc) {
+        Ok(mesh) => Some(mesh),
+        Err(err) => {
+            log::warn!("Failed to cook convex hull collider: {:?}", err);
+            None
+        }
+    }
+}
+
+/// Spawns shapes of a cooked convex hull, or nothing if it couldn't be cooked
+fn convex_hull_spawner(mesh: Option<PxConvexMesh>, material: PxMaterial, density: f32) -> ColliderSpawner {
+    Box::new(move |physics, scale| {
+        let Some(mesh) = &mesh else { return (vec![], vec![]) };
+        let geometry = PxConvexMeshGeometry::new(mesh, Some(PxMeshScale::from_scale(scale.abs())), None);
+        if !geometry.is_valid() {
+            log::warn!("Invalid convex hull collider. scale={scale:?}");
+            return (vec![], vec![]);
+        }
+        let shape = PxShape::new(physics.physics, &geometry, &[&material], Some(true), None);
+        shape.set_user_data(PxShapeUserData { entity: EntityId::null(), density, ..Default::default() });
+        (vec![shape.clone()], vec![shape])
+    })
 }
 
 type ColliderSpawner = Box<dyn Fn(&Physics, Vec3) -> (Vec<PxShape>, Vec<PxShape>) + Sync + Send>;
@@ -409,6 +548,7 @@ impl ColliderDef {
                     entity: EntityId::null(),
                     density,
                     base_pose: Mat4::from_scale_rotation_translation(size / scale, Default::default(), center * scale),
+                    base_capsule: None,
                 });
                 (vec![shape.clone()], vec![shape])
             })),
@@ -423,6 +563,7 @@ impl ColliderDef {
                     entity: EntityId::null(),
                     density,
                     base_pose: Mat4::from_scale_rotation_translation(Vec3::splat(radius), Default::default(), center * scale),
+                    base_capsule: None,
                 });
                 (vec![shape.clone()], vec![shape])
             })),
@@ -430,9 +571,46 @@ impl ColliderDef {
                 let geometry = PxPlaneGeometry::new();
                 let shape = PxShape::new(physics.physics, &geometry, &[&material], Some(true), None);
                 shape.set_local_pose(&PxTransform::from_rotation(Quat::from_rotation_y(-PI / 2.)));
-                shape.set_user_data(PxShapeUserData { entity: EntityId::null(), density, base_pose: Mat4::from_rotation_y(-PI / 2.) });
+                shape.set_user_data(PxShapeUserData {
+                    entity: EntityId::null(),
+                    density,
+                    base_pose: Mat4::from_rotation_y(-PI / 2.),
+                    base_capsule: None,
+                });
                 (vec![shape.clone()], vec![shape])
             })),
+            ColliderDef::Capsule { radius, half_height, axis, center } => Ok(Box::new(move |physics, scale| {
+                if radius <= 0. || half_height < 0. {
+                    return (vec![], vec![]);
+                }
+                let rot = axis.rotation();
+                let geometry = scaled_capsule(radius, half_height, rot, scale);
+                let shape = PxShape::new(physics.physics, &geometry, &[&material], Some(true), None);
+                shape.set_local_pose(&PxTransform::new(center * scale, rot));
+                shape.set_user_data(PxShapeUserData {
+                    entity: EntityId::null(),
+                    density,
+                    base_pose: Mat4::from_rotation_translation(rot, center * scale),
+                    base_capsule: Some((radius, half_height)),
+                });
+                (vec![shape.clone()], vec![shape])
+            })),
+            ColliderDef::Cylinder { radius, half_height, axis, center } => {
+                if radius <= 0. || half_height <= 0. {
+                    return Ok(Box::new(|_, _| (vec![], vec![])));
+                }
+                let rot = axis.rotation();
+                let points = (0..CYLINDER_SEGMENTS)
+                    .flat_map(|i| {
+                        let angle = i as f32 / CYLINDER_SEGMENTS as f32 * PI * 2.;
+                        [-half_height, half_height].map(|x| center + rot * vec3(x, radius * angle.cos(), radius * angle.sin()))
+                    })
+                    .collect_vec();
+                Ok(convex_hull_spawner(cook_convex_hull(&PhysicsKey.get(&assets), points), material, density))
+            }
+            ColliderDef::ConvexHull { points } => {
+                Ok(convex_hull_spawner(cook_convex_hull(&PhysicsKey.get(&assets), points), material, density))
+            }
             ColliderDef::Asset { collider } => {
                 let collider = collider.unwrap_abs();
                 let collider_from_urls: Arc<ColliderFromUrls> = JsonFromUrl::new(collider.clone(), true).get(&assets).await?;
@@ -528,9 +706,123 @@ impl Collider {
                     entity: EntityId::null(),
                     density,
                     base_pose: Mat4::from_scale_rotation_translation(scale.abs(), rotation, translation),
+                    base_capsule: None,
                 });
                 shape
             })
             .collect_vec()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use physxx::PxGeometryType;
+
+    use super::*;
+    use crate::{helpers::scale_shape, test_world};
+
+    const AXES: [ColliderAxis; 3] = [ColliderAxis::X, ColliderAxis::Y, ColliderAxis::Z];
+
+    fn spawner(world: &World, def: ColliderDef) -> ColliderSpawner {
+        let assets = world.resource(asset_cache()).clone();
+        tokio::runtime::Runtime::new().unwrap().block_on(def.spawner(assets, 1.)).unwrap()
+    }
+
+    /// Spawns the collider at a scale of one
+    fn spawn(world: &World, def: ColliderDef) -> Vec<PxShape> {
+        spawner(world, def)(world.resource(physics()), Vec3::ONE).0
+    }
+
+    fn capsule_size(shape: &PxShape) -> (f32, f32) {
+        let geometry = shape.get_geometry().as_capsule().unwrap();
+        (geometry.radius(), geometry.half_height())
+    }
+
+    /// The bounds of the shape, with the shape attached to an actor at the origin
+    fn bounds(world: &World, shape: &PxShape) -> (Vec3, Vec3) {
+        let physics = world.resource(physics());
+        let actor = PxRigidStaticRef::new(physics.physics, &PxTransform::identity()).as_rigid_actor();
+        assert!(actor.attach_shape(shape));
+        let bounds = actor.get_world_bounds(1.);
+        actor.detach_shape(shape, false);
+        actor.release();
+        bounds
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!(a.distance(b) < 1e-3, "{a} != {b}");
+    }
+
+    #[test]
+    fn capsules_rescale_along_their_axis() {
+        let world = test_world();
+        let scale = vec3(2., 3., 4.);
+        let center = vec3(1., 2., 3.);
+        // The half height follows the scale along the axis, and the radius the largest scale across it
+        for (axis, expected) in AXES.into_iter().zip([(2., 2.), (2., 3.), (1.5, 4.)]) {
+            let shape = spawn(&world, ColliderDef::Capsule { radius: 0.5, half_height: 1., axis, center }).pop().unwrap();
+            assert_eq!(capsule_size(&shape), (0.5, 1.));
+            assert_close(shape.get_local_pose().translation(), center);
+
+            scale_shape(shape.clone(), scale);
+            let (radius, half_height) = capsule_size(&shape);
+            assert!((radius - expected.0).abs() < 1e-5 && (half_height - expected.1).abs() < 1e-5, "{axis:?}: {radius}, {half_height}");
+            let pose = shape.get_local_pose();
+            assert_close(pose.translation(), center * scale);
+            assert!(pose.rotation().angle_between(axis.rotation()) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn capsules_without_half_height_rescale() {
+        let world = test_world();
+        let shape =
+            spawn(&world, ColliderDef::Capsule { radius: 1., half_height: 0., axis: ColliderAxis::Z, center: Vec3::ZERO }).pop().unwrap();
+        assert_eq!(capsule_size(&shape), (1., 0.));
+        scale_shape(shape.clone(), vec3(2., 2., 3.));
+        assert_eq!(capsule_size(&shape), (2., 0.));
+
+        let invalid = ColliderDef::Capsule { radius: 1., half_height: -1., axis: ColliderAxis::Z, center: Vec3::ZERO };
+        assert!(spawn(&world, invalid).is_empty());
+    }
+
+    #[test]
+    fn cylinders_rescale_along_each_axis() {
+        let world = test_world();
+        let scale = vec3(2., 3., 4.);
+        let center = vec3(1., 2., 3.);
+        for (axis, extents) in AXES.into_iter().zip([vec3(1., 0.5, 0.5), vec3(0.5, 1., 0.5), vec3(0.5, 0.5, 1.)]) {
+            let shape = spawn(&world, ColliderDef::Cylinder { radius: 0.5, half_height: 1., axis, center }).pop().unwrap();
+            assert_eq!(shape.get_geometry_type(), PxGeometryType::ConvexMesh);
+            let (min, max) = bounds(&world, &shape);
+            assert_close(min, center - extents);
+            assert_close(max, center + extents);
+
+            scale_shape(shape.clone(), scale);
+            assert_eq!(shape.get_geometry().as_convex_mesh().unwrap().scale().scale(), scale);
+            // The offset is part of the hull, so it's scaled along with it
+            assert_close(shape.get_local_pose().translation(), Vec3::ZERO);
+            let (min, max) = bounds(&world, &shape);
+            assert_close(min, (center - extents) * scale);
+            assert_close(max, (center + extents) * scale);
+        }
+    }
+
+    #[test]
+    fn convex_hulls_are_cooked_once_and_rescale() {
+        let world = test_world();
+        let points = (0..8).map(|i| vec3((i & 1) as f32, ((i >> 1) & 1) as f32 * 2., (i >> 2) as f32 * 3.)).collect_vec();
+        let spawner = spawner(&world, ColliderDef::ConvexHull { points });
+        let physics = world.resource(physics());
+        let mesh = |shape: &PxShape| shape.get_geometry().as_convex_mesh().unwrap().mesh().0;
+        let (shape, respawned) = (spawner(physics, Vec3::ONE).0.pop().unwrap(), spawner(physics, Vec3::ONE).0.pop().unwrap());
+        assert_eq!(mesh(&shape), mesh(&respawned));
+
+        let scale = vec3(2., 3., 4.);
+        scale_shape(shape.clone(), scale);
+        assert_eq!(shape.get_geometry().as_convex_mesh().unwrap().scale().scale(), scale);
+        let (min, max) = bounds(&world, &shape);
+        assert_close(min, Vec3::ZERO);
+        assert_close(max, vec3(2., 6., 12.));
+    }
+}
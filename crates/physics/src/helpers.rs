@@ -3,12 +3,13 @@ use std::collections::HashSet;
 use ambient_core::transform::{get_world_position, rotation, translation};
 use ambient_ecs::{query, ECSError, EntityId, World};
 use anyhow::{bail, Context};
-use glam::{vec3, Vec3};
+use glam::{vec3, Quat, Vec3};
 use itertools::Itertools;
 use physxx::{
-    AsPxActor, AsPxRigidActor, PxActor, PxActorTypeFlag, PxBase, PxBoxGeometry, PxConvexMeshGeometry, PxJoint, PxMeshScale,
-    PxOverlapCallback, PxQueryFilterData, PxQueryFlag, PxRevoluteJointRef, PxRigidActor, PxRigidActorRef, PxRigidBody, PxRigidBodyFlag,
-    PxRigidDynamicRef, PxRigidStaticRef, PxSceneRef, PxShape, PxSphereGeometry, PxTransform, PxTriangleMeshGeometry, PxUserData,
+    AsPxActor, AsPxRigidActor, PxActor, PxActorTypeFlag, PxBase, PxBoxGeometry, PxCapsuleGeometry, PxConvexMeshGeometry, PxJoint,
+    PxMeshScale, PxOverlapCallback, PxQueryFilterData, PxQueryFlag, PxRevoluteJointRef, PxRigidActor, PxRigidActorRef, PxRigidBody,
+    PxRigidBodyFlag, PxRigidDynamicRef, PxRigidStaticRef, PxSceneRef, PxShape, PxSphereGeometry, PxTransform, PxTriangleMeshGeometry,
+    PxUserData,
 };
 
 use crate::{
@@ -145,6 +146,10 @@ pub fn scale_shape(shape: PxShape, scale: Vec3) {
     } else if let Some(_geo) = geo.as_sphere() {
         let new_geo = PxSphereGeometry::new((size).x);
         shape.set_geometry(&new_geo);
+    } else if let Some(_geo) = geo.as_capsule() {
+        if let Some((radius, half_height)) = ud.base_capsule {
+            shape.set_geometry(&scaled_capsule(radius, half_height, base_rot, scale));
+        }
     } else if let Some(_geo) = geo.as_box() {
         let new_geo = PxBoxGeometry::new(size.x, size.y, size.z);
         shape.set_geometry(&new_geo);
@@ -156,6 +161,13 @@ pub fn scale_shape(shape: PxShape, scale: Vec3) {
     shape.set_local_pose(&PxTransform::new(scale * base_pos, base_rot));
 }
 
+/// A capsule of `radius` and `half_height` along the X axis of a shape with the rotation `rotation`, scaled by `scale`. The radius
+/// can't be scaled non-uniformly, so it takes the largest of the scales across the axis
+pub(crate) fn scaled_capsule(radius: f32, half_height: f32, rotation: Quat, scale: Vec3) -> PxCapsuleGeometry {
+    let scale = (rotation.inverse() * scale).abs();
+    PxCapsuleGeometry::new(radius * scale.y.max(scale.z), half_height * scale.x)
+}
+
 #[tracing::instrument(skip(world), level = "info")]
 pub fn update_actor_entity_transforms(world: &mut World, actor: PxRigidActorRef) {
     for shape in actor.get_shapes() {
@@ -0,0 +1,65 @@
+use ambient_core::{
+    dtime,
+    transform::{rotation, translation},
+};
+use ambient_ecs::{components, query, SystemGroup};
+use glam::{Quat, Vec3};
+
+use crate::physx::physics_controlled;
+
+components!("physics", {
+    physics_interpolation: PhysicsInterpolation,
+});
+
+/// The two latest physics states of an entity received from the server, and how far it has been rendered between them
+#[derive(Debug, Clone)]
+pub struct PhysicsInterpolation {
+    from: (Vec3, Quat),
+    to: (Vec3, Quat),
+    /// What was last written to the entity; anything else was received from the server
+    rendered: (Vec3, Quat),
+    /// How long it took for the latest state to arrive, which is how long it takes to move to it
+    duration: f32,
+    elapsed: f32,
+}
+impl PhysicsInterpolation {
+    fn new(state: (Vec3, Quat)) -> Self {
+        Self { from: state, to: state, rendered: state, duration: 0., elapsed: 0. }
+    }
+}
+
+/// Physics states arrive once per server tick, so physics controlled entities are moved smoothly from the state they're rendered in
+/// to the latest one, over the time it took for that state to arrive. This delays them by up to one tick.
+pub fn client_systems() -> SystemGroup {
+    SystemGroup::new(
+        "physics/interpolation",
+        vec![
+            query((translation(), rotation())).incl(physics_controlled()).excl(physics_interpolation()).to_system(|q, world, qs, _| {
+                for (id, state) in q.collect_cloned(world, qs) {
+                    world.add_component(id, physics_interpolation(), PhysicsInterpolation::new(state)).unwrap();
+                }
+            }),
+            query((translation(), rotation(), physics_interpolation())).incl(physics_controlled()).to_system(|q, world, qs, _| {
+                let dtime = *world.resource(dtime());
+                for (id, (pos, rot, mut interpolation)) in q.collect_cloned(world, qs) {
+                    interpolation.elapsed += dtime;
+                    if (pos, rot) != interpolation.rendered {
+                        // Only the components that changed on the server are received
+                        let to_pos = if pos != interpolation.rendered.0 { pos } else { interpolation.to.0 };
+                        let to_rot = if rot != interpolation.rendered.1 { rot } else { interpolation.to.1 };
+                        interpolation.from = interpolation.rendered;
+                        interpolation.to = (to_pos, to_rot);
+                        interpolation.duration = interpolation.elapsed;
+                        interpolation.elapsed = 0.;
+                    }
+                    let alpha = if interpolation.duration > 0. { (interpolation.elapsed / interpolation.duration).min(1.) } else { 1. };
+                    let (from, to) = (interpolation.from, interpolation.to);
+                    interpolation.rendered = (from.0.lerp(to.0, alpha), from.1.slerp(to.1, alpha));
+                    world.set(id, translation(), interpolation.rendered.0).unwrap();
+                    world.set(id, rotation(), interpolation.rendered.1).unwrap();
+                    world.set(id, physics_interpolation(), interpolation).unwrap();
+                }
+            }),
+        ],
+    )
+}
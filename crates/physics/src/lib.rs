@@ -1,14 +1,17 @@
 use std::sync::Arc;
 
-use ambient_core::asset_cache;
+use ambient_core::{
+    asset_cache, dtime,
+    transform::{rotation, translation},
+};
 use ambient_ecs::{
-    components, query, Debuggable, Description, DynSystem, EntityData, EntityId, FnSystem, Name, Networked, Resource, Store, SystemGroup,
-    World,
+    components, query, Component, Debuggable, Description, DynSystem, EntityData, EntityId, FnSystem, FrameEvent, Name, Networked,
+    Resource, Store, System, SystemGroup, World,
 };
 use ambient_network::server::{ForkingEvent, ShutdownEvent};
 use ambient_std::asset_cache::{AssetCache, SyncAssetKey, SyncAssetKeyExt};
 use collider::collider_shapes;
-use glam::{vec3, Mat4, Vec3};
+use glam::{vec3, Mat4, Quat, Vec3};
 use helpers::release_px_scene;
 use parking_lot::Mutex;
 use physx::{
@@ -16,8 +19,8 @@ use physx::{
     physics_shape, revolute_joint, rigid_actor, rigid_dynamic, rigid_static,
 };
 use physxx::{
-    AsPxActor, PxContactPairHeader, PxControllerManagerRef, PxMaterial, PxPvdSceneFlag, PxRigidActor, PxRigidActorRef, PxSceneDesc,
-    PxSceneFlags, PxSceneRef, PxSimulationEventCallback, PxUserData,
+    AsPxActor, PxContactPairHeader, PxControllerFilters, PxControllerManagerRef, PxMaterial, PxPairFlags, PxPvdSceneFlag, PxRigidActor,
+    PxRigidActorRef, PxSceneDesc, PxSceneFlags, PxSceneRef, PxSimulationEventCallback, PxUserData,
 };
 use serde::{Deserialize, Serialize};
 
@@ -25,10 +28,14 @@ use crate::physx::PhysicsKey;
 
 pub mod collider;
 pub mod helpers;
+pub mod interpolation;
 pub mod intersection;
 pub mod mesh;
 pub mod physx;
+pub mod ragdoll;
 pub mod rc_asset;
+pub mod trigger;
+pub mod vehicle;
 pub mod visualization;
 
 components!("physics", {
@@ -42,8 +49,33 @@ components!("physics", {
     main_controller_manager: PxControllerManagerRef,
     @[Resource]
     wood_physics_material: PxMaterial,
+    /// Resource: the collisions of this physics tick
     @[Debuggable, Resource]
-    collisions: Arc<Mutex<Vec<(PxRigidActorRef, PxRigidActorRef)>>>,
+    collisions: Arc<Mutex<Vec<Collision>>>,
+    @[
+        Debuggable, Networked, Store,
+        Name["Collision contacts"],
+        Description["If attached, the collisions of this entity will include their contact points, normals and impulse.\nThey'll also be sent while the contact persists and when it ends, and not only when it begins."]
+    ]
+    collision_contacts: (),
+    @[
+        Debuggable,
+        Name["Contact points"],
+        Description["The points where the entities of a collision touch, in world space."]
+    ]
+    contact_points: Vec<Vec3>,
+    @[
+        Debuggable,
+        Name["Contact normals"],
+        Description["The normals of the contact points of a collision, pointing from the second entity towards the first."]
+    ]
+    contact_normals: Vec<Vec3>,
+    @[
+        Debuggable,
+        Name["Contact impulse"],
+        Description["The total impulse applied to resolve a collision, in Newton-seconds. Its length can be used to scale the effects of an impact."]
+    ]
+    contact_impulse: Vec3,
 
     @[
         Debuggable, Networked, Store,
@@ -75,11 +107,75 @@ components!("physics", {
         Description["All physics objects will be made static when loaded."]
     ]
     make_physics_static: bool,
+    @[
+        Debuggable, Resource,
+        Name["Physics timestep"],
+        Description["How long each step of the physics simulation is, in seconds."]
+    ]
+    physics_timestep: f32,
+    @[
+        Debuggable, Resource,
+        Name["Physics max substeps"],
+        Description["The most physics steps that are taken in one server tick. Time the simulation can't catch up on is dropped."]
+    ]
+    physics_max_substeps: u32,
+    /// Resource: true while a physics step runs concurrently with the rest of the tick
+    @[Resource]
+    simulation_running: bool,
 });
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionPhase {
+    Begin,
+    Persist,
+    End,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CollisionContacts {
+    pub points: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub impulse: Vec3,
+}
+
+/// A collision between two actors. Only the beginning of collisions are recorded unless one of the entities has
+/// `collision_contacts`, in which case the phases and contacts of its collisions are too
+#[derive(Debug, Clone)]
+pub struct Collision {
+    pub actors: (PxRigidActorRef, PxRigidActorRef),
+    pub phase: CollisionPhase,
+    pub contacts: Option<CollisionContacts>,
+}
+
+/// The bit of `word3` of the simulation filter data of the shapes whose contacts are reported
+const CONTACT_REPORT_FILTER: u32 = 1;
+
+fn set_contact_reporting(actor: PxRigidActorRef, enabled: bool) {
+    let mut changed = false;
+    for shape in actor.get_shapes() {
+        let mut data = shape.get_simulation_filter_data();
+        let word3 = if enabled { data.word3 | CONTACT_REPORT_FILTER } else { data.word3 & !CONTACT_REPORT_FILTER };
+        if word3 != data.word3 {
+            data.word3 = word3;
+            shape.set_simulation_filter_data(&data);
+            changed = true;
+        }
+    }
+    // The pairs the shapes are already in keep the flags the filter shader gave them until it's run again
+    if changed {
+        if let Some(scene) = actor.get_scene() {
+            scene.reset_filtering(&actor);
+        }
+    }
+}
+
 pub fn init_all_components() {
     init_components();
     physx::init_components();
     collider::init_components();
+    interpolation::init_components();
+    ragdoll::init_components();
+    trigger::init_components();
+    vehicle::init_components();
     visualization::init_components();
 }
 
@@ -91,22 +187,49 @@ pub fn create_server_resources(assets: &AssetCache, server_resources: &mut Entit
     let mut main_scene_desc = PxSceneDesc::new(physics.physics);
     main_scene_desc.set_cpu_dispatcher(&physics.dispatcher);
     main_scene_desc.set_gravity(vec3(0., 0., -GRAVITY));
-    main_scene_desc.update_flags(|flags| flags | PxSceneFlags::ENABLE_CCD);
+    main_scene_desc.update_flags(|flags| flags | PxSceneFlags::ENABLE_CCD | PxSceneFlags::ENABLE_ENHANCED_DETERMINISM);
     main_scene_desc.set_filter_shader(main_physx_scene_filter_shader, true);
     let collisions = Arc::new(Mutex::new(Vec::new()));
     {
         let collisions = collisions.clone();
         main_scene_desc.set_simulation_event_callbacks(PxSimulationEventCallback {
             collision_callback: Some(Box::new(move |header: &PxContactPairHeader| {
-                if let (Some(a), Some(b)) = (header.actors[0], header.actors[1]) {
-                    collisions.lock().push((a, b));
-                }
+                let (a, b) = match header.actors {
+                    [Some(a), Some(b)] => (a, b),
+                    _ => return,
+                };
+                let events = header.pairs.iter().fold(PxPairFlags::empty(), |events, pair| events | pair.events);
+                let phase = if events.contains(PxPairFlags::NOTIFY_TOUCH_FOUND) {
+                    CollisionPhase::Begin
+                } else if events.contains(PxPairFlags::NOTIFY_TOUCH_PERSISTS) {
+                    CollisionPhase::Persist
+                } else if events.contains(PxPairFlags::NOTIFY_TOUCH_LOST) {
+                    CollisionPhase::End
+                } else {
+                    return;
+                };
+                let reported = header
+                    .pairs
+                    .iter()
+                    .any(|pair| pair.shapes.iter().flatten().any(|shape| shape.simulation_filter_data.word3 & CONTACT_REPORT_FILTER != 0));
+                let contacts = reported.then(|| {
+                    let mut contacts = CollisionContacts::default();
+                    for point in header.pairs.iter().flat_map(|pair| pair.contacts.iter()) {
+                        contacts.points.push(point.position);
+                        contacts.normals.push(point.normal);
+                        contacts.impulse += point.impulse;
+                    }
+                    contacts
+                });
+                collisions.lock().push(Collision { actors: (a, b), phase, contacts });
             })),
         });
     }
     let main_scene = PxSceneRef::new(&physics.physics, &main_scene_desc);
     server_resources.set_self(self::collisions(), collisions);
     server_resources.set_self(self::collider_loads(), vec![]);
+    server_resources.set_self(self::simulation_running(), false);
+    server_resources.set_self(trigger::trigger_events(), vec![]);
 
     main_scene.get_scene_pvd_client().set_scene_pvd_flags(
         PxPvdSceneFlag::TRANSMIT_CONSTRAINTS | PxPvdSceneFlag::TRANSMIT_SCENEQUERIES | PxPvdSceneFlag::TRANSMIT_CONTACTS,
@@ -129,17 +252,41 @@ pub fn create_server_resources(assets: &AssetCache, server_resources: &mut Entit
     server_resources.set_self(self::wood_physics_material(), PxMaterial::new(physics.physics, 0.5, 0.5, 0.6));
 }
 
+/// A world with the server resources of the physics, for tests. PhysX can only be initialized once, so they share it
+#[cfg(test)]
+pub(crate) fn test_world() -> World {
+    use std::time::Duration;
+
+    use ambient_core::time;
+    use once_cell::sync::Lazy;
+
+    static RUNTIME: Lazy<tokio::runtime::Runtime> = Lazy::new(|| tokio::runtime::Runtime::new().unwrap());
+    static ASSETS: Lazy<AssetCache> = Lazy::new(|| AssetCache::new(RUNTIME.handle().clone()));
+
+    ambient_core::init_all_components();
+    init_all_components();
+    let mut resources = EntityData::new().set(asset_cache(), ASSETS.clone()).set(time(), Duration::ZERO).set(dtime(), 1. / 60.);
+    create_server_resources(&ASSETS, &mut resources);
+    resources.set_self(physics_timestep(), 1. / 60.);
+    resources.set_self(physics_max_substeps(), 4);
+    let mut world = World::new("physics_test");
+    world.add_components(world.resource_entity(), resources).unwrap();
+    world
+}
+
 #[derive(Debug, Clone)]
 pub struct PxShapeUserData {
     pub entity: EntityId,
     pub density: f32,
     /// The local pose of the shape before additional scaling is applied
     pub base_pose: Mat4,
+    /// The radius and half height of a capsule shape before additional scaling is applied. Capsules without it keep their size
+    pub base_capsule: Option<(f32, f32)>,
 }
 
 impl Default for PxShapeUserData {
     fn default() -> Self {
-        Self { entity: EntityId::null(), density: 1.0, base_pose: Mat4::IDENTITY }
+        Self { entity: EntityId::null(), density: 1.0, base_pose: Mat4::IDENTITY, base_capsule: None }
     }
 }
 
@@ -194,6 +341,15 @@ unsafe extern "C" fn main_physx_scene_filter_shader(mut info: *mut physxx::sys::
         | physxx::sys::PxPairFlag::eDETECT_CCD_CONTACT
         | physxx::sys::PxPairFlag::eCONTACT_DEFAULT
         | physxx::sys::PxPairFlag::eNOTIFY_TOUCH_FOUND) as u16;
+    if ((*info).filterData0.word3 | (*info).filterData1.word3) & CONTACT_REPORT_FILTER != 0 {
+        (*(*info).pairFlags).mBits |= (physxx::sys::PxPairFlag::eNOTIFY_TOUCH_PERSISTS
+            | physxx::sys::PxPairFlag::eNOTIFY_TOUCH_LOST
+            | physxx::sys::PxPairFlag::eNOTIFY_CONTACT_POINTS) as u16;
+    }
+    // Used by ragdolls, so that their links don't collide with each other or with the rest of their entity
+    if (*info).filterData0.word2 != 0 && (*info).filterData0.word2 == (*info).filterData1.word2 {
+        return (physxx::sys::PxFilterFlag::eSUPPRESS) as u16;
+    }
     (physxx::sys::PxFilterFlag::eDEFAULT) as u16
 }
 
@@ -218,30 +374,100 @@ pub fn server_systems() -> SystemGroup {
                     }
                 }
             }),
+            // The shapes are replaced when the collider changes, so they're checked every frame
+            query(rigid_actor()).incl(collision_contacts()).to_system(|q, world, qs, _| {
+                for (_, actor) in q.iter(world, qs) {
+                    set_contact_reporting(*actor, true);
+                }
+            }),
+            query(collision_contacts()).despawned().to_system(|q, world, qs, _| {
+                for (id, _) in q.collect_cloned(world, qs) {
+                    if let Ok(actor) = world.get(id, rigid_actor()) {
+                        set_contact_reporting(actor, false);
+                    }
+                }
+            }),
             query((character_controller(),)).despawned().to_system(|q, world, qs, _| {
                 for (_, (controller,)) in q.iter(world, qs) {
                     controller.release();
                 }
             }),
             Box::new(collider::server_systems()),
+            Box::new(trigger::server_systems()),
+            Box::new(ragdoll::server_systems()),
             Box::new(visualization::server_systems()),
         ],
     )
 }
 
+/// Moves entities by the deltas in `delta_translation` and `delta_rotation`, such as the root motion of their animations, and
+/// resets them. Character controllers collide while they're moved; their translation is read back after the simulation
+pub fn apply_motion_system(delta_translation: Component<Vec3>, delta_rotation: Component<Quat>) -> DynSystem {
+    query((delta_translation, delta_rotation)).to_system(move |q, world, qs, _| {
+        let dtime = *world.resource(dtime());
+        for (id, (translation_delta, rotation_delta)) in q.collect_cloned(world, qs) {
+            if translation_delta == Vec3::ZERO && rotation_delta == Quat::IDENTITY {
+                continue;
+            }
+            if let Ok(controller) = world.get(id, character_controller()) {
+                controller.move_controller(translation_delta, 0.001, dtime, &PxControllerFilters::new(), None);
+            } else if let Ok(pos) = world.get_mut(id, translation()) {
+                *pos += translation_delta;
+            }
+            if let Ok(rot) = world.get_mut(id, rotation()) {
+                *rot = rotation_delta * *rot;
+            }
+            world.set(id, delta_translation, Vec3::ZERO).unwrap();
+            world.set(id, delta_rotation, Quat::IDENTITY).unwrap();
+        }
+    })
+}
+
 pub fn client_systems() -> SystemGroup {
-    SystemGroup::new("physics", vec![Box::new(visualization::client_systems())])
+    SystemGroup::new("physics", vec![Box::new(interpolation::client_systems()), Box::new(visualization::client_systems())])
 }
 
-/// Starts the physx simulation step concurrently.
+/// Steps the physx simulation by [`physics_timestep`] as many times as fit in the time since the previous tick, [`dtime`], at
+/// most [`physics_max_substeps`] times. The time that doesn't fill a step is carried over to the next tick. Forces that only
+/// last one step, such as those of vehicles, are applied before each step.
 ///
-/// Results will be available after [`fetch_simulation_system`]
+/// The last step runs concurrently, and its results will be available after [`fetch_simulation_system`]
 pub fn run_simulation_system() -> DynSystem {
-    Box::new(FnSystem::new(|world, _| {
+    Box::new(SimulationSystem { accumulator: 0., substep_systems: vehicle::substep_systems() })
+}
+
+#[derive(Debug)]
+struct SimulationSystem {
+    /// Time of the ticks that hasn't been simulated yet
+    accumulator: f32,
+    substep_systems: SystemGroup,
+}
+impl System for SimulationSystem {
+    fn run(&mut self, world: &mut World, event: &FrameEvent) {
         profiling::scope!("run_simulation_system");
-        let scene = world.resource(main_physics_scene());
-        scene.simulate(1. / 60.);
-    }))
+        let timestep = *world.resource(physics_timestep());
+        let max_substeps = (*world.resource(physics_max_substeps())).max(1);
+        // Only whole steps are simulated, so the results only depend on the durations of the ticks, and not on when they ran
+        let tick = *world.resource(dtime());
+
+        // Ticks longer than the max substeps drop the time that doesn't fit, so that a slow server doesn't fall further behind
+        self.accumulator = (self.accumulator + tick).min(timestep * max_substeps as f32);
+        // The small margin keeps rounding errors from alternating between zero and two steps when ticks are as long as steps
+        let steps = (self.accumulator / timestep + 1e-3) as u32;
+        self.accumulator = (self.accumulator - steps as f32 * timestep).max(0.);
+
+        // The collisions of all the steps are collected until the next tick
+        world.resource(collisions()).lock().clear();
+        let scene = *world.resource(main_physics_scene());
+        for step in 0..steps {
+            if step > 0 {
+                scene.fetch_results(true);
+            }
+            self.substep_systems.run(world, event);
+            scene.simulate(timestep);
+        }
+        world.set(world.resource_entity(), simulation_running(), steps > 0).unwrap();
+    }
 }
 
 /// Ensures the physx simulation data is available.
@@ -251,11 +477,12 @@ pub fn fetch_simulation_system() -> DynSystem {
     Box::new(FnSystem::new(|world, _| {
         profiling::scope!("fetch_simulation_system");
 
-        world.resource(collisions()).lock().clear();
         world.resource_mut(collider_loads()).clear();
-        let scene = world.resource(main_physics_scene());
-        // Ensure the previous simulation has completed
-        scene.fetch_results(true);
+        if *world.resource(simulation_running()) {
+            // Ensure the previous simulation has completed
+            world.resource(main_physics_scene()).fetch_results(true);
+            world.set(world.resource_entity(), simulation_running(), false).unwrap();
+        }
     }))
 }
 
@@ -317,3 +544,123 @@ pub fn on_shutdown_systems() -> SystemGroup<ShutdownEvent> {
         }))],
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use physxx::{AsPxRigidActor, PxBoxGeometry, PxRigidBody, PxRigidDynamicRef, PxRigidStaticRef, PxShape, PxTransform};
+
+    use super::*;
+    use crate::physx::physics;
+
+    fn step(world: &World, steps: usize) -> Vec<Collision> {
+        let scene = *world.resource(main_physics_scene());
+        world.resource(collisions()).lock().clear();
+        for _ in 0..steps {
+            scene.simulate(1. / 60.);
+            scene.fetch_results(true);
+        }
+        world.resource(collisions()).lock().clone()
+    }
+
+    #[test]
+    fn contacts_are_reported_for_existing_pairs() {
+        let world = test_world();
+        let physics = world.resource(physics()).clone();
+        let material = world.resource(wood_physics_material()).clone();
+        let scene = *world.resource(main_physics_scene());
+
+        let ground = PxRigidStaticRef::new(physics.physics, &PxTransform::from_translation(Vec3::ZERO)).as_rigid_actor();
+        assert!(ground.attach_shape(&PxShape::new(physics.physics, &PxBoxGeometry::new(5., 5., 0.5), &[&material], Some(true), None)));
+        scene.add_actor(&ground);
+        let geometry = PxBoxGeometry::new(0.5, 0.5, 0.5);
+        let transform = PxTransform::from_translation(vec3(0., 0., 1.));
+        let rigid_dynamic =
+            PxRigidDynamicRef::new_with_geometry(&physics.physics, &transform, &geometry, &material, 1., &PxTransform::identity());
+        let actor = rigid_dynamic.as_rigid_actor();
+        scene.add_actor(&actor);
+
+        // Only the beginning of the contact is reported until reporting is enabled
+        let collisions = step(&world, 3);
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].phase, CollisionPhase::Begin);
+        assert!(collisions[0].contacts.is_none());
+        assert!(step(&world, 3).is_empty());
+
+        // The pair already exists, so its contacts are only reported once it's been filtered again
+        set_contact_reporting(actor, true);
+        let collisions = step(&world, 3);
+        assert_eq!(collisions.last().unwrap().phase, CollisionPhase::Persist);
+        for collision in &collisions {
+            let contacts = collision.contacts.as_ref().unwrap();
+            assert!(!contacts.points.is_empty());
+            assert_eq!(contacts.points.len(), contacts.normals.len());
+            assert!(contacts.normals.iter().all(|normal| normal.z.abs() > 0.99));
+        }
+
+        set_contact_reporting(actor, false);
+        step(&world, 1);
+        assert!(step(&world, 3).is_empty());
+    }
+
+    /// Runs ticks of the durations in `ticks`, in seconds. Returns the translations of a stack of boxes, and how many steps
+    /// were taken
+    fn run_ticks(ticks: &[f32]) -> (Vec<Vec3>, u32) {
+        let mut world = test_world();
+        let physics = world.resource(physics()).clone();
+        let material = world.resource(wood_physics_material()).clone();
+        let scene = *world.resource(main_physics_scene());
+        let timestep = *world.resource(physics_timestep());
+        let ground = PxRigidStaticRef::new(physics.physics, &PxTransform::from_translation(vec3(0., 0., -0.5))).as_rigid_actor();
+        assert!(ground.attach_shape(&PxShape::new(physics.physics, &PxBoxGeometry::new(5., 5., 0.5), &[&material], Some(true), None)));
+        scene.add_actor(&ground);
+        let geometry = PxBoxGeometry::new(0.5, 0.5, 0.5);
+        let boxes = (0..5)
+            .map(|i| {
+                let transform = PxTransform::from_translation(vec3(0.1 * i as f32, 0.05 * i as f32, 0.6 + 1.1 * i as f32));
+                let body =
+                    PxRigidDynamicRef::new_with_geometry(&physics.physics, &transform, &geometry, &material, 1., &PxTransform::identity());
+                scene.add_actor(&body);
+                body
+            })
+            .collect::<Vec<_>>();
+        // Moves away from everything else at a constant speed along x, so its x is the simulated time
+        let clock = PxRigidDynamicRef::new_with_geometry(
+            &physics.physics,
+            &PxTransform::from_translation(vec3(0., 100., 0.)),
+            &geometry,
+            &material,
+            1.,
+            &PxTransform::identity(),
+        );
+        clock.set_linear_velocity(Vec3::X, true);
+        scene.add_actor(&clock);
+
+        let (mut simulation, mut fetch) = (run_simulation_system(), fetch_simulation_system());
+        for &tick in ticks {
+            world.set(world.resource_entity(), dtime(), tick).unwrap();
+            fetch.run(&mut world, &FrameEvent);
+            simulation.run(&mut world, &FrameEvent);
+        }
+        fetch.run(&mut world, &FrameEvent);
+        let steps = (clock.get_global_pose().translation().x / timestep).round() as u32;
+        (boxes.iter().map(|body| body.get_global_pose().translation()).collect(), steps)
+    }
+
+    #[test]
+    fn simulation_takes_whole_steps_of_the_ticks() {
+        let timestep = 1. / 60.;
+        let ticks = [0.005, 0.031, 0.012, 0.002, 0.024, 0.017].repeat(20);
+        let (poses, steps) = run_ticks(&ticks);
+        // The time that doesn't fill a step is carried over to the next ticks
+        let total = ticks.iter().map(|&tick| tick as f64).sum::<f64>();
+        assert_eq!(steps, (total / timestep).floor() as u32);
+        // The boxes have fallen onto each other
+        assert!(poses.last().unwrap().z < 4.6);
+
+        // A tick that's longer than the max substeps only takes the max, and the rest of its time is dropped
+        assert_eq!(run_ticks(&[0.5, 0.004]).1, 4);
+
+        // The same ticks give the same results
+        assert_eq!(run_ticks(&ticks), (poses, steps));
+    }
+}
@@ -162,7 +162,8 @@ pub fn sync_ecs_physics() -> SystemGroup {
                                 // update_actor_entity_transforms(world, actor);
                             }
                         } else if let Ok(controller) = world.get(id, character_controller()) {
-                            controller.set_position(pos.as_dvec3());
+                            // The translation of a character is the position of its feet, as read back below
+                            controller.set_foot_position(pos.as_dvec3());
                         }
                     }
                 }
@@ -0,0 +1,455 @@
+use std::{
+    f32::consts::PI,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use ambient_animation::{pose_override_bone, pose_override_of, sample_skeleton, SkeletonNode};
+use ambient_core::{
+    asset_cache,
+    async_ecs::async_run,
+    runtime,
+    transform::{rotation, scale, translation},
+};
+use ambient_ecs::{components, query, Debuggable, Description, EntityData, EntityId, Name, Networked, Store, SystemGroup, World};
+use ambient_std::{asset_cache::AsyncAssetKeyExt, asset_url::AbsAssetUrl, download_asset::JsonFromUrl};
+use glam::{Mat4, Quat, Vec3};
+use itertools::Itertools;
+use physxx::{
+    articulation_reduced_coordinate::{PxArticulationJointRef, PxArticulationRef},
+    PxArticulationAxis, PxArticulationJointBase, PxArticulationJointType, PxArticulationLinkRef, PxArticulationMotion, PxCapsuleGeometry,
+    PxRigidActor, PxRigidBody, PxShape, PxShapeFlag, PxTransform, PxUserData,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    main_physics_scene,
+    physx::{character_controller, physics, rigid_actor},
+    wood_physics_material, PxShapeUserData,
+};
+
+components!("physics", {
+    @[
+        Debuggable, Networked, Store,
+        Name["Ragdoll from URL"],
+        Description["Load the ragdoll of this entity's model from the given URL or relative path.\nIt's simulated while `ragdoll_active` is true."]
+    ]
+    ragdoll_from_url: String,
+    @[
+        Debuggable, Networked, Store,
+        Name["Ragdoll active"],
+        Description["If true, the model of this entity is posed by its ragdoll instead of its animations.\nThe ragdoll starts from the pose the animations of the entity give the model, and is removed when this is set to false."]
+    ]
+    ragdoll_active: bool,
+    @[Debuggable]
+    ragdoll_def: Arc<RagdollDef>,
+    ragdoll_instance: RagdollInstance,
+});
+
+/// The bodies and joints of a ragdoll, generated from the skin of a model
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RagdollDef {
+    /// The transform of the model, which the skeleton is relative to
+    pub transform: Mat4,
+    /// The nodes of the model from its roots to the bones, which are posed by the animations of the entity when the ragdoll starts
+    pub skeleton: Vec<SkeletonNode>,
+    /// The bones are ordered so that parents come before their children
+    pub bones: Vec<RagdollBone>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagdollBone {
+    pub bind_id: String,
+    /// The index of the node of this bone in the skeleton
+    pub node: usize,
+    /// The index of the parent of this bone in the ragdoll; the first bone is the root
+    pub parent: Option<usize>,
+    /// The transform of the bone in the base pose, in the space of the model
+    pub transform: Mat4,
+    /// The direction of the bone's capsule from its origin, relative to the rotation of the bone
+    pub direction: Vec3,
+    /// The length of the bone in meters
+    pub length: f32,
+    /// The radius of the bone's capsule in meters
+    pub radius: f32,
+    pub density: f32,
+    /// How far, in radians, the bone can swing away from its base pose relative to its parent
+    pub swing_limit: f32,
+    /// How far, in radians, the bone can twist around its direction relative to its parent
+    pub twist_limit: (f32, f32),
+}
+
+/// Changes to a generated ragdoll bone
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RagdollBoneOverride {
+    /// Leave the bone out of the ragdoll. Its children are attached to its parent instead.
+    #[serde(default)]
+    pub exclude: bool,
+    /// The radius of the bone's capsule, in meters.
+    pub radius: Option<f32>,
+    /// The length of the bone's capsule, in meters.
+    pub length: Option<f32>,
+    /// The density of the bone's capsule. Defaults to 1.
+    pub density: Option<f32>,
+    /// How far, in degrees, the bone can swing away from its base pose. Defaults to 45.
+    pub swing_limit: Option<f32>,
+    /// How far, in degrees, the bone can twist around its direction, from the first to the second angle. Defaults to `[-30, 30]`.
+    pub twist_limit: Option<(f32, f32)>,
+}
+
+#[derive(Clone)]
+pub struct RagdollInstance {
+    articulation: PxArticulationRef,
+    links: Vec<PxArticulationLinkRef>,
+    /// The entities that override the poses of the bones, which have the poses of the links
+    link_entities: Vec<EntityId>,
+}
+
+static NEXT_COLLISION_GROUP: AtomicU32 = AtomicU32::new(1);
+
+/// Shapes whose simulation filter data has the same non-zero `word2` don't collide, so that the links of a ragdoll don't collide
+/// with each other or with the rest of their entity. Each ragdoll gets its own group
+fn next_collision_group() -> u32 {
+    loop {
+        let group = NEXT_COLLISION_GROUP.fetch_add(1, Ordering::Relaxed);
+        if group != 0 {
+            return group;
+        }
+    }
+}
+
+fn set_collision_group(shapes: Vec<PxShape>, group: u32) {
+    for shape in shapes {
+        let mut data = shape.get_simulation_filter_data();
+        data.word2 = group;
+        shape.set_simulation_filter_data(&data);
+    }
+}
+
+fn entity_shapes(world: &World, id: EntityId) -> Vec<PxShape> {
+    let mut shapes = world.get(id, rigid_actor()).map(|actor| actor.get_shapes()).unwrap_or_default();
+    if let Ok(controller) = world.get(id, character_controller()) {
+        shapes.extend(controller.get_actor().get_shapes());
+    }
+    shapes
+}
+
+/// The twist and swing angles of a rotation in the frame of a spherical joint, whose twist axis is X
+fn twist_swing_angles(rotation: Quat) -> (f32, f32, f32) {
+    let rotation = if rotation.w < 0. { -rotation } else { rotation };
+    let twist = 2. * rotation.x.atan2(rotation.w);
+    let swing = (rotation * Quat::from_rotation_x(twist).inverse()).to_scaled_axis();
+    (twist, swing.y, swing.z)
+}
+
+/// The limits of the joint of `bone`, when the pose it starts in is `base` away from its base pose in the frame of the joint.
+/// The joint is at rest in the pose it starts in, so the limits are moved by `base` to stay around the base pose, and are
+/// widened to allow the starting pose if it's outside of them.
+fn joint_limits(bone: &RagdollBone, base: Quat) -> [(PxArticulationAxis, f32, f32); 3] {
+    let (twist, swing1, swing2) = twist_swing_angles(base);
+    let limit = |offset: f32, min: f32, max: f32| ((offset + min).clamp(-PI, 0.), (offset + max).clamp(0., PI));
+    let limits = [
+        (PxArticulationAxis::Twist, limit(twist, bone.twist_limit.0, bone.twist_limit.1)),
+        (PxArticulationAxis::Swing1, limit(swing1, -bone.swing_limit, bone.swing_limit)),
+        (PxArticulationAxis::Swing2, limit(swing2, -bone.swing_limit, bone.swing_limit)),
+    ];
+    limits.map(|(axis, (min, max))| (axis, min, max))
+}
+
+fn spawn_ragdoll(world: &mut World, id: EntityId, def: &RagdollDef) {
+    let physics = world.resource(physics()).clone();
+    let material = world.resource(wood_physics_material()).clone();
+    let entity_scale = world.get(id, scale()).unwrap_or(Vec3::ONE);
+    let entity_transform = Mat4::from_scale_rotation_translation(
+        entity_scale,
+        world.get(id, rotation()).unwrap_or_default(),
+        world.get(id, translation()).unwrap_or_default(),
+    );
+    // The capsules can't be scaled non-uniformly, so they're scaled by the largest axis
+    let size_scale = entity_scale.max_element();
+    let group = next_collision_group();
+
+    // The ragdoll starts from the current pose of the animations, so that the model doesn't jump when it's switched to it
+    let pose = match sample_skeleton(world, id, &def.skeleton) {
+        Ok(pose) => Some(pose),
+        Err(err) => {
+            log::warn!("Failed to sample the animations of ragdoll {}, starting from the base pose: {}", id, err);
+            None
+        }
+    };
+    let bone_transform =
+        |bone: &RagdollBone| pose.as_ref().and_then(|pose| pose.get(bone.node)).map(|node| def.transform * *node).unwrap_or(bone.transform);
+
+    let mut articulation = PxArticulationRef::new(&physics.physics);
+    let mut links: Vec<PxArticulationLinkRef> = Vec::new();
+    let mut frames: Vec<(Quat, Vec3)> = Vec::new();
+    for bone in &def.bones {
+        let (_, rot, pos) = (entity_transform * bone_transform(bone)).to_scale_rotation_translation();
+        let link = PxArticulationLinkRef::new(&articulation, bone.parent.map(|i| &links[i]), &PxTransform::new(pos, rot));
+
+        // Capsules lie along the X axis
+        let axis = Quat::from_rotation_arc(Vec3::X, bone.direction.try_normalize().unwrap_or(Vec3::X));
+        let (length, radius) = (bone.length * size_scale, bone.radius * size_scale);
+        let shape = PxShape::new(
+            physics.physics,
+            &PxCapsuleGeometry::new(radius, (length / 2. - radius).max(0.01)),
+            &[&material],
+            Some(true),
+            None,
+        );
+        shape.set_local_pose(&PxTransform::new(axis * Vec3::X * length / 2., axis));
+        shape.set_flag(PxShapeFlag::VISUALIZATION, false);
+        shape.set_user_data(PxShapeUserData { entity: id, density: bone.density, ..Default::default() });
+        link.attach_shape(&shape);
+        link.update_mass_and_inertia(vec![bone.density], None, None);
+
+        if let (Some(parent), Some(mut joint)) = (bone.parent, PxArticulationJointRef::from_link(&link)) {
+            // The twist axis of the joint is its X axis, which is aligned with the bone on both sides
+            let (parent_rot, parent_pos) = frames[parent];
+            joint.set_parent_pose(&PxTransform::new(parent_rot.inverse() * (pos - parent_pos), parent_rot.inverse() * rot * axis));
+            joint.set_child_pose(&PxTransform::from_rotation(axis));
+            joint.set_joint_type(PxArticulationJointType::Spherical);
+            // The rotation of the bone relative to its parent in the base pose, compared to the one it starts with
+            let base_rotation = |bone: &RagdollBone| bone.transform.to_scale_rotation_translation().1;
+            let base_relative = base_rotation(&def.bones[parent]).inverse() * base_rotation(bone);
+            let base = axis.inverse() * (parent_rot.inverse() * rot).inverse() * base_relative * axis;
+            for (joint_axis, min, max) in joint_limits(bone, base) {
+                joint.set_motion(joint_axis, PxArticulationMotion::Limited);
+                joint.set_limit(joint_axis, min, max);
+            }
+        }
+        frames.push((rot, pos));
+        links.push(link);
+    }
+    if links.is_empty() {
+        articulation.release();
+        return;
+    }
+    set_collision_group(links.iter().flat_map(|link| link.get_shapes()).collect(), group);
+    set_collision_group(entity_shapes(world, id), group);
+    world.resource(main_physics_scene()).add_articulation(&articulation);
+
+    // Each link is networked on its own, so that only the ones which move are sent
+    let link_entities = def
+        .bones
+        .iter()
+        .zip(&frames)
+        .map(|(bone, (rot, pos))| {
+            EntityData::new()
+                .set(pose_override_of(), id)
+                .set(pose_override_bone(), bone.bind_id.clone())
+                .set(translation(), *pos)
+                .set(rotation(), *rot)
+                .spawn(world)
+        })
+        .collect_vec();
+    world.add_component(id, ragdoll_instance(), RagdollInstance { articulation, links, link_entities }).unwrap();
+}
+
+fn release_ragdoll(world: &mut World, id: EntityId, mut instance: RagdollInstance) {
+    world.resource(main_physics_scene()).remove_articulation(&instance.articulation, true);
+    for link in &instance.links {
+        for shape in link.get_shapes() {
+            shape.remove_user_data::<PxShapeUserData>();
+        }
+    }
+    instance.articulation.release();
+    set_collision_group(entity_shapes(world, id), 0);
+    for entity in instance.link_entities {
+        world.despawn(entity);
+    }
+}
+
+/// Loads the ragdolls of entities, simulates them while they're active and copies the poses of their links to the bones
+pub fn server_systems() -> SystemGroup {
+    SystemGroup::new(
+        "physics/ragdoll",
+        vec![
+            query(ragdoll_from_url().changed()).to_system(|q, world, qs, _| {
+                let assets = world.resource(asset_cache()).clone();
+                let runtime = world.resource(runtime()).clone();
+                let async_run = world.resource(async_run()).clone();
+                for (id, url) in q.collect_cloned(world, qs) {
+                    let url = match AbsAssetUrl::parse(&url) {
+                        Ok(url) => url,
+                        Err(err) => {
+                            log::warn!("Failed to parse ragdoll url {}: {:?}", url, err);
+                            continue;
+                        }
+                    };
+                    let assets = assets.clone();
+                    let async_run = async_run.clone();
+                    runtime.spawn(async move {
+                        let def: Arc<RagdollDef> = match JsonFromUrl::new(url, true).get(&assets).await {
+                            Ok(def) => def,
+                            Err(err) => {
+                                tracing::warn!("Failed to load ragdoll: {:#}", err);
+                                return;
+                            }
+                        };
+                        async_run.run(move |world| {
+                            world.add_component(id, ragdoll_def(), def).ok();
+                        });
+                    });
+                }
+            }),
+            query((ragdoll_def(), ragdoll_active())).excl(ragdoll_instance()).to_system(|q, world, qs, _| {
+                for (id, (def, active)) in q.collect_cloned(world, qs) {
+                    if active {
+                        spawn_ragdoll(world, id, &def);
+                    }
+                }
+            }),
+            query(ragdoll_instance()).to_system(|q, world, qs, _| {
+                for (id, instance) in q.collect_cloned(world, qs) {
+                    if world.get(id, ragdoll_active()).unwrap_or(false) {
+                        for (link, entity) in instance.links.iter().zip(&instance.link_entities) {
+                            let pose = link.get_global_pose();
+                            world.set_if_changed(*entity, translation(), pose.translation()).ok();
+                            world.set_if_changed(*entity, rotation(), pose.rotation()).ok();
+                        }
+                    } else {
+                        // The articulation is released when the instance is removed
+                        world.remove_component(id, ragdoll_instance()).unwrap();
+                    }
+                }
+            }),
+            query(ragdoll_instance()).despawned().to_system(|q, world, qs, _| {
+                for (id, instance) in q.collect_cloned(world, qs) {
+                    release_ragdoll(world, id, instance);
+                }
+            }),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use ambient_animation::{
+        animation_controller, AnimationAction, AnimationActionTime, AnimationClip, AnimationClipRef, AnimationController, AnimationOutputs,
+        AnimationTarget, AnimationTrack,
+    };
+    use glam::vec3;
+
+    use super::*;
+    use crate::test_world;
+
+    fn node(bind_id: &str, parent: Option<usize>, translation: Vec3) -> SkeletonNode {
+        SkeletonNode { bind_id: Some(bind_id.to_string()), parent, translation, rotation: Quat::IDENTITY, scale: Vec3::ONE }
+    }
+
+    fn bone(bind_id: &str, parent: Option<usize>, translation: Vec3) -> RagdollBone {
+        RagdollBone {
+            bind_id: bind_id.to_string(),
+            node: parent.map(|parent| parent + 1).unwrap_or(0),
+            parent,
+            transform: Mat4::from_translation(translation),
+            direction: Vec3::Z,
+            length: 0.5,
+            radius: 0.1,
+            density: 1.,
+            swing_limit: 0.5,
+            twist_limit: (-0.5, 0.5),
+        }
+    }
+
+    /// A hip with a spine above it
+    fn ragdoll_def() -> RagdollDef {
+        RagdollDef {
+            transform: Mat4::IDENTITY,
+            skeleton: vec![node("Hips", None, vec3(0., 0., 1.)), node("Spine", Some(0), vec3(0., 0., 0.5))],
+            bones: vec![bone("Hips", None, vec3(0., 0., 1.)), bone("Spine", Some(0), vec3(0., 0., 1.5))],
+        }
+    }
+
+    fn ragdoll_world() -> World {
+        let world = test_world();
+        ambient_model::init_components();
+        ambient_animation::init_components();
+        world
+    }
+
+    #[test]
+    fn spawns_in_the_animated_pose() {
+        let mut world = ragdoll_world();
+        let bend = Quat::from_rotation_x(FRAC_PI_2);
+        let clip = AnimationClip::from_tracks(vec![AnimationTrack {
+            target: AnimationTarget::BinderId("Hips".to_string()),
+            inputs: vec![0., 1.],
+            outputs: AnimationOutputs::Quat { component: rotation(), data: vec![bend, bend] },
+        }]);
+        let controller = AnimationController {
+            actions: vec![AnimationAction {
+                clip: AnimationClipRef::Clip(Arc::new(clip)),
+                time: AnimationActionTime::Absolute { time: 0. },
+                looping: false,
+                weight: 1.,
+            }],
+            apply_base_pose: false,
+        };
+        let id = EntityData::new().set(translation(), vec3(2., 0., 0.)).set(animation_controller(), controller).spawn(&mut world);
+        spawn_ragdoll(&mut world, id, &ragdoll_def());
+
+        let instance = world.get_ref(id, ragdoll_instance()).unwrap().clone();
+        let expected = [(vec3(2., 0., 1.), bend), (vec3(2., -0.5, 1.), bend)];
+        for ((entity, link), (pos, rot)) in instance.link_entities.iter().zip(&instance.links).zip(expected) {
+            assert_eq!(world.get(*entity, pose_override_of()).unwrap(), id);
+            assert!(world.get(*entity, translation()).unwrap().distance(pos) < 1e-5);
+            assert!(world.get(*entity, rotation()).unwrap().angle_between(rot) < 1e-3);
+            assert!(link.get_global_pose().translation().distance(pos) < 1e-5);
+        }
+        let bones = instance.link_entities.iter().map(|entity| world.get_ref(*entity, pose_override_bone()).unwrap().clone()).collect_vec();
+        assert_eq!(bones, vec!["Hips", "Spine"]);
+
+        // The joints are at rest in the pose the ragdoll starts in, so the spine doesn't snap back to the base pose
+        let scene = *world.resource(main_physics_scene());
+        scene.simulate(1. / 60.);
+        scene.fetch_results(true);
+        let spine = instance.links[1].get_global_pose();
+        assert!(spine.translation().distance(vec3(2., -0.5, 1.)) < 0.05);
+        assert!(spine.rotation().angle_between(bend) < 0.1);
+
+        world.remove_component(id, ragdoll_instance()).unwrap();
+        release_ragdoll(&mut world, id, instance.clone());
+        assert!(instance.link_entities.iter().all(|entity| !world.exists(*entity)));
+    }
+
+    #[test]
+    fn ragdolls_have_their_own_collision_groups() {
+        let mut world = ragdoll_world();
+        let groups = (0..2)
+            .map(|_| {
+                let id = EntityData::new().spawn(&mut world);
+                spawn_ragdoll(&mut world, id, &ragdoll_def());
+                let instance = world.get_ref(id, ragdoll_instance()).unwrap();
+                let groups = instance.links.iter().flat_map(|link| link.get_shapes()).map(|shape| shape.get_simulation_filter_data().word2);
+                groups.dedup().collect_vec()
+            })
+            .collect_vec();
+        assert_eq!(groups[0].len(), 1);
+        assert_eq!(groups[1].len(), 1);
+        assert_ne!(groups[0][0], 0);
+        assert_ne!(groups[0][0], groups[1][0]);
+    }
+
+    #[test]
+    fn joint_limits_follow_the_base_pose() {
+        let bone = bone("Spine", Some(0), Vec3::ZERO);
+        let limits = |base: Quat| joint_limits(&bone, base).map(|(_, min, max)| vec3(min, max, 0.));
+        let assert_limits = |base: Quat, expected: [(f32, f32); 3]| {
+            for (limit, (min, max)) in limits(base).into_iter().zip(expected) {
+                assert!(limit.distance(vec3(min, max, 0.)) < 1e-4, "{limit} != ({min}, {max})");
+            }
+        };
+
+        assert_limits(Quat::IDENTITY, [(-0.5, 0.5), (-0.5, 0.5), (-0.5, 0.5)]);
+        assert_limits(Quat::from_rotation_x(0.2), [(-0.3, 0.7), (-0.5, 0.5), (-0.5, 0.5)]);
+        assert_limits(Quat::from_rotation_y(-0.2), [(-0.5, 0.5), (-0.7, 0.3), (-0.5, 0.5)]);
+        // A starting pose outside of the limits is let in
+        assert_limits(Quat::from_rotation_x(-1.), [(-1.5, 0.), (-0.5, 0.5), (-0.5, 0.5)]);
+    }
+}
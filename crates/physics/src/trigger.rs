@@ -0,0 +1,174 @@
+use std::collections::BTreeSet;
+
+use ambient_ecs::{components, query, Debuggable, Description, EntityId, Name, Networked, Resource, Store, SystemGroup};
+use physxx::{PxGeometryType, PxOverlapCallback, PxQueryFilterData, PxQueryFlag, PxRigidActor, PxRigidActorRef, PxSceneRef, PxUserData};
+
+use crate::{
+    collider::{collider_type, ColliderType},
+    main_physics_scene,
+    physx::rigid_actor,
+    PxShapeUserData,
+};
+
+components!("physics", {
+    @[
+        Debuggable, Networked, Store,
+        Name["Entities in trigger"],
+        Description["The entities which are inside this trigger area.\nIt's only kept up to date if it's attached to the trigger area."]
+    ]
+    entities_in_trigger: Vec<EntityId>,
+    /// The entities that were inside this trigger area the last time it was checked
+    @[Debuggable]
+    trigger_overlaps: BTreeSet<EntityId>,
+    /// Resource: the entities which entered or exited a trigger area in this physics tick
+    @[Debuggable, Resource]
+    trigger_events: Vec<TriggerEvent>,
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerEventKind {
+    Enter,
+    Exit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriggerEvent {
+    pub trigger: EntityId,
+    pub entity: EntityId,
+    pub kind: TriggerEventKind,
+}
+
+/// The number of hits an overlap query starts with. It's doubled until all hits fit, up to [MAX_OVERLAP_HITS]
+const OVERLAP_HITS: usize = 256;
+const MAX_OVERLAP_HITS: usize = 16384;
+
+/// Whether shapes with the geometry can be used for trigger areas. Trigger areas are checked with overlap queries, which
+/// don't support triangle meshes, height fields and planes.
+pub fn is_trigger_geometry(geometry: PxGeometryType) -> bool {
+    !matches!(geometry, PxGeometryType::TRIANGLEMESH | PxGeometryType::HEIGHTFIELD | PxGeometryType::PLANE)
+}
+
+/// The entities in the main physics scene which overlap the shapes of the trigger area's actor
+fn overlapping_entities(scene: &PxSceneRef, actor: PxRigidActorRef) -> BTreeSet<EntityId> {
+    let mut filter_data = PxQueryFilterData::new();
+    // All hits are touches, so that none of them stop the query
+    filter_data.set_flags(PxQueryFlag::STATIC | PxQueryFlag::DYNAMIC | PxQueryFlag::NO_BLOCK);
+    let mut res = BTreeSet::new();
+    for shape in actor.get_shapes() {
+        let mut max_hits = OVERLAP_HITS;
+        let hits = loop {
+            let mut hit_call = PxOverlapCallback::new(max_hits);
+            if !scene.overlap(&shape.get_geometry(), shape.get_global_pose(actor), &mut hit_call, &filter_data) {
+                break Vec::new();
+            }
+            let hits = hit_call.touches();
+            // PhysX drops the hits that don't fit, so the query is made again with room for more
+            if hits.len() < max_hits {
+                break hits;
+            }
+            if max_hits >= MAX_OVERLAP_HITS {
+                log::warn!("A trigger area overlaps more than {MAX_OVERLAP_HITS} shapes; the others are ignored");
+                break hits;
+            }
+            max_hits *= 2;
+        };
+        for hit in hits {
+            if let Some(ud) = hit.shape.get_user_data::<PxShapeUserData>() {
+                if ud.entity != EntityId::null() {
+                    res.insert(ud.entity);
+                }
+            }
+        }
+    }
+    res
+}
+
+/// Compares what's inside each trigger area with the previous physics tick, and records the entities which entered or exited it
+/// in `trigger_events`
+pub fn server_systems() -> SystemGroup {
+    SystemGroup::new(
+        "physics/trigger",
+        vec![query((collider_type(), rigid_actor())).to_system(|q, world, qs, _| {
+            let scene = *world.resource(main_physics_scene());
+            let mut events = Vec::new();
+            for (id, (ty, actor)) in q.collect_cloned(world, qs) {
+                if ty != ColliderType::TriggerArea {
+                    continue;
+                }
+                let inside = overlapping_entities(&scene, actor);
+                let previous = world.get_ref(id, trigger_overlaps()).cloned().unwrap_or_default();
+                // It may have been attached after the entities entered the trigger area, so it's compared with what's inside
+                if world.get_ref(id, entities_in_trigger()).map(|entities| !entities.iter().eq(inside.iter())).unwrap_or(false) {
+                    world.set(id, entities_in_trigger(), inside.iter().copied().collect()).unwrap();
+                }
+                if inside == previous {
+                    continue;
+                }
+                events.extend(inside.difference(&previous).map(|&entity| TriggerEvent {
+                    trigger: id,
+                    entity,
+                    kind: TriggerEventKind::Enter,
+                }));
+                events.extend(previous.difference(&inside).map(|&entity| TriggerEvent {
+                    trigger: id,
+                    entity,
+                    kind: TriggerEventKind::Exit,
+                }));
+                if world.has_component(id, trigger_overlaps()) {
+                    world.set(id, trigger_overlaps(), inside).unwrap();
+                } else {
+                    world.add_component(id, trigger_overlaps(), inside).unwrap();
+                }
+            }
+            *world.resource_mut(trigger_events()) = events;
+        })],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use ambient_ecs::World;
+    use glam::Vec3;
+    use physxx::{AsPxRigidActor, PxBoxGeometry, PxGeometry, PxMaterial, PxPlaneGeometry, PxRigidStaticRef, PxShape, PxTransform};
+
+    use super::*;
+    use crate::{physx::physics, test_world, wood_physics_material};
+
+    fn static_actor(world: &World, geometry: &dyn PxGeometry, position: Vec3, entity: EntityId) -> PxRigidActorRef {
+        let physics = world.resource(physics()).clone();
+        let material: PxMaterial = world.resource(wood_physics_material()).clone();
+        let shape = PxShape::new(physics.physics, geometry, &[&material], Some(true), None);
+        shape.set_user_data(PxShapeUserData { entity, ..Default::default() });
+        let actor = PxRigidStaticRef::new(physics.physics, &PxTransform::from_translation(position)).as_rigid_actor();
+        assert!(actor.attach_shape(&shape));
+        actor
+    }
+
+    #[test]
+    fn overlapping_entities_finds_all_hits() {
+        let world = test_world();
+        let scene = *world.resource(main_physics_scene());
+        let inside = (0..OVERLAP_HITS * 3)
+            .map(|i| {
+                let entity = EntityId::new();
+                let position = Vec3::new((i % 30) as f32 * 0.1, (i / 30) as f32 * 0.1, 0.);
+                scene.add_actor(&static_actor(&world, &PxBoxGeometry::new(0.01, 0.01, 0.01), position, entity));
+                entity
+            })
+            .collect::<BTreeSet<_>>();
+        let outside = static_actor(&world, &PxBoxGeometry::new(0.01, 0.01, 0.01), Vec3::new(0., 0., 10.), EntityId::new());
+        scene.add_actor(&outside);
+
+        // More entities than fit in the first query are inside the trigger area
+        let trigger = static_actor(&world, &PxBoxGeometry::new(5., 5., 1.), Vec3::ZERO, EntityId::new());
+        assert_eq!(overlapping_entities(&scene, trigger), inside);
+    }
+
+    #[test]
+    fn trigger_geometry() {
+        assert!(is_trigger_geometry(PxBoxGeometry::new(1., 1., 1.).get_type()));
+        assert!(!is_trigger_geometry(PxPlaneGeometry::new().get_type()));
+        assert!(!is_trigger_geometry(PxGeometryType::TRIANGLEMESH));
+        assert!(!is_trigger_geometry(PxGeometryType::HEIGHTFIELD));
+    }
+}
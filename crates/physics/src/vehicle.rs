@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+
+use ambient_core::transform::{rotation, translation};
+use ambient_ecs::{components, query, Debuggable, Description, EntityId, Name, Networked, Store, SystemGroup, World};
+use glam::{vec2, Quat, Vec2, Vec3};
+use physxx::{
+    PxBase, PxQueryFilterData, PxQueryFlag, PxRaycastCallback, PxRaycastHit, PxRigidActor, PxRigidBody, PxRigidDynamicRef, PxSceneRef,
+    PxUserData,
+};
+
+use crate::{main_physics_scene, physics_timestep, physx::rigid_actor, PxShapeUserData};
+
+components!("physics", {
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle throttle"],
+        Description["How much of the engine torque of this vehicle is applied to its driven wheels, from -1 (full reverse) to 1 (full forward)."]
+    ]
+    vehicle_throttle: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle steer"],
+        Description["How far the steered wheels of this vehicle are turned, from -1 (fully left) to 1 (fully right)."]
+    ]
+    vehicle_steer: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle brake"],
+        Description["How hard the wheels of this vehicle are braked, from 0 to 1."]
+    ]
+    vehicle_brake: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle engine torque"],
+        Description["The torque in Newton-meters that the engine of this vehicle applies to its driven wheels at full throttle. Defaults to 800."]
+    ]
+    vehicle_engine_torque: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle speed"],
+        Description["The speed of this vehicle along its forward axis, in meters per second. It's negative when it's reversing."]
+    ]
+    vehicle_speed: f32,
+
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle wheel"],
+        Description["Makes this entity a wheel of the given vehicle. The vehicle must have a dynamic collider; it drives towards its -Y axis, with Z up.\nThe translation and rotation of the wheel are updated to where it touches the ground, so it shouldn't have a parent."]
+    ]
+    vehicle_wheel: EntityId,
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle wheel offset"],
+        Description["Where the suspension of this wheel is attached to its vehicle, relative to the vehicle. The wheel hangs below it when the suspension is fully extended."]
+    ]
+    vehicle_wheel_offset: Vec3,
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle wheel radius"],
+        Description["The radius of this wheel in meters. Defaults to 0.35."]
+    ]
+    vehicle_wheel_radius: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle wheel suspension length"],
+        Description["How far the suspension of this wheel can travel, in meters. Defaults to 0.3."]
+    ]
+    vehicle_wheel_suspension_length: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle wheel suspension stiffness"],
+        Description["The stiffness of the spring of this wheel's suspension, in Newtons per meter of compression. Defaults to 30000."]
+    ]
+    vehicle_wheel_suspension_stiffness: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle wheel suspension damping"],
+        Description["The damping of this wheel's suspension, in Newtons per meter per second of compression. Defaults to 3000."]
+    ]
+    vehicle_wheel_suspension_damping: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle wheel friction"],
+        Description["The friction coefficient of this wheel's tire. The tire can push the vehicle with up to this many times the load on the wheel. Defaults to 1.2."]
+    ]
+    vehicle_wheel_friction: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle wheel friction curve"],
+        Description["How the grip of this wheel's tire depends on how much it slides sideways.\n`x` is the slip angle in radians at which it has the most grip, and `y` is the fraction of the grip left when it's sliding. Defaults to `(0.15, 0.7)`."]
+    ]
+    vehicle_wheel_friction_curve: Vec2,
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle wheel max steer"],
+        Description["The angle in radians this wheel turns when the vehicle steers fully. Defaults to 0, which means it isn't steered."]
+    ]
+    vehicle_wheel_max_steer: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle wheel drive"],
+        Description["The fraction of the engine torque of the vehicle applied to this wheel. Defaults to 0, which means it isn't driven."]
+    ]
+    vehicle_wheel_drive: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle wheel brake torque"],
+        Description["The torque in Newton-meters this wheel brakes with when the vehicle brakes fully. Defaults to 1500."]
+    ]
+    vehicle_wheel_brake_torque: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Vehicle wheel compression"],
+        Description["How far the suspension of this wheel is compressed, in meters. It's 0 when the wheel is in the air."]
+    ]
+    vehicle_wheel_compression: f32,
+    /// The angle the wheel has rolled, in radians
+    @[Debuggable]
+    vehicle_wheel_spin: f32,
+});
+
+struct Wheel {
+    offset: Vec3,
+    radius: f32,
+    suspension_length: f32,
+    stiffness: f32,
+    damping: f32,
+    friction: f32,
+    friction_curve: Vec2,
+    max_steer: f32,
+    drive: f32,
+    brake_torque: f32,
+}
+impl Wheel {
+    fn from_world(world: &World, id: EntityId, offset: Vec3) -> Self {
+        Self {
+            offset,
+            radius: world.get(id, vehicle_wheel_radius()).unwrap_or(0.35),
+            suspension_length: world.get(id, vehicle_wheel_suspension_length()).unwrap_or(0.3),
+            stiffness: world.get(id, vehicle_wheel_suspension_stiffness()).unwrap_or(30000.),
+            damping: world.get(id, vehicle_wheel_suspension_damping()).unwrap_or(3000.),
+            friction: world.get(id, vehicle_wheel_friction()).unwrap_or(1.2),
+            friction_curve: world.get(id, vehicle_wheel_friction_curve()).unwrap_or(vec2(0.15, 0.7)),
+            max_steer: world.get(id, vehicle_wheel_max_steer()).unwrap_or(0.),
+            drive: world.get(id, vehicle_wheel_drive()).unwrap_or(0.),
+            brake_torque: world.get(id, vehicle_wheel_brake_torque()).unwrap_or(1500.),
+        }
+    }
+}
+
+struct Controls {
+    throttle: f32,
+    steer: f32,
+    brake: f32,
+    engine_torque: f32,
+}
+
+/// The fraction of the grip of a tire at the slip angle `slip`. It rises to all of it at the peak of the curve, and then falls
+/// to the sliding grip at twice that angle.
+fn friction_curve(slip: f32, curve: Vec2) -> f32 {
+    let peak = curve.x.max(1e-3);
+    if slip < peak {
+        slip / peak
+    } else {
+        1. + (curve.y - 1.) * ((slip - peak) / peak).min(1.)
+    }
+}
+
+/// The closest thing below the wheel, other than its own vehicle
+fn raycast_ground(scene: &PxSceneRef, vehicle: EntityId, origin: Vec3, dir: Vec3, distance: f32) -> Option<PxRaycastHit> {
+    let mut hit_call = PxRaycastCallback::new(16);
+    let mut filter_data = PxQueryFilterData::new();
+    filter_data.set_flags(PxQueryFlag::STATIC | PxQueryFlag::DYNAMIC | PxQueryFlag::NO_BLOCK);
+    if !scene.raycast(origin, dir, distance, &mut hit_call, None, &filter_data) {
+        return None;
+    }
+    hit_call
+        .touches()
+        .into_iter()
+        .filter(|hit| hit.shape.as_ref().and_then(|shape| shape.get_user_data::<PxShapeUserData>()).map(|ud| ud.entity) != Some(vehicle))
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
+
+/// Pushes the vehicle up with the suspension of the wheel and along the ground with its tire, and moves the wheel to where it is
+fn update_wheel(world: &mut World, id: EntityId, vehicle: EntityId, body: PxRigidDynamicRef, wheel: Wheel, mass: f32, timestep: f32) {
+    let controls = Controls {
+        throttle: world.get(vehicle, vehicle_throttle()).unwrap_or(0.).clamp(-1., 1.),
+        steer: world.get(vehicle, vehicle_steer()).unwrap_or(0.).clamp(-1., 1.),
+        brake: world.get(vehicle, vehicle_brake()).unwrap_or(0.).clamp(0., 1.),
+        engine_torque: world.get(vehicle, vehicle_engine_torque()).unwrap_or(800.),
+    };
+    let pose = body.get_global_pose();
+    let wheel_rotation = pose.rotation() * Quat::from_rotation_z(controls.steer * wheel.max_steer);
+    let up = pose.rotation() * Vec3::Z;
+    let mount = pose.translation() + pose.rotation() * wheel.offset;
+    let previous_compression = world.get(id, vehicle_wheel_compression()).unwrap_or(0.);
+
+    let hit = raycast_ground(world.resource(main_physics_scene()), vehicle, mount, -up, wheel.suspension_length + wheel.radius);
+    let (compression, rolling_speed) = match hit {
+        Some(hit) => {
+            let compression = (wheel.suspension_length + wheel.radius - hit.distance).clamp(0., wheel.suspension_length);
+            let load = (wheel.stiffness * compression + wheel.damping * (compression - previous_compression) / timestep).max(0.);
+            body.add_force_at_pos(up * load, hit.position, None, None);
+
+            // The directions the tire rolls and slides in, along the ground
+            let along_ground = |dir: Vec3| (dir - hit.normal * hit.normal.dot(dir)).normalize_or_zero();
+            let (forward, side) = (along_ground(wheel_rotation * -Vec3::Y), along_ground(wheel_rotation * Vec3::X));
+            let velocity = body.get_velocity_at_pos(hit.position);
+            let (forward_speed, side_speed) = (velocity.dot(forward), velocity.dot(side));
+            // The forces are limited to what stops the wheel's share of the vehicle, so that it doesn't jitter when it stands still
+            let stopping_force = |speed: f32| speed.abs() * mass / timestep;
+
+            let mut forward_force = controls.throttle * controls.engine_torque * wheel.drive / wheel.radius;
+            if controls.brake > 0. {
+                forward_force -=
+                    forward_speed.signum() * (controls.brake * wheel.brake_torque / wheel.radius).min(stopping_force(forward_speed));
+            }
+            let grip = wheel.friction * load;
+            let slip_angle = side_speed.abs().atan2(forward_speed.abs());
+            let mut side_force =
+                -side_speed.signum() * (grip * friction_curve(slip_angle, wheel.friction_curve)).min(stopping_force(side_speed));
+            // Asking the tire for more than it can grip makes it slide
+            let total_force = vec2(forward_force, side_force).length();
+            if total_force > grip {
+                let scale = grip * wheel.friction_curve.y / total_force;
+                forward_force *= scale;
+                side_force *= scale;
+            }
+            body.add_force_at_pos(forward * forward_force + side * side_force, hit.position, None, None);
+            (compression, forward_speed)
+        }
+        None => (0., 0.),
+    };
+
+    let spin = world.get(id, vehicle_wheel_spin()).unwrap_or(0.) + rolling_speed / wheel.radius * timestep;
+    let wheel_translation = mount - up * (wheel.suspension_length - compression);
+    world.set(id, translation(), wheel_translation).ok();
+    world.set(id, rotation(), wheel_rotation * Quat::from_rotation_x(spin)).ok();
+    if world.has_component(id, vehicle_wheel_compression()) {
+        world.set(id, vehicle_wheel_compression(), compression).unwrap();
+        world.set(id, vehicle_wheel_spin(), spin).unwrap();
+    } else {
+        world.add_component(id, vehicle_wheel_compression(), compression).unwrap();
+        world.add_component(id, vehicle_wheel_spin(), spin).unwrap();
+    }
+}
+
+/// Simulates raycast vehicles: each wheel casts a ray down from its vehicle, and pushes the vehicle with its suspension and tire
+/// where the ray hits the ground. The forces only last one physics step, so these run before each of them.
+pub(crate) fn substep_systems() -> SystemGroup {
+    SystemGroup::new(
+        "physics/vehicle",
+        vec![query((vehicle_wheel(), vehicle_wheel_offset())).to_system(|q, world, qs, _| {
+            let timestep = *world.resource(physics_timestep());
+            let wheels = q.collect_cloned(world, qs);
+            let mut wheel_counts = HashMap::new();
+            for (_, (vehicle, _)) in &wheels {
+                *wheel_counts.entry(*vehicle).or_insert(0) += 1;
+            }
+            for (id, (vehicle, offset)) in wheels {
+                let body = match world.get(vehicle, rigid_actor()).ok().and_then(|actor| actor.to_rigid_dynamic()) {
+                    Some(body) => body,
+                    None => continue,
+                };
+                let wheel = Wheel::from_world(world, id, offset);
+                update_wheel(world, id, vehicle, body, wheel, body.get_mass() / wheel_counts[&vehicle] as f32, timestep);
+            }
+            for vehicle in wheel_counts.into_keys() {
+                if let Some(body) = world.get(vehicle, rigid_actor()).ok().and_then(|actor| actor.to_rigid_dynamic()) {
+                    let speed = body.get_linear_velocity().dot(body.get_global_pose().rotation() * -Vec3::Y);
+                    if world.has_component(vehicle, vehicle_speed()) {
+                        world.set(vehicle, vehicle_speed(), speed).unwrap();
+                    } else {
+                        world.add_component(vehicle, vehicle_speed(), speed).unwrap();
+                    }
+                }
+            }
+        })],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use ambient_ecs::{EntityData, FrameEvent, System};
+    use glam::vec3;
+    use physxx::{AsPxRigidActor, PxBoxGeometry, PxRigidStaticRef, PxShape, PxTransform};
+
+    use super::*;
+    use crate::{physx::physics, test_world, wood_physics_material};
+
+    /// A 400 kg vehicle on flat ground, whose rear wheels are driven
+    fn spawn_vehicle(world: &mut World) -> (EntityId, PxRigidDynamicRef) {
+        let physics = world.resource(physics()).clone();
+        let material = world.resource(wood_physics_material()).clone();
+        let scene = *world.resource(main_physics_scene());
+        let ground = PxRigidStaticRef::new(physics.physics, &PxTransform::from_translation(vec3(0., 0., -0.5))).as_rigid_actor();
+        assert!(ground.attach_shape(&PxShape::new(physics.physics, &PxBoxGeometry::new(100., 100., 0.5), &[&material], Some(true), None)));
+        scene.add_actor(&ground);
+
+        let vehicle = EntityData::new().spawn(world);
+        let transform = PxTransform::from_translation(vec3(0., 0., 0.8));
+        let geometry = PxBoxGeometry::new(1., 2., 0.25);
+        let body = PxRigidDynamicRef::new_with_geometry(&physics.physics, &transform, &geometry, &material, 100., &PxTransform::identity());
+        for shape in body.get_shapes() {
+            shape.set_user_data(PxShapeUserData { entity: vehicle, ..Default::default() });
+        }
+        scene.add_actor(&body);
+        world.add_component(vehicle, rigid_actor(), body.as_rigid_actor()).unwrap();
+        for (x, y) in [(-1., -1.5), (1., -1.5), (-1., 1.5), (1., 1.5)] {
+            let wheel = EntityData::new().set(vehicle_wheel(), vehicle).set(vehicle_wheel_offset(), vec3(x, y, -0.25));
+            let wheel = if y > 0. { wheel.set(vehicle_wheel_drive(), 0.5) } else { wheel };
+            wheel.spawn(world);
+        }
+        (vehicle, body)
+    }
+
+    /// Runs the physics steps of `seconds` of simulation, applying the forces of the vehicles before each step
+    fn simulate(world: &mut World, seconds: f32, timestep: f32) {
+        world.set(world.resource_entity(), physics_timestep(), timestep).unwrap();
+        let scene = *world.resource(main_physics_scene());
+        let mut systems = substep_systems();
+        for _ in 0..(seconds / timestep).round() as usize {
+            systems.run(world, &FrameEvent);
+            scene.simulate(timestep);
+            scene.fetch_results(true);
+        }
+    }
+
+    #[test]
+    fn rests_on_its_suspension() {
+        let mut world = test_world();
+        let (_, body) = spawn_vehicle(&mut world);
+        simulate(&mut world, 3., 1. / 60.);
+
+        // Each wheel carries a quarter of the weight of the vehicle
+        let compression = 100. * crate::GRAVITY / 30000.;
+        for (_, compressed) in query(vehicle_wheel_compression()).iter(&world, None) {
+            assert!((compressed - compression).abs() < 0.005, "{compressed} != {compression}");
+        }
+        assert!(body.get_linear_velocity().length() < 0.05);
+    }
+
+    #[test]
+    fn drives_the_same_at_any_timestep() {
+        let speed_after_driving = |timestep: f32| {
+            let mut world = test_world();
+            let (vehicle, body) = spawn_vehicle(&mut world);
+            simulate(&mut world, 1., timestep);
+            world.add_component(vehicle, vehicle_throttle(), 1.).unwrap();
+            simulate(&mut world, 1., timestep);
+            // It drives towards -Y
+            assert!(body.get_global_pose().translation().y < -1.);
+            world.get(vehicle, vehicle_speed()).unwrap()
+        };
+        let (fast, slow) = (speed_after_driving(1. / 120.), speed_after_driving(1. / 30.));
+        assert!(fast > 3.);
+        assert!((fast - slow).abs() < fast * 0.05, "{fast} != {slow}");
+    }
+
+    #[test]
+    fn friction_curve_peaks_and_slides() {
+        let curve = vec2(0.2, 0.6);
+        assert_eq!(friction_curve(0., curve), 0.);
+        assert!((friction_curve(0.1, curve) - 0.5).abs() < 1e-6);
+        assert_eq!(friction_curve(0.2, curve), 1.);
+        assert!((friction_curve(0.3, curve) - 0.8).abs() < 1e-6);
+        assert!((friction_curve(0.4, curve) - 0.6).abs() < 1e-6);
+        assert!((friction_curve(1., curve) - 0.6).abs() < 1e-6);
+    }
+}
@@ -3,10 +3,11 @@ use std::{collections::HashMap, sync::Arc};
 use ambient_core::{asset_cache, async_ecs::async_run, hierarchy::children, runtime};
 use ambient_decals::decal;
 use ambient_ecs::{
-    components, query, query_mut, Debuggable, Description, DeserWorldWithWarnings, EntityId, Name, Networked, Store, SystemGroup, World,
+    components, query, query_mut, ComponentDesc, Debuggable, Description, DeserWorldWithWarnings, EntityId, Name, Networked, Store,
+    SystemGroup, World,
 };
 use ambient_model::model_from_url;
-use ambient_physics::collider::collider;
+use ambient_physics::{collider::collider, ragdoll::ragdoll_from_url};
 use ambient_std::{
     asset_cache::{AssetCache, AsyncAssetKey, AsyncAssetKeyExt},
     asset_url::AssetUrl,
@@ -29,14 +30,19 @@ components!("prefab", {
         Description["If attached, this entity was built from a prefab that has finished spawning."]
     ]
     spawned: (),
+    @[
+        Name["Prefab components"],
+        Description["The components that were added to this entity from its prefab. They are removed before the prefab is applied again, e.g. when it's reloaded."]
+    ]
+    prefab_components: Vec<ComponentDesc>,
 });
 
 pub fn systems() -> SystemGroup {
     SystemGroup::new(
         "prefab",
-        vec![query(prefab_from_url()).spawned().to_system(|q, world, qs, _| {
+        vec![query((prefab_from_url().changed(),)).to_system(|q, world, qs, _| {
             let mut to_load = HashMap::<String, Vec<EntityId>>::new();
-            for (id, url) in q.collect_cloned(world, qs) {
+            for (id, (url,)) in q.collect_cloned(world, qs) {
                 let url = if url.ends_with("/prefabs/main.json") { url } else { format!("{url}/prefabs/main.json") };
                 to_load.entry(url).or_default().push(id);
             }
@@ -51,9 +57,15 @@ pub fn systems() -> SystemGroup {
                     let base_ent_id = obj.resource(children())[0];
                     // TODO: This only handles prefabs with a single entity
                     let entity = obj.clone_entity(base_ent_id).unwrap();
+                    let components = entity.components();
                     async_run.run(move |world| {
                         for id in ids {
+                            if !world.exists(id) {
+                                continue;
+                            }
+                            remove_prefab_components(world, id);
                             world.add_components(id, entity.clone()).unwrap();
+                            world.add_component(id, prefab_components(), components.clone()).unwrap();
                             world.add_component(id, spawned(), ()).unwrap();
                         }
                     });
@@ -63,6 +75,14 @@ pub fn systems() -> SystemGroup {
     )
 }
 
+/// Removes the components a prefab previously added to the entity. Anything spawned for them, such as the
+/// nodes of a model, is cleaned up by the systems of those components.
+fn remove_prefab_components(world: &mut World, id: EntityId) {
+    let Ok(mut components) = world.get_cloned(id, prefab_components()) else { return };
+    components.retain(|&component| world.has_component_ref(id, component));
+    world.remove_components(id, components).ok();
+}
+
 #[derive(Debug, Clone)]
 pub struct PrefabFromUrl(pub AssetUrl);
 #[async_trait]
@@ -70,15 +90,19 @@ impl AsyncAssetKey<Result<Arc<World>, AssetError>> for PrefabFromUrl {
     async fn load(self, assets: AssetCache) -> Result<Arc<World>, AssetError> {
         let obj_url = self.0.abs().context(format!("PrefabFromUrl got relative url: {}", self.0))?;
         let data = BytesFromUrl::new(obj_url.clone(), true).get(&assets).await?;
-        let DeserWorldWithWarnings { mut world, warnings } = tokio::task::block_in_place(|| serde_json::from_slice(&data))
+        let DeserWorldWithWarnings { mut world, warnings, migrated } = tokio::task::block_in_place(|| serde_json::from_slice(&data))
             .with_context(|| format!("Failed to deserialize object2 from url {obj_url}"))?;
         warnings.log_warnings();
+        migrated.log_migrations();
         for (_id, (url,), _) in query_mut((model_from_url(),), ()).iter(&mut world, None) {
             *url = AssetUrl::parse(&url).context("Invalid model url")?.resolve(&obj_url).context("Failed to resolve model url")?.into();
         }
         for (_id, (def,), _) in query_mut((collider(),), ()).iter(&mut world, None) {
             def.resolve(&obj_url).context("Failed to resolve collider")?;
         }
+        for (_id, (url,), _) in query_mut((ragdoll_from_url(),), ()).iter(&mut world, None) {
+            *url = AssetUrl::parse(&url).context("Invalid ragdoll url")?.resolve(&obj_url).context("Failed to resolve ragdoll url")?.into();
+        }
         for (_id, (def,), _) in query_mut((decal(),), ()).iter(&mut world, None) {
             *def = def.resolve(&obj_url).context("Failed to resolve decal")?.into();
         }
@@ -1,8 +1,8 @@
 use std::{collections::HashMap, fmt::Display};
 
 use ambient_ecs::{
-    components, ExternalComponentAttributes, ExternalComponentDesc, ExternalComponentFlagAttributes, Networked, PrimitiveComponentType,
-    Store,
+    components, ComponentMigration, ExternalComponentAttributes, ExternalComponentDesc, ExternalComponentFlagAttributes, Networked,
+    PrimitiveComponentType, Store,
 };
 use serde::{de::Visitor, Deserialize, Serialize};
 use thiserror::Error;
@@ -22,18 +22,26 @@ pub struct Manifest {
     pub components: HashMap<IdentifierPathBuf, NamespaceOrComponent>,
     #[serde(default)]
     pub concepts: HashMap<Identifier, Concept>,
+    #[serde(default)]
+    pub migrations: Vec<Migration>,
+    #[serde(default)]
+    pub physics: Physics,
 }
 impl Manifest {
     pub fn parse(manifest: &str) -> Result<Self, toml::de::Error> {
         toml::from_str(manifest)
     }
 
-    pub fn all_defined_components(&self, global_namespace: bool) -> Result<Vec<ExternalComponentDesc>, &'static str> {
-        let project_path: Vec<_> = if global_namespace {
+    fn project_path(&self, global_namespace: bool) -> Vec<Identifier> {
+        if global_namespace {
             vec![]
         } else {
             self.project.organization.iter().chain(std::iter::once(&self.project.id)).cloned().collect()
-        };
+        }
+    }
+
+    pub fn all_defined_components(&self, global_namespace: bool) -> Result<Vec<ExternalComponentDesc>, &'static str> {
+        let project_path = self.project_path(global_namespace);
 
         self.components
             .iter()
@@ -49,12 +57,37 @@ impl Manifest {
                     attributes: ExternalComponentAttributes {
                         name: Some(component.name.clone()),
                         description: Some(component.description.clone()),
+                        version: component.version,
                         flags: ExternalComponentFlagAttributes::from_iter(component.attributes.iter().map(|s| s.as_str())),
                     },
                 })
             })
             .collect::<Result<Vec<_>, _>>()
     }
+
+    /// Returns the migrations defined by this manifest, with all component paths resolved to their full paths
+    pub fn all_defined_migrations(&self, global_namespace: bool) -> Result<Vec<ComponentMigration>, &'static str> {
+        let project_path = self.project_path(global_namespace);
+        let full_path = |id: &IdentifierPathBuf| IdentifierPathBuf(project_path.iter().chain(id.0.iter()).cloned().collect()).to_string();
+
+        self.migrations
+            .iter()
+            .map(|migration| {
+                Ok(match migration {
+                    Migration::Rename { from, to } => ComponentMigration::Rename { from: full_path(from), to: full_path(to) },
+                    Migration::Convert { component, from_type, version } => {
+                        ComponentMigration::Convert { component: full_path(component), from_type: from_type.try_into()?, version: *version }
+                    }
+                    Migration::Split { from, to } => {
+                        ComponentMigration::Split { from: full_path(from), to: to.iter().map(full_path).collect() }
+                    }
+                    Migration::Merge { from, to } => {
+                        ComponentMigration::Merge { from: from.iter().map(full_path).collect(), to: full_path(to) }
+                    }
+                })
+            })
+            .collect()
+    }
 }
 
 #[derive(Deserialize, Clone, Debug, PartialEq)]
@@ -68,6 +101,22 @@ pub struct Project {
     pub organization: Option<Identifier>,
 }
 
+/// How the physics of the project are simulated
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Physics {
+    /// How many times per second the physics are stepped. Every step advances the simulation by the same amount of time.
+    pub simulation_rate: u32,
+    /// The most steps that are taken in one server tick. If more steps fit in the time since the previous tick, the simulation
+    /// slows down instead.
+    pub max_substeps: u32,
+}
+impl Default for Physics {
+    fn default() -> Self {
+        Self { simulation_rate: 60, max_substeps: 4 }
+    }
+}
+
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum NamespaceOrComponent {
@@ -99,6 +148,9 @@ pub struct Component {
     pub type_: ComponentType,
     #[serde(default)]
     pub attributes: Vec<String>,
+    /// The version of the component's data layout. Bump this when changing the type, and add a [Migration] to upgrade old data.
+    #[serde(default)]
+    pub version: u32,
 }
 
 #[derive(Deserialize, Clone, Debug, PartialEq)]
@@ -133,6 +185,16 @@ impl TryFrom<&ComponentType> for PrimitiveComponentType {
     }
 }
 
+/// Upgrades stored data (prefabs and snapshots) after a component has been renamed or retyped. See [ComponentMigration].
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum Migration {
+    Rename { from: IdentifierPathBuf, to: IdentifierPathBuf },
+    Convert { component: IdentifierPathBuf, from_type: ComponentType, version: u32 },
+    Split { from: IdentifierPathBuf, to: Vec<IdentifierPathBuf> },
+    Merge { from: Vec<IdentifierPathBuf>, to: IdentifierPathBuf },
+}
+
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct Concept {
     pub name: String,
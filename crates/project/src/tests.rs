@@ -2,7 +2,12 @@ use std::collections::HashMap;
 
 use ambient_ecs::primitive_component_definitions;
 
-use crate::{Component, ComponentType, Concept, Identifier, IdentifierPathBuf, Manifest, Namespace, Project, Version, VersionError};
+use ambient_ecs::{ComponentMigration, PrimitiveComponentType};
+
+use crate::{
+    Component, ComponentType, Concept, Identifier, IdentifierPathBuf, Manifest, Migration, Namespace, Physics, Project, Version,
+    VersionError,
+};
 
 #[test]
 fn can_parse_tictactoe_toml() {
@@ -39,7 +44,8 @@ fn can_parse_tictactoe_toml() {
                     name: "Cell".to_string(),
                     description: "The ID of the cell this player is in".to_string(),
                     type_: ComponentType::String("I32".to_string()),
-                    attributes: vec!["Store".to_string()]
+                    attributes: vec!["Store".to_string()],
+                    version: 0,
                 }
                 .into()
             )]),
@@ -52,6 +58,8 @@ fn can_parse_tictactoe_toml() {
                     components: HashMap::from_iter([(IdentifierPathBuf::new("cell").unwrap(), toml::Value::Integer(0))])
                 }
             )]),
+            migrations: vec![],
+            physics: Physics::default(),
         })
     )
 }
@@ -91,16 +99,77 @@ fn can_parse_manifest_with_namespaces() {
                         name: "Main Scene".to_string(),
                         description: "".to_string(),
                         type_: ComponentType::String("Empty".to_string()),
-                        attributes: vec![]
+                        attributes: vec![],
+                        version: 0,
                     }
                     .into()
                 )
             ]),
             concepts: HashMap::new(),
+            migrations: vec![],
+            physics: Physics::default(),
         })
     )
 }
 
+#[test]
+fn can_parse_manifest_with_migrations() {
+    const TOML: &str = r#"
+    [project]
+    id = "tictactoe"
+    version = "0.0.1"
+
+    [components]
+    cell = { type = "F32", name = "Cell", description = "", attributes = ["Store"], version = 1 }
+
+    [[migrations]]
+    type = "Rename"
+    from = "old_cell"
+    to = "cell"
+
+    [[migrations]]
+    type = "Convert"
+    component = "cell"
+    from_type = "I32"
+    version = 1
+    "#;
+
+    let manifest = Manifest::parse(TOML).unwrap();
+    assert_eq!(
+        manifest.migrations,
+        vec![
+            Migration::Rename { from: IdentifierPathBuf::new("old_cell").unwrap(), to: IdentifierPathBuf::new("cell").unwrap() },
+            Migration::Convert {
+                component: IdentifierPathBuf::new("cell").unwrap(),
+                from_type: ComponentType::String("I32".to_string()),
+                version: 1
+            },
+        ]
+    );
+    assert_eq!(
+        manifest.all_defined_migrations(false),
+        Ok(vec![
+            ComponentMigration::Rename { from: "tictactoe::old_cell".to_string(), to: "tictactoe::cell".to_string() },
+            ComponentMigration::Convert { component: "tictactoe::cell".to_string(), from_type: PrimitiveComponentType::I32, version: 1 },
+        ])
+    );
+    assert_eq!(manifest.all_defined_components(false).unwrap()[0].attributes.version, 1);
+}
+
+#[test]
+fn can_parse_manifest_with_physics() {
+    const TOML: &str = r#"
+    [project]
+    id = "tictactoe"
+    version = "0.0.1"
+
+    [physics]
+    simulation_rate = 120
+    "#;
+
+    assert_eq!(Manifest::parse(TOML).unwrap().physics, Physics { simulation_rate: 120, max_substeps: 4 });
+}
+
 #[test]
 fn can_validate_identifiers() {
     use Identifier as I;
@@ -2,7 +2,7 @@ use std::{sync::Arc, time::Instant};
 
 use ambient_core::{
     camera::{far, fog, get_active_camera, projection_view},
-    transform::{get_world_position, get_world_rotation, local_to_world},
+    transform::{get_world_position, local_to_world},
 };
 use ambient_ecs::{Component, ECSError, World};
 use ambient_gpu::{
@@ -15,7 +15,7 @@ use ambient_std::asset_cache::{AssetCache, SyncAssetKeyExt};
 use glam::{vec3, Mat4, UVec2, Vec3, Vec4};
 use wgpu::BindGroup;
 
-use super::{fog_color, get_active_sun, light_ambient, light_diffuse, RenderTarget, ShadowCameraData};
+use super::{fog_color, get_active_sun, get_light_direction, light_ambient, light_diffuse, RenderTarget, ShadowCameraData};
 use crate::{fog_density, fog_height_falloff};
 
 #[repr(C)]
@@ -244,7 +244,7 @@ impl ForwardGlobals {
                 }
             }
 
-            update(&mut p.sun_direction, get_world_rotation(world, sun), |v| v.mul_vec3(Vec3::X).extend(1.));
+            update(&mut p.sun_direction, get_light_direction(world, sun), |v| v.extend(1.));
             update(&mut p.sun_diffuse, world.get(sun, light_diffuse()), |v| v.extend(1.));
             update(&mut p.sun_ambient, world.get(sun, light_ambient()), |v| v.extend(1.));
             update(&mut p.fog_color, world.get(sun, fog_color()), |v| v.extend(1.));
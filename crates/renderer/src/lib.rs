@@ -4,10 +4,10 @@ use ambient_core::{
     gpu_components,
     gpu_ecs::{ComponentToGpuSystem, GpuComponentFormat, GpuWorldShaderModuleKey, GpuWorldSyncEvent},
     mesh,
-    transform::get_world_rotation,
+    transform::{get_world_rotation, local_to_world},
 };
 use ambient_ecs::{
-    components, query_mut, Debuggable, Description, EntityId, MakeDefault, Name, Networked, Resource, Store, SystemGroup, World,
+    components, query_mut, Debuggable, Description, ECSError, EntityId, MakeDefault, Name, Networked, Resource, Store, SystemGroup, World,
 };
 use ambient_gpu::{
     mesh_buffer::{get_mesh_buffer_types, GpuMesh, MESH_BUFFER_TYPES_WGSL},
@@ -91,7 +91,7 @@ components!("rendering", {
     @[
         Debuggable, Networked, Store,
         Name["Light diffuse"],
-        Description["The diffuse light color of the `sun`."]
+        Description["The diffuse light color of the `sun`, `directional_light`, `point_light` or `spot_light`.\nLights imported from glTF are converted to the irradiance in W/m² for directional lights, and to the power in W for point and spot lights."]
     ]
     light_diffuse: Vec3,
     @[
@@ -100,6 +100,42 @@ components!("rendering", {
         Description["The ambient light color of the `sun`."]
     ]
     light_ambient: Vec3,
+    @[
+        Debuggable, Networked, Store,
+        Name["Directional light"],
+        Description["Marks this entity as a directional light, which emits `light_diffuse` along its X axis.\nThis is only metadata (i.e. from an imported model); add `sun` to light the scene with it."]
+    ]
+    directional_light: (),
+    @[
+        Debuggable, Networked, Store,
+        Name["Point light"],
+        Description["Marks this entity as a point light, which emits `light_diffuse` in all directions from its position.\nThis is only metadata (i.e. from an imported model); the renderer does not light the scene with it yet."]
+    ]
+    point_light: (),
+    @[
+        Debuggable, Networked, Store,
+        Name["Spot light"],
+        Description["Marks this entity as a spot light, which emits `light_diffuse` in a cone around its X axis.\nThe cone is described by `spot_light_inner_angle` and `spot_light_outer_angle`.\nThis is only metadata (i.e. from an imported model); the renderer does not light the scene with it yet."]
+    ]
+    spot_light: (),
+    @[
+        Debuggable, Networked, Store,
+        Name["Spot light inner angle"],
+        Description["The angle from the direction of this `spot_light`, in radians, within which the light is at full intensity."]
+    ]
+    spot_light_inner_angle: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Spot light outer angle"],
+        Description["The angle from the direction of this `spot_light`, in radians, at which the light has faded out completely."]
+    ]
+    spot_light_outer_angle: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Light range"],
+        Description["The distance from this `point_light` or `spot_light`, in meters, at which the light has faded out completely.\nIf not attached, the range is unlimited."]
+    ]
+    light_range: f32,
     @[
         Debuggable, Networked, Store,
         Name["Fog color"],
@@ -186,10 +222,15 @@ pub fn get_active_sun(world: &World, scene: Component<()>) -> Option<EntityId> {
     query((scene, sun())).iter(world, None).max_by_key(|(_, (_, x))| OrderedFloat(**x)).map(|(id, _)| id)
 }
 pub fn get_sun_light_direction(world: &World, scene: Component<()>) -> Vec3 {
-    get_active_sun(world, scene)
-        .and_then(|sun| get_world_rotation(world, sun).ok())
-        .map(|rot| rot.mul_vec3(Vec3::X))
-        .unwrap_or(default_sun_direction())
+    get_active_sun(world, scene).and_then(|sun| get_light_direction(world, sun).ok()).unwrap_or(default_sun_direction())
+}
+/// The direction of a `sun` or `spot_light`, which is its X axis in world space.
+/// The full transform is used rather than just the rotation, as the latter is flipped in mirrored hierarchies (such as imported models).
+pub fn get_light_direction(world: &World, light: EntityId) -> Result<Vec3, ECSError> {
+    match world.get(light, local_to_world()) {
+        Ok(ltw) => Ok(ltw.transform_vector3(Vec3::X).normalize()),
+        Err(_) => get_world_rotation(world, light).map(|rot| rot.mul_vec3(Vec3::X)),
+    }
 }
 
 #[derive(Clone, Debug)]
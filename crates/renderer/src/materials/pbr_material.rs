@@ -103,7 +103,8 @@ pub struct PbrMaterialParams {
     pub alpha_cutoff: f32,
     pub metallic: f32,
     pub roughness: f32,
-    pub _padding: u32,
+    /// 1 if the normal map only stores x and y (i.e. when block compressed with BC5), so that z has to be reconstructed
+    pub two_channel_normalmap: u32,
 }
 impl Default for PbrMaterialParams {
     fn default() -> Self {
@@ -113,7 +114,7 @@ impl Default for PbrMaterialParams {
             alpha_cutoff: 0.5,
             metallic: 1.,
             roughness: 1.,
-            _padding: Default::default(),
+            two_channel_normalmap: 0,
         }
     }
 }
@@ -321,14 +322,14 @@ impl AsyncAssetKey<Result<Arc<PbrMaterial>, AssetError>> for PbrMaterialFromUrl
             None => PixelTextureViewKey::white().get(&assets),
         };
         let normalmap = if let Some(normalmap) = &self.normalmap {
-            Arc::new(
-                TextureFromUrl { url: normalmap.clone().unwrap_abs(), format: wgpu::TextureFormat::Rgba8Unorm }
-                    .get(&assets)
-                    .await?
-                    .create_view(&Default::default()),
-            )
+            Some(TextureFromUrl { url: normalmap.clone().unwrap_abs(), format: wgpu::TextureFormat::Rgba8Unorm }.get(&assets).await?)
         } else {
-            DefaultNormalMapViewKey.get(&assets)
+            None
+        };
+        let two_channel_normalmap = matches!(&normalmap, Some(normalmap) if normalmap.format == wgpu::TextureFormat::Bc5RgUnorm);
+        let normalmap = match normalmap {
+            Some(normalmap) => Arc::new(normalmap.create_view(&Default::default())),
+            None => DefaultNormalMapViewKey.get(&assets),
         };
 
         let metallic_roughness = if let Some(metallic_roughness) = self.metallic_roughness {
@@ -348,7 +349,7 @@ impl AsyncAssetKey<Result<Arc<PbrMaterial>, AssetError>> for PbrMaterialFromUrl
             alpha_cutoff: self.alpha_cutoff.unwrap_or(0.01),
             metallic: self.metallic,
             roughness: self.roughness,
-            _padding: Default::default(),
+            two_channel_normalmap: two_channel_normalmap as u32,
         };
 
         let name = self.name.or(self.base_color.map(|x| x.to_string())).unwrap_or_default();
@@ -4,6 +4,7 @@ struct PbrMaterialParams {
     alpha_cutoff: f32,
     metallic: f32,
     roughness: f32,
+    two_channel_normalmap: u32,
 };
 
 @group(#MATERIAL_BIND_GROUP)
@@ -39,7 +40,10 @@ fn get_material(in: MaterialInput) -> MaterialOutput {
     out.base_color = color.rgb;
     out.emissive_factor = pbr_params.emissive_factor.rgb;
     out.shading = 1.;
-    let normal = textureSample(normal_texture, base_color_sampler, in.texcoord).xyz * 2. - 1.;
+    var normal = textureSample(normal_texture, base_color_sampler, in.texcoord).xyz * 2. - 1.;
+    if (pbr_params.two_channel_normalmap != 0u) {
+        normal.z = sqrt(max(1. - dot(normal.xy, normal.xy), 0.));
+    }
     out.normal = in.normal_matrix * normal;
     return out;
 }
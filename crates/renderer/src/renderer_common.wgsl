@@ -12,13 +12,27 @@ struct ModelToWorld {
 }
 
 /// Transform a vertex from model space to world space by applying
-// joint matrices (if applicable) and transformation matrices
+// morph targets and joint matrices (if applicable) and transformation matrices
 fn model_to_world(loc: vec2<u32>, mesh_index: u32, vertex_index: u32) -> ModelToWorld {
     let model = get_entity_mesh_to_world(loc);
-    let pos = vec4<f32>(get_mesh_position(mesh_index, vertex_index), 1.0);
-    let normal = vec4<f32>(get_mesh_normal(mesh_index, vertex_index), 0.0);
+    var pos = vec4<f32>(get_mesh_position(mesh_index, vertex_index), 1.0);
+    var normal = vec4<f32>(get_mesh_normal(mesh_index, vertex_index), 0.0);
     let tangent = vec4<f32>(get_mesh_tangent(mesh_index, vertex_index), 0.0);
 
+    if (has_entity_morph(loc)) {
+        let metadata = mesh_metadatas.data[mesh_index];
+        let weights_offset = get_entity_morph(loc);
+        for (var i: u32 = 0u; i < metadata.morph_target_count; i = i + 1u) {
+            // The weights are packed into the skins buffer, 16 per matrix
+            let weight = skins.data[weights_offset + i / 16u][(i / 4u) % 4u][i % 4u];
+            // The targets follow the positions of the mesh, and each holds the position offsets of all vertices, followed by the normal offsets
+            let target_offset = metadata.position_offset + metadata.vertex_count + i * metadata.vertex_count * 2u + vertex_index;
+            pos = pos + vec4<f32>(mesh_position.data[target_offset], 0.0) * weight;
+            normal = normal + vec4<f32>(mesh_position.data[target_offset + metadata.vertex_count], 0.0) * weight;
+        }
+        normal = vec4<f32>(normalize(normal.xyz), 0.0);
+    }
+
     if (has_entity_skin(loc)) {
         let joint = get_mesh_joint(mesh_index, vertex_index);
         let weight = get_mesh_weight(mesh_index, vertex_index);
@@ -1,6 +1,6 @@
 use std::{num::NonZeroU32, sync::Arc};
 
-use ambient_core::{camera::Camera, gpu_ecs::ENTITIES_BIND_GROUP, main_scene, transform::*};
+use ambient_core::{camera::Camera, gpu_ecs::ENTITIES_BIND_GROUP, main_scene};
 use ambient_ecs::{ArchetypeFilter, World};
 use ambient_gpu::{
     gpu::GpuKey,
@@ -9,16 +9,16 @@ use ambient_gpu::{
 };
 use ambient_std::asset_cache::{AssetCache, SyncAssetKeyExt};
 use bytemuck::{Pod, Zeroable};
-use glam::{Mat4, Vec3};
+use glam::Mat4;
 use itertools::Itertools;
 use smallvec::SmallVec;
 use wgpu::DepthBiasState;
 
 use super::{
-    cast_shadows, get_active_sun, FSMain, RendererCollectState, RendererResources, ShadowAndUIGlobals, TreeRenderer, TreeRendererConfig,
-    GLOBALS_BIND_GROUP, MAX_SHADOW_CASCADES, RESOURCES_BIND_GROUP,
+    cast_shadows, get_sun_light_direction, FSMain, RendererCollectState, RendererResources, ShadowAndUIGlobals, TreeRenderer,
+    TreeRendererConfig, GLOBALS_BIND_GROUP, MAX_SHADOW_CASCADES, RESOURCES_BIND_GROUP,
 };
-use crate::{default_sun_direction, RendererConfig};
+use crate::RendererConfig;
 
 pub struct ShadowsRenderer {
     renderer: TreeRenderer,
@@ -107,11 +107,7 @@ impl ShadowsRenderer {
     ) {
         let main_camera = Camera::get_active(world, main_scene()).unwrap_or_default();
 
-        let sun_direction = if let Some(sun) = get_active_sun(world, main_scene()) {
-            get_world_rotation(world, sun).unwrap().mul_vec3(Vec3::X)
-        } else {
-            default_sun_direction()
-        };
+        let sun_direction = get_sun_light_direction(world, main_scene());
 
         self.renderer.update(world);
 
@@ -1,6 +1,9 @@
-use std::sync::{
-    atomic::{AtomicU32, Ordering},
-    Arc,
+use std::{
+    ops::Range,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
 };
 
 use ambient_core::{
@@ -38,9 +41,18 @@ components!("rendering", {
 
     @[Networked, Store]
     joints_by_fbx_id: Vec<i64>,
+
+    @[
+        Networked, Store,
+        Name["Morph weights"],
+        Description["The weight of each morph target (blend shape) of the meshes of this entity.\nThese are usually between 0 and 1, where 0 leaves the mesh unchanged."]
+    ]
+    morph_weights: Vec<f32>,
+    morph: Morph,
 });
 gpu_components! {
     skin() => skin: GpuComponentFormat::U32,
+    morph() => morph: GpuComponentFormat::U32,
 }
 
 #[derive(Debug, Clone)]
@@ -63,10 +75,76 @@ impl SyncAssetKey<Arc<Mutex<SkinsBuffer>>> for SkinsBufferKey {
     }
 }
 
+/// The morph weights of an entity, which are packed into the [`SkinsBuffer`], 16 weights per matrix
+#[derive(Debug, Clone)]
+pub struct Morph {
+    offset: u32,
+    weight_count: u32,
+}
+impl Morph {
+    pub fn get_offset(&self) -> u32 {
+        self.offset
+    }
+    fn size(&self) -> u32 {
+        morph_weights_size(self.weight_count)
+    }
+}
+
+/// The number of matrices needed to store `weight_count` morph weights
+fn morph_weights_size(weight_count: u32) -> u32 {
+    (weight_count + 15) / 16
+}
+
+/// Keeps track of which parts of a buffer are in use, so that the parts that were freed can be reused
+#[derive(Debug, Default)]
+struct BufferRanges {
+    len: u32,
+    free: Vec<Range<u32>>,
+}
+impl BufferRanges {
+    /// Returns the offset of `size` items. The buffer needs to be at least [`Self::len`] long afterwards
+    fn allocate(&mut self, size: u32) -> u32 {
+        if let Some(index) = self.free.iter().position(|range| range.len() as u32 >= size) {
+            let range = &mut self.free[index];
+            let offset = range.start;
+            range.start += size;
+            if range.start == range.end {
+                self.free.remove(index);
+            }
+            offset
+        } else {
+            let offset = self.len;
+            self.len += size;
+            offset
+        }
+    }
+    fn free(&mut self, offset: u32, size: u32) {
+        if size == 0 {
+            return;
+        }
+        let index = self.free.partition_point(|range| range.start < offset);
+        self.free.insert(index, offset..offset + size);
+        // Merge with the neighbours, so that large allocations can reuse them too
+        if index + 1 < self.free.len() && self.free[index].end == self.free[index + 1].start {
+            self.free[index].end = self.free.remove(index + 1).end;
+        }
+        if index > 0 && self.free[index - 1].end == self.free[index].start {
+            self.free[index - 1].end = self.free.remove(index).end;
+        }
+        if self.free.last().map(|range| range.end == self.len).unwrap_or_default() {
+            self.len = self.free.pop().unwrap().start;
+        }
+    }
+    fn len(&self) -> u32 {
+        self.len
+    }
+}
+
 // TODO: The skins are currently leaking memory as they are never cleaned up. Need to implement something similar to how MeshBuffer
 // works; keep an index buffer and a data buffer, and re-use indices
 pub struct SkinsBuffer {
     pub buffer: TypedBuffer<Mat4>,
+    ranges: BufferRanges,
 }
 impl SkinsBuffer {
     fn new(gpu: Arc<Gpu>) -> Self {
@@ -78,51 +156,153 @@ impl SkinsBuffer {
                 1,
                 wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
             ),
+            ranges: BufferRanges { len: 1, free: Vec::new() },
+        }
+    }
+    fn allocate(&mut self, size: u32) -> u32 {
+        let offset = self.ranges.allocate(size);
+        if self.ranges.len() as u64 > self.buffer.len() {
+            self.buffer.resize(self.ranges.len() as u64, true);
         }
+        offset
     }
     pub fn create(&mut self, size: u32) -> Skin {
-        let skin = Skin(Arc::new(AtomicU32::new(self.buffer.len() as u32)));
-        self.buffer.resize(self.buffer.len() + size as u64, true);
-        skin
+        Skin(Arc::new(AtomicU32::new(self.allocate(size))))
     }
     pub fn update(&self, skin: &Skin, joint_matrices: &[Mat4]) {
         self.buffer.write(skin.get_offset() as u64, joint_matrices);
     }
+    pub fn create_morph(&mut self, weight_count: u32) -> Morph {
+        Morph { offset: self.allocate(morph_weights_size(weight_count)), weight_count }
+    }
+    pub fn free_morph(&mut self, morph: &Morph) {
+        self.ranges.free(morph.offset, morph.size());
+    }
+    pub fn update_morph(&self, morph: &Morph, weights: &[f32]) {
+        let mut matrices = vec![0.; morph.size() as usize * 16];
+        let count = weights.len().min(matrices.len());
+        matrices[..count].copy_from_slice(&weights[..count]);
+        self.buffer.write(morph.offset as u64, &matrices.chunks_exact(16).map(Mat4::from_cols_slice).collect_vec());
+    }
 }
 
 pub fn skinning_systems() -> SystemGroup {
     SystemGroup::new(
         "skinning_systems",
-        vec![query((inv_local_to_world(), inverse_bind_matrices(), joints(), skin())).to_system(|q, world, qs, _| {
-            let skins_h = SkinsBufferKey.get(world.resource(asset_cache()));
-            let skins = skins_h.lock();
-            let mut commands = Commands::new();
-            for (id, (&inv_local_to_world, inverse_bind_matrices, joints, skin)) in q.iter(world, qs) {
-                let joint_matrices = joints
-                    .iter()
-                    .enumerate()
-                    .map(|(i, joint)| {
-                        inv_local_to_world
-                            * world.get(*joint, local_to_world()).unwrap()
-                            * *inverse_bind_matrices.get(i).unwrap_or(&glam::Mat4::IDENTITY)
-                    })
-                    .collect_vec();
-                skins.update(skin, &joint_matrices);
-                commands.set(id, self::joint_matrices(), joint_matrices);
-            }
-            commands.apply(world).unwrap();
-        })],
+        vec![
+            query(morph_weights()).excl(morph()).to_system(|q, world, qs, _| {
+                let skins_h = SkinsBufferKey.get(world.resource(asset_cache()));
+                let mut skins = skins_h.lock();
+                for (id, weights) in q.collect_cloned(world, qs) {
+                    let morph = skins.create_morph(weights.len() as u32);
+                    skins.update_morph(&morph, &weights);
+                    world.add_component(id, self::morph(), morph).unwrap();
+                }
+            }),
+            query((morph_weights().changed(), morph())).to_system(|q, world, qs, _| {
+                let skins_h = SkinsBufferKey.get(world.resource(asset_cache()));
+                let mut skins = skins_h.lock();
+                let mut commands = Commands::new();
+                for (id, (weights, morph)) in q.iter(world, qs) {
+                    if weights.len() as u32 == morph.weight_count {
+                        skins.update_morph(morph, weights);
+                    } else {
+                        skins.free_morph(morph);
+                        let morph = skins.create_morph(weights.len() as u32);
+                        skins.update_morph(&morph, weights);
+                        commands.set(id, self::morph(), morph);
+                    }
+                }
+                commands.apply(world).unwrap();
+            }),
+            query(morph()).excl(morph_weights()).to_system(|q, world, qs, _| {
+                for (id, _) in q.collect_cloned(world, qs) {
+                    world.remove_component(id, morph()).unwrap();
+                }
+            }),
+            query(morph()).despawned().to_system(|q, world, qs, _| {
+                let skins_h = SkinsBufferKey.get(world.resource(asset_cache()));
+                let mut skins = skins_h.lock();
+                for (_, morph) in q.iter(world, qs) {
+                    skins.free_morph(morph);
+                }
+            }),
+            query((inv_local_to_world(), inverse_bind_matrices(), joints(), skin())).to_system(|q, world, qs, _| {
+                let skins_h = SkinsBufferKey.get(world.resource(asset_cache()));
+                let skins = skins_h.lock();
+                let mut commands = Commands::new();
+                for (id, (&inv_local_to_world, inverse_bind_matrices, joints, skin)) in q.iter(world, qs) {
+                    let joint_matrices = joints
+                        .iter()
+                        .enumerate()
+                        .map(|(i, joint)| {
+                            inv_local_to_world
+                                * world.get(*joint, local_to_world()).unwrap()
+                                * *inverse_bind_matrices.get(i).unwrap_or(&glam::Mat4::IDENTITY)
+                        })
+                        .collect_vec();
+                    skins.update(skin, &joint_matrices);
+                    commands.set(id, self::joint_matrices(), joint_matrices);
+                }
+                commands.apply(world).unwrap();
+            }),
+        ],
     )
 }
 
 pub fn gpu_world_systems() -> SystemGroup<GpuWorldSyncEvent> {
     SystemGroup::new(
         "skinning/gpu_world",
-        vec![Box::new(MappedComponentToGpuSystem::new(
-            GpuComponentFormat::U32,
-            skin(),
-            gpu_components::skin(),
-            Box::new(|_, _, skin| skin.get_offset()),
-        ))],
+        vec![
+            Box::new(MappedComponentToGpuSystem::new(
+                GpuComponentFormat::U32,
+                skin(),
+                gpu_components::skin(),
+                Box::new(|_, _, skin| skin.get_offset()),
+            )),
+            Box::new(MappedComponentToGpuSystem::new(
+                GpuComponentFormat::U32,
+                morph(),
+                gpu_components::morph(),
+                Box::new(|_, _, morph| morph.get_offset()),
+            )),
+        ],
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_ranges() {
+        let mut ranges = BufferRanges::default();
+        let a = ranges.allocate(morph_weights_size(20));
+        let b = ranges.allocate(morph_weights_size(4));
+        let c = ranges.allocate(morph_weights_size(16));
+        assert_eq!((a, b, c), (0, 2, 3));
+        assert_eq!(ranges.len(), 4);
+
+        // Freed ranges are reused
+        ranges.free(a, 2);
+        assert_eq!(ranges.allocate(1), 0);
+        assert_eq!(ranges.allocate(1), 1);
+        assert_eq!(ranges.len(), 4);
+
+        // Resizing frees the old range, and only grows the buffer if no freed range is large enough
+        ranges.free(b, 1);
+        let b = ranges.allocate(morph_weights_size(40));
+        assert_eq!(b, 4);
+        assert_eq!(ranges.len(), 7);
+
+        // Adjacent free ranges are merged, and freeing the end shrinks the used length
+        ranges.free(0, 1);
+        ranges.free(1, 1);
+        assert_eq!(ranges.allocate(3), 0);
+        ranges.free(b, 3);
+        assert_eq!(ranges.len(), 4);
+        ranges.free(c, 1);
+        ranges.free(0, 3);
+        assert_eq!(ranges.len(), 0);
+    }
+}
@@ -107,6 +107,17 @@ impl AbsAssetUrl {
         url.set_path(&format!("{}.{}", url.path(), extension));
         Self(url)
     }
+    /// Replaces the extension: test.ktx2 -> test.png
+    pub fn with_extension(&self, extension: &str) -> Self {
+        let mut url = self.0.clone();
+        let path = url.path().to_string();
+        let stem = match path.rsplit_once('.') {
+            Some((stem, ext)) if !ext.contains('/') => stem,
+            _ => &path,
+        };
+        url.set_path(&format!("{stem}.{extension}"));
+        Self(url)
+    }
 
     #[cfg(target_arch = "wasm32")]
     pub fn to_file_path(&self) -> anyhow::Result<Option<PathBuf>> {
@@ -473,6 +484,7 @@ pub enum AssetType {
     /// Represents a vorbis backed file
     VorbisTrack,
     SoundGraph,
+    AnimationGraph,
 }
 
 impl AssetType {
@@ -590,6 +602,14 @@ impl GetAssetType for ColliderAssetType {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct AnimationGraphAssetType;
+impl GetAssetType for AnimationGraphAssetType {
+    fn asset_type() -> AssetType {
+        AssetType::AnimationGraph
+    }
+}
+
 #[test]
 fn test_join() {
     let obj = TypedAssetUrl::<PrefabAssetType>::parse("https://playdims.com/api/v1/assetdb/crates/RxH7k2ox5Ug6DNcqJhta/1.7.0/quixel_groundcover_wcwmchzja_2k_3dplant_ms_wcwmchzja_json0/objects/main.json").unwrap();
@@ -15,6 +15,16 @@ pub struct Mesh {
     pub joint_indices: Option<Vec<UVec4>>,
     pub joint_weights: Option<Vec<Vec4>>,
     pub indices: Option<Vec<u32>>,
+    pub morph_targets: Vec<MorphTarget>,
+}
+
+/// A blend shape of a mesh. The values are offsets from the base mesh, one per vertex, which are
+/// scaled by the weight of the target before being added to the vertex.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MorphTarget {
+    pub name: String,
+    pub positions: Vec<Vec3>,
+    pub normals: Option<Vec<Vec3>>,
 }
 
 impl std::fmt::Debug for Mesh {
@@ -29,6 +39,7 @@ impl std::fmt::Debug for Mesh {
             .field("joint_indices", &self.joint_indices.as_ref().map(|v| v.len()).unwrap_or_default())
             .field("joint_weights", &self.joint_weights.as_ref().map(|v| v.len()).unwrap_or_default())
             .field("indices", &self.indices.as_ref().map(|v| v.len()).unwrap_or_default())
+            .field("morph_targets", &self.morph_targets.iter().map(|t| &t.name).collect_vec())
             .finish()
     }
 }
@@ -45,6 +56,7 @@ impl Default for Mesh {
             joint_indices: None,
             joint_weights: None,
             indices: None,
+            morph_targets: Vec::new(),
         }
     }
 }
@@ -75,6 +87,14 @@ impl Mesh {
                 *p = transform.transform_vector3(vec3(p[0], p[1], p[2])).normalize();
             }
         }
+        for target in &mut self.morph_targets {
+            for p in &mut target.positions {
+                *p = transform.transform_vector3(*p);
+            }
+            for p in target.normals.iter_mut().flatten() {
+                *p = transform.transform_vector3(*p);
+            }
+        }
     }
     /// Flips indicies so that what was front facing will become back facing and vice versa
     pub fn invert_indicies(&mut self) {
@@ -95,6 +115,24 @@ impl Mesh {
             }
         }
     }
+    /// Adds the morph targets, scaled by `weights`, to the positions and normals of the mesh
+    pub fn apply_morph_targets(&mut self, weights: &[f32]) {
+        for (target, &weight) in self.morph_targets.iter().zip(weights) {
+            if weight == 0. {
+                continue;
+            }
+            if let Some(positions) = &mut self.positions {
+                for (position, offset) in positions.iter_mut().zip(&target.positions) {
+                    *position += *offset * weight;
+                }
+            }
+            if let (Some(normals), Some(offsets)) = (&mut self.normals, &target.normals) {
+                for (normal, offset) in normals.iter_mut().zip(offsets) {
+                    *normal = (*normal + *offset * weight).normalize_or_zero();
+                }
+            }
+        }
+    }
 
     #[profiling::function]
     pub fn append(&mut self, mut mesh: Mesh) {
@@ -120,6 +158,32 @@ impl Mesh {
         if let Some(x) = &mut self.indices {
             x.extend(mesh.indices.unwrap().into_iter().map(|i| i + indices_offset));
         }
+        // Vertices from a mesh without a matching target are left in place
+        let appended_count = self.positions.as_ref().unwrap().len() - indices_offset as usize;
+        let existing_count = indices_offset as usize;
+        if mesh.morph_targets.len() > self.morph_targets.len() {
+            let missing = mesh.morph_targets[self.morph_targets.len()..]
+                .iter()
+                .map(|target| MorphTarget { name: target.name.clone(), positions: vec![Vec3::ZERO; existing_count], normals: None })
+                .collect_vec();
+            self.morph_targets.extend(missing);
+        }
+        for (i, target) in self.morph_targets.iter_mut().enumerate() {
+            let other = mesh.morph_targets.get_mut(i);
+            let (positions, normals) = match other {
+                Some(other) => (std::mem::take(&mut other.positions), other.normals.take()),
+                None => (Vec::new(), None),
+            };
+            target.positions.resize(existing_count, Vec3::ZERO);
+            target.positions.extend(positions);
+            target.positions.resize(existing_count + appended_count, Vec3::ZERO);
+            if target.normals.is_some() || normals.is_some() {
+                let target_normals = target.normals.get_or_insert_with(Vec::new);
+                target_normals.resize(existing_count, Vec3::ZERO);
+                target_normals.extend(normals.unwrap_or_default());
+                target_normals.resize(existing_count + appended_count, Vec3::ZERO);
+            }
+        }
     }
 
     #[profiling::function]
@@ -167,6 +231,13 @@ impl Mesh {
             .drain(..)
             .map(|texcoords| texcoords.into_iter().enumerate().filter_map(|(i, v)| if used[i] { Some(v) } else { None }).collect())
             .collect();
+        for target in &mut self.morph_targets {
+            target.positions = target.positions.drain(..).enumerate().filter_map(|(i, v)| if used[i] { Some(v) } else { None }).collect();
+            target.normals = target
+                .normals
+                .as_mut()
+                .map(|normals| normals.drain(..).enumerate().filter_map(|(i, v)| if used[i] { Some(v) } else { None }).collect());
+        }
     }
     pub fn try_ensure_tangents(&mut self) {
         if self.tangents.is_some() || self.positions.is_none() || self.texcoords.is_empty() {
@@ -224,5 +295,61 @@ impl Mesh {
             + self.joint_weights.as_ref().map(|x| std::mem::size_of_val(&**x)).unwrap_or(0)
             + self.indices.as_ref().map(|x| std::mem::size_of_val(&**x)).unwrap_or(0)
             + self.texcoords.iter().map(|x| std::mem::size_of_val(&**x)).sum::<usize>()
+            + self
+                .morph_targets
+                .iter()
+                .map(|x| std::mem::size_of_val(&*x.positions) + x.normals.as_ref().map(|x| std::mem::size_of_val(&**x)).unwrap_or(0))
+                .sum::<usize>()
     }
 }
+
+#[test]
+fn test_morph_targets() {
+    let triangle = || Mesh {
+        positions: Some(vec![vec3(0., 0., 0.), vec3(1., 0., 0.), vec3(0., 1., 0.)]),
+        indices: Some(vec![0, 1, 2]),
+        ..Default::default()
+    };
+    let mut mesh = Mesh {
+        morph_targets: vec![MorphTarget { name: "raise".to_string(), positions: vec![vec3(0., 0., 2.); 3], normals: None }],
+        ..triangle()
+    };
+    mesh.append(triangle());
+    assert_eq!(mesh.morph_targets[0].positions.len(), 6);
+
+    mesh.apply_morph_targets(&[0.5]);
+    let positions = mesh.positions.unwrap();
+    assert_eq!(positions[1], vec3(1., 0., 1.));
+    assert_eq!(positions[4], vec3(1., 0., 0.));
+}
+
+#[test]
+fn test_append_morph_targets() {
+    let triangle = |targets: Vec<MorphTarget>| Mesh {
+        positions: Some(vec![vec3(0., 0., 0.), vec3(1., 0., 0.), vec3(0., 1., 0.)]),
+        indices: Some(vec![0, 1, 2]),
+        morph_targets: targets,
+        ..Default::default()
+    };
+    let target = |name: &str, offset: Vec3, normals: Option<Vec3>| MorphTarget {
+        name: name.to_string(),
+        positions: vec![offset; 3],
+        normals: normals.map(|normal| vec![normal; 3]),
+    };
+
+    // The appended mesh has more targets, and is the only one with normals
+    let mut mesh = triangle(vec![target("a", Vec3::X, None)]);
+    mesh.append(triangle(vec![target("a", Vec3::Y, Some(Vec3::Z)), target("b", Vec3::Z, None)]));
+    assert_eq!(mesh.morph_targets.len(), 2);
+    assert_eq!(mesh.morph_targets[0].positions, [vec![Vec3::X; 3], vec![Vec3::Y; 3]].concat());
+    assert_eq!(mesh.morph_targets[0].normals, Some([vec![Vec3::ZERO; 3], vec![Vec3::Z; 3]].concat()));
+    assert_eq!(mesh.morph_targets[1].name, "b");
+    assert_eq!(mesh.morph_targets[1].positions, [vec![Vec3::ZERO; 3], vec![Vec3::Z; 3]].concat());
+    assert_eq!(mesh.morph_targets[1].normals, None);
+
+    // The appended mesh has no targets
+    mesh.append(triangle(Vec::new()));
+    assert!(mesh.morph_targets.iter().all(|target| target.positions.len() == 9));
+    assert_eq!(mesh.morph_targets[0].positions[6..], [Vec3::ZERO; 3]);
+    assert_eq!(mesh.morph_targets[0].normals.as_ref().unwrap().len(), 9);
+}
@@ -1,10 +1,15 @@
 use std::sync::Arc;
 
+use ambient_animation::animation_events;
 use ambient_ecs::{
     query, Component, ComponentEntry, EntityData, EntityId, FnSystem, SystemGroup, World,
 };
 use ambient_network::server::{ForkingEvent, ShutdownEvent};
-use ambient_physics::{collider_loads, collisions, PxShapeUserData};
+use ambient_physics::{
+    collider_loads, collisions, contact_impulse, contact_normals, contact_points,
+    trigger::{trigger_events, TriggerEventKind},
+    CollisionPhase, PxShapeUserData,
+};
 use itertools::Itertools;
 use parking_lot::RwLock;
 use physxx::{PxRigidActor, PxRigidActorRef, PxUserData};
@@ -81,7 +86,8 @@ pub fn systems<
                     Some(collisions) => collisions.lock().clone(),
                     None => return,
                 };
-                for (a, b) in collisions.into_iter() {
+                for collision in collisions.into_iter() {
+                    let (a, b) = collision.actors;
                     let select_entity = |px: PxRigidActorRef| {
                         px.get_shapes()
                             .into_iter()
@@ -93,13 +99,43 @@ pub fn systems<
                         .into_iter()
                         .flatten()
                         .collect_vec();
+                    let name = match collision.phase {
+                        CollisionPhase::Begin => "core/collision",
+                        CollisionPhase::Persist => "core/collision_persist",
+                        CollisionPhase::End => "core/collision_end",
+                    };
+                    let mut data = EntityData::new().set(ambient_ecs::ids(), ids);
+                    if let Some(contacts) = collision.contacts {
+                        data = data
+                            .set(contact_points(), contacts.points)
+                            .set(contact_normals(), contacts.normals)
+                            .set(contact_impulse(), contacts.impulse);
+                    }
+                    run_all(world, state_component, &RunContext::new(world, name, data));
+                }
+            })),
+            Box::new(FnSystem::new(move |world, _| {
+                profiling::scope!("WASM module trigger events");
+                let events = match world.resource_opt(trigger_events()) {
+                    Some(events) => events.clone(),
+                    None => return,
+                };
+                for event in events {
+                    let name = match event.kind {
+                        TriggerEventKind::Enter => "core/trigger_enter",
+                        TriggerEventKind::Exit => "core/trigger_exit",
+                    };
                     run_all(
                         world,
                         state_component,
                         &RunContext::new(
                             world,
-                            "core/collision",
-                            vec![ComponentEntry::new(ambient_ecs::ids(), ids)].into(),
+                            name,
+                            vec![ComponentEntry::new(
+                                ambient_ecs::ids(),
+                                vec![event.trigger, event.entity],
+                            )]
+                            .into(),
                         ),
                     );
                 }
@@ -123,6 +159,28 @@ pub fn systems<
                     );
                 }
             })),
+            Box::new(FnSystem::new(move |world, _| {
+                profiling::scope!("WASM module animation events");
+                let events = match world.resource_opt(animation_events()) {
+                    Some(events) => events.clone(),
+                    None => return,
+                };
+                for event in events {
+                    run_all(
+                        world,
+                        state_component,
+                        &RunContext::new(
+                            world,
+                            "core/animation_event",
+                            vec![
+                                ComponentEntry::new(ambient_ecs::id(), event.entity),
+                                ComponentEntry::new(ambient_core::name(), event.name),
+                            ]
+                            .into(),
+                        ),
+                    );
+                }
+            })),
         ],
     )
 }
@@ -1,7 +1,7 @@
 use std::{f32::consts::TAU, sync::Arc};
 
 use ambient_app::{App, AppBuilder};
-use ambient_audio::{track::Track, Attenuation, AudioEmitter, AudioListener, AudioStream, Source};
+use ambient_audio::{track::Track, Attenuation, AudioEmitter, AudioListener, AudioStream, Bus, Source};
 use ambient_core::{
     asset_cache,
     camera::{active_camera, far, near},
@@ -30,6 +30,7 @@ fn spawn_emitters(world: &mut World) {
             amplitude: 5.0,
             attenuation: Attenuation::InversePoly { quad: 0.1, lin: 0.0, constant: 1.0 },
             pos,
+            occlusion: 0.0,
         }));
 
         let id = Cube
@@ -41,7 +42,7 @@ fn spawn_emitters(world: &mut World) {
             .set(audio_emitter(), emitter)
             .spawn_static(world);
 
-        play_sound_on_entity(world, id, track.decode().repeat()).expect("Failed to play sound");
+        play_sound_on_entity(world, id, Bus::Sfx, track.decode().repeat()).expect("Failed to play sound");
     }
 }
 
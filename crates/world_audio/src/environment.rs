@@ -0,0 +1,246 @@
+use ambient_audio::{Bus, ReverbParams};
+use ambient_core::transform::{local_to_world, translation};
+use ambient_ecs::{query, Component, EntityId, SystemGroup, World};
+use ambient_physics::{intersection::raycast_collider_type, ColliderScene};
+use ambient_std::shapes::Ray;
+use glam::Vec3;
+use itertools::Itertools;
+
+use crate::{
+    audio_emitter, audio_listener, audio_mixer, audio_occlusion, audio_occlusion_listeners, reverb_zone_damping, reverb_zone_falloff,
+    reverb_zone_radius, reverb_zone_room_size, reverb_zone_wet,
+};
+
+/// The buses whose sounds are played in the world, and have the reverb of the zones around the listener
+const REVERB_BUSES: [Bus; 2] = [Bus::Sfx, Bus::Voice];
+/// How much of the sound every collider between an emitter and the listener lets through
+const TRANSMISSION_PER_COLLIDER: f32 = 0.5;
+const DEFAULT_REVERB_WET: f32 = 0.3;
+
+/// Blends the reverb of the zones around the listener, and applies the occlusion of the emitters
+pub fn client_systems() -> SystemGroup {
+    SystemGroup::new(
+        "audio/environment",
+        vec![
+            query((audio_listener(), local_to_world())).to_system(|q, world, qs, _| {
+                let Some(mixer) = world.resource_opt(audio_mixer()) else { return };
+                let Some((_, (_, ltw))) = q.iter(world, qs).next() else { return };
+                let reverb = zone_reverb(world, ltw.w_axis.truncate());
+                for bus in REVERB_BUSES {
+                    mixer.set_bus_reverb(bus, reverb);
+                }
+            }),
+            query((audio_emitter(), audio_occlusion(), audio_occlusion_listeners())).to_system(|q, world, qs, _| {
+                let listener = query(audio_listener()).iter(world, None).next().map(|(id, _)| id);
+                for (_, (emitter, occlusions, listeners)) in q.iter(world, qs) {
+                    // Emitters that haven't been occluded from this listener yet aren't occluded
+                    let index = listener.and_then(|listener| listeners.iter().position(|&id| id == listener));
+                    emitter.lock().occlusion = index.and_then(|index| occlusions.get(index).copied()).unwrap_or_default();
+                }
+            }),
+        ],
+    )
+}
+
+/// Computes the occlusion of the entities with `audio_occlusion` from every entity with `listener`,
+/// by casting rays through the physics colliders between them
+pub fn server_systems(listener: Component<()>) -> SystemGroup {
+    SystemGroup::new(
+        "audio/environment",
+        vec![query((audio_occlusion(), local_to_world())).to_system(move |q, world, qs, _| {
+            let (ids, listeners): (Vec<_>, Vec<_>) =
+                query(local_to_world()).incl(listener).iter(world, None).map(|(id, ltw)| (id, ltw.w_axis.truncate())).unzip();
+            for (id, (_, ltw)) in q.collect_cloned(world, qs) {
+                let pos = ltw.w_axis.truncate();
+                let occlusions = listeners.iter().map(|&listener| occlusion(world, id, listener, pos)).collect_vec();
+                world.set_if_changed(id, audio_occlusion(), occlusions).unwrap();
+                if world.get_ref(id, audio_occlusion_listeners()).map_or(true, |listeners| *listeners != ids) {
+                    world.add_component(id, audio_occlusion_listeners(), ids.clone()).unwrap();
+                }
+            }
+        })],
+    )
+}
+
+/// The reverb of the zones around `pos`, weighted by how far inside them it is, or None outside of all of them
+fn zone_reverb(world: &World, pos: Vec3) -> Option<ReverbParams> {
+    let mut total_weight = 0.;
+    let mut params = ReverbParams { room_size: 0., damping: 0., wet: 0. };
+    for (id, (&radius, &zone_pos)) in query((reverb_zone_radius(), translation())).iter(world, None) {
+        let falloff = world.get(id, reverb_zone_falloff()).unwrap_or_default();
+        let outside = pos.distance(zone_pos) - radius;
+        let weight = if outside <= 0. {
+            1.
+        } else if falloff > 0. {
+            (1. - outside / falloff).max(0.)
+        } else {
+            0.
+        };
+        if weight <= 0. {
+            continue;
+        }
+
+        let defaults = ReverbParams::default();
+        total_weight += weight;
+        params.room_size += weight * world.get(id, reverb_zone_room_size()).unwrap_or(defaults.room_size);
+        params.damping += weight * world.get(id, reverb_zone_damping()).unwrap_or(defaults.damping);
+        params.wet += weight * world.get(id, reverb_zone_wet()).unwrap_or(DEFAULT_REVERB_WET);
+    }
+    if total_weight <= 0. {
+        return None;
+    }
+
+    // Where zones overlap, their rooms are averaged; at the edge of a zone, its reverb fades out
+    Some(ReverbParams {
+        room_size: params.room_size / total_weight,
+        damping: params.damping / total_weight,
+        wet: params.wet / total_weight * total_weight.min(1.),
+    })
+}
+
+/// How much the colliders between the listener and the emitter block its sound, from 0 to 1
+fn occlusion(world: &World, emitter: EntityId, listener: Vec3, pos: Vec3) -> f32 {
+    let distance = listener.distance(pos);
+    if distance <= f32::EPSILON {
+        return 0.;
+    }
+
+    let ray = Ray::new(listener, (pos - listener) / distance);
+    // Colliders the listener is inside of, such as the body of the player, are hit at a distance of 0
+    let colliders = raycast_collider_type(world, ColliderScene::Physics, ray)
+        .into_iter()
+        .filter(|&(id, dist)| id != emitter && dist > 0. && dist < distance)
+        .map(|(id, _)| id)
+        .unique()
+        .count();
+    1. - TRANSMISSION_PER_COLLIDER.powi(colliders as i32)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use ambient_audio::{AudioEmitter, AudioListener};
+    use ambient_core::asset_cache;
+    use ambient_ecs::{components, EntityData, FrameEvent, System};
+    use ambient_physics::{create_server_resources, main_physics_scene, physx::physics, wood_physics_material, PxShapeUserData};
+    use ambient_std::asset_cache::AssetCache;
+    use glam::{vec3, Mat4};
+    use once_cell::sync::Lazy;
+    use parking_lot::Mutex;
+    use physxx::{AsPxRigidActor, PxBoxGeometry, PxRigidActor, PxRigidStaticRef, PxShape, PxShapeFlag, PxTransform, PxUserData};
+
+    use super::*;
+
+    components!("test", {
+        test_listener: (),
+    });
+
+    fn init() {
+        ambient_core::init_all_components();
+        ambient_physics::init_all_components();
+        crate::init_components();
+        init_components();
+    }
+
+    /// A world with the physics scenes. PhysX can only be initialized once, so the worlds share it
+    fn physics_world() -> World {
+        static RUNTIME: Lazy<tokio::runtime::Runtime> = Lazy::new(|| tokio::runtime::Runtime::new().unwrap());
+        static ASSETS: Lazy<AssetCache> = Lazy::new(|| AssetCache::new(RUNTIME.handle().clone()));
+
+        init();
+        let mut resources = EntityData::new().set(asset_cache(), ASSETS.clone());
+        create_server_resources(&ASSETS, &mut resources);
+        let mut world = World::new("audio_environment_test");
+        world.add_components(world.resource_entity(), resources).unwrap();
+        world
+    }
+
+    fn spawn_at(world: &mut World, pos: Vec3, data: EntityData) -> EntityId {
+        data.set(local_to_world(), Mat4::from_translation(pos)).set(translation(), pos).spawn(world)
+    }
+
+    /// Gives the entity a static box collider which raycasts hit
+    fn add_box_collider(world: &World, id: EntityId, pos: Vec3, half_size: Vec3) {
+        let physics = world.resource(physics()).clone();
+        let material = world.resource(wood_physics_material()).clone();
+        let actor = PxRigidStaticRef::new(physics.physics, &PxTransform::from_translation(pos)).as_rigid_actor();
+        let geometry = PxBoxGeometry::new(half_size.x, half_size.y, half_size.z);
+        let shape = PxShape::new(physics.physics, &geometry, &[&material], Some(true), Some(PxShapeFlag::SCENE_QUERY_SHAPE));
+        shape.set_user_data(PxShapeUserData { entity: id, ..Default::default() });
+        assert!(actor.attach_shape(&shape));
+        world.resource(main_physics_scene()).add_actor(&actor);
+    }
+
+    fn assert_reverb_eq(a: Option<ReverbParams>, b: Option<ReverbParams>) {
+        let close = match (a, b) {
+            (Some(a), Some(b)) => {
+                (a.room_size - b.room_size).abs() < 1e-5 && (a.damping - b.damping).abs() < 1e-5 && (a.wet - b.wet).abs() < 1e-5
+            }
+            (a, b) => a == b,
+        };
+        assert!(close, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn zone_reverb_blends_the_zones_around_the_listener() {
+        init();
+        let mut world = World::new("zone_reverb");
+        assert_reverb_eq(zone_reverb(&world, Vec3::ZERO), None);
+
+        let hall = ReverbParams { room_size: 0.8, damping: 0.2, wet: 0.5 };
+        let data = EntityData::new()
+            .set(reverb_zone_radius(), 2.)
+            .set(reverb_zone_falloff(), 2.)
+            .set(reverb_zone_room_size(), hall.room_size)
+            .set(reverb_zone_damping(), hall.damping)
+            .set(reverb_zone_wet(), hall.wet);
+        spawn_at(&mut world, Vec3::ZERO, data);
+        assert_reverb_eq(zone_reverb(&world, vec3(0., 1., 0.)), Some(hall));
+        // The reverb fades out over the falloff
+        assert_reverb_eq(zone_reverb(&world, vec3(0., 3., 0.)), Some(ReverbParams { wet: hall.wet / 2., ..hall }));
+        assert_reverb_eq(zone_reverb(&world, vec3(0., 5., 0.)), None);
+
+        // The rooms of overlapping zones are averaged, and the zones without parameters have the default ones
+        spawn_at(&mut world, vec3(3., 0., 0.), EntityData::new().set(reverb_zone_radius(), 2.));
+        let defaults = ReverbParams::default();
+        let blend = ReverbParams {
+            room_size: (hall.room_size + defaults.room_size) / 2.,
+            damping: (hall.damping + defaults.damping) / 2.,
+            wet: (hall.wet + DEFAULT_REVERB_WET) / 2.,
+        };
+        assert_reverb_eq(zone_reverb(&world, vec3(1.5, 0., 0.)), Some(blend));
+    }
+
+    #[test]
+    fn occlusion_is_computed_for_every_listener() {
+        let mut world = physics_world();
+        for x in [4., 6.] {
+            let wall = spawn_at(&mut world, vec3(x, 0., 0.), EntityData::new());
+            add_box_collider(&world, wall, vec3(x, 0., 0.), vec3(0.5, 5., 5.));
+        }
+        // The collider of the emitter itself doesn't occlude it
+        let emitter = spawn_at(&mut world, vec3(10., 0., 0.), EntityData::new().set(audio_occlusion(), Vec::new()));
+        add_box_collider(&world, emitter, vec3(10., 0., 0.), Vec3::splat(0.5));
+        let behind_walls = spawn_at(&mut world, Vec3::ZERO, EntityData::new().set(test_listener(), ()));
+        let in_the_open = spawn_at(&mut world, vec3(15., 0., 0.), EntityData::new().set(test_listener(), ()));
+
+        server_systems(test_listener()).run(&mut world, &FrameEvent);
+        let listeners = world.get_cloned(emitter, audio_occlusion_listeners()).unwrap();
+        let occlusions = world.get_cloned(emitter, audio_occlusion()).unwrap();
+        assert_eq!(listeners.len(), 2);
+        let occlusion_from = |listener| occlusions[listeners.iter().position(|&id| id == listener).unwrap()];
+        assert_eq!(occlusion_from(behind_walls), 1. - TRANSMISSION_PER_COLLIDER.powi(2));
+        assert_eq!(occlusion_from(in_the_open), 0.);
+
+        // Every client hears the occlusion from its own listener
+        let audio_emitter_value = Arc::new(Mutex::new(AudioEmitter::default()));
+        world.add_component(emitter, audio_emitter(), audio_emitter_value.clone()).unwrap();
+        for listener in [behind_walls, in_the_open] {
+            world.add_component(listener, audio_listener(), Arc::new(Mutex::new(AudioListener::new(Mat4::IDENTITY, Vec3::X)))).unwrap();
+            client_systems().run(&mut world, &FrameEvent);
+            assert_eq!(audio_emitter_value.lock().occlusion, occlusion_from(listener));
+            world.remove_component(listener, audio_listener()).unwrap();
+        }
+    }
+}
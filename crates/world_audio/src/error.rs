@@ -10,6 +10,8 @@ pub enum Error {
     MissingEffect(String),
     #[error("There are no more available sinks")]
     NoAvailableSink,
+    #[error("Invalid audio graph: {0}")]
+    InvalidGraph(String),
     #[error(transparent)]
     AudioError(#[from] Arc<ambient_audio::Error>),
 }
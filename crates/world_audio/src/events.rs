@@ -1,7 +1,8 @@
+use ambient_audio::Bus;
 use ambient_core::asset_cache;
 use ambient_ecs::{EntityId, World};
 
-use crate::{play_sound_on_entity, AudioNode, AudioSeed};
+use crate::{audio_mixer, play_sound_on_entity, AudioNode, AudioSeed};
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 /// Plays a sound on an entity. Requires that the entity has an AudioEmitterDef on the server.
 /// Otherwise, the audio is played on a temporary emitter
@@ -11,11 +12,15 @@ pub struct PlayLocalSound {
     /// A human readable label describing what this sound is
     pub label: String,
     pub seed: AudioSeed,
+    /// The bus the sound is mixed in
+    #[serde(default)]
+    pub bus: Bus,
 }
 
 pub fn play_local_sound(world: &mut World, event: PlayLocalSound) -> anyhow::Result<()> {
     let assets = world.resource(asset_cache());
-    let source = match event.source.try_build(assets, event.seed).transpose() {
+    let sample_rate = world.resource(audio_mixer()).sample_rate();
+    let source = match event.source.try_build(assets, event.seed, sample_rate).transpose() {
         Some(source) => source?,
         None => {
             tracing::warn!("Sound {} is not yet loaded", event.label);
@@ -23,7 +28,7 @@ pub fn play_local_sound(world: &mut World, event: PlayLocalSound) -> anyhow::Res
         }
     };
 
-    play_sound_on_entity(world, event.id, source)?;
+    play_sound_on_entity(world, event.id, event.bus, source)?;
     Ok(())
 }
 
@@ -1,15 +1,23 @@
-use std::fmt::Debug;
+use std::{any::Any, fmt::Debug, sync::Arc, time::Duration};
 
-use ambient_audio::{Source, VorbisFromUrl};
+use ambient_audio::{
+    track::Track,
+    vorbis::{stream_vorbis, LoopPoints, VorbisTrack},
+    AudioFromUrl, BufferedSource, Chain, Crossfade, DynamicMix, RepeatWith, SampleRate, Source, VorbisFromUrl, VorbisStreamFromUrl,
+};
 use ambient_std::{
     self,
     asset_cache::{AssetCache, AsyncAssetKeyExt},
     asset_url::AbsAssetUrl,
 };
-use rand::{thread_rng, Rng, SeedableRng};
+use parking_lot::Mutex;
+use rand::{distributions::WeightedIndex, prelude::Distribution, thread_rng, Rng, SeedableRng};
 use rand_chacha::ChaCha12Rng;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
+
+/// The extension of sound graph files
+pub const SOUND_GRAPH_EXTENSION: &str = "sgr";
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 /// Textual representation of a node in the audio graph which specifies how to construct a Sound.
@@ -21,6 +29,65 @@ pub enum AudioNode {
         /// Url asset
         url: String,
     },
+    /// Play from a `.ogg` or `.wav` file from a url
+    Track {
+        /// Url asset
+        url: String,
+    },
+    /// Play from a vorbis `.ogg` file from a url, decoding it while it plays, which suits long tracks such as music.
+    /// If `loop_start` or `loop_end` is set, the track jumps back to `loop_start` seconds when it reaches `loop_end` seconds, or its end.
+    Stream {
+        /// Url asset
+        url: String,
+        #[serde(default)]
+        loop_start: Option<f32>,
+        #[serde(default)]
+        loop_end: Option<f32>,
+    },
+    /// Multiplies the amplitude of a node
+    Gain { node: Box<AudioNode>, gain: f32 },
+    /// Plays the nodes at the same time, until the shortest of them ends
+    Mix { nodes: Vec<AudioNode> },
+    /// Plays the nodes one after the other
+    Chain { nodes: Vec<AudioNode> },
+    /// Plays `from`, and fades over to `to` during the last `duration` seconds of it
+    Crossfade { from: Box<AudioNode>, to: Box<AudioNode>, duration: f32 },
+    /// Plays a node `count` times, or forever if there is no count.
+    /// Random nodes inside it are picked again for every repetition, so it only starts once all of them are loaded.
+    Repeat {
+        node: Box<AudioNode>,
+        #[serde(default)]
+        count: Option<u32>,
+    },
+    /// Plays a node from `start` seconds to `end` seconds, or to its end if there is no end
+    Slice {
+        node: Box<AudioNode>,
+        #[serde(default)]
+        start: f32,
+        #[serde(default)]
+        end: Option<f32>,
+    },
+    /// Removes the frequencies of a node above `freq` Hz
+    LowPass { node: Box<AudioNode>, freq: f32, bandwidth: f32 },
+    /// Removes the frequencies of a node below `freq` Hz
+    HighPass { node: Box<AudioNode>, freq: f32, bandwidth: f32 },
+    /// Keeps the frequencies of a node around `freq` Hz
+    BandPass { node: Box<AudioNode>, freq: f32, bandwidth: f32 },
+    /// Plays one of the nodes, picked with the seed of the sound.
+    /// `weights` are the relative chances of the nodes; they're all as likely if there are none.
+    RandomChoice {
+        nodes: Vec<AudioNode>,
+        #[serde(default)]
+        weights: Vec<f32>,
+    },
+    /// Plays a node faster or slower, which also raises or lowers its pitch.
+    /// The speed is multiplied by a random factor between `1 - variation` and `1 + variation`, picked with the seed of the sound.
+    Speed {
+        node: Box<AudioNode>,
+        speed: f32,
+        #[serde(default)]
+        variation: f32,
+    },
 }
 
 impl Default for AudioNode {
@@ -31,19 +98,219 @@ impl Default for AudioNode {
 
 impl AudioNode {
     /// Builds the adapter into a proper source.
-    /// If the graph can not immediately be built, it returns None
-    pub fn try_build(self, assets: &AssetCache, _seed: AudioSeed) -> Result<Option<Box<dyn Source>>> {
+    /// If the graph can not immediately be built, it returns None.
+    ///
+    /// `sample_rate` is the sample rate of the mixer the source is played in, which the nodes without any samples of their own
+    /// take, so that they don't make the rest of the graph be converted to another one.
+    pub fn try_build(self, assets: &AssetCache, seed: AudioSeed, sample_rate: SampleRate) -> Result<Option<Box<dyn Source>>> {
+        // Every child gets its own seed, so that the random choices of a node don't depend on the rest of the graph
+        let mut rng = ChaCha12Rng::from_seed(seed.rng_seed);
+        let child = |node: AudioNode, rng: &mut ChaCha12Rng| node.try_build(assets, AudioSeed::from_rng(rng), sample_rate);
+        Ok(match self {
+            AudioNode::Identity => Some(empty_source(sample_rate)),
+            AudioNode::Vorbis { url } => vorbis_track(&url, assets)?.map(|track| Box::new(track.decode()) as DynSource),
+            AudioNode::Track { url } => track(&url, assets)?.map(|track| Box::new(track.decode()) as DynSource),
+            AudioNode::Stream { url, loop_start, loop_end } => {
+                let bytes = stream_bytes(&url, assets)?;
+                let loop_points = (loop_start.is_some() || loop_end.is_some()).then(|| LoopPoints {
+                    start: Duration::from_secs_f32(loop_start.unwrap_or_default().max(0.)),
+                    end: loop_end.map(|end| Duration::from_secs_f32(end.max(0.))),
+                });
+                match bytes {
+                    Some(bytes) => Some(Box::new(stream_vorbis(bytes, loop_points).map_err(Arc::new)?) as DynSource),
+                    None => None,
+                }
+            }
+            AudioNode::Gain { node, gain } => child(*node, &mut rng)?.map(|source| Box::new(source.gain(gain)) as DynSource),
+            AudioNode::Mix { nodes } => build_all(nodes, assets, &mut rng, sample_rate)?.map(|sources| {
+                if sources.is_empty() {
+                    return empty_source(sample_rate);
+                }
+                let weights = vec![1.; sources.len()].into_boxed_slice();
+                Box::new(DynamicMix::new(sources, Arc::new(Mutex::new(weights)))) as DynSource
+            }),
+            AudioNode::Chain { nodes } => build_all(nodes, assets, &mut rng, sample_rate)?.map(|sources| {
+                let chain = sources.into_iter().reduce(|first, second| Box::new(Chain::new(first, second)) as DynSource);
+                chain.unwrap_or_else(|| empty_source(sample_rate))
+            }),
+            AudioNode::Crossfade { from, to, duration } => match (child(*from, &mut rng)?, child(*to, &mut rng)?) {
+                (Some(from), Some(to)) => {
+                    // The fade can't start before `from` does
+                    let duration = Duration::from_secs_f32(duration.max(0.));
+                    let duration = from.duration().map(|length| length.min(duration)).unwrap_or(duration);
+                    Some(Box::new(Crossfade::new(from, to, duration)) as DynSource)
+                }
+                _ => None,
+            },
+            AudioNode::Repeat { node, count } => {
+                // Every repetition is built again, possibly with other random choices, so the assets of all of them are loaded
+                // before the first one plays, and kept until the last one is built
+                let mut loaded = Vec::new();
+                if !node.load_assets(assets, &mut loaded)? {
+                    return Ok(None);
+                }
+                child((*node).clone(), &mut rng)?.map(|first| {
+                    let assets = assets.clone();
+                    let mut first = Some(first);
+                    Box::new(RepeatWith::new(
+                        move || {
+                            let _loaded = &loaded;
+                            if let Some(source) = first.take() {
+                                return Some(source);
+                            }
+                            match node.clone().try_build(&assets, AudioSeed::from_rng(&mut rng), sample_rate) {
+                                Ok(Some(source)) => Some(source),
+                                Ok(None) => {
+                                    tracing::warn!("The repeated audio node {node:?} could not be built again, so it stops repeating");
+                                    None
+                                }
+                                Err(err) => {
+                                    tracing::error!("Failed to build the repeated audio node {node:?}, so it stops repeating: {err}");
+                                    None
+                                }
+                            }
+                        },
+                        count,
+                    )) as DynSource
+                })
+            }
+            AudioNode::Slice { node, start, end } => child(*node, &mut rng)?.map(|source| {
+                let start = Duration::from_secs_f32(start.max(0.));
+                match end {
+                    Some(end) => Box::new(source.slice(start..Duration::from_secs_f32(end.max(0.)).max(start))) as DynSource,
+                    None => Box::new(source.skip(start)) as DynSource,
+                }
+            }),
+            AudioNode::LowPass { node, freq, bandwidth } => {
+                child(*node, &mut rng)?.map(|source| Box::new(source.low_pass(freq, bandwidth)) as DynSource)
+            }
+            AudioNode::HighPass { node, freq, bandwidth } => {
+                child(*node, &mut rng)?.map(|source| Box::new(source.high_pass(freq, bandwidth)) as DynSource)
+            }
+            AudioNode::BandPass { node, freq, bandwidth } => {
+                child(*node, &mut rng)?.map(|source| Box::new(source.band_pass(freq, bandwidth)) as DynSource)
+            }
+            AudioNode::RandomChoice { mut nodes, weights } => {
+                if nodes.is_empty() {
+                    Some(empty_source(sample_rate))
+                } else {
+                    let index = if weights.is_empty() {
+                        rng.gen_range(0..nodes.len())
+                    } else if weights.len() != nodes.len() {
+                        return Err(Error::InvalidGraph(format!("{} weights were given for {} nodes", weights.len(), nodes.len())));
+                    } else {
+                        let weights = WeightedIndex::new(&weights).map_err(|err| Error::InvalidGraph(format!("Invalid weights: {err}")))?;
+                        weights.sample(&mut rng)
+                    };
+                    child(nodes.swap_remove(index), &mut rng)?
+                }
+            }
+            AudioNode::Speed { node, speed, variation } => {
+                let variation = variation.clamp(0., 1.);
+                let speed = speed * (1. + rng.gen_range(-variation..=variation));
+                child(*node, &mut rng)?.map(|source| Box::new(source.speed(speed)) as DynSource)
+            }
+        })
+    }
+
+    /// Resolves the urls of this node and its children, which may be relative to `base_url`
+    pub fn resolve_urls(&mut self, base_url: &AbsAssetUrl) -> anyhow::Result<()> {
         match self {
-            AudioNode::Vorbis { url } => {
-                let track = VorbisFromUrl { url: AbsAssetUrl::parse(url).unwrap() }.peek(assets).transpose()?;
-                match track {
-                    Some(track) => Ok(Some(Box::new(track.decode()))),
-                    None => Ok(None),
+            AudioNode::Vorbis { url } | AudioNode::Track { url } | AudioNode::Stream { url, .. } => {
+                *url = base_url.resolve(url.as_str())?.to_string();
+            }
+            _ => {
+                for child in self.children_mut() {
+                    child.resolve_urls(base_url)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts loading the assets of this node and its children, and adds the ones that are loaded to `loaded`.
+    /// Returns whether all of them are loaded.
+    fn load_assets(&self, assets: &AssetCache, loaded: &mut Vec<Arc<dyn Any + Send + Sync>>) -> Result<bool> {
+        let asset = match self {
+            AudioNode::Vorbis { url } => vorbis_track(url, assets)?.map(|track| track as Arc<dyn Any + Send + Sync>),
+            AudioNode::Track { url } => track(url, assets)?.map(|track| track as Arc<dyn Any + Send + Sync>),
+            AudioNode::Stream { url, .. } => stream_bytes(url, assets)?.map(|bytes| Arc::new(bytes) as Arc<dyn Any + Send + Sync>),
+            _ => {
+                let mut all_loaded = true;
+                for child in self.children() {
+                    all_loaded &= child.load_assets(assets, loaded)?;
                 }
+                return Ok(all_loaded);
+            }
+        };
+        Ok(match asset {
+            Some(asset) => {
+                loaded.push(asset);
+                true
             }
-            _ => unimplemented!(),
+            None => false,
+        })
+    }
+
+    fn children(&self) -> Vec<&AudioNode> {
+        match self {
+            AudioNode::Identity | AudioNode::Vorbis { .. } | AudioNode::Track { .. } | AudioNode::Stream { .. } => Vec::new(),
+            AudioNode::Gain { node, .. }
+            | AudioNode::Repeat { node, .. }
+            | AudioNode::Slice { node, .. }
+            | AudioNode::LowPass { node, .. }
+            | AudioNode::HighPass { node, .. }
+            | AudioNode::BandPass { node, .. }
+            | AudioNode::Speed { node, .. } => vec![node.as_ref()],
+            AudioNode::Mix { nodes } | AudioNode::Chain { nodes } | AudioNode::RandomChoice { nodes, .. } => nodes.iter().collect(),
+            AudioNode::Crossfade { from, to, .. } => vec![from.as_ref(), to.as_ref()],
         }
     }
+
+    fn children_mut(&mut self) -> Vec<&mut AudioNode> {
+        match self {
+            AudioNode::Identity | AudioNode::Vorbis { .. } | AudioNode::Track { .. } | AudioNode::Stream { .. } => Vec::new(),
+            AudioNode::Gain { node, .. }
+            | AudioNode::Repeat { node, .. }
+            | AudioNode::Slice { node, .. }
+            | AudioNode::LowPass { node, .. }
+            | AudioNode::HighPass { node, .. }
+            | AudioNode::BandPass { node, .. }
+            | AudioNode::Speed { node, .. } => vec![node.as_mut()],
+            AudioNode::Mix { nodes } | AudioNode::Chain { nodes } | AudioNode::RandomChoice { nodes, .. } => nodes.iter_mut().collect(),
+            AudioNode::Crossfade { from, to, .. } => vec![from.as_mut(), to.as_mut()],
+        }
+    }
+}
+
+type DynSource = Box<dyn Source>;
+
+/// A source without any samples
+fn empty_source(sample_rate: SampleRate) -> DynSource {
+    Box::new(BufferedSource::new(Vec::<f32>::new(), 2, sample_rate))
+}
+
+fn parse_url(url: &str) -> Result<AbsAssetUrl> {
+    AbsAssetUrl::parse(url).map_err(|err| Error::InvalidGraph(format!("Invalid url {url:?}: {err}")))
+}
+
+fn vorbis_track(url: &str, assets: &AssetCache) -> Result<Option<Arc<VorbisTrack>>> {
+    Ok(VorbisFromUrl { url: parse_url(url)? }.peek(assets).transpose()?)
+}
+
+fn track(url: &str, assets: &AssetCache) -> Result<Option<Arc<Track>>> {
+    Ok(AudioFromUrl { url: parse_url(url)? }.peek(assets).transpose()?)
+}
+
+fn stream_bytes(url: &str, assets: &AssetCache) -> Result<Option<Arc<[u8]>>> {
+    Ok(VorbisStreamFromUrl { url: parse_url(url)? }.peek(assets).transpose()?)
+}
+
+/// Builds all the nodes, or None if any of them can't be built yet
+fn build_all(nodes: Vec<AudioNode>, assets: &AssetCache, rng: &mut ChaCha12Rng, sample_rate: SampleRate) -> Result<Option<Vec<DynSource>>> {
+    let sources =
+        nodes.into_iter().map(|node| node.try_build(assets, AudioSeed::from_rng(rng), sample_rate)).collect::<Result<Vec<_>>>()?;
+    Ok(sources.into_iter().collect())
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
@@ -56,6 +323,10 @@ impl AudioSeed {
     pub fn new() -> Self {
         Self { rng_seed: thread_rng().gen() }
     }
+
+    fn from_rng(rng: &mut ChaCha12Rng) -> Self {
+        Self { rng_seed: rng.gen() }
+    }
 }
 
 impl Default for AudioSeed {
@@ -63,3 +334,174 @@ impl Default for AudioSeed {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use ambient_audio::Frame;
+    use itertools::Itertools;
+
+    use super::*;
+
+    const SAMPLE_RATE: SampleRate = 10;
+
+    /// Writes a mono 32 bit float wav file, and returns its url
+    fn write_wav(path: &Path, samples: &[f32]) -> String {
+        let mut bytes = Vec::new();
+        let data_len = samples.len() as u32 * 4;
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        // IEEE float format, 1 channel
+        bytes.extend_from_slice(&3u16.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&(SAMPLE_RATE as u32).to_le_bytes());
+        bytes.extend_from_slice(&(SAMPLE_RATE as u32 * 4).to_le_bytes());
+        bytes.extend_from_slice(&4u16.to_le_bytes());
+        bytes.extend_from_slice(&32u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        std::fs::write(path, bytes).unwrap();
+        AbsAssetUrl::from_file_path(path).to_string()
+    }
+
+    /// A directory with the tracks `a`, `b` and `c`, which are two samples of 0.1, 0.2 and 0.3, and `ramp`, which is the
+    /// samples 0 to 0.9
+    fn write_tracks(name: &str) -> [AudioNode; 4] {
+        let dir = std::env::temp_dir().join(format!("ambient_world_audio_{name}_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let ramp = (0..10).map(|i| i as f32 / 10.).collect_vec();
+        let tracks = [
+            write_wav(&dir.join("a.wav"), &[0.1, 0.1]),
+            write_wav(&dir.join("b.wav"), &[0.2, 0.2]),
+            write_wav(&dir.join("c.wav"), &[0.3, 0.3]),
+            write_wav(&dir.join("ramp.wav"), &ramp),
+        ];
+        tracks.map(|url| AudioNode::Track { url })
+    }
+
+    /// Builds the node once its assets are loaded
+    async fn build(node: &AudioNode, assets: &AssetCache, seed: &AudioSeed) -> Result<DynSource> {
+        for _ in 0..500 {
+            if let Some(source) = node.clone().try_build(assets, seed.clone(), SAMPLE_RATE)? {
+                return Ok(source);
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("{node:?} was never loaded");
+    }
+
+    async fn samples(node: &AudioNode, assets: &AssetCache, seed: &AudioSeed) -> Vec<f32> {
+        let mut source = build(node, assets, seed).await.unwrap();
+        std::iter::from_fn(|| source.next_sample()).map(|frame: Frame| frame.x).collect_vec()
+    }
+
+    fn close(a: &[f32], b: &[f32]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5)
+    }
+
+    #[tokio::test]
+    async fn random_choice_is_seeded() {
+        let assets = AssetCache::new(tokio::runtime::Handle::current());
+        let [a, b, c, _] = write_tracks("random_choice");
+        let choice = AudioNode::RandomChoice { nodes: vec![a.clone(), b.clone(), c.clone()], weights: Vec::new() };
+
+        // The same seed always picks the same node, and every node is picked with some seed
+        let mut picked = Vec::new();
+        for _ in 0..32 {
+            let seed = AudioSeed::new();
+            let first = samples(&choice, &assets, &seed).await;
+            assert_eq!(first, samples(&choice, &assets, &seed).await);
+            picked.push(first[0]);
+        }
+        for value in [0.1, 0.2, 0.3] {
+            assert!(picked.iter().any(|picked| (picked - value).abs() < 1e-5), "{value} was never picked");
+        }
+
+        // Nodes without any weight are never picked
+        let weighted = AudioNode::RandomChoice { nodes: vec![a.clone(), b.clone(), c.clone()], weights: vec![0., 1., 0.] };
+        for _ in 0..8 {
+            assert!(close(&samples(&weighted, &assets, &AudioSeed::new()).await, &[0.2, 0.2]));
+        }
+
+        let invalid = AudioNode::RandomChoice { nodes: vec![a, b, c], weights: vec![1.] };
+        assert!(matches!(build(&invalid, &assets, &AudioSeed::new()).await, Err(Error::InvalidGraph(_))));
+    }
+
+    #[tokio::test]
+    async fn repeats_pick_every_variant() {
+        let assets = AssetCache::new(tokio::runtime::Handle::current());
+        let [a, b, c, _] = write_tracks("repeat");
+        let repeat =
+            AudioNode::Repeat { node: Box::new(AudioNode::RandomChoice { nodes: vec![a, b, c], weights: Vec::new() }), count: Some(16) };
+
+        let seed = AudioSeed::new();
+        let samples = samples(&repeat, &assets, &seed).await;
+        assert_eq!(samples.len(), 32);
+        // Each repetition plays a whole variant, and the variants are picked again for every one of them
+        for repetition in samples.chunks(2) {
+            assert_eq!(repetition[0], repetition[1]);
+        }
+        assert!(samples.chunks(2).map(|repetition| (repetition[0] * 10.).round() as i32).unique().count() > 1);
+    }
+
+    #[tokio::test]
+    async fn slice() {
+        let assets = AssetCache::new(tokio::runtime::Handle::current());
+        let [_, _, _, ramp] = write_tracks("slice");
+        let slice = |start: f32, end: Option<f32>| AudioNode::Slice { node: Box::new(ramp.clone()), start, end };
+        let seed = AudioSeed::new();
+
+        assert!(close(&samples(&slice(0.25, Some(0.55)), &assets, &seed).await, &[0.2, 0.3, 0.4]));
+        assert!(close(&samples(&slice(0.75, None), &assets, &seed).await, &[0.7, 0.8, 0.9]));
+        // An end before the start is empty
+        assert!(samples(&slice(0.55, Some(0.25)), &assets, &seed).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn speed() {
+        let assets = AssetCache::new(tokio::runtime::Handle::current());
+        let [_, _, _, ramp] = write_tracks("speed");
+        let speed = |variation: f32| AudioNode::Speed { node: Box::new(ramp.clone()), speed: 2., variation };
+
+        // The samples are the same, but played at another rate
+        let source = build(&speed(0.), &assets, &AudioSeed::new()).await.unwrap();
+        assert_eq!(source.sample_rate(), 2 * SAMPLE_RATE);
+        assert_eq!(source.sample_count(), Some(10));
+
+        // The variation is picked with the seed
+        let seed = AudioSeed::new();
+        let sample_rate = build(&speed(0.5), &assets, &seed).await.unwrap().sample_rate();
+        assert_eq!(build(&speed(0.5), &assets, &seed).await.unwrap().sample_rate(), sample_rate);
+        let mut sample_rates = Vec::new();
+        for _ in 0..16 {
+            let sample_rate = build(&speed(0.5), &assets, &AudioSeed::new()).await.unwrap().sample_rate();
+            assert!((SAMPLE_RATE..=3 * SAMPLE_RATE).contains(&sample_rate));
+            sample_rates.push(sample_rate);
+        }
+        assert!(sample_rates.iter().unique().count() > 1);
+    }
+
+    #[tokio::test]
+    async fn empty_nodes_take_the_sample_rate_of_the_mixer() {
+        let assets = AssetCache::new(tokio::runtime::Handle::current());
+        let [a, ..] = write_tracks("empty");
+        let seed = AudioSeed::new();
+
+        for node in
+            [AudioNode::Identity, AudioNode::Mix { nodes: Vec::new() }, AudioNode::RandomChoice { nodes: Vec::new(), weights: Vec::new() }]
+        {
+            let source = build(&node, &assets, &seed).await.unwrap();
+            assert_eq!(source.sample_rate(), SAMPLE_RATE);
+            assert_eq!(source.sample_count(), Some(0));
+        }
+        // The tracks after an empty node aren't converted to another sample rate
+        let chain = AudioNode::Chain { nodes: vec![AudioNode::Identity, a] };
+        assert!(close(&samples(&chain, &assets, &seed).await, &[0.1, 0.1]));
+    }
+}
@@ -1,6 +1,8 @@
+pub mod environment;
 mod error;
 mod events;
 mod graph;
+pub mod music;
 mod sounds;
 pub mod systems;
 pub use ambient_audio as core;
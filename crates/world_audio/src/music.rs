@@ -0,0 +1,154 @@
+use std::{collections::VecDeque, fmt::Debug, sync::Arc, time::Duration};
+
+use ambient_audio::{Bus, MusicPlayer, SampleRate, Source};
+use ambient_core::asset_cache;
+use ambient_ecs::{query, EntityId, SystemGroup, World};
+use ambient_std::{
+    asset_cache::{AssetCache, AsyncAssetKeyExt},
+    asset_url::AbsAssetUrl,
+    download_asset::JsonFromUrl,
+};
+use itertools::Itertools;
+use parking_lot::Mutex;
+
+use crate::{
+    audio_mixer, music_player, music_player_crossfade, music_player_queue, music_player_track, AudioNode, AudioSeed, SOUND_GRAPH_EXTENSION,
+};
+
+/// The player of a music player entity, and the tracks it was last asked to play
+#[derive(Debug, Clone)]
+pub struct MusicPlayerState {
+    player: MusicPlayer,
+    track: String,
+    /// Whether `track` has yet to be loaded and played
+    loading_track: bool,
+    queue: Vec<String>,
+    /// The tracks at the end of `queue` which have yet to be queued on the player, in order. Shared by the clones of the state
+    loading_queue: Arc<Mutex<VecDeque<QueuedTrack>>>,
+}
+
+/// A track of the queue of a music player
+struct QueuedTrack {
+    url: String,
+    /// The source of the track, once it's built
+    source: Option<Box<dyn Source>>,
+}
+
+impl Debug for QueuedTrack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueuedTrack").field("url", &self.url).field("built", &self.source.is_some()).finish()
+    }
+}
+
+/// Plays the tracks of the music players on the music bus, as they're loaded
+pub fn client_systems() -> SystemGroup {
+    SystemGroup::new(
+        "audio/music",
+        vec![
+            query(music_player_track()).excl(music_player()).to_system(|q, world, qs, _| {
+                let Some(mixer) = world.resource_opt(audio_mixer()).cloned() else { return };
+                for (id, _) in q.collect_cloned(world, qs) {
+                    let player = MusicPlayer::new(mixer.sample_rate());
+                    mixer.play_on(Bus::Music, player.clone());
+                    let state = MusicPlayerState {
+                        player,
+                        track: String::new(),
+                        loading_track: false,
+                        queue: Vec::new(),
+                        loading_queue: Default::default(),
+                    };
+                    world.add_component(id, music_player(), state).unwrap();
+                }
+            }),
+            query((music_player(), music_player_track())).to_system(|q, world, qs, _| {
+                for (id, (mut state, track)) in q.collect_cloned(world, qs) {
+                    update_player(world, id, &mut state, track);
+                    world.set(id, music_player(), state).unwrap();
+                }
+            }),
+            query(music_player()).excl(music_player_track()).to_system(|q, world, qs, _| {
+                for (id, state) in q.collect_cloned(world, qs) {
+                    state.player.finish();
+                    world.remove_component(id, music_player()).unwrap();
+                }
+            }),
+            query(music_player()).despawned().to_system(|q, world, qs, _| {
+                for (_, state) in q.iter(world, qs) {
+                    state.player.finish();
+                }
+            }),
+        ],
+    )
+}
+
+fn update_player(world: &World, id: EntityId, state: &mut MusicPlayerState, track: String) {
+    let assets = world.resource(asset_cache());
+
+    if track != state.track {
+        state.track = track;
+        state.loading_track = true;
+    }
+    if state.loading_track {
+        match load_track(assets, &state.track, state.player.sample_rate()) {
+            Ok(Some(source)) => {
+                let crossfade = world.get(id, music_player_crossfade()).unwrap_or_default().max(0.);
+                state.player.crossfade(source, Duration::from_secs_f32(crossfade));
+                state.loading_track = false;
+            }
+            Ok(None) => {}
+            Err(err) => {
+                tracing::warn!("Failed to load music track {:?}: {err:?}", state.track);
+                state.loading_track = false;
+            }
+        }
+    }
+
+    let queue = world.get_cloned(id, music_player_queue()).unwrap_or_default();
+    let mut loading_queue = state.loading_queue.lock();
+    if queue != state.queue {
+        let queued = |urls: &[String]| urls.iter().map(|url| QueuedTrack { url: url.clone(), source: None }).collect_vec();
+        match queue.strip_prefix(&state.queue[..]) {
+            // Tracks added to the end of the queue are played after the ones which were already queued
+            Some(added) => loading_queue.extend(queued(added)),
+            // Any other change replaces the tracks which haven't been played yet
+            None => {
+                state.player.clear_queue();
+                *loading_queue = queued(&queue).into();
+            }
+        }
+        state.queue = queue;
+    }
+
+    // Each track is built once, and queued as soon as the tracks before it are
+    loading_queue.retain_mut(|track| {
+        if track.source.is_none() {
+            match load_track(assets, &track.url, state.player.sample_rate()) {
+                Ok(source) => track.source = source,
+                Err(err) => {
+                    tracing::warn!("Failed to load music track {:?}: {err:?}", track.url);
+                    return false;
+                }
+            }
+        }
+        true
+    });
+    while let Some(QueuedTrack { source: Some(_), .. }) = loading_queue.front() {
+        if let Some(QueuedTrack { source: Some(source), .. }) = loading_queue.pop_front() {
+            state.player.queue(source);
+        }
+    }
+}
+
+/// Builds the source of a track, or None if it's still loading. Sound graphs are played as they are, and vorbis files are streamed.
+fn load_track(assets: &AssetCache, url: &str, sample_rate: SampleRate) -> anyhow::Result<Option<Box<dyn Source>>> {
+    let abs_url = AbsAssetUrl::parse(url)?;
+    let node = match abs_url.extension().as_deref() {
+        Some(SOUND_GRAPH_EXTENSION) => match JsonFromUrl::<AudioNode>::new(abs_url, true).peek(assets) {
+            Some(node) => (*node?).clone(),
+            None => return Ok(None),
+        },
+        Some("ogg") => AudioNode::Stream { url: url.to_string(), loop_start: None, loop_end: None },
+        _ => AudioNode::Track { url: url.to_string() },
+    };
+    Ok(node.try_build(assets, AudioSeed::new(), sample_rate)?)
+}
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
-use ambient_audio::{hrtf::HrtfLib, Attenuation, AudioEmitter, AudioListener, AudioMixer, Sound, Source};
-use ambient_ecs::{components, query, EntityId, Resource, World};
+use ambient_audio::{hrtf::HrtfLib, Attenuation, AudioEmitter, AudioListener, AudioMixer, Bus, Sound, Source};
+use ambient_ecs::{components, query, Component, Debuggable, Description, EntityId, Name, Networked, Resource, Store, World};
 use ambient_element::ElementComponentExt;
 use ambient_std::{cb, Cb};
 use ambient_ui::{
@@ -15,6 +15,8 @@ use itertools::Itertools;
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 
+use crate::music::MusicPlayerState;
+
 components!("audio", {
     @[Resource]
     hrtf_lib: Arc<HrtfLib>,
@@ -23,8 +25,145 @@ components!("audio", {
 
     @[Resource]
     audio_mixer: AudioMixer,
+
+    @[
+        Debuggable, Networked, Store, Resource,
+        Name["Master volume"],
+        Description["The volume of all audio, from 0 to 1."]
+    ]
+    audio_master_volume: f32,
+    @[
+        Debuggable, Networked, Store, Resource,
+        Name["Music volume"],
+        Description["The volume of the music, from 0 to 1."]
+    ]
+    audio_music_volume: f32,
+    @[
+        Debuggable, Networked, Store, Resource,
+        Name["Sound effects volume"],
+        Description["The volume of sound effects, from 0 to 1."]
+    ]
+    audio_sfx_volume: f32,
+    @[
+        Debuggable, Networked, Store, Resource,
+        Name["Voice volume"],
+        Description["The volume of voices, from 0 to 1."]
+    ]
+    audio_voice_volume: f32,
+    @[
+        Debuggable, Networked, Store, Resource,
+        Name["UI volume"],
+        Description["The volume of UI sounds, from 0 to 1."]
+    ]
+    audio_ui_volume: f32,
+    @[
+        Debuggable, Networked, Store, Resource,
+        Name["Master muted"],
+        Description["If true, all audio is muted."]
+    ]
+    audio_master_muted: bool,
+    @[
+        Debuggable, Networked, Store, Resource,
+        Name["Music muted"],
+        Description["If true, the music is muted."]
+    ]
+    audio_music_muted: bool,
+    @[
+        Debuggable, Networked, Store, Resource,
+        Name["Sound effects muted"],
+        Description["If true, sound effects are muted."]
+    ]
+    audio_sfx_muted: bool,
+    @[
+        Debuggable, Networked, Store, Resource,
+        Name["Voice muted"],
+        Description["If true, voices are muted."]
+    ]
+    audio_voice_muted: bool,
+    @[
+        Debuggable, Networked, Store, Resource,
+        Name["UI muted"],
+        Description["If true, UI sounds are muted."]
+    ]
+    audio_ui_muted: bool,
+
+    @[
+        Debuggable, Networked, Store,
+        Name["Audio occlusion"],
+        Description["How much the sound of this entity is blocked by colliders between it and each of the listeners in `audio_occlusion_listeners`, from 0 to 1. Occluded sounds are quieter and muffled.\nIf attached, it's recomputed on the server with raycasts from every player camera, and every client hears the occlusion from its own camera."]
+    ]
+    audio_occlusion: Vec<f32>,
+    @[
+        Debuggable, Networked, Store,
+        Name["Audio occlusion listeners"],
+        Description["The player cameras the values of `audio_occlusion` are computed from, in the same order."]
+    ]
+    audio_occlusion_listeners: Vec<EntityId>,
+
+    @[
+        Debuggable, Networked, Store,
+        Name["Reverb zone radius"],
+        Description["If attached, this entity is a reverb zone: the sounds heard by a listener within this distance of its `translation` have the reverb of its room."]
+    ]
+    reverb_zone_radius: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Reverb zone falloff"],
+        Description["The distance outside of the radius of a reverb zone over which its reverb fades out. Defaults to 0."]
+    ]
+    reverb_zone_falloff: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Reverb zone room size"],
+        Description["How large the room of a reverb zone is, from 0 to 1, which sets how long its reverb lasts. Defaults to 0.5."]
+    ]
+    reverb_zone_room_size: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Reverb zone damping"],
+        Description["How much the room of a reverb zone absorbs high frequencies, from 0 to 1. Defaults to 0.5."]
+    ]
+    reverb_zone_damping: f32,
+    @[
+        Debuggable, Networked, Store,
+        Name["Reverb zone wet"],
+        Description["How loud the reverb of a reverb zone is compared to the original sound, from 0 to 1. Defaults to 0.3."]
+    ]
+    reverb_zone_wet: f32,
+
+    @[
+        Debuggable, Networked, Store,
+        Name["Music player track"],
+        Description["If attached, this entity is a music player, which plays the track at this url on the music bus of every client. The track is a sound graph, or an `.ogg` file, which is streamed.\nWhen it changes, the player crossfades to the new track over `music_player_crossfade` seconds."]
+    ]
+    music_player_track: String,
+    @[
+        Debuggable, Networked, Store,
+        Name["Music player queue"],
+        Description["The urls of the tracks a music player plays after its current track ends, in order and without gaps. Urls added to the end are queued after the others, while any other change replaces the tracks which haven't been played yet."]
+    ]
+    music_player_queue: Vec<String>,
+    @[
+        Debuggable, Networked, Store,
+        Name["Music player crossfade"],
+        Description["How long a music player takes to fade to a new track, in seconds. Defaults to 0."]
+    ]
+    music_player_crossfade: f32,
+    /// The player of a music player entity on a client
+    music_player: MusicPlayerState,
 });
 
+/// The components that control the volume and muting of a bus
+pub fn bus_level_components(bus: Bus) -> (Component<f32>, Component<bool>) {
+    match bus {
+        Bus::Master => (audio_master_volume(), audio_master_muted()),
+        Bus::Music => (audio_music_volume(), audio_music_muted()),
+        Bus::Sfx => (audio_sfx_volume(), audio_sfx_muted()),
+        Bus::Voice => (audio_voice_volume(), audio_voice_muted()),
+        Bus::Ui => (audio_ui_volume(), audio_ui_muted()),
+    }
+}
+
 /// TODO: hook this into the Attenuation inside ambient_audio
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, DerefMut, Deref, From, Into)]
 pub struct AttenuationEditorVisual(Attenuation);
@@ -69,13 +208,13 @@ fn get_audio_listener(world: &World) -> anyhow::Result<&Arc<Mutex<AudioListener>
     Ok(listener)
 }
 
-/// Makes a sound source emit from the entity
-pub fn play_sound_on_entity<S: 'static + Source>(world: &World, id: EntityId, source: S) -> anyhow::Result<Sound> {
+/// Makes a sound source emit from the entity, mixed in `bus`
+pub fn play_sound_on_entity<S: 'static + Source>(world: &World, id: EntityId, bus: Bus, source: S) -> anyhow::Result<Sound> {
     let hrtf_lib = world.resource(hrtf_lib());
     let mixer = world.resource(audio_mixer());
     let emitter = world.get_ref(id, audio_emitter()).context("No audio emitter on entity")?;
 
     let listener = get_audio_listener(world)?;
 
-    Ok(mixer.play(source.spatial(hrtf_lib, listener.clone(), emitter.clone())))
+    Ok(mixer.play_on(bus, source.spatial(hrtf_lib, listener.clone(), emitter.clone())))
 }
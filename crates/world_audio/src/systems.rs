@@ -1,11 +1,15 @@
-use std::{io::Cursor, sync::Arc};
+use std::{io::Cursor, sync::Arc, time::Duration};
 
-use ambient_audio::{hrtf::HrtfLib, AudioMixer};
+use ambient_audio::{hrtf::HrtfLib, AudioMixer, Bus, BusLevels, Ducking};
 use ambient_core::transform::local_to_world;
-use ambient_ecs::{query, SystemGroup, World};
+use ambient_ecs::{query, FnSystem, SystemGroup, World};
+use ambient_network::ServerWorldExt;
 use glam::{vec4, Mat4};
 
-use crate::{audio_emitter, audio_listener, audio_mixer, hrtf_lib};
+use crate::{audio_emitter, audio_listener, audio_mixer, bus_level_components, environment, hrtf_lib, music};
+
+/// How long it takes for the buses to reach new levels set through their resources
+const BUS_LEVELS_TRANSITION: Duration = Duration::from_millis(100);
 
 /// Initializes the HRTF sphere and adds the appropriate resources
 ///
@@ -14,11 +18,49 @@ pub fn setup_audio(world: &mut World, mixer: AudioMixer) -> anyhow::Result<()> {
     let hrtf = Arc::new(HrtfLib::load(Cursor::new(include_bytes!("../IRC_1002_C.bin")))?);
     world.add_resource(hrtf_lib(), hrtf);
 
+    // The music is lowered while someone speaks
+    mixer.set_bus_ducking(
+        Bus::Music,
+        Some(Ducking {
+            trigger: Bus::Voice,
+            gain: 0.3,
+            threshold: 0.01,
+            attack: Duration::from_millis(50),
+            release: Duration::from_millis(500),
+        }),
+    );
     world.add_resource(audio_mixer(), mixer);
 
     Ok(())
 }
 
+/// The levels of a bus, from the resources of the client and the synced resources of the server
+fn bus_levels(world: &World, bus: Bus) -> BusLevels {
+    let (volume, muted) = bus_level_components(bus);
+    let synced = world.synced_resource_entity();
+    let get = |id| world.get(id, volume).ok();
+    let is_muted = |id| world.get(id, muted).unwrap_or_default();
+
+    let resources = world.resource_entity();
+    BusLevels {
+        gain: get(resources).unwrap_or(1.0) * synced.and_then(get).unwrap_or(1.0),
+        muted: is_muted(resources) || synced.map_or(false, is_muted),
+    }
+}
+
+/// Applies the volume and mute resources to the buses of the mixer
+pub fn bus_levels_system() -> FnSystem {
+    FnSystem::new(|world, _| {
+        let Some(mixer) = world.resource_opt(audio_mixer()) else { return };
+        for bus in Bus::ALL {
+            let levels = bus_levels(world, bus);
+            if levels != mixer.bus_levels(bus) {
+                mixer.set_bus_levels(bus, levels, BUS_LEVELS_TRANSITION);
+            }
+        }
+    })
+}
+
 /// This translates elements RHS Z-up coordinate system to the HRIR sphere LHS Y-up
 /// https://github.com/mrDIMAS/hrir_sphere_builder/blob/e52a10ece678a2b80a0978f7cf23f3ad9cce41c3/src/hrtf_builder.cpp#L155-L162
 pub const Y_UP_LHS: Mat4 =
@@ -47,5 +89,13 @@ pub fn spatial_audio_systems() -> SystemGroup {
 }
 
 pub fn client_systems() -> SystemGroup {
-    SystemGroup::new("Spatial audio", vec![Box::new(spatial_audio_systems())])
+    SystemGroup::new(
+        "Spatial audio",
+        vec![
+            Box::new(spatial_audio_systems()),
+            Box::new(bus_levels_system()),
+            Box::new(environment::client_systems()),
+            Box::new(music::client_systems()),
+        ],
+    )
 }
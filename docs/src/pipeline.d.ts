@@ -41,6 +41,15 @@ export type Pipeline = {
       flip_normals?: boolean,
       /// Whether or not the indices should be reversed for each triangle. On by default.
       reverse_indices?: boolean,
+    } | {
+      /// Approximate the model with a set of convex hulls, computed when building.
+      /// Unlike `FromModel`, this can be used for `Dynamic` colliders.
+      type: "ConvexDecomposition",
+      /// The maximum number of convex hulls. Defaults to 16.
+      max_hulls?: u32,
+      /// The number of voxels along the longest axis of the model that the decomposition works with.
+      /// Higher values are more accurate, but slower to compute. Defaults to 64.
+      resolution?: u32,
     } | {
       /// Use a spherical character collider.
       type: "Character",
@@ -76,6 +85,9 @@ export type Pipeline = {
       /// Cap this model's textures to SIZE x SIZE.
       /// It is strongly recommended that this is a power of two.
       {"Custom": u32},
+    /// Whether or not the textures should also be written as block compressed (BC7, BC5 or BC4) KTX2 textures with
+    /// precomputed mipmaps, which are used instead of the uncompressed ones where the GPU supports them.
+    compress_textures?: boolean,
     /// Treats all assets in the pipeline as variations, and outputs a single asset which is a collection of all assets.
     /// Most useful for grass and other entities whose individual identity is not important.
     collection_of_variants?: boolean,
@@ -188,6 +200,42 @@ export type Pipeline = {
       /// Re-center this mesh such that the root is located at the origin.
       type: "Center",
     })[],
+    /// If specified, simplified versions of the meshes will be generated and used as lower levels of detail.
+    generate_lods?: {
+      /// The number of levels to generate, in addition to the original mesh.
+      levels: u32,
+      /// The target fraction of triangles to keep for each generated level, relative to the original mesh.
+      /// Defaults to halving the triangle count for every level (i.e. `[0.5, 0.25, ...]`).
+      ratios?: f32[],
+      /// The screen sizes (as a fraction of the screen height) at which to switch to the next level, starting with the original mesh.
+      /// Spread out evenly down to 4% of the screen by default.
+      cutoffs?: f32[],
+    },
+    /// If specified, the horizontal translation and yaw of the bone with this bind id (i.e. `Hips`) are extracted from the animations
+    /// as root motion, which moves the animated entity instead of the bone.
+    extract_root_motion?: string,
+    /// If specified, a ragdoll is generated from the skin of the model, which the prefab can switch to with `ragdoll_active`.
+    ragdoll?: {
+      /// The radius of the capsules relative to their length. Defaults to 0.25.
+      radius_scale?: f32,
+      /// Changes to the generated bones, by their bind id (i.e. `LeftHand`).
+      overrides?: {[bind_id: string]: {
+        /// Leave the bone out of the ragdoll. Its children are attached to its parent instead.
+        exclude?: boolean,
+        /// The radius of the bone's capsule, in meters.
+        radius?: f32,
+        /// The length of the bone's capsule, in meters.
+        length?: f32,
+        /// The density of the bone's capsule. Defaults to 1.
+        density?: f32,
+        /// How far, in degrees, the bone can swing away from its base pose. Defaults to 45.
+        swing_limit?: f32,
+        /// How far, in degrees, the bone can twist around its direction, from the first to the second angle. Defaults to `[-30, 30]`.
+        twist_limit?: [f32, f32],
+      }},
+    },
+    /// Make the directional lights of the model a `sun`, so that they light the scene when it is spawned. Off by default.
+    directional_lights_as_sun?: boolean,
   } | {
     /// The materials asset pipeline.
     /// Will import specific materials without needing to be part of a model.
@@ -235,10 +283,28 @@ export type Pipeline = {
     },
     /// Whether or not decal prefabs should be created for each of these materials.
     output_decals?: boolean,
+    /// Whether or not the textures should also be written as block compressed (BC7, BC5 or BC4) KTX2 textures with
+    /// precomputed mipmaps, which are used instead of the uncompressed ones where the GPU supports them.
+    compress_textures?: boolean,
   } | {
     /// The audio asset pipeline.
-    /// Will import supported audio file formats and produce Ogg Vorbis files to be used by the runtime.
+    /// Will import supported audio file formats (WAV, MP3, FLAC and Ogg Vorbis) and produce Ogg Vorbis files to be used by the runtime.
     type: "Audio",
+    /// Resample the audio to this sample rate, in Hz. The original sample rate is kept if not specified.
+    sample_rate?: u32,
+    /// Mix all channels down to a single channel.
+    mono?: boolean,
+    /// Normalize the loudness (RMS level) of the audio to this level, in dBFS (e.g. `-16.0`).
+    /// The gain is limited so that the audio never clips.
+    normalize_loudness?: f32,
+    /// Remove silence at the start of the audio. Samples quieter than this level, in dBFS (e.g. `-60.0`), are considered silent.
+    trim_leading_silence?: f32,
+    /// The quality of the Ogg Vorbis encoding, from -0.2 (lowest) to 1.0 (highest). Defaults to 0.5.
+    quality?: f32,
+  } | {
+    /// The animation graphs pipeline.
+    /// Will import animation state machines from `.animgraph.json` files, to be used with the `animation_graph` component.
+    type: "AnimationGraphs",
   },
   /// Filter the sources used to feed this pipeline.
   /// This is a list of glob patterns for accepted files.
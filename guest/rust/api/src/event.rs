@@ -3,9 +3,21 @@ use crate::{ecs::Entity, internal::host};
 /// Fired each frame.
 pub const FRAME: &str = "core/frame";
 /// Fired on a collision. Components will contain the `ids` of the objects.
+///
+/// If one of the objects has `collision_contacts`, they will also contain the `contact_points`, `contact_normals` and `contact_impulse` of the collision.
 pub const COLLISION: &str = "core/collision";
+/// Fired while the objects of a collision keep touching, if one of them has `collision_contacts`. Components are the same as for [COLLISION].
+pub const COLLISION_PERSIST: &str = "core/collision_persist";
+/// Fired when the objects of a collision stop touching, if one of them has `collision_contacts`. Components are the same as for [COLLISION].
+pub const COLLISION_END: &str = "core/collision_end";
+/// Fired when an entity enters a trigger area. Components will contain the `ids` of the trigger area and the entity, in that order.
+pub const TRIGGER_ENTER: &str = "core/trigger_enter";
+/// Fired when an entity exits a trigger area. Components will contain the `ids` of the trigger area and the entity, in that order.
+pub const TRIGGER_EXIT: &str = "core/trigger_exit";
 /// Fired when a collider is loaded. Components will contain the `id` of the object.
 pub const COLLIDER_LOAD: &str = "core/collider_load";
+/// Fired when an animation plays past one of the events of its clip. Components will contain the `id` of the animated entity and the `name` of the event.
+pub const ANIMATION_EVENT: &str = "core/animation_event";
 /// Fired when the module is loaded.
 pub const MODULE_LOAD: &str = "core/module_load";
 /// Fired when the module is unloaded.
@@ -78,6 +78,12 @@ impl PxCapsuleGeometry {
     pub fn new(radius: f32, half_height: f32) -> Self {
         Self(unsafe { physx_sys::PxCapsuleGeometry_new_1(radius, half_height) })
     }
+    pub fn radius(&self) -> f32 {
+        self.0.radius
+    }
+    pub fn half_height(&self) -> f32 {
+        self.0.halfHeight
+    }
 }
 impl PxGeometry for PxCapsuleGeometry {
     fn as_geometry_ptr(&self) -> *const physx_sys::PxGeometry {
@@ -212,6 +218,12 @@ impl PxGeometryHolder {
         }
         unsafe { Some(PxSphereGeometry(*physx_sys::PxGeometryHolder_sphere(&self.0))) }
     }
+    pub fn as_capsule(&self) -> Option<PxCapsuleGeometry> {
+        if self.get_type() != PxGeometryType::Capsule {
+            return None;
+        }
+        unsafe { Some(PxCapsuleGeometry(*physx_sys::PxGeometryHolder_capsule(&self.0))) }
+    }
     pub fn as_convex_mesh(&self) -> Option<PxConvexMeshGeometry> {
         if self.get_type() != PxGeometryType::ConvexMesh {
             return None;
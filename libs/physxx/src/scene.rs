@@ -1,4 +1,4 @@
-use std::ptr::null_mut;
+use std::{ffi::c_void, ptr::null_mut};
 
 use glam::Vec3;
 use physx_sys::{create_overlap_buffer, create_raycast_buffer, create_sweep_buffer, delete_overlap_callback, delete_raycast_callback};
@@ -6,8 +6,8 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     sweep::PxSweepHit, to_glam_vec3, to_physx_vec3, AsArticulationBase, AsPxActor, PxActorRef, PxAggregateRef, PxCollectionRef,
-    PxConstraintRef, PxDefaultCpuDispatcherRef, PxGeometry, PxHitFlags, PxPhysicsRef, PxPvdSceneClientRef, PxRaycastHit, PxRigidActorRef,
-    PxShape, PxTransform,
+    PxConstraintRef, PxDefaultCpuDispatcherRef, PxFilterData, PxGeometry, PxHitFlags, PxPhysicsRef, PxPvdSceneClientRef, PxRaycastHit,
+    PxRigidActorRef, PxShape, PxTransform,
 };
 
 pub struct PxSceneDesc(physx_sys::PxSceneDesc);
@@ -39,13 +39,14 @@ impl PxSceneDesc {
     pub fn set_simulation_event_callbacks<C: FnMut(&PxContactPairHeader)>(&mut self, callbacks: PxSimulationEventCallback<C>) {
         unsafe {
             unsafe extern "C" fn collision_callback_trampoline<C: FnMut(&PxContactPairHeader)>(
-                user_data: *mut std::ffi::c_void,
+                user_data: *mut c_void,
                 pair_header: *const physx_sys::PxContactPairHeader,
-                _pairs: *const physx_sys::PxContactPair,
-                _nb_pairs: u32,
+                pairs: *const physx_sys::PxContactPair,
+                nb_pairs: u32,
             ) {
                 let mut cb: Box<C> = Box::from_raw(user_data as _);
                 let pair_header_flags = PxContactPairHeaderFlag::from_bits((*pair_header).flags.mBits).unwrap();
+                let pairs = std::slice::from_raw_parts(pairs, nb_pairs as usize).iter().map(|pair| PxContactPair::from_px(pair)).collect();
                 cb(&PxContactPairHeader {
                     actors: [
                         if pair_header_flags.contains(PxContactPairHeaderFlag::REMOVED_ACTOR_0) {
@@ -59,6 +60,7 @@ impl PxSceneDesc {
                             PxRigidActorRef::from_ptr((*pair_header).actors[1])
                         },
                     ],
+                    pairs,
                 });
                 Box::into_raw(cb);
             }
@@ -85,6 +87,67 @@ impl PxSceneDesc {
 
 pub struct PxContactPairHeader {
     pub actors: [Option<PxRigidActorRef>; 2],
+    pub pairs: Vec<PxContactPair>,
+}
+
+/// The data of a shape of a [`PxContactPair`], copied when the pair is reported so that the shape isn't referenced by it
+#[derive(Debug, Clone, Copy)]
+pub struct PxContactShape {
+    pub user_data: *mut c_void,
+    pub simulation_filter_data: PxFilterData,
+}
+
+/// The contacts between a pair of shapes of the actors of a [`PxContactPairHeader`]. The shapes are `None` if they've been removed
+pub struct PxContactPair {
+    pub shapes: [Option<PxContactShape>; 2],
+    pub events: PxPairFlags,
+    pub contacts: Vec<PxContactPairPoint>,
+}
+impl PxContactPair {
+    pub(crate) unsafe fn from_px(pair: &physx_sys::PxContactPair) -> Self {
+        let flags = PxContactPairFlag::from_bits_truncate(pair.flags.mBits);
+        let shape = |i: usize, removed: PxContactPairFlag| {
+            if flags.contains(removed) || pair.shapes[i].is_null() {
+                None
+            } else {
+                let shape = &*pair.shapes[i];
+                let data = physx_sys::PxShape_getSimulationFilterData(shape);
+                Some(PxContactShape {
+                    user_data: shape.userData,
+                    simulation_filter_data: PxFilterData { word0: data.word0, word1: data.word1, word2: data.word2, word3: data.word3 },
+                })
+            }
+        };
+        let mut contacts = Vec::new();
+        if pair.contactCount > 0 {
+            let mut buffer = vec![std::mem::zeroed::<physx_sys::PxContactPairPoint>(); pair.contactCount as usize];
+            let count = physx_sys::PxContactPair_extractContacts(pair, buffer.as_mut_ptr(), buffer.len() as u32);
+            contacts = buffer
+                .iter()
+                .take(count as usize)
+                .map(|point| PxContactPairPoint {
+                    position: to_glam_vec3(&point.position),
+                    separation: point.separation,
+                    normal: to_glam_vec3(&point.normal),
+                    impulse: to_glam_vec3(&point.impulse),
+                })
+                .collect();
+        }
+        Self {
+            shapes: [shape(0, PxContactPairFlag::REMOVED_SHAPE_0), shape(1, PxContactPairFlag::REMOVED_SHAPE_1)],
+            events: PxPairFlags::from_bits_truncate(pair.events.mBits),
+            contacts,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PxContactPairPoint {
+    pub position: Vec3,
+    pub separation: f32,
+    /// Points from the second shape of the pair towards the first
+    pub normal: Vec3,
+    pub impulse: Vec3,
 }
 
 pub struct PxSimulationEventCallback<C: FnMut(&PxContactPairHeader)> {
@@ -98,6 +161,22 @@ bitflags! {
     }
 }
 
+bitflags! {
+    pub struct PxContactPairFlag: u16 {
+        const REMOVED_SHAPE_0 = physx_sys::PxContactPairFlag::eREMOVED_SHAPE_0 as u16;
+        const REMOVED_SHAPE_1 = physx_sys::PxContactPairFlag::eREMOVED_SHAPE_1 as u16;
+    }
+}
+
+bitflags! {
+    pub struct PxPairFlags: u16 {
+        const NOTIFY_TOUCH_FOUND = physx_sys::PxPairFlag::eNOTIFY_TOUCH_FOUND as u16;
+        const NOTIFY_TOUCH_PERSISTS = physx_sys::PxPairFlag::eNOTIFY_TOUCH_PERSISTS as u16;
+        const NOTIFY_TOUCH_LOST = physx_sys::PxPairFlag::eNOTIFY_TOUCH_LOST as u16;
+        const NOTIFY_CONTACT_POINTS = physx_sys::PxPairFlag::eNOTIFY_CONTACT_POINTS as u16;
+    }
+}
+
 bitflags! {
     pub struct PxSceneFlags: u32 {
         const ADAPTIVE_FORCE = physx_sys::PxSceneFlag::eADAPTIVE_FORCE;
@@ -185,6 +264,11 @@ impl PxSceneRef {
             physx_sys::PxScene_addActor_mut(self.0, actor.as_actor().0, null_mut());
         }
     }
+    /// Re-runs the filter shader for the pairs of the shapes of an actor in the scene, which is needed for changes
+    /// to their simulation filter data to apply to pairs that already exist
+    pub fn reset_filtering(&self, actor: &dyn AsPxActor) -> bool {
+        unsafe { physx_sys::PxScene_resetFiltering_mut(self.0, actor.as_actor().0) }
+    }
     pub fn remove_actor(&self, actor: &dyn AsPxActor, wake_on_lost_touch: bool) {
         unsafe {
             physx_sys::PxScene_removeActor_mut(self.0, actor.as_actor().0, wake_on_lost_touch);
@@ -99,6 +99,23 @@ impl PxShape {
     pub fn set_rest_offset(&self, offset: f32) {
         unsafe { physx_sys::PxShape_setRestOffset_mut(self.0, offset) }
     }
+    pub fn get_simulation_filter_data(&self) -> PxFilterData {
+        let data = unsafe { physx_sys::PxShape_getSimulationFilterData(self.0) };
+        PxFilterData { word0: data.word0, word1: data.word1, word2: data.word2, word3: data.word3 }
+    }
+    pub fn set_simulation_filter_data(&self, data: &PxFilterData) {
+        let data = physx_sys::PxFilterData { word0: data.word0, word1: data.word1, word2: data.word2, word3: data.word3 };
+        unsafe { physx_sys::PxShape_setSimulationFilterData_mut(self.0, &data as *const physx_sys::PxFilterData) }
+    }
+}
+
+/// The data passed to the filter shader for each shape of a pair
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PxFilterData {
+    pub word0: u32,
+    pub word1: u32,
+    pub word2: u32,
+    pub word3: u32,
 }
 impl AsPxBase for PxShape {
     fn as_base(&self) -> PxBaseRef {